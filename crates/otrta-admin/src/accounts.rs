@@ -0,0 +1,249 @@
+use anyhow::{Context as _, anyhow, bail};
+use clap::Subcommand;
+use otrta::{
+    db::{
+        api_keys::{
+            CreateApiKeyRequest, UpdateApiKeyRequest, create_api_key,
+            get_api_keys_for_organization, update_api_key,
+        },
+        members::{get_member, get_members, set_member_role},
+        organizations::{create_organization, get_all_organizations, get_organization_by_id},
+        sessions::revoke_sessions_for_user,
+        users::{create_user, get_user_by_npub, validate_npub},
+    },
+    models::{CreateOrganizationRequest, CreateUserRequest, Organization, UserContext},
+    permissions::Role,
+};
+use uuid::Uuid;
+
+use crate::Context;
+
+#[derive(Subcommand)]
+pub enum OrgCommand {
+    /// Create an organization.
+    Create { name: String },
+    /// List organizations.
+    List,
+    /// List an organization's members and their roles.
+    Members { id: Uuid },
+}
+
+#[derive(Subcommand)]
+pub enum UserCommand {
+    /// Add a user to an organization.
+    Create {
+        npub: String,
+        #[arg(long)]
+        org: Uuid,
+        #[arg(long)]
+        display_name: Option<String>,
+        #[arg(long)]
+        email: Option<String>,
+        /// owner, admin, member, viewer or billing.
+        #[arg(long, default_value = "member")]
+        role: Role,
+    },
+    /// Change a user's role in an organization.
+    SetRole {
+        npub: String,
+        role: Role,
+        /// Organization to change the role in; the user's default if omitted.
+        #[arg(long)]
+        org: Option<Uuid>,
+    },
+    /// Revoke all of a user's login sessions.
+    Logout { npub: String },
+}
+
+#[derive(Subcommand)]
+pub enum ApiKeyCommand {
+    /// Issue a key for a user.
+    Create {
+        #[arg(long)]
+        name: String,
+        /// npub of the user the key acts as.
+        #[arg(long)]
+        user: String,
+        /// Expiry as an RFC 3339 timestamp.
+        #[arg(long)]
+        expires_at: Option<String>,
+    },
+    /// List an organization's keys.
+    List {
+        #[arg(long)]
+        org: Uuid,
+    },
+    /// Deactivate a key so it can no longer authenticate.
+    Revoke { id: Uuid },
+}
+
+async fn require_organization(ctx: &Context, id: &Uuid) -> anyhow::Result<Organization> {
+    get_organization_by_id(&ctx.db, id)
+        .await?
+        .ok_or_else(|| anyhow!("organization {} not found", id))
+}
+
+pub async fn org(ctx: &Context, command: OrgCommand) -> anyhow::Result<()> {
+    match command {
+        OrgCommand::Create { name } => {
+            let organization =
+                create_organization(&ctx.db, &CreateOrganizationRequest { name }).await?;
+            ctx.output.print(&organization, |org| {
+                println!("Created organization {} ({})", org.name, org.id);
+            })
+        }
+        OrgCommand::List => {
+            let organizations = get_all_organizations(&ctx.db).await?;
+            ctx.output.print(&organizations, |organizations| {
+                for org in organizations {
+                    println!(
+                        "{}  {}{}",
+                        org.id,
+                        org.name,
+                        if org.is_active { "" } else { "  (inactive)" }
+                    );
+                }
+            })
+        }
+        OrgCommand::Members { id } => {
+            require_organization(ctx, &id).await?;
+            let members = get_members(&ctx.db, &id).await?;
+            ctx.output.print(&members, |members| {
+                for member in members {
+                    println!("{}  {}", member.npub, member.role);
+                }
+            })
+        }
+    }
+}
+
+pub async fn user(ctx: &Context, command: UserCommand) -> anyhow::Result<()> {
+    match command {
+        UserCommand::Create {
+            npub,
+            org,
+            display_name,
+            email,
+            role,
+        } => {
+            validate_npub(&npub)?;
+            require_organization(ctx, &org).await?;
+            if get_user_by_npub(&ctx.db, &npub).await?.is_some() {
+                bail!("user {} already exists", npub);
+            }
+
+            let user = create_user(
+                &ctx.db,
+                &CreateUserRequest {
+                    npub,
+                    display_name,
+                    email,
+                    organization_id: org,
+                },
+            )
+            .await?;
+            set_member_role(&ctx.db, &org, &user.npub, role).await?;
+            ctx.output.print(&user, |user| {
+                println!(
+                    "Created user {} in organization {} as {}",
+                    user.npub, user.organization_id, role
+                );
+            })
+        }
+        UserCommand::SetRole { npub, role, org } => {
+            let user = get_user_by_npub(&ctx.db, &npub)
+                .await?
+                .ok_or_else(|| anyhow!("user {} not found", npub))?;
+            let org = org.unwrap_or(user.organization_id);
+            if get_member(&ctx.db, &org, &npub).await?.is_none() {
+                bail!("{} is not a member of organization {}", npub, org);
+            }
+            let member = set_member_role(&ctx.db, &org, &npub, role).await?;
+            ctx.output.print(&member, |member| {
+                println!(
+                    "{} is now {} of organization {}",
+                    member.npub, member.role, member.organization_id
+                );
+            })
+        }
+        UserCommand::Logout { npub } => {
+            get_user_by_npub(&ctx.db, &npub)
+                .await?
+                .ok_or_else(|| anyhow!("user {} not found", npub))?;
+            let revoked = revoke_sessions_for_user(&ctx.db, &npub).await?;
+            ctx.output.print(
+                &serde_json::json!({ "npub": npub, "revoked": revoked }),
+                |_| {
+                    println!("Revoked {} sessions of {}", revoked, npub);
+                },
+            )
+        }
+    }
+}
+
+pub async fn api_key(ctx: &Context, command: ApiKeyCommand) -> anyhow::Result<()> {
+    match command {
+        ApiKeyCommand::Create {
+            name,
+            user,
+            expires_at,
+        } => {
+            let user = get_user_by_npub(&ctx.db, &user)
+                .await?
+                .ok_or_else(|| anyhow!("user {} not found", user))?;
+            let organization = require_organization(ctx, &user.organization_id).await?;
+
+            if let Some(expires_at) = &expires_at {
+                chrono::DateTime::parse_from_rfc3339(expires_at)
+                    .with_context(|| format!("invalid --expires-at {:?}", expires_at))?;
+            }
+
+            let api_key = create_api_key(
+                &ctx.db,
+                CreateApiKeyRequest {
+                    name,
+                    expires_at,
+                    is_active: Some(true),
+                },
+                &UserContext::new(user.npub, organization),
+            )
+            .await?;
+            ctx.output.print(&api_key, |key| {
+                println!("Created API key {} ({})", key.name, key.id);
+                println!("Key: {}", key.key);
+            })
+        }
+        ApiKeyCommand::List { org } => {
+            require_organization(ctx, &org).await?;
+            let keys = get_api_keys_for_organization(&ctx.db, &org.to_string()).await?;
+            ctx.output.print(&keys, |keys| {
+                for key in keys {
+                    println!(
+                        "{}  {}  user={}  {}  last used {}",
+                        key.id,
+                        key.name,
+                        key.user_id,
+                        if key.is_active { "active" } else { "revoked" },
+                        key.last_used_at.as_deref().unwrap_or("never")
+                    );
+                }
+            })
+        }
+        ApiKeyCommand::Revoke { id } => {
+            let key = update_api_key(
+                &ctx.db,
+                &id.to_string(),
+                UpdateApiKeyRequest {
+                    name: None,
+                    is_active: Some(false),
+                    expires_at: None,
+                },
+            )
+            .await?
+            .ok_or_else(|| anyhow!("API key {} not found", id))?;
+            ctx.output.print(&key, |key| {
+                println!("Revoked API key {} ({})", key.name, key.id);
+            })
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Operator CLI for an otrta instance. Talks to the database and wallet
+//! directory directly, so it works without the web UI or NIP-98 signing.
+
+mod accounts;
+mod output;
+mod wallet;
+
+use anyhow::{Context as _, anyhow};
+use clap::{Parser, Subcommand};
+use otrta::db::provider::refresh_providers_from_nostr_global;
+use output::Output;
+use serde::Serialize;
+use sqlx::pool::PoolOptions;
+use std::process::ExitCode;
+use tracing_subscriber::EnvFilter;
+
+#[derive(Parser)]
+#[command(name = "otrta-admin", version, about = "Operate an otrta instance")]
+struct Cli {
+    /// Connection string of the instance's database (a `sqlite://` URL
+    /// when built with the `sqlite` feature).
+    #[arg(long, env = "DATABASE_URL", global = true, hide_env_values = true)]
+    database_url: Option<String>,
+
+    /// Wallet directory prefix, as set for the server.
+    #[arg(
+        long,
+        env = "WALLET_DATA_DIR",
+        default_value = "/multimint",
+        global = true
+    )]
+    wallet_dir: String,
+
+    /// Print machine-readable JSON instead of text.
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Apply pending database migrations.
+    Migrate,
+    /// Manage organizations.
+    #[command(subcommand)]
+    Org(accounts::OrgCommand),
+    /// Manage users.
+    #[command(subcommand)]
+    User(accounts::UserCommand),
+    /// Issue, list and revoke API keys.
+    #[command(subcommand, name = "api-key")]
+    ApiKey(accounts::ApiKeyCommand),
+    /// Show wallet balances per organization and mint.
+    Balance {
+        /// Only this organization.
+        #[arg(long)]
+        org: Option<uuid::Uuid>,
+    },
+    /// Export and import wallet backups.
+    #[command(subcommand)]
+    Wallet(wallet::WalletCommand),
+    /// Fetch provider announcements from Nostr relays.
+    DiscoverProviders {
+        /// Relay to query; repeat for several. Defaults to the built-in list.
+        #[arg(long = "relay")]
+        relays: Vec<String>,
+    },
+    /// Reclaim proofs left pending by interrupted payments.
+    RedeemPendings {
+        /// Only this organization.
+        #[arg(long)]
+        org: Option<uuid::Uuid>,
+        /// Give up on a wallet after this many seconds.
+        #[arg(long, default_value_t = 60)]
+        timeout_seconds: u64,
+    },
+}
+
+pub struct Context {
+    pub db: otrta::db::Pool,
+    pub wallet_dir: String,
+    pub output: Output,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    // Library logs go to stderr and stay quiet unless asked for, so stdout
+    // only carries command output.
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> anyhow::Result<()> {
+    let database_url = cli
+        .database_url
+        .ok_or_else(|| anyhow!("no database given; set DATABASE_URL or pass --database-url"))?;
+    let db = PoolOptions::new()
+        .max_connections(2)
+        .connect(&database_url)
+        .await
+        .context("failed to connect to the database")?;
+
+    let ctx = Context {
+        db,
+        wallet_dir: cli.wallet_dir,
+        output: Output::new(cli.json),
+    };
+
+    let result = match cli.command {
+        Command::Migrate => migrate(&ctx).await,
+        Command::Org(command) => accounts::org(&ctx, command).await,
+        Command::User(command) => accounts::user(&ctx, command).await,
+        Command::ApiKey(command) => accounts::api_key(&ctx, command).await,
+        Command::Balance { org } => wallet::balance(&ctx, org).await,
+        Command::Wallet(command) => wallet::wallet(&ctx, command).await,
+        Command::DiscoverProviders { relays } => discover_providers(&ctx, relays).await,
+        Command::RedeemPendings {
+            org,
+            timeout_seconds,
+        } => wallet::redeem_pendings(&ctx, org, timeout_seconds).await,
+    };
+
+    // Closing the pool lets pending writes finish before the process exits.
+    ctx.db.close().await;
+    result
+}
+
+#[derive(Serialize)]
+struct MigrateReport {
+    applied: usize,
+    latest_version: Option<i64>,
+}
+
+async fn migrate(ctx: &Context) -> anyhow::Result<()> {
+    #[cfg(not(feature = "sqlite"))]
+    let migrator = sqlx::migrate!("../otrta-ui/migrations");
+    #[cfg(feature = "sqlite")]
+    let migrator = sqlx::migrate!("../otrta-ui/migrations-sqlite");
+
+    // The table doesn't exist before the first run.
+    let applied_before: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM _sqlx_migrations WHERE success")
+            .fetch_one(&ctx.db)
+            .await
+            .unwrap_or(0);
+    migrator
+        .run(&ctx.db)
+        .await
+        .context("failed to apply migrations")?;
+    let applied_after: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM _sqlx_migrations WHERE success")
+            .fetch_one(&ctx.db)
+            .await?;
+
+    let report = MigrateReport {
+        applied: (applied_after - applied_before).max(0) as usize,
+        latest_version: migrator.iter().map(|migration| migration.version).max(),
+    };
+    ctx.output.print(&report, |report| {
+        println!(
+            "Applied {} migration(s); database is at version {}",
+            report.applied,
+            report
+                .latest_version
+                .map(|version| version.to_string())
+                .unwrap_or_else(|| "none".to_string())
+        );
+    })
+}
+
+async fn discover_providers(ctx: &Context, relays: Vec<String>) -> anyhow::Result<()> {
+    let relays = if relays.is_empty() {
+        otrta_nostr::DEFAULT_RELAYS
+            .iter()
+            .map(|relay| relay.to_string())
+            .collect()
+    } else {
+        relays
+    };
+
+    let response = refresh_providers_from_nostr_global(&ctx.db, relays)
+        .await
+        .map_err(|e| anyhow!("provider discovery failed: {}", e))?;
+    ctx.output.print(&response, |response| {
+        println!(
+            "Providers: {} added, {} updated",
+            response.providers_added, response.providers_updated
+        );
+    })
+}
@@ -0,0 +1,539 @@
+use anyhow::{Context as _, anyhow, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{DateTime, Utc};
+use clap::Subcommand;
+use otrta::{
+    db::{
+        mint::{
+            CreateMintRequest, MintWithBalances, create_mint_for_organization,
+            get_mint_by_url_for_organization, get_mints_for_organization,
+        },
+        organizations::{get_all_organizations, get_organization_by_id},
+        server_config::{
+            create_with_seed_for_organization, get_config_by_organization, update_seed,
+        },
+    },
+    models::Organization,
+    multimint_manager::MultimintManager,
+};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+use uuid::Uuid;
+
+use crate::Context;
+
+const BACKUP_VERSION: u32 = 1;
+
+#[derive(Subcommand)]
+pub enum WalletCommand {
+    /// Write an organization's seed, mints and wallet database to a file.
+    /// Stop otrta-ui first so the wallet database is consistent.
+    Export {
+        #[arg(long)]
+        org: Uuid,
+        #[arg(long, short)]
+        output: PathBuf,
+    },
+    /// Restore a backup written by `wallet export`. Restart otrta-ui
+    /// afterwards so it reopens the wallet.
+    Import {
+        file: PathBuf,
+        /// Restore into this organization instead of the one in the backup.
+        #[arg(long)]
+        org: Option<Uuid>,
+        /// Replace an existing wallet and seed.
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct WalletBackup {
+    version: u32,
+    created_at: DateTime<Utc>,
+    organization_id: Uuid,
+    organization_name: String,
+    seed: String,
+    mints: Vec<BackupMint>,
+    /// Base64 of the wallet's SQLite database.
+    wallet_db: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BackupMint {
+    mint_url: String,
+    currency_unit: String,
+    name: Option<String>,
+}
+
+#[derive(Serialize)]
+struct OrganizationBalance {
+    organization_id: Uuid,
+    organization_name: String,
+    total_balance: u64,
+    mints: Vec<MintWithBalances>,
+}
+
+#[derive(Serialize)]
+struct RedeemResult {
+    organization_id: Uuid,
+    organization_name: String,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ExportReport {
+    organization_id: Uuid,
+    path: PathBuf,
+    mints: usize,
+    wallet_db_bytes: usize,
+}
+
+#[derive(Serialize)]
+struct ImportReport {
+    organization_id: Uuid,
+    mints_added: usize,
+    seed_replaced: bool,
+    wallet_db_path: String,
+    /// Where the replaced wallet database was moved to.
+    previous_wallet_db: Option<PathBuf>,
+}
+
+/// The requested organization, or every organization.
+async fn organizations(ctx: &Context, org: Option<Uuid>) -> anyhow::Result<Vec<Organization>> {
+    match org {
+        Some(id) => Ok(vec![
+            get_organization_by_id(&ctx.db, &id)
+                .await?
+                .ok_or_else(|| anyhow!("organization {} not found", id))?,
+        ]),
+        None => Ok(get_all_organizations(&ctx.db).await?),
+    }
+}
+
+pub async fn balance(ctx: &Context, org: Option<Uuid>) -> anyhow::Result<()> {
+    let manager = MultimintManager::new(ctx.wallet_dir.clone(), ctx.db.clone());
+    let mut balances = Vec::new();
+
+    for organization in organizations(ctx, org).await? {
+        // Opening a wallet creates it, so skip organizations without one.
+        if !manager.has_wallet(&organization.id) {
+            continue;
+        }
+
+        let wallet = manager.get_or_create_multimint(&organization.id).await?;
+        let balance = wallet
+            .get_balances_by_units(&ctx.db, &organization.id)
+            .await
+            .map_err(|e| anyhow!("failed to read balance of {}: {}", organization.id, e))?;
+        balances.push(OrganizationBalance {
+            organization_id: organization.id,
+            organization_name: organization.name,
+            total_balance: balance.total_balance,
+            mints: balance.mints_with_balances,
+        });
+    }
+
+    ctx.output.print(&balances, |balances| {
+        if balances.is_empty() {
+            println!("No wallets found in {}", ctx.wallet_dir);
+        }
+        for org in balances {
+            println!("{} ({})", org.organization_name, org.organization_id);
+            for mint in &org.mints {
+                for unit in &mint.unit_balances {
+                    println!("  {}  {} {}", mint.mint_url, unit.balance, unit.unit);
+                }
+            }
+        }
+    })
+}
+
+pub async fn redeem_pendings(
+    ctx: &Context,
+    org: Option<Uuid>,
+    timeout_seconds: u64,
+) -> anyhow::Result<()> {
+    let manager = MultimintManager::new(ctx.wallet_dir.clone(), ctx.db.clone());
+    let timeout = Duration::from_secs(timeout_seconds);
+    let mut results = Vec::new();
+
+    for organization in organizations(ctx, org).await? {
+        if !manager.has_wallet(&organization.id) {
+            continue;
+        }
+
+        let wallet = manager.get_or_create_multimint(&organization.id).await?;
+        let error = match tokio::time::timeout(timeout, wallet.redeem_pendings()).await {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(e.to_string()),
+            Err(_) => Some(format!("timed out after {}s", timeout_seconds)),
+        };
+        results.push(RedeemResult {
+            organization_id: organization.id,
+            organization_name: organization.name,
+            error,
+        });
+    }
+
+    let failed = results
+        .iter()
+        .filter(|result| result.error.is_some())
+        .count();
+    ctx.output.print(&results, |results| {
+        for result in results {
+            match &result.error {
+                None => println!(
+                    "{} ({}): pending proofs redeemed",
+                    result.organization_name, result.organization_id
+                ),
+                Some(e) => println!(
+                    "{} ({}): failed: {}",
+                    result.organization_name, result.organization_id, e
+                ),
+            }
+        }
+    })?;
+
+    if failed > 0 {
+        bail!("{} of {} wallet(s) failed", failed, results.len());
+    }
+    Ok(())
+}
+
+pub async fn wallet(ctx: &Context, command: WalletCommand) -> anyhow::Result<()> {
+    match command {
+        WalletCommand::Export { org, output } => export(ctx, org, output).await,
+        WalletCommand::Import { file, org, force } => import(ctx, file, org, force).await,
+    }
+}
+
+async fn export(ctx: &Context, org: Uuid, output: PathBuf) -> anyhow::Result<()> {
+    let organization = get_organization_by_id(&ctx.db, &org)
+        .await?
+        .ok_or_else(|| anyhow!("organization {} not found", org))?;
+    let seed = get_config_by_organization(&ctx.db, &org)
+        .await?
+        .and_then(|config| config.seed)
+        .ok_or_else(|| anyhow!("organization {} has no wallet seed", org))?;
+
+    let manager = MultimintManager::new(ctx.wallet_dir.clone(), ctx.db.clone());
+    let wallet_db_path = manager.wallet_db_path(&org);
+    ensure_checkpointed(&wallet_db_path)?;
+    let wallet_db = std::fs::read(&wallet_db_path)
+        .with_context(|| format!("failed to read {}", wallet_db_path))?;
+
+    let mints: Vec<BackupMint> = get_mints_for_organization(&ctx.db, &org)
+        .await?
+        .into_iter()
+        .map(|mint| BackupMint {
+            mint_url: mint.mint_url,
+            currency_unit: mint.currency_unit,
+            name: mint.name,
+        })
+        .collect();
+
+    let backup = WalletBackup {
+        version: BACKUP_VERSION,
+        created_at: Utc::now(),
+        organization_id: org,
+        organization_name: organization.name,
+        seed,
+        mints,
+        wallet_db: STANDARD.encode(&wallet_db),
+    };
+    write_private(&output, &serde_json::to_vec_pretty(&backup)?)
+        .with_context(|| format!("failed to write {}", output.display()))?;
+
+    eprintln!("warning: the backup contains the wallet seed; store it securely");
+    let report = ExportReport {
+        organization_id: org,
+        path: output,
+        mints: backup.mints.len(),
+        wallet_db_bytes: wallet_db.len(),
+    };
+    ctx.output.print(&report, |report| {
+        println!(
+            "Exported wallet of {} with {} mint(s) to {}",
+            report.organization_id,
+            report.mints,
+            report.path.display()
+        );
+    })
+}
+
+async fn import(
+    ctx: &Context,
+    file: PathBuf,
+    org: Option<Uuid>,
+    force: bool,
+) -> anyhow::Result<()> {
+    let (backup, wallet_db) = read_backup(&file)?;
+
+    let org = org.unwrap_or(backup.organization_id);
+    get_organization_by_id(&ctx.db, &org)
+        .await?
+        .ok_or_else(|| anyhow!("organization {} not found; create it first", org))?;
+
+    let manager = MultimintManager::new(ctx.wallet_dir.clone(), ctx.db.clone());
+    let wallet_db_path = manager.wallet_db_path(&org);
+    let config = get_config_by_organization(&ctx.db, &org).await?;
+    let existing_seed = config.as_ref().and_then(|config| config.seed.as_deref());
+    let seed_replaced = existing_seed.is_some_and(|seed| seed != backup.seed);
+
+    if !force {
+        if manager.has_wallet(&org) {
+            bail!(
+                "{} already exists; pass --force to replace it",
+                wallet_db_path
+            );
+        }
+        if seed_replaced {
+            bail!(
+                "organization {} has a different seed; pass --force to replace it",
+                org
+            );
+        }
+    }
+
+    // The wallet file goes first: if it can't be replaced, the seed must not
+    // be either, or the old proofs would be left under a seed they don't
+    // derive from.
+    let previous_wallet_db = restore_wallet_db(&wallet_db_path, &wallet_db)?;
+
+    match config {
+        Some(_) => update_seed(&ctx.db, &backup.seed, &org).await?,
+        None => create_with_seed_for_organization(&ctx.db, &backup.seed, &org).await?,
+    };
+
+    let mut mints_added = 0;
+    for mint in &backup.mints {
+        if get_mint_by_url_for_organization(&ctx.db, &mint.mint_url, &org)
+            .await?
+            .is_some()
+        {
+            continue;
+        }
+        create_mint_for_organization(
+            &ctx.db,
+            CreateMintRequest {
+                mint_url: mint.mint_url.clone(),
+                currency_unit: Some(mint.currency_unit.clone()),
+                name: mint.name.clone(),
+            },
+            &org,
+        )
+        .await?;
+        mints_added += 1;
+    }
+
+    let report = ImportReport {
+        organization_id: org,
+        mints_added,
+        seed_replaced,
+        wallet_db_path,
+        previous_wallet_db,
+    };
+    ctx.output.print(&report, |report| {
+        println!(
+            "Restored wallet of {} to {} ({} mint(s) added{})",
+            report.organization_id,
+            report.wallet_db_path,
+            report.mints_added,
+            if report.seed_replaced {
+                ", seed replaced"
+            } else {
+                ""
+            }
+        );
+        if let Some(previous) = &report.previous_wallet_db {
+            println!("The replaced wallet was moved to {}", previous.display());
+        }
+        println!("Restart otrta-ui to load the restored wallet.");
+    })
+}
+
+fn read_backup(file: &std::path::Path) -> anyhow::Result<(WalletBackup, Vec<u8>)> {
+    let contents =
+        std::fs::read(file).with_context(|| format!("failed to read {}", file.display()))?;
+    let backup: WalletBackup = serde_json::from_slice(&contents)
+        .with_context(|| format!("{} is not a wallet backup", file.display()))?;
+    if backup.version != BACKUP_VERSION {
+        bail!("unsupported backup version {}", backup.version);
+    }
+    let wallet_db = STANDARD
+        .decode(&backup.wallet_db)
+        .context("backup has a corrupt wallet database")?;
+    Ok((backup, wallet_db))
+}
+
+/// Fails while the wallet database has writes that only exist in its WAL,
+/// which happens while otrta-ui has it open.
+fn ensure_checkpointed(wallet_db_path: &str) -> anyhow::Result<()> {
+    let wal_path = format!("{}-wal", wallet_db_path);
+    if std::fs::metadata(&wal_path).is_ok_and(|wal| wal.len() > 0) {
+        bail!(
+            "{} has uncheckpointed writes; stop otrta-ui first",
+            wallet_db_path
+        );
+    }
+    Ok(())
+}
+
+/// Writes `wallet_db` to `wallet_db_path`, first moving any existing database
+/// aside to a timestamped `.bak` file, whose path is returned.
+fn restore_wallet_db(wallet_db_path: &str, wallet_db: &[u8]) -> anyhow::Result<Option<PathBuf>> {
+    ensure_checkpointed(wallet_db_path)?;
+
+    let previous = if std::path::Path::new(wallet_db_path).exists() {
+        let backup_path = PathBuf::from(format!(
+            "{}.{}.bak",
+            wallet_db_path,
+            Utc::now().format("%Y%m%dT%H%M%S%.3fZ")
+        ));
+        std::fs::rename(wallet_db_path, &backup_path).with_context(|| {
+            format!(
+                "failed to move {} to {}",
+                wallet_db_path,
+                backup_path.display()
+            )
+        })?;
+        Some(backup_path)
+    } else {
+        None
+    };
+
+    // Empty journal files are harmless but belong to the old database.
+    for suffix in ["-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", wallet_db_path, suffix));
+    }
+    write_private(std::path::Path::new(wallet_db_path), wallet_db)
+        .with_context(|| format!("failed to write {}", wallet_db_path))?;
+
+    Ok(previous)
+}
+
+/// Writes a file only the owner can read, since backups carry the seed. The
+/// contents go to a fresh 0600 file next to `path` that then replaces it, so
+/// an existing file's looser mode never applies and readers never see a
+/// partial write.
+fn write_private(path: &std::path::Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let file_name = path.file_name().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "path has no file name")
+    })?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(".{}.tmp", Uuid::new_v4().simple()));
+    let temp_path = path.with_file_name(temp_name);
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let written = options.open(&temp_path).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    });
+    match written.and_then(|()| std::fs::rename(&temp_path, path)) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp_path);
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("otrta-admin-{}", Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn path(&self, name: &str) -> String {
+            self.0.join(name).to_string_lossy().into_owned()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_backup_round_trip() {
+        let dir = TempDir::new();
+        let backup = WalletBackup {
+            version: BACKUP_VERSION,
+            created_at: Utc::now(),
+            organization_id: Uuid::new_v4(),
+            organization_name: "acme".to_string(),
+            seed: "seed words".to_string(),
+            mints: vec![BackupMint {
+                mint_url: "https://mint.example".to_string(),
+                currency_unit: "sat".to_string(),
+                name: None,
+            }],
+            wallet_db: STANDARD.encode(b"exported wallet"),
+        };
+        let file = PathBuf::from(dir.path("backup.json"));
+        // A file left readable by others doesn't stay that way
+        std::fs::write(&file, b"old backup").unwrap();
+        write_private(&file, &serde_json::to_vec_pretty(&backup).unwrap()).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&file).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let (restored, wallet_db) = read_backup(&file).unwrap();
+        assert_eq!(restored.organization_id, backup.organization_id);
+        assert_eq!(restored.seed, backup.seed);
+        assert_eq!(restored.mints.len(), 1);
+        assert_eq!(wallet_db, b"exported wallet");
+
+        // Into a fresh directory
+        let wallet_db_path = dir.path("wallet.sqlite");
+        assert_eq!(
+            restore_wallet_db(&wallet_db_path, &wallet_db).unwrap(),
+            None
+        );
+        assert_eq!(std::fs::read(&wallet_db_path).unwrap(), b"exported wallet");
+
+        // Over an existing wallet, which is kept
+        std::fs::write(&wallet_db_path, b"current wallet").unwrap();
+        std::fs::write(format!("{}-wal", wallet_db_path), b"").unwrap();
+        let previous = restore_wallet_db(&wallet_db_path, &wallet_db)
+            .unwrap()
+            .unwrap();
+        assert_eq!(std::fs::read(&previous).unwrap(), b"current wallet");
+        assert_eq!(std::fs::read(&wallet_db_path).unwrap(), b"exported wallet");
+        assert!(!std::path::Path::new(&format!("{}-wal", wallet_db_path)).exists());
+    }
+
+    #[test]
+    fn test_restore_refuses_a_wallet_in_use() {
+        let dir = TempDir::new();
+        let wallet_db_path = dir.path("wallet.sqlite");
+        std::fs::write(&wallet_db_path, b"current wallet").unwrap();
+        std::fs::write(format!("{}-wal", wallet_db_path), b"pending writes").unwrap();
+
+        assert!(restore_wallet_db(&wallet_db_path, b"exported wallet").is_err());
+        assert_eq!(std::fs::read(&wallet_db_path).unwrap(), b"current wallet");
+        assert_eq!(std::fs::read_dir(&dir.0).unwrap().count(), 2);
+    }
+}
@@ -2,6 +2,6 @@ pub mod nip91_discovery;
 
 // Re-export main types for easier access
 pub use nip91_discovery::{
-    Discovery, NostrProvider, Provider, ProviderContent, discover_providers,
-    get_updated_providers_since,
+    DEFAULT_RELAYS, Discovery, NostrProvider, Provider, ProviderContent, discover_providers,
+    discover_providers_from_relays, get_updated_providers_since,
 };
@@ -8,7 +8,8 @@ use tracing::{debug, info, warn};
 
 const PROVIDER_ANNOUNCEMENT_KIND: u16 = 38421;
 
-const DEFAULT_RELAYS: &[&str] = &[
+/// Relays queried for provider announcements unless others are configured.
+pub const DEFAULT_RELAYS: &[&str] = &[
     "wss://relay.damus.io",
     "wss://relay.snort.social",
     "wss://nos.lol",
@@ -55,19 +56,19 @@ fn parse_tags_to_map(tags: &Tags) -> HashMap<String, Vec<String>> {
 
     for tag in tags.iter() {
         let tag_str = format!("{:?}", tag);
-        if let Some(start) = tag_str.find('[') {
-            if let Some(end) = tag_str.find(']') {
-                let inner = &tag_str[start + 1..end];
-                let parts: Vec<&str> = inner
-                    .split(',')
-                    .map(|s| s.trim().trim_matches('"'))
-                    .collect();
-
-                if parts.len() >= 2 {
-                    let key = parts[0].to_string();
-                    let value = parts[1].to_string();
-                    map.entry(key).or_default().push(value);
-                }
+        if let Some(start) = tag_str.find('[')
+            && let Some(end) = tag_str.find(']')
+        {
+            let inner = &tag_str[start + 1..end];
+            let parts: Vec<&str> = inner
+                .split(',')
+                .map(|s| s.trim().trim_matches('"'))
+                .collect();
+
+            if parts.len() >= 2 {
+                let key = parts[0].to_string();
+                let value = parts[1].to_string();
+                map.entry(key).or_default().push(value);
             }
         }
     }
@@ -143,8 +144,6 @@ impl NostrProviderDiscovery {
             }
         }
 
-        if providers.is_empty() {}
-
         info!("Discovered {} providers", providers.len());
         Ok(providers)
     }
@@ -179,10 +178,10 @@ impl NostrProviderDiscovery {
             }
         }
 
-        if let Some(tag_version) = tag_map.get("version") {
-            if let Some(v) = tag_version.first() {
-                version = Some(v.clone());
-            }
+        if let Some(tag_version) = tag_map.get("version")
+            && let Some(v) = tag_version.first()
+        {
+            version = Some(v.clone());
         }
 
         if urls.is_empty() {
@@ -203,7 +202,7 @@ impl NostrProviderDiscovery {
             mints,
             version,
             created_at: DateTime::from_timestamp(event.created_at.as_u64() as i64, 0)
-                .unwrap_or_else(|| Utc::now()),
+                .unwrap_or_else(Utc::now),
             updated_at: Utc::now(),
             followers: 0,
             zaps: 0,
@@ -221,6 +220,11 @@ pub async fn discover_providers() -> Result<Vec<NostrProvider>> {
     discovery.discover_providers().await
 }
 
+pub async fn discover_providers_from_relays(relays: Vec<String>) -> Result<Vec<NostrProvider>> {
+    let discovery = NostrProviderDiscovery::with_relays(relays).await?;
+    discovery.discover_providers().await
+}
+
 pub async fn get_updated_providers_since(since: DateTime<Utc>) -> Result<Vec<NostrProvider>> {
     let discovery = NostrProviderDiscovery::new().await?;
     discovery.get_updated_providers(since).await
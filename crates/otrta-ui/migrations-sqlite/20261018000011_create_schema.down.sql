@@ -0,0 +1,26 @@
+DROP TABLE IF EXISTS background_job_runs;
+DROP TABLE IF EXISTS background_jobs;
+DROP TABLE IF EXISTS payment_intents;
+DROP TABLE IF EXISTS retention_policies;
+DROP TABLE IF EXISTS ledger_postings;
+DROP TABLE IF EXISTS ledger_entries;
+DROP TABLE IF EXISTS ledger_accounts;
+DROP TABLE IF EXISTS spending_budgets;
+DROP TABLE IF EXISTS exchange_rate_history;
+DROP TABLE IF EXISTS user_searches;
+DROP TABLE IF EXISTS user_search_groups;
+DROP TABLE IF EXISTS mint_auto_refill_settings;
+DROP TABLE IF EXISTS nwc_connections;
+DROP TABLE IF EXISTS model_pricing;
+DROP TABLE IF EXISTS models;
+DROP TABLE IF EXISTS organization_providers;
+DROP TABLE IF EXISTS providers;
+DROP TABLE IF EXISTS mint_units;
+DROP TABLE IF EXISTS mints;
+DROP TABLE IF EXISTS transaction_daily_rollups;
+DROP TABLE IF EXISTS transactions;
+DROP TABLE IF EXISTS credits;
+DROP TABLE IF EXISTS server_config;
+DROP TABLE IF EXISTS api_keys;
+DROP TABLE IF EXISTS users;
+DROP TABLE IF EXISTS organizations;
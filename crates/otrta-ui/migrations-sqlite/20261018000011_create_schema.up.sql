@@ -0,0 +1,429 @@
+-- SQLite baseline, equivalent to the Postgres migrations up to
+-- 20261018000011. UUIDs are stored as 16-byte blobs, timestamps as RFC 3339
+-- text and arrays as JSON text.
+
+CREATE TABLE organizations (
+    id BLOB PRIMARY KEY NOT NULL DEFAULT (randomblob(16)),
+    name VARCHAR(255) NOT NULL,
+    created_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    is_active BOOLEAN DEFAULT TRUE
+);
+CREATE INDEX idx_organizations_created_at ON organizations (created_at);
+CREATE INDEX idx_organizations_is_active ON organizations (is_active);
+
+INSERT INTO organizations (name) VALUES ('Default Organization');
+
+CREATE TABLE users (
+    npub VARCHAR(63) PRIMARY KEY NOT NULL,
+    display_name VARCHAR(255),
+    email VARCHAR(255),
+    created_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    last_login_at DATETIME,
+    is_active BOOLEAN DEFAULT TRUE,
+    organization_id BLOB NOT NULL REFERENCES organizations (id) ON DELETE RESTRICT
+);
+CREATE INDEX idx_users_created_at ON users (created_at);
+CREATE INDEX idx_users_email ON users (email) WHERE email IS NOT NULL;
+CREATE INDEX idx_users_is_active ON users (is_active);
+CREATE INDEX idx_users_organization_id ON users (organization_id);
+
+CREATE TABLE api_keys (
+    id BLOB PRIMARY KEY NOT NULL DEFAULT (randomblob(16)),
+    name VARCHAR(255) NOT NULL,
+    key VARCHAR(255) NOT NULL UNIQUE,
+    user_id VARCHAR(255),
+    organization_id VARCHAR(255),
+    last_used_at DATETIME,
+    expires_at DATETIME,
+    is_active BOOLEAN DEFAULT TRUE,
+    created_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+CREATE INDEX idx_api_keys_is_active ON api_keys (is_active);
+CREATE INDEX idx_api_keys_organization_id ON api_keys (organization_id);
+CREATE INDEX idx_api_keys_user_id ON api_keys (user_id);
+
+CREATE TABLE server_config (
+    id TEXT PRIMARY KEY NOT NULL,
+    endpoint TEXT NOT NULL,
+    api_key TEXT NOT NULL,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME,
+    seed TEXT,
+    organization_id BLOB NOT NULL REFERENCES organizations (id) ON DELETE CASCADE
+);
+CREATE INDEX idx_server_config_organization_id ON server_config (organization_id);
+
+CREATE TABLE credits (
+    id BLOB PRIMARY KEY NOT NULL,
+    created_at DATETIME NOT NULL,
+    token TEXT NOT NULL,
+    amount TEXT NOT NULL,
+    redeemed BOOLEAN NOT NULL
+);
+
+CREATE TABLE transactions (
+    id BLOB PRIMARY KEY NOT NULL,
+    created_at DATETIME NOT NULL,
+    token TEXT,
+    amount TEXT NOT NULL,
+    direction TEXT NOT NULL CHECK (direction IN ('Incoming', 'Outgoing')),
+    api_key_id BLOB,
+    user_id VARCHAR(63),
+    type TEXT NOT NULL DEFAULT 'api' CHECK (type IN ('chat', 'api')),
+    provider_url TEXT,
+    unit TEXT,
+    model TEXT,
+    fiat_amount DOUBLE PRECISION,
+    fiat_currency VARCHAR(16),
+    fiat_rate DOUBLE PRECISION,
+    token_hash VARCHAR(64),
+    token_mint TEXT,
+    token_keyset_ids TEXT,
+    token_expires_at DATETIME,
+    request_id VARCHAR(128)
+);
+CREATE INDEX idx_transactions_request_id ON transactions (request_id) WHERE request_id IS NOT NULL;
+CREATE INDEX idx_transactions_token_expires_at ON transactions (token_expires_at) WHERE token_expires_at IS NOT NULL;
+CREATE INDEX idx_transactions_token_hash ON transactions (token_hash);
+
+CREATE TABLE transaction_daily_rollups (
+    id BLOB PRIMARY KEY NOT NULL DEFAULT (randomblob(16)),
+    organization_id BLOB NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    date DATE NOT NULL,
+    api_key_id BLOB,
+    user_id VARCHAR(63),
+    direction TEXT NOT NULL CHECK (direction IN ('Incoming', 'Outgoing')),
+    unit TEXT NOT NULL,
+    model TEXT NOT NULL DEFAULT '',
+    transaction_count BIGINT NOT NULL DEFAULT 0,
+    amount BIGINT NOT NULL DEFAULT 0,
+    fiat_amount DOUBLE PRECISION NOT NULL DEFAULT 0.0,
+    created_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+CREATE INDEX idx_transaction_daily_rollups_api_key ON transaction_daily_rollups (api_key_id, date) WHERE api_key_id IS NOT NULL;
+CREATE UNIQUE INDEX idx_transaction_daily_rollups_unique ON transaction_daily_rollups (
+    organization_id, date, COALESCE(api_key_id, X'00000000000000000000000000000000'),
+    COALESCE(user_id, ''), direction, unit, model
+);
+
+CREATE TABLE mints (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    mint_url VARCHAR(500) NOT NULL,
+    currency_unit VARCHAR(50) DEFAULT 'Msat',
+    is_active BOOLEAN DEFAULT TRUE,
+    name VARCHAR(255),
+    created_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    organization_id BLOB,
+    UNIQUE (mint_url, organization_id)
+);
+
+CREATE TABLE mint_units (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    mint_id INTEGER NOT NULL REFERENCES mints (id) ON DELETE CASCADE,
+    unit VARCHAR(50) NOT NULL,
+    keyset_id VARCHAR(100) NOT NULL,
+    active BOOLEAN DEFAULT TRUE,
+    created_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    input_fee_ppk BIGINT NOT NULL DEFAULT 0,
+    UNIQUE (mint_id, unit)
+);
+CREATE INDEX idx_mint_units_active ON mint_units (active);
+CREATE INDEX idx_mint_units_mint_id ON mint_units (mint_id);
+
+CREATE TABLE providers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name VARCHAR(255) NOT NULL,
+    url VARCHAR(500) NOT NULL,
+    mints TEXT DEFAULT '[]',
+    use_onion BOOLEAN DEFAULT FALSE,
+    followers INTEGER DEFAULT 0,
+    zaps INTEGER DEFAULT 0,
+    is_default BOOLEAN DEFAULT FALSE,
+    created_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    is_custom BOOLEAN DEFAULT FALSE,
+    organization_id BLOB,
+    source VARCHAR(20) NOT NULL DEFAULT 'manual',
+    UNIQUE (url, source)
+);
+CREATE INDEX idx_providers_source ON providers (source);
+
+CREATE TABLE organization_providers (
+    organization_id BLOB NOT NULL,
+    provider_id INTEGER NOT NULL REFERENCES providers (id) ON DELETE CASCADE,
+    is_default BOOLEAN DEFAULT FALSE,
+    is_active BOOLEAN DEFAULT TRUE,
+    created_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    PRIMARY KEY (organization_id, provider_id)
+);
+CREATE INDEX idx_organization_providers_active ON organization_providers (organization_id, is_active);
+CREATE UNIQUE INDEX idx_organization_providers_default ON organization_providers (organization_id) WHERE is_default = TRUE;
+
+CREATE TABLE models (
+    id BLOB PRIMARY KEY NOT NULL DEFAULT (randomblob(16)),
+    name TEXT NOT NULL,
+    input_cost BIGINT NOT NULL DEFAULT 0,
+    output_cost BIGINT NOT NULL DEFAULT 0,
+    min_cash_per_request BIGINT NOT NULL DEFAULT 0,
+    min_cost_per_request BIGINT,
+    provider TEXT,
+    soft_deleted BOOLEAN DEFAULT FALSE,
+    model_type TEXT,
+    description TEXT,
+    context_length INTEGER,
+    is_free BOOLEAN DEFAULT FALSE,
+    created_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    last_seen_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    provider_id INTEGER NOT NULL REFERENCES providers (id) ON DELETE CASCADE,
+    modality TEXT,
+    input_modalities TEXT,
+    output_modalities TEXT,
+    tokenizer TEXT,
+    instruct_type TEXT,
+    created_timestamp BIGINT,
+    prompt_cost DOUBLE PRECISION,
+    completion_cost DOUBLE PRECISION,
+    request_cost DOUBLE PRECISION,
+    image_cost DOUBLE PRECISION,
+    web_search_cost DOUBLE PRECISION,
+    internal_reasoning_cost DOUBLE PRECISION,
+    max_cost DOUBLE PRECISION,
+    max_completion_tokens INTEGER,
+    is_moderated BOOLEAN,
+    UNIQUE (provider_id, name)
+);
+CREATE INDEX idx_models_last_seen_at ON models (last_seen_at);
+CREATE INDEX idx_models_name ON models (name);
+CREATE INDEX idx_models_provider_id ON models (provider_id);
+CREATE UNIQUE INDEX idx_models_provider_name_unique ON models (provider, name);
+CREATE INDEX idx_models_soft_deleted ON models (soft_deleted);
+
+CREATE TABLE model_pricing (
+    id BLOB PRIMARY KEY NOT NULL DEFAULT (randomblob(16)),
+    normalized_model_name VARCHAR NOT NULL,
+    provider_id INTEGER NOT NULL REFERENCES providers (id) ON DELETE CASCADE,
+    provider_name VARCHAR NOT NULL,
+    model_name VARCHAR NOT NULL,
+    input_cost BIGINT NOT NULL DEFAULT 0,
+    output_cost BIGINT NOT NULL DEFAULT 0,
+    min_cash_per_request BIGINT NOT NULL DEFAULT 0,
+    is_free BOOLEAN DEFAULT FALSE,
+    created_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    last_updated DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    UNIQUE (normalized_model_name, provider_id)
+);
+CREATE INDEX idx_model_pricing_is_free ON model_pricing (is_free);
+CREATE INDEX idx_model_pricing_normalized_name ON model_pricing (normalized_model_name);
+CREATE INDEX idx_model_pricing_provider_id ON model_pricing (provider_id);
+CREATE INDEX idx_model_pricing_updated ON model_pricing (last_updated);
+
+CREATE TABLE nwc_connections (
+    id BLOB PRIMARY KEY NOT NULL DEFAULT (randomblob(16)),
+    organization_id BLOB NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    connection_uri TEXT NOT NULL,
+    is_active BOOLEAN DEFAULT TRUE,
+    created_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+CREATE INDEX idx_nwc_connections_is_active ON nwc_connections (is_active);
+CREATE INDEX idx_nwc_connections_organization_id ON nwc_connections (organization_id);
+
+CREATE TABLE mint_auto_refill_settings (
+    id BLOB PRIMARY KEY NOT NULL DEFAULT (randomblob(16)),
+    mint_id INTEGER NOT NULL REFERENCES mints (id) ON DELETE CASCADE,
+    organization_id BLOB NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    nwc_connection_id BLOB NOT NULL REFERENCES nwc_connections (id) ON DELETE CASCADE,
+    min_balance_threshold_msat BIGINT NOT NULL DEFAULT 1000000,
+    refill_amount_msat BIGINT NOT NULL DEFAULT 10000000,
+    is_enabled BOOLEAN DEFAULT TRUE,
+    last_refill_at DATETIME,
+    created_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    UNIQUE (mint_id, organization_id)
+);
+CREATE INDEX idx_mint_auto_refill_settings_is_enabled ON mint_auto_refill_settings (is_enabled);
+CREATE INDEX idx_mint_auto_refill_settings_last_refill_at ON mint_auto_refill_settings (last_refill_at);
+CREATE INDEX idx_mint_auto_refill_settings_mint_id ON mint_auto_refill_settings (mint_id);
+CREATE INDEX idx_mint_auto_refill_settings_organization_id ON mint_auto_refill_settings (organization_id);
+
+CREATE TABLE user_search_groups (
+    id BLOB PRIMARY KEY NOT NULL DEFAULT (randomblob(16)),
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+CREATE INDEX idx_user_search_groups_created_at ON user_search_groups (created_at);
+CREATE INDEX idx_user_search_groups_user_id ON user_search_groups (user_id);
+
+CREATE TABLE user_searches (
+    id BLOB PRIMARY KEY NOT NULL DEFAULT (randomblob(16)),
+    user_id TEXT NOT NULL,
+    user_search_group_id BLOB NOT NULL REFERENCES user_search_groups (id) ON DELETE CASCADE,
+    created_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    name TEXT NOT NULL,
+    search TEXT NOT NULL,
+    status VARCHAR(20) DEFAULT 'completed',
+    started_at DATETIME,
+    completed_at DATETIME,
+    error_message TEXT,
+    search_metadata TEXT
+);
+CREATE INDEX idx_user_searches_created_at ON user_searches (created_at);
+CREATE INDEX idx_user_searches_group_id ON user_searches (user_search_group_id);
+CREATE INDEX idx_user_searches_group_status ON user_searches (user_search_group_id, status);
+CREATE INDEX idx_user_searches_status ON user_searches (user_id, status);
+CREATE INDEX idx_user_searches_user_id ON user_searches (user_id);
+
+CREATE TABLE exchange_rate_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    currency VARCHAR(16) NOT NULL,
+    rate DOUBLE PRECISION NOT NULL,
+    source VARCHAR(50) NOT NULL,
+    fetched_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+CREATE INDEX idx_exchange_rate_history_currency_fetched_at ON exchange_rate_history (currency, fetched_at DESC);
+
+CREATE TABLE spending_budgets (
+    id BLOB PRIMARY KEY NOT NULL DEFAULT (randomblob(16)),
+    organization_id BLOB NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    api_key_id BLOB REFERENCES api_keys (id) ON DELETE CASCADE,
+    amount DOUBLE PRECISION NOT NULL CHECK (amount >= 0),
+    currency VARCHAR(16) NOT NULL DEFAULT 'usd',
+    period VARCHAR(16) NOT NULL DEFAULT 'monthly'
+        CHECK (period IN ('daily', 'weekly', 'monthly', 'total')),
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+CREATE UNIQUE INDEX idx_spending_budgets_api_key ON spending_budgets (api_key_id) WHERE api_key_id IS NOT NULL;
+CREATE UNIQUE INDEX idx_spending_budgets_organization ON spending_budgets (organization_id) WHERE api_key_id IS NULL;
+
+CREATE TABLE ledger_accounts (
+    id BLOB PRIMARY KEY NOT NULL DEFAULT (randomblob(16)),
+    organization_id BLOB NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    account_type VARCHAR(16) NOT NULL
+        CHECK (account_type IN ('wallet', 'spend', 'fees', 'external')),
+    api_key_id BLOB,
+    user_id VARCHAR(63),
+    unit VARCHAR(32) NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+CREATE INDEX idx_ledger_accounts_api_key_id ON ledger_accounts (api_key_id) WHERE api_key_id IS NOT NULL;
+CREATE UNIQUE INDEX idx_ledger_accounts_unique ON ledger_accounts (
+    organization_id, account_type, COALESCE(api_key_id, X'00000000000000000000000000000000'),
+    COALESCE(user_id, ''), unit
+);
+CREATE INDEX idx_ledger_accounts_user_id ON ledger_accounts (user_id) WHERE user_id IS NOT NULL;
+
+CREATE TABLE ledger_entries (
+    id BLOB PRIMARY KEY NOT NULL DEFAULT (randomblob(16)),
+    request_id BLOB NOT NULL,
+    organization_id BLOB NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    entry_type VARCHAR(16) NOT NULL
+        CHECK (entry_type IN ('charge', 'change', 'refund', 'fee', 'deposit', 'withdrawal')),
+    amount BIGINT NOT NULL CHECK (amount >= 0),
+    unit VARCHAR(32) NOT NULL,
+    api_key_id BLOB,
+    user_id VARCHAR(63),
+    provider_url TEXT,
+    model TEXT,
+    fiat_amount DOUBLE PRECISION,
+    fiat_currency VARCHAR(16),
+    created_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    latency_ms INTEGER
+);
+CREATE INDEX idx_ledger_entries_api_key_created ON ledger_entries (api_key_id, created_at) WHERE api_key_id IS NOT NULL;
+CREATE INDEX idx_ledger_entries_organization_created ON ledger_entries (organization_id, created_at);
+CREATE INDEX idx_ledger_entries_request_id ON ledger_entries (request_id);
+
+CREATE TABLE ledger_postings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    entry_id BLOB NOT NULL REFERENCES ledger_entries (id) ON DELETE CASCADE,
+    account_id BLOB NOT NULL REFERENCES ledger_accounts (id) ON DELETE CASCADE,
+    amount BIGINT NOT NULL
+);
+CREATE INDEX idx_ledger_postings_account_id ON ledger_postings (account_id);
+CREATE INDEX idx_ledger_postings_entry_id ON ledger_postings (entry_id);
+
+CREATE TABLE retention_policies (
+    id BLOB PRIMARY KEY NOT NULL DEFAULT (randomblob(16)),
+    organization_id BLOB REFERENCES organizations (id) ON DELETE CASCADE,
+    transaction_retention_days INTEGER CHECK (transaction_retention_days > 0),
+    transaction_action VARCHAR(16) NOT NULL DEFAULT 'aggregate'
+        CHECK (transaction_action IN ('aggregate', 'delete')),
+    search_retention_days INTEGER CHECK (search_retention_days > 0),
+    credit_retention_days INTEGER CHECK (credit_retention_days > 0),
+    last_enforced_at DATETIME,
+    last_report TEXT,
+    created_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+CREATE UNIQUE INDEX idx_retention_policies_organization ON retention_policies (
+    COALESCE(organization_id, X'00000000000000000000000000000000')
+);
+
+CREATE TABLE payment_intents (
+    id BLOB PRIMARY KEY NOT NULL,
+    organization_id BLOB NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    state VARCHAR(32) NOT NULL DEFAULT 'created'
+        CHECK (state IN ('created', 'token_minted', 'upstream_sent', 'response_received',
+                         'completed', 'refunded', 'failed', 'recovered', 'spent')),
+    mint_url TEXT,
+    unit VARCHAR(32),
+    amount BIGINT,
+    token TEXT,
+    change_token TEXT,
+    api_key_id BLOB,
+    user_id VARCHAR(63),
+    provider_url TEXT,
+    model TEXT,
+    recovery_attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+CREATE INDEX idx_payment_intents_organization_id ON payment_intents (organization_id);
+CREATE INDEX idx_payment_intents_unfinished ON payment_intents (updated_at)
+    WHERE state IN ('created', 'token_minted', 'upstream_sent', 'response_received');
+
+CREATE TABLE background_jobs (
+    job_name VARCHAR(64) PRIMARY KEY NOT NULL,
+    holder VARCHAR(255),
+    lease_expires_at DATETIME,
+    last_started_at DATETIME,
+    last_finished_at DATETIME,
+    last_status VARCHAR(16) CHECK (last_status IN ('running', 'success', 'failure')),
+    last_error TEXT,
+    last_duration_ms BIGINT,
+    run_count BIGINT NOT NULL DEFAULT 0,
+    updated_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    default_interval_seconds INTEGER NOT NULL DEFAULT 300,
+    interval_seconds INTEGER CHECK (interval_seconds > 0),
+    paused BOOLEAN NOT NULL DEFAULT FALSE,
+    next_run_at DATETIME,
+    trigger_requested_at DATETIME
+);
+
+CREATE TABLE background_job_runs (
+    id BLOB PRIMARY KEY NOT NULL DEFAULT (randomblob(16)),
+    job_name VARCHAR(64) NOT NULL REFERENCES background_jobs (job_name) ON DELETE CASCADE,
+    instance_id VARCHAR(255) NOT NULL,
+    started_at DATETIME NOT NULL,
+    finished_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    status VARCHAR(16) NOT NULL CHECK (status IN ('success', 'failure')),
+    error TEXT,
+    duration_ms BIGINT NOT NULL,
+    triggered BOOLEAN NOT NULL DEFAULT FALSE
+);
+CREATE INDEX idx_background_job_runs_job_started ON background_job_runs (job_name, started_at DESC);
@@ -0,0 +1,16 @@
+-- Logins made with a single NIP-98 event. Access tokens are signed and carry
+-- the session ID, which is checked here on every request so a revoked
+-- session stops working at once. Refresh tokens are stored hashed.
+CREATE TABLE sessions (
+    id BLOB PRIMARY KEY NOT NULL DEFAULT (randomblob(16)),
+    npub VARCHAR(63) NOT NULL REFERENCES users (npub) ON DELETE CASCADE,
+    organization_id BLOB NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    refresh_token_hash VARCHAR(64) NOT NULL UNIQUE,
+    user_agent TEXT,
+    created_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    refreshed_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    expires_at DATETIME NOT NULL,
+    revoked_at DATETIME
+);
+CREATE INDEX idx_sessions_npub ON sessions (npub);
+CREATE INDEX idx_sessions_expires_at ON sessions (expires_at);
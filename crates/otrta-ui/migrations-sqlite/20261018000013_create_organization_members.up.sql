@@ -0,0 +1,20 @@
+-- A user's role in an organization. Existing users keep acting on their
+-- organization: the earliest user of each becomes its owner, later ones
+-- admins, so withdrawals need an explicit owner or billing role.
+CREATE TABLE organization_members (
+    organization_id BLOB NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    npub VARCHAR(63) NOT NULL REFERENCES users (npub) ON DELETE CASCADE,
+    role VARCHAR(16) NOT NULL
+        CHECK (role IN ('owner', 'admin', 'member', 'viewer', 'billing')),
+    created_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    PRIMARY KEY (organization_id, npub)
+);
+CREATE INDEX idx_organization_members_npub ON organization_members (npub);
+
+INSERT INTO organization_members (organization_id, npub, role)
+SELECT organization_id, npub,
+    CASE WHEN ROW_NUMBER() OVER (
+        PARTITION BY organization_id ORDER BY created_at, npub
+    ) = 1 THEN 'owner' ELSE 'admin' END
+FROM users;
@@ -0,0 +1,18 @@
+-- Invitations to join an organization, accepted when the invited npub next
+-- logs in. At most one can be pending per organization and npub.
+CREATE TABLE organization_invitations (
+    id BLOB PRIMARY KEY NOT NULL DEFAULT (randomblob(16)),
+    organization_id BLOB NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    npub VARCHAR(63) NOT NULL,
+    role VARCHAR(16) NOT NULL
+        CHECK (role IN ('owner', 'admin', 'member', 'viewer', 'billing')),
+    invited_by VARCHAR(63) NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    expires_at DATETIME NOT NULL,
+    accepted_at DATETIME,
+    revoked_at DATETIME
+);
+CREATE INDEX idx_organization_invitations_npub ON organization_invitations (npub);
+CREATE UNIQUE INDEX idx_organization_invitations_pending
+    ON organization_invitations (organization_id, npub)
+    WHERE accepted_at IS NULL AND revoked_at IS NULL;
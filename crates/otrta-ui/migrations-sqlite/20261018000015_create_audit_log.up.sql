@@ -0,0 +1,26 @@
+-- Append-only record of security-sensitive actions. Entries outlive the
+-- organizations and users they mention, so there are no foreign keys.
+CREATE TABLE audit_log (
+    id BLOB PRIMARY KEY NOT NULL DEFAULT (randomblob(16)),
+    organization_id BLOB NOT NULL,
+    actor_npub VARCHAR(63) NOT NULL,
+    action VARCHAR(64) NOT NULL,
+    target_type VARCHAR(32) NOT NULL,
+    target_id TEXT,
+    before TEXT,
+    after TEXT,
+    source_ip VARCHAR(64),
+    created_at DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+CREATE INDEX idx_audit_log_organization_created ON audit_log (organization_id, created_at DESC);
+CREATE INDEX idx_audit_log_actor ON audit_log (actor_npub);
+
+CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
+
+CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
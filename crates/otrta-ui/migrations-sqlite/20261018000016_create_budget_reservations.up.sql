@@ -0,0 +1,11 @@
+-- Budget headroom claimed by requests between the budget check and their
+-- charge entry. The charge deletes the reservation in the same transaction,
+-- so concurrent requests can't all pass the check on the same spend.
+CREATE TABLE budget_reservations (
+    request_id BLOB PRIMARY KEY NOT NULL,
+    organization_id BLOB NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    api_key_id BLOB REFERENCES api_keys (id) ON DELETE CASCADE,
+    fiat_amount DOUBLE PRECISION NOT NULL,
+    expires_at DATETIME NOT NULL
+);
+CREATE INDEX idx_budget_reservations_organization_id ON budget_reservations (organization_id);
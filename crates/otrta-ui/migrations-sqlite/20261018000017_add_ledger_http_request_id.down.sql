@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_ledger_entries_http_request_id;
+ALTER TABLE payment_intents DROP COLUMN http_request_id;
+ALTER TABLE ledger_entries DROP COLUMN http_request_id;
@@ -0,0 +1 @@
+ALTER TABLE mint_units DROP COLUMN IF EXISTS input_fee_ppk;
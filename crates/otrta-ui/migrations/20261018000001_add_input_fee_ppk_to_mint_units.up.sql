@@ -0,0 +1,2 @@
+-- Track the keyset input fee (parts per thousand per proof) for each mint unit
+ALTER TABLE mint_units ADD COLUMN input_fee_ppk BIGINT NOT NULL DEFAULT 0;
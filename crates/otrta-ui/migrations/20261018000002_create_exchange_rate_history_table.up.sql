@@ -0,0 +1,11 @@
+-- History of BTC exchange rates fetched from external or static sources
+CREATE TABLE exchange_rate_history (
+    id BIGSERIAL PRIMARY KEY,
+    currency VARCHAR(16) NOT NULL,
+    rate DOUBLE PRECISION NOT NULL,
+    source VARCHAR(50) NOT NULL,
+    fetched_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_exchange_rate_history_currency_fetched_at
+    ON exchange_rate_history(currency, fetched_at DESC);
@@ -0,0 +1,5 @@
+DROP TABLE IF EXISTS spending_budgets;
+
+ALTER TABLE transactions DROP COLUMN IF EXISTS fiat_rate;
+ALTER TABLE transactions DROP COLUMN IF EXISTS fiat_currency;
+ALTER TABLE transactions DROP COLUMN IF EXISTS fiat_amount;
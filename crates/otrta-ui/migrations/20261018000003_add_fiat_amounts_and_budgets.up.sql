@@ -0,0 +1,23 @@
+-- Fiat value of each transaction, computed with the exchange rate in effect when it was recorded
+ALTER TABLE transactions ADD COLUMN fiat_amount DOUBLE PRECISION;
+ALTER TABLE transactions ADD COLUMN fiat_currency VARCHAR(16);
+ALTER TABLE transactions ADD COLUMN fiat_rate DOUBLE PRECISION;
+
+-- Spending budgets expressed in fiat, per organization or per API key
+CREATE TABLE spending_budgets (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    api_key_id UUID REFERENCES api_keys(id) ON DELETE CASCADE,
+    amount DOUBLE PRECISION NOT NULL CHECK (amount >= 0),
+    currency VARCHAR(16) NOT NULL DEFAULT 'usd',
+    period VARCHAR(16) NOT NULL DEFAULT 'monthly'
+        CHECK (period IN ('daily', 'weekly', 'monthly', 'total')),
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_spending_budgets_organization
+    ON spending_budgets(organization_id) WHERE api_key_id IS NULL;
+CREATE UNIQUE INDEX idx_spending_budgets_api_key
+    ON spending_budgets(api_key_id) WHERE api_key_id IS NOT NULL;
@@ -0,0 +1,110 @@
+-- Double-entry ledger. Every payment-related event is a journal entry whose
+-- postings sum to zero. Amounts are integers in the canonical unit: msat for
+-- bitcoin mints, the mint's own minor unit for custom (fiat) units.
+CREATE TABLE ledger_accounts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    account_type VARCHAR(16) NOT NULL
+        CHECK (account_type IN ('wallet', 'spend', 'fees', 'external')),
+    api_key_id UUID,
+    user_id VARCHAR(63),
+    unit VARCHAR(32) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_ledger_accounts_unique ON ledger_accounts (
+    organization_id,
+    account_type,
+    COALESCE(api_key_id, '00000000-0000-0000-0000-000000000000'::uuid),
+    COALESCE(user_id, ''),
+    unit
+);
+CREATE INDEX idx_ledger_accounts_api_key_id ON ledger_accounts(api_key_id) WHERE api_key_id IS NOT NULL;
+CREATE INDEX idx_ledger_accounts_user_id ON ledger_accounts(user_id) WHERE user_id IS NOT NULL;
+
+CREATE TABLE ledger_entries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    request_id UUID NOT NULL,
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    entry_type VARCHAR(16) NOT NULL
+        CHECK (entry_type IN ('charge', 'change', 'refund', 'fee', 'deposit', 'withdrawal')),
+    amount BIGINT NOT NULL CHECK (amount >= 0),
+    unit VARCHAR(32) NOT NULL,
+    api_key_id UUID,
+    user_id VARCHAR(63),
+    provider_url TEXT,
+    model TEXT,
+    fiat_amount DOUBLE PRECISION,
+    fiat_currency VARCHAR(16),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_ledger_entries_request_id ON ledger_entries(request_id);
+CREATE INDEX idx_ledger_entries_organization_created ON ledger_entries(organization_id, created_at);
+CREATE INDEX idx_ledger_entries_api_key_created ON ledger_entries(api_key_id, created_at) WHERE api_key_id IS NOT NULL;
+
+CREATE TABLE ledger_postings (
+    id BIGSERIAL PRIMARY KEY,
+    entry_id UUID NOT NULL REFERENCES ledger_entries(id) ON DELETE CASCADE,
+    account_id UUID NOT NULL REFERENCES ledger_accounts(id) ON DELETE CASCADE,
+    amount BIGINT NOT NULL
+);
+
+CREATE INDEX idx_ledger_postings_entry_id ON ledger_postings(entry_id);
+CREATE INDEX idx_ledger_postings_account_id ON ledger_postings(account_id);
+
+-- Backfill from the flat transactions table. Old rows carry no request ID, so
+-- each one becomes its own entry; incoming rows can't be told apart from
+-- refunds and are booked as change.
+INSERT INTO ledger_entries (
+    id, request_id, organization_id, entry_type, amount, unit, api_key_id, user_id,
+    provider_url, model, fiat_amount, fiat_currency, created_at
+)
+SELECT
+    t.id,
+    t.id,
+    COALESCE(ak_org.id, u.organization_id),
+    CASE WHEN t.direction = 'Outgoing' THEN 'charge' ELSE 'change' END,
+    CASE WHEN LOWER(COALESCE(t.unit, 'msat')) = 'sat' THEN t.amount::bigint * 1000 ELSE t.amount::bigint END,
+    CASE WHEN LOWER(COALESCE(t.unit, 'msat')) IN ('sat', 'msat') THEN 'msat' ELSE LOWER(t.unit) END,
+    t.api_key_id,
+    t.user_id,
+    t.provider_url,
+    t.model,
+    t.fiat_amount,
+    t.fiat_currency,
+    t.created_at
+FROM transactions t
+LEFT JOIN api_keys ak ON t.api_key_id = ak.id
+LEFT JOIN organizations ak_org ON ak_org.id::text = ak.organization_id
+LEFT JOIN users u ON t.user_id = u.npub
+WHERE t.amount ~ '^[0-9]+$'
+  AND COALESCE(ak_org.id, u.organization_id) IS NOT NULL;
+
+INSERT INTO ledger_accounts (organization_id, account_type, unit)
+SELECT DISTINCT organization_id, 'wallet', unit FROM ledger_entries
+ON CONFLICT DO NOTHING;
+
+INSERT INTO ledger_accounts (organization_id, account_type, api_key_id, user_id, unit)
+SELECT DISTINCT organization_id, 'spend', api_key_id, user_id, unit FROM ledger_entries
+ON CONFLICT DO NOTHING;
+
+INSERT INTO ledger_postings (entry_id, account_id, amount)
+SELECT e.id, a.id, CASE WHEN e.entry_type = 'charge' THEN -e.amount ELSE e.amount END
+FROM ledger_entries e
+JOIN ledger_accounts a
+  ON a.organization_id = e.organization_id
+ AND a.account_type = 'wallet'
+ AND a.unit = e.unit
+WHERE e.amount > 0;
+
+INSERT INTO ledger_postings (entry_id, account_id, amount)
+SELECT e.id, a.id, CASE WHEN e.entry_type = 'charge' THEN e.amount ELSE -e.amount END
+FROM ledger_entries e
+JOIN ledger_accounts a
+  ON a.organization_id = e.organization_id
+ AND a.account_type = 'spend'
+ AND a.api_key_id IS NOT DISTINCT FROM e.api_key_id
+ AND a.user_id IS NOT DISTINCT FROM e.user_id
+ AND a.unit = e.unit
+WHERE e.amount > 0;
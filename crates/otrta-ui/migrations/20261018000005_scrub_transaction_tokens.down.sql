@@ -0,0 +1,9 @@
+-- Scrubbed tokens cannot be restored
+DROP INDEX IF EXISTS idx_transactions_token_expires_at;
+DROP INDEX IF EXISTS idx_transactions_token_hash;
+UPDATE transactions SET token = '' WHERE token IS NULL;
+ALTER TABLE transactions ALTER COLUMN token SET NOT NULL;
+ALTER TABLE transactions DROP COLUMN token_expires_at;
+ALTER TABLE transactions DROP COLUMN token_keyset_ids;
+ALTER TABLE transactions DROP COLUMN token_mint;
+ALTER TABLE transactions DROP COLUMN token_hash;
@@ -0,0 +1,18 @@
+-- Transactions keep a fingerprint of each Cashu token instead of the token
+-- itself. Full tokens are only stored when debug retention is enabled, and
+-- are scrubbed once token_expires_at passes.
+ALTER TABLE transactions ADD COLUMN token_hash VARCHAR(64);
+ALTER TABLE transactions ADD COLUMN token_mint TEXT;
+ALTER TABLE transactions ADD COLUMN token_keyset_ids TEXT[];
+ALTER TABLE transactions ADD COLUMN token_expires_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE transactions ALTER COLUMN token DROP NOT NULL;
+
+UPDATE transactions
+SET token_hash = encode(sha256(convert_to(btrim(token), 'UTF8')), 'hex')
+WHERE btrim(token) <> '';
+
+UPDATE transactions SET token = NULL;
+
+CREATE INDEX idx_transactions_token_hash ON transactions(token_hash);
+CREATE INDEX idx_transactions_token_expires_at ON transactions(token_expires_at)
+    WHERE token_expires_at IS NOT NULL;
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS transaction_daily_rollups;
+DROP TABLE IF EXISTS retention_policies;
@@ -0,0 +1,50 @@
+-- Data retention per organization. A row without organization_id is the
+-- instance default: it applies to organizations without their own policy and
+-- to data that isn't scoped to an organization (credits). NULL day counts
+-- mean "keep forever".
+CREATE TABLE retention_policies (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID REFERENCES organizations(id) ON DELETE CASCADE,
+    transaction_retention_days INTEGER CHECK (transaction_retention_days > 0),
+    transaction_action VARCHAR(16) NOT NULL DEFAULT 'aggregate'
+        CHECK (transaction_action IN ('aggregate', 'delete')),
+    search_retention_days INTEGER CHECK (search_retention_days > 0),
+    credit_retention_days INTEGER CHECK (credit_retention_days > 0),
+    last_enforced_at TIMESTAMP WITH TIME ZONE,
+    last_report JSONB,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_retention_policies_organization ON retention_policies (
+    COALESCE(organization_id, '00000000-0000-0000-0000-000000000000'::uuid)
+);
+
+-- Daily aggregates of transactions removed by retention
+CREATE TABLE transaction_daily_rollups (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    date DATE NOT NULL,
+    api_key_id UUID,
+    user_id VARCHAR(63),
+    direction transaction_direction NOT NULL,
+    unit TEXT NOT NULL,
+    model TEXT NOT NULL DEFAULT '',
+    transaction_count BIGINT NOT NULL DEFAULT 0,
+    amount BIGINT NOT NULL DEFAULT 0,
+    fiat_amount DOUBLE PRECISION NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_transaction_daily_rollups_unique ON transaction_daily_rollups (
+    organization_id,
+    date,
+    COALESCE(api_key_id, '00000000-0000-0000-0000-000000000000'::uuid),
+    COALESCE(user_id, ''),
+    direction,
+    unit,
+    model
+);
+CREATE INDEX idx_transaction_daily_rollups_api_key ON transaction_daily_rollups(api_key_id, date)
+    WHERE api_key_id IS NOT NULL;
@@ -0,0 +1,3 @@
+-- Time until the provider answered, recorded on every entry of a proxied
+-- request so usage exports can report it per request.
+ALTER TABLE ledger_entries ADD COLUMN latency_ms INTEGER;
@@ -0,0 +1,3 @@
+-- Correlates transactions with the HTTP request (X-Request-Id) that caused them
+ALTER TABLE transactions ADD COLUMN request_id VARCHAR(128);
+CREATE INDEX idx_transactions_request_id ON transactions(request_id) WHERE request_id IS NOT NULL;
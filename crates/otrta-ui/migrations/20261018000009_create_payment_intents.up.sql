@@ -0,0 +1,31 @@
+-- Write-ahead journal for proxied payments. An intent is created before the
+-- outgoing token is minted and advanced as the request progresses, so a
+-- crash mid-request leaves enough behind to reclaim the ecash. Tokens are
+-- spendable, so they are only kept while the intent is unfinished.
+CREATE TABLE payment_intents (
+    -- Same ID as the request's ledger entries.
+    id UUID PRIMARY KEY,
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    state VARCHAR(32) NOT NULL DEFAULT 'created'
+        CHECK (state IN (
+            'created', 'token_minted', 'upstream_sent', 'response_received',
+            'completed', 'refunded', 'failed', 'recovered', 'spent'
+        )),
+    mint_url TEXT,
+    unit VARCHAR(32),
+    amount BIGINT,
+    token TEXT,
+    change_token TEXT,
+    api_key_id UUID,
+    user_id VARCHAR(63),
+    provider_url TEXT,
+    model TEXT,
+    recovery_attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_payment_intents_unfinished ON payment_intents(updated_at)
+    WHERE state IN ('created', 'token_minted', 'upstream_sent', 'response_received');
+CREATE INDEX idx_payment_intents_organization_id ON payment_intents(organization_id);
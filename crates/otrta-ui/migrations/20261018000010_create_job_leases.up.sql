@@ -0,0 +1,16 @@
+-- One row per background job. Only the holder of an unexpired lease runs the
+-- job, so replicas sharing this database don't duplicate work; the rest of
+-- the row records the last run so any instance can report it.
+CREATE TABLE job_leases (
+    job_name VARCHAR(64) PRIMARY KEY,
+    holder VARCHAR(255),
+    lease_expires_at TIMESTAMP WITH TIME ZONE,
+    last_started_at TIMESTAMP WITH TIME ZONE,
+    last_finished_at TIMESTAMP WITH TIME ZONE,
+    last_status VARCHAR(16)
+        CHECK (last_status IN ('running', 'success', 'failure')),
+    last_error TEXT,
+    last_duration_ms BIGINT,
+    run_count BIGINT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
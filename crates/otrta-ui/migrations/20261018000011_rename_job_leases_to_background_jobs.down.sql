@@ -0,0 +1,10 @@
+DROP TABLE IF EXISTS background_job_runs;
+
+ALTER TABLE background_jobs
+    DROP COLUMN IF EXISTS default_interval_seconds,
+    DROP COLUMN IF EXISTS interval_seconds,
+    DROP COLUMN IF EXISTS paused,
+    DROP COLUMN IF EXISTS next_run_at,
+    DROP COLUMN IF EXISTS trigger_requested_at;
+
+ALTER TABLE background_jobs RENAME TO job_leases;
@@ -0,0 +1,25 @@
+-- Leases grow into the job scheduler's table: per-job interval overrides,
+-- pausing, manual triggers and the next scheduled run.
+ALTER TABLE job_leases RENAME TO background_jobs;
+
+ALTER TABLE background_jobs
+    ADD COLUMN default_interval_seconds INTEGER NOT NULL DEFAULT 300,
+    -- Set by an administrator; NULL falls back to the default.
+    ADD COLUMN interval_seconds INTEGER CHECK (interval_seconds > 0),
+    ADD COLUMN paused BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN next_run_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN trigger_requested_at TIMESTAMP WITH TIME ZONE;
+
+CREATE TABLE background_job_runs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    job_name VARCHAR(64) NOT NULL REFERENCES background_jobs(job_name) ON DELETE CASCADE,
+    instance_id VARCHAR(255) NOT NULL,
+    started_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    finished_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    status VARCHAR(16) NOT NULL CHECK (status IN ('success', 'failure')),
+    error TEXT,
+    duration_ms BIGINT NOT NULL,
+    triggered BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX idx_background_job_runs_job_started ON background_job_runs(job_name, started_at DESC);
@@ -0,0 +1,17 @@
+-- Logins made with a single NIP-98 event. Access tokens are signed and carry
+-- the session ID, which is checked here on every request so a revoked
+-- session stops working at once. Refresh tokens are stored hashed.
+CREATE TABLE sessions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    npub VARCHAR(63) NOT NULL REFERENCES users(npub) ON DELETE CASCADE,
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    refresh_token_hash VARCHAR(64) NOT NULL UNIQUE,
+    user_agent TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    refreshed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    revoked_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX idx_sessions_npub ON sessions(npub);
+CREATE INDEX idx_sessions_expires_at ON sessions(expires_at);
@@ -0,0 +1,19 @@
+-- Invitations to join an organization, accepted when the invited npub next
+-- logs in. At most one can be pending per organization and npub.
+CREATE TABLE organization_invitations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    npub VARCHAR(63) NOT NULL,
+    role VARCHAR(16) NOT NULL
+        CHECK (role IN ('owner', 'admin', 'member', 'viewer', 'billing')),
+    invited_by VARCHAR(63) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    accepted_at TIMESTAMP WITH TIME ZONE,
+    revoked_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX idx_organization_invitations_npub ON organization_invitations(npub);
+CREATE UNIQUE INDEX idx_organization_invitations_pending
+    ON organization_invitations(organization_id, npub)
+    WHERE accepted_at IS NULL AND revoked_at IS NULL;
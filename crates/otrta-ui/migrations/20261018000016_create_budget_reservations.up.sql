@@ -0,0 +1,12 @@
+-- Budget headroom claimed by requests between the budget check and their
+-- charge entry. The charge deletes the reservation in the same transaction,
+-- so concurrent requests can't all pass the check on the same spend.
+CREATE TABLE budget_reservations (
+    request_id UUID PRIMARY KEY,
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    api_key_id UUID REFERENCES api_keys(id) ON DELETE CASCADE,
+    fiat_amount DOUBLE PRECISION NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX idx_budget_reservations_organization_id ON budget_reservations(organization_id);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_ledger_entries_http_request_id;
+ALTER TABLE payment_intents DROP COLUMN IF EXISTS http_request_id;
+ALTER TABLE ledger_entries DROP COLUMN IF EXISTS http_request_id;
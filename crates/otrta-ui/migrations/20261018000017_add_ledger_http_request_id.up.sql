@@ -0,0 +1,7 @@
+-- Correlates ledger entries and payment intents with the HTTP request
+-- (X-Request-Id) that caused them. Their own request_id stays server-issued:
+-- it keys payment intents and budget reservations, and clients choose
+-- X-Request-Id.
+ALTER TABLE ledger_entries ADD COLUMN http_request_id VARCHAR(128);
+ALTER TABLE payment_intents ADD COLUMN http_request_id VARCHAR(128);
+CREATE INDEX idx_ledger_entries_http_request_id ON ledger_entries(http_request_id) WHERE http_request_id IS NOT NULL;
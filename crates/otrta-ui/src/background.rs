@@ -1,84 +1,222 @@
 use super::*;
+use connection::JobSettings;
+use otrta::auto_refill_service::{AutoRefillConfig, AutoRefillService};
+use otrta::db::sessions::delete_expired_sessions;
+use otrta::db::transaction::scrub_expired_tokens;
+use otrta::exchange_rate::REPORTING_CURRENCY;
 use otrta::handlers::refresh_models_background;
+use otrta::keyset_rotation::rotate_keysets;
+use otrta::metrics::record_job_run;
+use otrta::payment_recovery::recover_payment_intents;
+use otrta::retention::enforce_retention_policies;
+use otrta::scheduler::{JobResult, Scheduler};
+use std::time::Instant;
 use tokio::time::{Duration, interval};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 pub struct BackgroundJobRunner {
     app_state: Arc<AppState>,
+    jobs: JobSettings,
+    auto_refill: AutoRefillConfig,
 }
 
 impl BackgroundJobRunner {
-    pub fn new(app_state: Arc<AppState>) -> Self {
-        Self { app_state }
+    pub fn new(app_state: Arc<AppState>, jobs: JobSettings, auto_refill: AutoRefillConfig) -> Self {
+        Self {
+            app_state,
+            jobs,
+            auto_refill,
+        }
     }
 
     pub async fn start_all_jobs(&self) {
         info!("Starting background jobs...");
 
-        let state_clone = Arc::clone(&self.app_state);
-        tokio::spawn(async move {
-            Self::model_refresh_job(state_clone, 300).await;
-        });
+        let mut scheduler = Scheduler::new(Arc::clone(&self.app_state));
+        scheduler.register(
+            "model_refresh",
+            Duration::from_secs(self.jobs.model_refresh_interval_seconds),
+            Self::model_refresh_job,
+        );
+        scheduler.register(
+            "nostr_provider_discovery",
+            Duration::from_secs(self.jobs.nostr_provider_discovery_interval_seconds),
+            Self::nostr_provider_discovery_job,
+        );
+        scheduler.register(
+            "keyset_rotation",
+            Duration::from_secs(self.jobs.keyset_rotation_interval_seconds),
+            Self::keyset_rotation_job,
+        );
+        scheduler.register(
+            "token_scrub",
+            Duration::from_secs(self.jobs.token_scrub_interval_seconds),
+            Self::token_scrub_job,
+        );
+        scheduler.register(
+            "retention",
+            Duration::from_secs(self.jobs.retention_interval_seconds),
+            Self::retention_job,
+        );
+        // Never-run jobs are due immediately, so a fresh deployment also gets
+        // a startup recovery pass for payments cut off by a crash.
+        scheduler.register(
+            "payment_recovery",
+            Duration::from_secs(self.jobs.payment_recovery_interval_seconds),
+            Self::payment_recovery_job,
+        );
+
+        if self.auto_refill.enabled {
+            let service = Arc::new(AutoRefillService::new(
+                self.app_state.db.clone(),
+                self.app_state.multimint_manager.clone(),
+                self.auto_refill.min_refill_interval_minutes,
+            ));
+            scheduler.register(
+                "auto_refill",
+                Duration::from_secs(self.auto_refill.check_interval_seconds),
+                move |_| {
+                    let service = Arc::clone(&service);
+                    async move {
+                        service
+                            .check_and_refill_balances()
+                            .await
+                            .map_err(|e| e.to_string())
+                    }
+                },
+            );
+        } else {
+            info!("Auto-refill service is disabled");
+        }
+
+        scheduler.start().await;
 
         let state_clone = Arc::clone(&self.app_state);
         tokio::spawn(async move {
-            Self::nostr_provider_discovery_job(state_clone, 300).await;
+            Self::exchange_rate_refresh_job(state_clone, 300).await;
         });
     }
 
-    async fn model_refresh_job(app_state: Arc<AppState>, interval_secs: u64) {
-        let mut interval = interval(Duration::from_secs(interval_secs));
+    async fn model_refresh_job(app_state: Arc<AppState>) -> JobResult {
+        info!("Running background model refresh...");
+        let response = refresh_models_background(axum::extract::State(app_state))
+            .await
+            .map_err(|e| format!("{:?}", e))?;
+        info!("Model refresh completed successfully: {:?}", response.0);
+        Ok(())
+    }
+
+    async fn nostr_provider_discovery_job(app_state: Arc<AppState>) -> JobResult {
+        info!("Running background Nostr provider discovery...");
+        let (added, updated) = Self::discover_and_update_nostr_providers(&app_state)
+            .await
+            .map_err(|e| e.to_string())?;
         info!(
-            "Background model refresh job started with {}s interval",
-            interval_secs
+            "Nostr provider discovery completed successfully: {} added, {} updated",
+            added, updated
         );
+        Ok(())
+    }
 
-        loop {
-            interval.tick().await;
-            info!("Running background model refresh...");
-
-            match refresh_models_background(axum::extract::State(Arc::clone(&app_state))).await {
-                Ok(response) => {
-                    info!("Model refresh completed successfully: {:?}", response.0);
-                }
-                Err(e) => {
-                    error!("Model refresh failed: {:?}", e);
-                }
-            }
-        }
+    async fn keyset_rotation_job(app_state: Arc<AppState>) -> JobResult {
+        info!("Running background keyset rotation check...");
+        let summary = rotate_keysets(&app_state.db, &app_state.multimint_manager)
+            .await
+            .map_err(|e| e.to_string())?;
+        info!("Keyset rotation check completed: {:?}", summary);
+        Ok(())
     }
 
-    async fn nostr_provider_discovery_job(app_state: Arc<AppState>, interval_secs: u64) {
+    /// Keeps the reporting-currency rate fresh so transactions are valued at
+    /// a recent rate even when nothing else asks for one. Every instance runs
+    /// it, since each has its own rate cache.
+    async fn exchange_rate_refresh_job(app_state: Arc<AppState>, interval_secs: u64) {
         let mut interval = interval(Duration::from_secs(interval_secs));
         info!(
-            "Background Nostr provider discovery job started with {}s interval",
+            "Background exchange rate refresh job started with {}s interval",
             interval_secs
         );
 
         loop {
             interval.tick().await;
-            info!("Running background Nostr provider discovery...");
 
-            match Self::discover_and_update_nostr_providers(&app_state).await {
-                Ok((added, updated)) => {
+            let started = Instant::now();
+            let result = app_state.exchange_rates.get_rate(REPORTING_CURRENCY).await;
+            record_job_run("exchange_rate_refresh", started, result.is_ok());
+
+            match result {
+                Ok(rate) if rate.stale => {
+                    warn!(
+                        "Exchange rate refresh failed, using stale BTC/{} rate from {}",
+                        rate.currency, rate.fetched_at
+                    );
+                }
+                Ok(rate) => {
                     info!(
-                        "Nostr provider discovery completed successfully: {} added, {} updated",
-                        added, updated
+                        "Exchange rate refreshed: BTC/{} = {} ({})",
+                        rate.currency, rate.rate, rate.source
                     );
                 }
                 Err(e) => {
-                    error!("Nostr provider discovery failed: {:?}", e);
+                    error!("Exchange rate refresh failed: {:?}", e);
                 }
             }
         }
     }
 
+    async fn token_scrub_job(app_state: Arc<AppState>) -> JobResult {
+        let scrubbed = scrub_expired_tokens(&app_state.db)
+            .await
+            .map_err(|e| e.to_string())?;
+        if scrubbed > 0 {
+            info!("Scrubbed {} expired debug tokens", scrubbed);
+        }
+
+        let sessions = delete_expired_sessions(&app_state.db)
+            .await
+            .map_err(|e| e.to_string())?;
+        if sessions > 0 {
+            info!("Deleted {} expired or revoked sessions", sessions);
+        }
+        Ok(())
+    }
+
+    async fn retention_job(app_state: Arc<AppState>) -> JobResult {
+        info!("Running background retention enforcement...");
+        let reports = enforce_retention_policies(&app_state.db)
+            .await
+            .map_err(|e| e.to_string())?;
+        info!(
+            "Retention enforcement completed for {} policies",
+            reports.len()
+        );
+        Ok(())
+    }
+
+    async fn payment_recovery_job(app_state: Arc<AppState>) -> JobResult {
+        let report = recover_payment_intents(&app_state)
+            .await
+            .map_err(|e| e.to_string())?;
+        if report.examined > 0 {
+            info!(
+                "Payment recovery examined {} intents: {} recovered, {} spent, {} failed, {} unresolved",
+                report.examined, report.recovered, report.spent, report.failed, report.unresolved
+            );
+        }
+        Ok(())
+    }
+
     async fn discover_and_update_nostr_providers(
         app_state: &AppState,
     ) -> Result<(usize, usize), Box<dyn std::error::Error + Send + Sync>> {
         use otrta::db::provider::refresh_providers_from_nostr_global;
 
-        match refresh_providers_from_nostr_global(&app_state.db).await {
+        match refresh_providers_from_nostr_global(
+            &app_state.db,
+            app_state.runtime_config().nostr_relays,
+        )
+        .await
+        {
             Ok(response) => {
                 info!("{}", response.message.unwrap_or_default());
                 Ok((
@@ -88,10 +226,10 @@ impl BackgroundJobRunner {
             }
             Err(e) => {
                 error!("Failed to refresh providers from Nostr: {}", e);
-                Err(Box::new(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("Provider refresh failed: {}", e),
-                )))
+                Err(Box::new(std::io::Error::other(format!(
+                    "Provider refresh failed: {}",
+                    e
+                ))))
             }
         }
     }
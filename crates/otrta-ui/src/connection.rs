@@ -1,11 +1,37 @@
+use otrta::{
+    auto_refill_service::AutoRefillConfig,
+    exchange_rate::{ExchangeRateConfig, PROVIDER_NAMES, StaticRateProvider},
+    forwarded::TrustedProxies,
+    models::RuntimeConfig,
+    scheduler::MIN_JOB_INTERVAL,
+};
 use secrecy::{ExposeSecret, SecretString};
 use serde_aux::field_attributes::deserialize_number_from_string;
+#[cfg(not(feature = "sqlite"))]
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
+#[cfg(feature = "sqlite")]
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode};
+use std::time::Duration;
+
+/// Config file read when `OTRTA_CONFIG` is unset, if it exists.
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+/// Longest time full Cashu tokens may be kept for debugging.
+const MAX_DEBUG_TOKEN_RETENTION_SECONDS: u64 = 7 * 24 * 3600;
 
 #[derive(Debug, serde::Deserialize, Clone)]
 pub struct Settings {
     pub database: DatabaseSettings,
     pub application: ApplicationSettings,
+    pub tor: TorSettings,
+    pub nostr: NostrSettings,
+    pub jobs: JobSettings,
+    pub auto_refill: AutoRefillConfig,
+    pub sessions: SessionSettings,
+    pub exchange_rates: ExchangeRateConfig,
+    #[serde(default)]
+    pub metrics: MetricsSettings,
+    pub debug: DebugSettings,
 }
 
 #[derive(Debug, serde::Deserialize, Clone)]
@@ -16,22 +42,375 @@ pub struct ApplicationSettings {
     pub default_msats_per_request: u32,
     #[allow(dead_code)]
     pub mint_url: String,
+    pub wallet_data_dir: String,
+    pub shutdown_timeout_seconds: u64,
     #[serde(default)]
     pub enable_authentication: bool,
-    #[serde(default, deserialize_with = "deserialize_npubs")]
+    #[serde(default, deserialize_with = "deserialize_string_list")]
     pub whitelisted_npubs: Vec<String>,
+    /// Reverse proxies allowed to set `X-Forwarded-*` headers, as IP
+    /// addresses or CIDR networks.
+    #[serde(default, deserialize_with = "deserialize_string_list")]
+    pub trusted_proxies: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize, Clone)]
+pub struct TorSettings {
+    pub socks_proxy: String,
 }
 
-fn deserialize_npubs<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+#[derive(Debug, serde::Deserialize, Clone)]
+pub struct NostrSettings {
+    #[serde(deserialize_with = "deserialize_string_list")]
+    pub relays: Vec<String>,
+}
+
+/// Default interval of each scheduled job. Admin overrides set through
+/// `/api/jobs` take precedence.
+#[derive(Debug, serde::Deserialize, Clone)]
+pub struct JobSettings {
+    pub model_refresh_interval_seconds: u64,
+    pub nostr_provider_discovery_interval_seconds: u64,
+    pub keyset_rotation_interval_seconds: u64,
+    pub token_scrub_interval_seconds: u64,
+    pub retention_interval_seconds: u64,
+    pub payment_recovery_interval_seconds: u64,
+}
+
+/// Session tokens handed out by `/api/auth/login`.
+#[derive(Debug, serde::Deserialize, Clone)]
+pub struct SessionSettings {
+    /// Key signing access tokens. Replicas must share it. When unset, a
+    /// random key is used and sessions end when the process restarts.
+    #[serde(default)]
+    pub secret: Option<SecretString>,
+    pub access_token_ttl_seconds: u64,
+    /// How long a session stays usable without being refreshed.
+    pub refresh_token_ttl_seconds: u64,
+}
+
+#[derive(Debug, Default, serde::Deserialize, Clone)]
+pub struct MetricsSettings {
+    /// Bearer token `/metrics` requires. When unset the endpoint is open, or
+    /// not served at all when authentication is enabled.
+    #[serde(default)]
+    pub token: Option<SecretString>,
+}
+
+#[derive(Debug, serde::Deserialize, Clone)]
+pub struct DebugSettings {
+    /// How long full Cashu tokens are kept on their transactions for
+    /// debugging. 0 turns it off; at most a week.
+    pub token_retention_seconds: u64,
+}
+
+impl Settings {
+    /// Default interval of every job the scheduler runs, by job name.
+    pub fn job_intervals(&self) -> Vec<(&'static str, Duration)> {
+        let jobs = &self.jobs;
+        let mut intervals = vec![
+            ("model_refresh", jobs.model_refresh_interval_seconds),
+            (
+                "nostr_provider_discovery",
+                jobs.nostr_provider_discovery_interval_seconds,
+            ),
+            ("keyset_rotation", jobs.keyset_rotation_interval_seconds),
+            ("token_scrub", jobs.token_scrub_interval_seconds),
+            ("retention", jobs.retention_interval_seconds),
+            ("payment_recovery", jobs.payment_recovery_interval_seconds),
+        ];
+        if self.auto_refill.enabled {
+            intervals.push(("auto_refill", self.auto_refill.check_interval_seconds));
+        }
+        intervals
+            .into_iter()
+            .map(|(name, seconds)| (name, Duration::from_secs(seconds)))
+            .collect()
+    }
+
+    pub fn runtime_config(&self) -> RuntimeConfig {
+        RuntimeConfig {
+            whitelisted_npubs: self.application.whitelisted_npubs.clone(),
+            nostr_relays: self.nostr.relays.clone(),
+            trusted_proxies: TrustedProxies::parse(&self.application.trusted_proxies)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Settings that differ from `other` but only take effect after a
+    /// restart.
+    pub fn restart_required_changes(&self, other: &Settings) -> Vec<&'static str> {
+        let (app, other_app) = (&self.application, &other.application);
+        let (db, other_db) = (&self.database, &other.database);
+        let (rates, other_rates) = (&self.exchange_rates, &other.exchange_rates);
+        [
+            ("application.host", app.host != other_app.host),
+            ("application.port", app.port != other_app.port),
+            (
+                "application.default_msats_per_request",
+                app.default_msats_per_request != other_app.default_msats_per_request,
+            ),
+            ("application.mint_url", app.mint_url != other_app.mint_url),
+            (
+                "application.wallet_data_dir",
+                app.wallet_data_dir != other_app.wallet_data_dir,
+            ),
+            (
+                "application.shutdown_timeout_seconds",
+                app.shutdown_timeout_seconds != other_app.shutdown_timeout_seconds,
+            ),
+            (
+                "application.enable_authentication",
+                app.enable_authentication != other_app.enable_authentication,
+            ),
+            (
+                "database",
+                db.host != other_db.host
+                    || db.port != other_db.port
+                    || db.username != other_db.username
+                    || db.password.expose_secret() != other_db.password.expose_secret()
+                    || db.database_name != other_db.database_name
+                    || db.require_ssl != other_db.require_ssl
+                    || db.connections != other_db.connections
+                    || db.path != other_db.path,
+            ),
+            (
+                "sessions",
+                self.sessions.secret.as_ref().map(|s| s.expose_secret())
+                    != other.sessions.secret.as_ref().map(|s| s.expose_secret())
+                    || self.sessions.access_token_ttl_seconds
+                        != other.sessions.access_token_ttl_seconds
+                    || self.sessions.refresh_token_ttl_seconds
+                        != other.sessions.refresh_token_ttl_seconds,
+            ),
+            (
+                "tor.socks_proxy",
+                self.tor.socks_proxy != other.tor.socks_proxy,
+            ),
+            (
+                "auto_refill.enabled",
+                self.auto_refill.enabled != other.auto_refill.enabled,
+            ),
+            (
+                "auto_refill.min_refill_interval_minutes",
+                self.auto_refill.min_refill_interval_minutes
+                    != other.auto_refill.min_refill_interval_minutes,
+            ),
+            (
+                "exchange_rates",
+                rates.providers != other_rates.providers
+                    || rates.static_rates != other_rates.static_rates
+                    || rates.static_rates_file != other_rates.static_rates_file
+                    || rates.cache_ttl_seconds != other_rates.cache_ttl_seconds
+                    || rates.max_age_seconds != other_rates.max_age_seconds
+                    || rates.max_stale_seconds != other_rates.max_stale_seconds,
+            ),
+            (
+                "metrics.token",
+                self.metrics.token.as_ref().map(|t| t.expose_secret())
+                    != other.metrics.token.as_ref().map(|t| t.expose_secret()),
+            ),
+            (
+                "debug.token_retention_seconds",
+                self.debug.token_retention_seconds != other.debug.token_retention_seconds,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(name, changed)| changed.then_some(name))
+        .collect()
+    }
+
+    /// Checks values that deserialize fine but can't work, so startup fails
+    /// with every problem listed instead of misbehaving later.
+    pub fn validate(&self) -> Result<(), config::ConfigError> {
+        let mut problems = Vec::new();
+        let app = &self.application;
+
+        if app.host.trim().is_empty() {
+            problems.push("application.host must not be empty".to_string());
+        }
+        if app.port == 0 {
+            problems.push("application.port must not be 0".to_string());
+        }
+        if app.default_msats_per_request == 0 {
+            problems.push("application.default_msats_per_request must be positive".to_string());
+        }
+        if !has_scheme(&app.mint_url, &["http", "https"]) {
+            problems.push(format!(
+                "application.mint_url must be an http(s) URL, got {:?}",
+                app.mint_url
+            ));
+        }
+        if app.wallet_data_dir.trim().is_empty() {
+            problems.push("application.wallet_data_dir must not be empty".to_string());
+        }
+        if app.shutdown_timeout_seconds == 0 {
+            problems.push("application.shutdown_timeout_seconds must be positive".to_string());
+        }
+        for npub in &app.whitelisted_npubs {
+            // Bech32 npubs are always 63 characters.
+            if !npub.starts_with("npub1") || npub.len() != 63 {
+                problems.push(format!(
+                    "application.whitelisted_npubs contains an invalid npub: {:?}",
+                    npub
+                ));
+            }
+        }
+        if let Err(e) = TrustedProxies::parse(&app.trusted_proxies) {
+            problems.push(format!("application.trusted_proxies: {}", e));
+        }
+
+        if self.database.connections == 0 {
+            problems.push("database.connections must be positive".to_string());
+        }
+        if self.database.database_name.trim().is_empty() {
+            problems.push("database.database_name must not be empty".to_string());
+        }
+
+        if !has_scheme(&self.tor.socks_proxy, &["socks5", "socks5h"]) {
+            problems.push(format!(
+                "tor.socks_proxy must be a socks5:// or socks5h:// URL, got {:?}",
+                self.tor.socks_proxy
+            ));
+        }
+
+        if self.nostr.relays.is_empty() {
+            problems.push("nostr.relays must list at least one relay".to_string());
+        }
+        for relay in &self.nostr.relays {
+            if !has_scheme(relay, &["ws", "wss"]) {
+                problems.push(format!(
+                    "nostr.relays must be ws:// or wss:// URLs, got {:?}",
+                    relay
+                ));
+            }
+        }
+
+        if self
+            .sessions
+            .secret
+            .as_ref()
+            .is_some_and(|secret| secret.expose_secret().len() < 32)
+        {
+            problems.push("sessions.secret must be at least 32 characters".to_string());
+        }
+        if self.sessions.access_token_ttl_seconds == 0 {
+            problems.push("sessions.access_token_ttl_seconds must be positive".to_string());
+        }
+        if self.sessions.refresh_token_ttl_seconds < self.sessions.access_token_ttl_seconds {
+            problems.push(
+                "sessions.refresh_token_ttl_seconds must not be shorter than access_token_ttl_seconds"
+                    .to_string(),
+            );
+        }
+
+        let rates = &self.exchange_rates;
+        let providers: Vec<_> = rates
+            .providers
+            .split(',')
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+        if providers.is_empty() {
+            problems.push("exchange_rates.providers must name at least one provider".to_string());
+        }
+        for name in &providers {
+            if !PROVIDER_NAMES.contains(&name.as_str()) {
+                problems.push(format!(
+                    "exchange_rates.providers contains an unknown provider {:?}, expected one of {}",
+                    name,
+                    PROVIDER_NAMES.join(", ")
+                ));
+            }
+        }
+        if providers.iter().any(|name| name == "static")
+            && rates.static_rates.is_none()
+            && rates.static_rates_file.is_none()
+        {
+            problems.push(
+                "exchange_rates.providers includes static but neither static_rates nor static_rates_file is set"
+                    .to_string(),
+            );
+        }
+        if let Some(Err(e)) = rates
+            .static_rates
+            .as_deref()
+            .map(StaticRateProvider::from_spec)
+        {
+            problems.push(format!("exchange_rates.static_rates is invalid: {}", e));
+        }
+        if rates.max_age_seconds == 0 {
+            problems.push("exchange_rates.max_age_seconds must be positive".to_string());
+        }
+        if rates.max_stale_seconds < rates.max_age_seconds {
+            problems.push(
+                "exchange_rates.max_stale_seconds must not be shorter than max_age_seconds"
+                    .to_string(),
+            );
+        }
+
+        if self
+            .metrics
+            .token
+            .as_ref()
+            .is_some_and(|token| token.expose_secret().len() < 16)
+        {
+            problems.push("metrics.token must be at least 16 characters".to_string());
+        }
+
+        if self.debug.token_retention_seconds > MAX_DEBUG_TOKEN_RETENTION_SECONDS {
+            problems.push(format!(
+                "debug.token_retention_seconds must be at most {} (a week)",
+                MAX_DEBUG_TOKEN_RETENTION_SECONDS
+            ));
+        }
+
+        let mut intervals = self.job_intervals();
+        if !self.auto_refill.enabled {
+            intervals.push((
+                "auto_refill",
+                Duration::from_secs(self.auto_refill.check_interval_seconds),
+            ));
+        }
+        for (name, interval) in intervals {
+            if interval < MIN_JOB_INTERVAL {
+                problems.push(format!(
+                    "interval of job {} must be at least {}s, got {}s",
+                    name,
+                    MIN_JOB_INTERVAL.as_secs(),
+                    interval.as_secs()
+                ));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(config::ConfigError::Message(format!(
+                "invalid configuration:\n  - {}",
+                problems.join("\n  - ")
+            )))
+        }
+    }
+}
+
+fn has_scheme(url: &str, schemes: &[&str]) -> bool {
+    reqwest::Url::parse(url)
+        .is_ok_and(|url| schemes.contains(&url.scheme()) && url.host_str().is_some())
+}
+
+/// Accepts either a list or a comma-separated string, as env vars can only
+/// carry the latter.
+fn deserialize_string_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
     use serde::de::{self, Deserialize, Visitor};
     use std::fmt;
 
-    struct NpubsVisitor;
+    struct StringListVisitor;
 
-    impl<'de> Visitor<'de> for NpubsVisitor {
+    impl<'de> Visitor<'de> for StringListVisitor {
         type Value = Vec<String>;
 
         fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
@@ -43,7 +422,11 @@ where
             E: de::Error,
         {
             // Handle comma-separated string
-            Ok(value.split(',').map(|s| s.trim().to_string()).collect())
+            Ok(value
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect())
         }
 
         fn visit_seq<S>(self, seq: S) -> Result<Vec<String>, S::Error>
@@ -55,7 +438,7 @@ where
         }
     }
 
-    deserializer.deserialize_any(NpubsVisitor)
+    deserializer.deserialize_any(StringListVisitor)
 }
 
 #[derive(Debug, serde::Deserialize, Clone)]
@@ -68,8 +451,14 @@ pub struct DatabaseSettings {
     pub database_name: String,
     pub require_ssl: bool,
     pub connections: u32,
+    /// SQLite database file, used instead of the Postgres settings when built
+    /// with the `sqlite` feature. Defaults to `otrta.sqlite` under the wallet
+    /// data directory.
+    #[serde(default)]
+    pub path: Option<String>,
 }
 
+#[cfg(not(feature = "sqlite"))]
 impl DatabaseSettings {
     pub fn without_db(&self) -> PgConnectOptions {
         let ssl_mode = if self.require_ssl {
@@ -91,8 +480,26 @@ impl DatabaseSettings {
     }
 }
 
+#[cfg(feature = "sqlite")]
+impl DatabaseSettings {
+    pub fn with_db(&self) -> SqliteConnectOptions {
+        SqliteConnectOptions::new()
+            .filename(self.path.as_deref().unwrap_or("otrta.sqlite"))
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .foreign_keys(true)
+    }
+}
+
+/// Loads the configuration from defaults, then the TOML file named by
+/// `OTRTA_CONFIG` (or `config.toml` if present), then environment variables,
+/// and validates the result.
 pub fn get_configuration() -> Result<Settings, config::ConfigError> {
-    // Only load from environment variables (and .env via dotenv in main.rs)
+    let config_file = match std::env::var("OTRTA_CONFIG") {
+        Ok(path) if !path.is_empty() => config::File::with_name(&path).required(true),
+        _ => config::File::with_name(DEFAULT_CONFIG_FILE).required(false),
+    };
+
     let settings = config::Config::builder()
         // Application defaults
         .set_default("application.port", 3333)?
@@ -101,6 +508,9 @@ pub fn get_configuration() -> Result<Settings, config::ConfigError> {
         .set_default("application.mint_url", "https://ecashmint.otrta.me")?
         .set_default("application.enable_authentication", true)?
         .set_default("application.whitelisted_npubs", Vec::<String>::new())?
+        .set_default("application.trusted_proxies", Vec::<String>::new())?
+        .set_default("application.wallet_data_dir", "/multimint")?
+        .set_default("application.shutdown_timeout_seconds", 60)?
         // Database defaults
         .set_default("database.host", "localhost")?
         .set_default("database.port", 5432)?
@@ -109,6 +519,31 @@ pub fn get_configuration() -> Result<Settings, config::ConfigError> {
         .set_default("database.database_name", "otrta")?
         .set_default("database.require_ssl", false)?
         .set_default("database.connections", 10)?
+        .set_default("tor.socks_proxy", "socks5h://127.0.0.1:9050")?
+        .set_default(
+            "nostr.relays",
+            otrta_nostr::DEFAULT_RELAYS
+                .iter()
+                .map(|relay| relay.to_string())
+                .collect::<Vec<_>>(),
+        )?
+        .set_default("jobs.model_refresh_interval_seconds", 300)?
+        .set_default("jobs.nostr_provider_discovery_interval_seconds", 300)?
+        .set_default("jobs.keyset_rotation_interval_seconds", 3600)?
+        .set_default("jobs.token_scrub_interval_seconds", 300)?
+        .set_default("jobs.retention_interval_seconds", 3600)?
+        .set_default("jobs.payment_recovery_interval_seconds", 300)?
+        .set_default("sessions.access_token_ttl_seconds", 900)?
+        .set_default("sessions.refresh_token_ttl_seconds", 30 * 24 * 3600)?
+        .set_default("auto_refill.enabled", true)?
+        .set_default("auto_refill.check_interval_seconds", 300)?
+        .set_default("auto_refill.min_refill_interval_minutes", 60)?
+        .set_default("exchange_rates.providers", "coingecko,coinbase,binance")?
+        .set_default("exchange_rates.cache_ttl_seconds", 300)?
+        .set_default("exchange_rates.max_age_seconds", 3600)?
+        .set_default("exchange_rates.max_stale_seconds", 86400)?
+        .set_default("debug.token_retention_seconds", 0)?
+        .add_source(config_file)
         // Namespaced APP_* variables like APP_APPLICATION__PORT=3333
         .add_source(
             config::Environment::with_prefix("APP")
@@ -128,6 +563,7 @@ pub fn get_configuration() -> Result<Settings, config::ConfigError> {
         // Common aliases without namespaces (DB_*, POSTGRES_*, etc.)
         .add_source(
             config::Environment::default()
+                .separator("__")
                 .try_parsing(true)
                 .ignore_empty(true)
                 .source(Some({
@@ -155,6 +591,9 @@ pub fn get_configuration() -> Result<Settings, config::ConfigError> {
                     if let Ok(val) = std::env::var("DB_CONNECTIONS") {
                         env_map.insert("database__connections".to_string(), val);
                     }
+                    if let Ok(val) = std::env::var("DB_PATH") {
+                        env_map.insert("database__path".to_string(), val);
+                    }
 
                     // PostgreSQL standard environment variables (POSTGRES_* pattern)
                     if let Ok(val) = std::env::var("POSTGRES_HOST") {
@@ -193,11 +632,173 @@ pub fn get_configuration() -> Result<Settings, config::ConfigError> {
                     if let Ok(val) = std::env::var("WHITELISTED_NPUBS") {
                         env_map.insert("application__whitelisted_npubs".to_string(), val);
                     }
+                    if let Ok(val) = std::env::var("TRUSTED_PROXIES") {
+                        env_map.insert("application__trusted_proxies".to_string(), val);
+                    }
+                    if let Ok(val) = std::env::var("WALLET_DATA_DIR") {
+                        env_map.insert("application__wallet_data_dir".to_string(), val);
+                    }
+                    if let Ok(val) = std::env::var("SHUTDOWN_TIMEOUT_SECONDS") {
+                        env_map.insert("application__shutdown_timeout_seconds".to_string(), val);
+                    }
+
+                    if let Ok(val) = std::env::var("TOR_SOCKS_PROXY") {
+                        env_map.insert("tor__socks_proxy".to_string(), val);
+                    }
+                    if let Ok(val) = std::env::var("NOSTR_RELAYS") {
+                        env_map.insert("nostr__relays".to_string(), val);
+                    }
+
+                    if let Ok(val) = std::env::var("SESSION_SECRET") {
+                        env_map.insert("sessions__secret".to_string(), val);
+                    }
+
+                    if let Ok(val) = std::env::var("AUTO_REFILL_ENABLED") {
+                        env_map.insert("auto_refill__enabled".to_string(), val);
+                    }
+                    if let Ok(val) = std::env::var("AUTO_REFILL_CHECK_INTERVAL_SECONDS") {
+                        env_map.insert("auto_refill__check_interval_seconds".to_string(), val);
+                    }
+                    if let Ok(val) = std::env::var("AUTO_REFILL_MIN_INTERVAL_MINUTES") {
+                        env_map.insert("auto_refill__min_refill_interval_minutes".to_string(), val);
+                    }
+
+                    for (var, key) in [
+                        ("EXCHANGE_RATE_PROVIDERS", "exchange_rates__providers"),
+                        ("EXCHANGE_RATE_STATIC", "exchange_rates__static_rates"),
+                        (
+                            "EXCHANGE_RATE_STATIC_FILE",
+                            "exchange_rates__static_rates_file",
+                        ),
+                        (
+                            "EXCHANGE_RATE_CACHE_TTL_SECONDS",
+                            "exchange_rates__cache_ttl_seconds",
+                        ),
+                        (
+                            "EXCHANGE_RATE_MAX_AGE_SECONDS",
+                            "exchange_rates__max_age_seconds",
+                        ),
+                        (
+                            "EXCHANGE_RATE_MAX_STALE_SECONDS",
+                            "exchange_rates__max_stale_seconds",
+                        ),
+                        ("METRICS_TOKEN", "metrics__token"),
+                        (
+                            "DEBUG_TOKEN_RETENTION_SECONDS",
+                            "debug__token_retention_seconds",
+                        ),
+                    ] {
+                        if let Ok(val) = std::env::var(var) {
+                            env_map.insert(key.to_string(), val);
+                        }
+                    }
 
                     env_map
                 })),
         )
         .build()?;
 
-    settings.try_deserialize::<Settings>()
+    #[allow(unused_mut)]
+    let mut settings = settings.try_deserialize::<Settings>()?;
+    #[cfg(feature = "sqlite")]
+    if settings.database.path.is_none() {
+        settings.database.path = Some(
+            std::path::Path::new(&settings.application.wallet_data_dir)
+                .join("otrta.sqlite")
+                .to_string_lossy()
+                .into_owned(),
+        );
+    }
+    settings.validate()?;
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_settings() -> Settings {
+        config::Config::builder()
+            .add_source(config::File::from_str(
+                include_str!("../config.example.toml"),
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_example_config_is_valid() {
+        example_settings().validate().unwrap();
+    }
+
+    #[test]
+    fn test_validate_lists_every_problem() {
+        let mut settings = example_settings();
+        settings.application.port = 0;
+        settings.application.trusted_proxies = vec!["proxy.internal".to_string()];
+        settings.nostr.relays = vec!["https://relay.example".to_string()];
+        settings.exchange_rates.providers = "coingecko,static,kraken".to_string();
+        settings.exchange_rates.max_stale_seconds = 60;
+        settings.metrics.token = Some(SecretString::from("short"));
+        settings.debug.token_retention_seconds = 30 * 24 * 3600;
+        settings.jobs.retention_interval_seconds = 1;
+
+        let message = settings.validate().unwrap_err().to_string();
+        for expected in [
+            "application.port",
+            "application.trusted_proxies",
+            "nostr.relays",
+            "unknown provider \"kraken\"",
+            "includes static",
+            "max_stale_seconds",
+            "metrics.token",
+            "debug.token_retention_seconds",
+            "job retention",
+        ] {
+            assert!(
+                message.contains(expected),
+                "{} not in {}",
+                expected,
+                message
+            );
+        }
+
+        settings.exchange_rates.static_rates = Some("usd".to_string());
+        let message = settings.validate().unwrap_err().to_string();
+        assert!(message.contains("static_rates is invalid"));
+        assert!(!message.contains("includes static"));
+    }
+
+    #[test]
+    fn test_restart_required_changes() {
+        let startup = example_settings();
+
+        // Reloaded on SIGHUP
+        let mut reloadable = startup.clone();
+        reloadable.application.whitelisted_npubs = vec![format!("npub1{}", "q".repeat(58))];
+        reloadable.nostr.relays.pop();
+        reloadable.application.trusted_proxies = vec!["10.0.0.0/8".to_string()];
+        reloadable.jobs.model_refresh_interval_seconds = 60;
+        assert!(startup.restart_required_changes(&reloadable).is_empty());
+
+        let mut restart = startup.clone();
+        restart.application.port = 4444;
+        restart.database.password = SecretString::from("changed");
+        restart.exchange_rates.max_stale_seconds = 7200;
+        restart.metrics.token = Some(SecretString::from("a-new-metrics-token"));
+        restart.debug.token_retention_seconds = 3600;
+        assert_eq!(
+            startup.restart_required_changes(&restart),
+            vec![
+                "application.port",
+                "database",
+                "exchange_rates",
+                "metrics.token",
+                "debug.token_retention_seconds",
+            ]
+        );
+    }
 }
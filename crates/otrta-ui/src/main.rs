@@ -4,88 +4,162 @@ use axum::{
 };
 mod background;
 mod connection;
+#[cfg(unix)]
+mod reload;
+mod telemetry;
 use background::BackgroundJobRunner;
-use connection::{DatabaseSettings, get_configuration};
+use connection::{DatabaseSettings, SessionSettings, get_configuration};
 use otrta::{
     auth::{AuthConfig, AuthState, bearer_auth_middleware, nostr_auth_middleware_with_context},
-    auto_refill_service::{AutoRefillConfig, start_auto_refill_service},
+    exchange_rate::ExchangeRateService,
     handlers,
+    health::{self, ProviderProbes},
+    leader::{JobLeases, default_instance_id},
     models::AppState,
     multimint_manager::MultimintManager,
+    nip46::{AttemptLimiter, PendingConnects},
+    onion::set_tor_socks_proxy,
+    permissions::permission_middleware,
     proxy::{forward_any_request, forward_any_request_get},
+    request_id::request_id_middleware,
+    sessions::SessionTokens,
+    shutdown::{InFlightRequests, redeem_all_pendings},
 };
-use sqlx::{PgPool, postgres::PgPoolOptions};
-use std::sync::Arc;
+use secrecy::ExposeSecret;
+use sqlx::pool::PoolOptions;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 use tower_http::{
     cors::{Any, CorsLayer},
     trace::TraceLayer,
 };
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
 async fn main() {
     dotenv::dotenv().ok();
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG")
-                .unwrap_or_else(|_| "ecash-402-wallet=debug,tower_http=warn".into()),
-        ))
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
-    let configuration = get_configuration().expect("Failed to read configuration.");
+    let _telemetry = telemetry::init();
+
+    let configuration = match get_configuration() {
+        Ok(configuration) => configuration,
+        Err(e) => {
+            tracing::error!("Failed to load configuration: {}", e);
+            std::process::exit(1);
+        }
+    };
+    set_tor_socks_proxy(configuration.tor.socks_proxy.clone());
     let connection_pool = get_connection_pool(&configuration.database)
         .await
-        .expect("Failed to connect to Postgres.");
-    sqlx::migrate!("./migrations")
-        .run(&connection_pool)
-        .await
-        .unwrap();
+        .expect("Failed to connect to the database.");
+    #[cfg(not(feature = "sqlite"))]
+    let migrator = sqlx::migrate!("./migrations");
+    #[cfg(feature = "sqlite")]
+    let migrator = sqlx::migrate!("./migrations-sqlite");
+    migrator.run(&connection_pool).await.unwrap();
+    let latest_migration = migrator.iter().map(|migration| migration.version).max();
 
-    let wallet_dir = dotenv::var("WALLET_DATA_DIR").unwrap_or_else(|_| "/multimint".to_string());
+    let wallet_dir = configuration.application.wallet_data_dir.clone();
     std::fs::create_dir_all(&wallet_dir).unwrap();
 
     let multimint_manager = Arc::new(MultimintManager::new(wallet_dir, connection_pool.clone()));
 
+    let exchange_rates = Arc::new(ExchangeRateService::from_config(
+        &configuration.exchange_rates,
+        Some(connection_pool.clone()),
+    ));
+
+    // Storing spendable tokens is a debugging aid only, so it is opt-in.
+    let debug_token_retention = Some(configuration.debug.token_retention_seconds)
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs);
+    if let Some(retention) = debug_token_retention {
+        tracing::warn!(
+            "Debug token retention enabled: full Cashu tokens are stored for {}s",
+            retention.as_secs()
+        );
+    }
+
     let app_state = Arc::new(AppState {
         db: connection_pool.clone(),
         default_msats_per_request: configuration.application.default_msats_per_request,
         multimint_manager,
         search_cache: Arc::new(std::sync::Mutex::new(std::collections::HashMap::new())),
+        exchange_rates,
+        debug_token_retention,
+        metrics_token: configuration
+            .metrics
+            .token
+            .as_ref()
+            .map(|token| token.expose_secret().to_string()),
+        latest_migration,
+        in_flight: Arc::new(InFlightRequests::default()),
+        job_leases: Arc::new(JobLeases::new(
+            connection_pool.clone(),
+            default_instance_id(),
+        )),
+        runtime_config: Arc::new(std::sync::RwLock::new(configuration.runtime_config())),
+        session_tokens: Arc::new(session_tokens(&configuration.sessions)),
+        nostr_connects: Arc::new(PendingConnects::default()),
+        nip46_attempts: Arc::new(AttemptLimiter::default()),
+        provider_probes: Arc::new(ProviderProbes::default()),
     });
+    tracing::info!("Instance ID: {}", app_state.job_leases.instance_id());
 
-    let job_runner = BackgroundJobRunner::new(Arc::clone(&app_state));
+    let job_runner = BackgroundJobRunner::new(
+        Arc::clone(&app_state),
+        configuration.jobs.clone(),
+        configuration.auto_refill.clone(),
+    );
     job_runner.start_all_jobs().await;
+    tokio::spawn(health::probe_providers_periodically(Arc::clone(&app_state)));
 
-    let auto_refill_config = AutoRefillConfig {
-        enabled: std::env::var("AUTO_REFILL_ENABLED")
-            .unwrap_or_else(|_| "true".to_string())
-            .parse()
-            .unwrap_or(true),
-        check_interval_seconds: std::env::var("AUTO_REFILL_CHECK_INTERVAL_SECONDS")
-            .unwrap_or_else(|_| "300".to_string())
-            .parse()
-            .unwrap_or(300),
-        min_refill_interval_minutes: std::env::var("AUTO_REFILL_MIN_INTERVAL_MINUTES")
-            .unwrap_or_else(|_| "60".to_string())
-            .parse()
-            .unwrap_or(60),
-    };
-
-    let _auto_refill_handle = start_auto_refill_service(
-        auto_refill_config,
-        connection_pool.clone(),
-        app_state.multimint_manager.clone(),
-    )
-    .await;
+    #[cfg(unix)]
+    tokio::spawn(reload::reload_on_sighup(
+        Arc::clone(&app_state),
+        configuration.clone(),
+    ));
 
     let auth_config = AuthConfig {
         enabled: configuration.application.enable_authentication,
         max_age_seconds: 300,
-        whitelisted_npubs: configuration.application.whitelisted_npubs.clone(),
     };
 
     let mut protected_routes = Router::new()
+        .route("/api/auth/login", post(handlers::login_handler))
+        .route("/api/auth/logout", post(handlers::logout_handler))
+        .route("/api/auth/sessions", get(handlers::get_sessions_handler))
+        .route(
+            "/api/auth/sessions/{id}",
+            delete(handlers::revoke_session_handler),
+        )
+        .route(
+            "/api/auth/switch-organization",
+            post(handlers::switch_organization_handler),
+        )
+        .route("/api/user/profile", get(handlers::get_user_profile_handler))
+        .route(
+            "/api/user/organizations",
+            get(handlers::get_user_organizations_handler),
+        )
+        .route(
+            "/api/organization/members",
+            get(handlers::get_members_handler),
+        )
+        .route(
+            "/api/organization/members/{npub}",
+            put(handlers::update_member_handler).delete(handlers::remove_member_handler),
+        )
+        .route(
+            "/api/organization/leave",
+            post(handlers::leave_organization_handler),
+        )
+        .route(
+            "/api/organization/invitations",
+            get(handlers::get_invitations_handler).post(handlers::create_invitation_handler),
+        )
+        .route(
+            "/api/organization/invitations/{id}",
+            delete(handlers::revoke_invitation_handler),
+        )
+        .route("/api/audit-log", get(handlers::get_audit_log_handler))
         .route("/api/openai-models", get(handlers::list_openai_models))
         .route("/api/proxy/models", get(handlers::get_proxy_models))
         .route("/api/providers", get(handlers::get_providers))
@@ -141,6 +215,62 @@ async fn main() {
         )
         .route("/api/server-config", post(handlers::update_server_config))
         .route("/api/tor/health", get(handlers::tor_health_check))
+        .route("/api/rates", get(handlers::get_exchange_rates_handler))
+        .route(
+            "/api/rates/history",
+            get(handlers::get_exchange_rate_history_handler),
+        )
+        .route("/api/budgets", get(handlers::get_budgets_handler))
+        .route(
+            "/api/budgets/organization",
+            put(handlers::set_organization_budget_handler),
+        )
+        .route(
+            "/api/budgets/api-keys/{api_key_id}",
+            put(handlers::set_api_key_budget_handler),
+        )
+        .route("/api/budgets/{id}", delete(handlers::delete_budget_handler))
+        .route(
+            "/api/ledger/balances",
+            get(handlers::get_ledger_balances_handler),
+        )
+        .route(
+            "/api/ledger/requests/{request_id}",
+            get(handlers::get_ledger_request_handler),
+        )
+        .route(
+            "/api/ledger/reconciliation",
+            get(handlers::get_ledger_reconciliation_handler),
+        )
+        .route(
+            "/api/retention",
+            get(handlers::get_retention_policy_handler)
+                .put(handlers::set_retention_policy_handler)
+                .delete(handlers::delete_retention_policy_handler),
+        )
+        .route(
+            "/api/retention/default",
+            put(handlers::set_default_retention_policy_handler),
+        )
+        .route("/api/retention/run", post(handlers::run_retention_handler))
+        .route("/api/jobs", get(handlers::get_jobs_handler))
+        .route("/api/jobs/{name}", put(handlers::update_job_handler))
+        .route("/api/jobs/{name}/runs", get(handlers::get_job_runs_handler))
+        .route("/api/jobs/{name}/pause", post(handlers::pause_job_handler))
+        .route(
+            "/api/jobs/{name}/resume",
+            post(handlers::resume_job_handler),
+        )
+        .route("/api/jobs/{name}/run", post(handlers::trigger_job_handler))
+        .route(
+            "/api/retention/rollups",
+            get(handlers::get_transaction_rollups_handler),
+        )
+        .route("/api/usage/export", get(handlers::export_usage_handler))
+        .route(
+            "/api/analytics/spend",
+            get(handlers::get_spend_analytics_handler),
+        )
         .route("/api/credits", get(handlers::get_all_credits))
         .route("/api/transactions", get(handlers::get_all_transactions))
         .route(
@@ -298,6 +428,7 @@ async fn main() {
     let auth_state = AuthState {
         config: auth_config.clone(),
         app_state: app_state.clone(),
+        replay_cache: Arc::default(),
     };
 
     unprotected_routes = unprotected_routes.layer(middleware::from_fn_with_state(
@@ -305,12 +436,57 @@ async fn main() {
         bearer_auth_middleware,
     ));
 
-    protected_routes = protected_routes.layer(middleware::from_fn_with_state(
-        auth_state,
-        nostr_auth_middleware_with_context,
-    ));
+    // Layers run outside in, so the role is checked after authentication.
+    protected_routes = protected_routes
+        .layer(middleware::from_fn(permission_middleware))
+        .layer(middleware::from_fn_with_state(
+            auth_state,
+            nostr_auth_middleware_with_context,
+        ));
+
+    // Scraped by Prometheus, which can't sign Nostr events or hold an API key;
+    // guarded by METRICS_TOKEN instead. Per-mint balances stay private on an
+    // instance that requires login, so there it needs a token to be served.
+    let metrics_routes = match (
+        &app_state.metrics_token,
+        configuration.application.enable_authentication,
+    ) {
+        (None, true) => {
+            tracing::warn!("/metrics is disabled: authentication is on but metrics.token is unset");
+            Router::new()
+        }
+        (token, _) => {
+            if token.is_none() {
+                tracing::warn!("/metrics is open to anyone: set metrics.token to protect it");
+            }
+            Router::new()
+                .route("/metrics", get(handlers::metrics_handler))
+                .with_state(app_state.clone())
+        }
+    };
+
+    // Refresh tokens are credentials of their own, presented once the access
+    // token has expired.
+    let session_routes = Router::new()
+        .route("/api/auth/refresh", post(handlers::refresh_session_handler))
+        .route(
+            "/api/auth/nip46/nostrconnect",
+            post(handlers::nostr_connect_handler),
+        )
+        .route("/api/auth/nip46/login", post(handlers::nip46_login_handler))
+        .with_state(app_state.clone());
+
+    // Probed by Docker and Kubernetes, so no authentication.
+    let health_routes = Router::new()
+        .route("/healthz", get(handlers::healthz_handler))
+        .route("/readyz", get(handlers::readyz_handler))
+        .with_state(app_state.clone());
 
-    let app = protected_routes.merge(unprotected_routes);
+    let app = protected_routes
+        .merge(session_routes)
+        .merge(metrics_routes)
+        .merge(health_routes)
+        .merge(unprotected_routes);
 
     let app = app
         .layer(
@@ -321,6 +497,7 @@ async fn main() {
                 .expose_headers(Any)
                 .allow_private_network(true),
         )
+        .layer(middleware::from_fn(request_id_middleware))
         .layer(TraceLayer::new_for_http());
     println!(
         "Server starting on http://{}:{}",
@@ -332,12 +509,102 @@ async fn main() {
     ))
     .await
     .unwrap();
-    axum::serve(listener, app).await.unwrap();
+
+    // Long enough for a streamed completion over Tor to finish and redeem its
+    // change.
+    let shutdown_timeout = Duration::from_secs(configuration.application.shutdown_timeout_seconds);
+
+    let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<()>();
+    let mut server = tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(async {
+            let _ = stop_rx.await;
+        })
+        .await
+    });
+
+    tokio::select! {
+        result = &mut server => {
+            result.unwrap().unwrap();
+            return;
+        }
+        _ = shutdown_signal() => {}
+    }
+
+    // Stop accepting connections and let in-flight requests settle their
+    // payments before the process goes away.
+    tracing::info!(
+        "Shutting down, waiting up to {}s for {} in-flight proxied request(s)",
+        shutdown_timeout.as_secs(),
+        app_state.in_flight.count()
+    );
+    let _ = stop_tx.send(());
+
+    if app_state.in_flight.wait_idle(shutdown_timeout).await {
+        tracing::info!("All in-flight proxied requests settled");
+    } else {
+        tracing::warn!(
+            "{} proxied request(s) still in flight after {}s; reclaiming their tokens from pending",
+            app_state.in_flight.count(),
+            shutdown_timeout.as_secs()
+        );
+    }
+    // Remaining connections (e.g. long-lived streams) get a short grace period.
+    let _ = tokio::time::timeout(Duration::from_secs(5), server).await;
+
+    redeem_all_pendings(&app_state.multimint_manager, Duration::from_secs(30)).await;
+    app_state.job_leases.release_all().await;
+    tracing::info!("Shutdown complete");
 }
 
-pub async fn get_connection_pool(configuration: &DatabaseSettings) -> Result<PgPool, sqlx::Error> {
-    PgPoolOptions::new()
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+pub async fn get_connection_pool(
+    configuration: &DatabaseSettings,
+) -> Result<otrta::db::Pool, sqlx::Error> {
+    PoolOptions::new()
         .max_connections(configuration.connections)
         .connect_with(configuration.with_db())
         .await
 }
+
+fn session_tokens(settings: &SessionSettings) -> SessionTokens {
+    let access_ttl = Duration::from_secs(settings.access_token_ttl_seconds);
+    let refresh_ttl = Duration::from_secs(settings.refresh_token_ttl_seconds);
+    match &settings.secret {
+        Some(secret) => {
+            SessionTokens::new(secret.expose_secret().as_bytes(), access_ttl, refresh_ttl)
+        }
+        None => {
+            tracing::warn!(
+                "SESSION_SECRET is not set; sessions will not survive a restart or work across replicas"
+            );
+            SessionTokens::with_random_secret(access_ttl, refresh_ttl)
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use crate::connection::{Settings, get_configuration};
+use otrta::{db::background_jobs::register_job, models::AppState};
+use std::sync::Arc;
+use tokio::signal::unix::{SignalKind, signal};
+use tracing::{error, info, warn};
+
+/// Re-reads the configuration on every SIGHUP and applies the parts that are
+/// safe to change while running: job intervals, the admin whitelist, the
+/// trusted proxies and the Nostr relays. An invalid configuration is logged and ignored, and other
+/// changes are reported as needing a restart.
+pub async fn reload_on_sighup(app_state: Arc<AppState>, startup: Settings) {
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            error!("Failed to install SIGHUP handler: {}", e);
+            return;
+        }
+    };
+
+    while hangup.recv().await.is_some() {
+        info!("SIGHUP received, reloading configuration");
+
+        let settings = match get_configuration() {
+            Ok(settings) => settings,
+            Err(e) => {
+                error!(
+                    "Configuration reload failed, keeping current settings: {}",
+                    e
+                );
+                continue;
+            }
+        };
+
+        *app_state.runtime_config.write().unwrap() = settings.runtime_config();
+
+        // Only jobs started at boot have a runner; enabling auto-refill
+        // takes a restart.
+        let running_jobs: Vec<&str> = startup
+            .job_intervals()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        for (name, interval) in settings.job_intervals() {
+            if !running_jobs.contains(&name) {
+                continue;
+            }
+            if let Err(e) = register_job(&app_state.db, name, interval).await {
+                error!("Failed to update interval of job {}: {}", name, e);
+            }
+        }
+
+        for setting in startup.restart_required_changes(&settings) {
+            warn!("{} changed; restart to apply it", setting);
+        }
+
+        info!(
+            "Configuration reloaded: {} whitelisted npubs, {} relays, {} trusted proxies",
+            settings.application.whitelisted_npubs.len(),
+            settings.nostr.relays.len(),
+            settings.application.trusted_proxies.len()
+        );
+    }
+}
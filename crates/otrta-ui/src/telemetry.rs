@@ -0,0 +1,85 @@
+use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
+
+/// Flushes buffered spans when dropped at shutdown.
+pub struct TelemetryGuard {
+    #[cfg(feature = "otlp")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otlp")]
+        if let Some(provider) = self.provider.take()
+            && let Err(e) = provider.shutdown()
+        {
+            eprintln!("Failed to flush OpenTelemetry spans: {}", e);
+        }
+    }
+}
+
+fn env_filter() -> EnvFilter {
+    EnvFilter::new(std::env::var("RUST_LOG").unwrap_or_else(|_| {
+        "ecash-402-wallet=debug,otrta=info,otrta_ui=info,tower_http=warn".into()
+    }))
+}
+
+/// Sets up logging, plus span export over OTLP/HTTP when
+/// `OTEL_EXPORTER_OTLP_ENDPOINT` is set (e.g. `http://localhost:4318/v1/traces`).
+#[cfg(feature = "otlp")]
+pub fn init() -> TelemetryGuard {
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::WithExportConfig;
+
+    let endpoint = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+        .ok()
+        .filter(|endpoint| !endpoint.is_empty());
+
+    let provider = endpoint.and_then(|endpoint| {
+        let exporter = match opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint.clone())
+            .build()
+        {
+            Ok(exporter) => exporter,
+            Err(e) => {
+                eprintln!("Failed to create OTLP exporter for {}: {}", endpoint, e);
+                return None;
+            }
+        };
+
+        let service_name =
+            std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "otrta".to_string());
+        Some(
+            opentelemetry_sdk::trace::SdkTracerProvider::builder()
+                .with_batch_exporter(exporter)
+                .with_resource(
+                    opentelemetry_sdk::Resource::builder()
+                        .with_service_name(service_name)
+                        .build(),
+                )
+                .build(),
+        )
+    });
+
+    let otel_layer = provider
+        .as_ref()
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer("otrta")));
+
+    tracing_subscriber::registry()
+        .with(env_filter())
+        .with(tracing_subscriber::fmt::layer())
+        .with(otel_layer)
+        .init();
+
+    TelemetryGuard { provider }
+}
+
+#[cfg(not(feature = "otlp"))]
+pub fn init() -> TelemetryGuard {
+    tracing_subscriber::registry()
+        .with(env_filter())
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    TelemetryGuard {}
+}
@@ -0,0 +1,158 @@
+//! Append-only trail of security-sensitive actions: configuration changes
+//! and funds leaving the wallet. Handlers take an [`Audit`] and record an
+//! [`AuditEvent`] once the action has succeeded.
+
+use axum::{
+    extract::{rejection::ExtensionRejection, Extension, FromRequestParts},
+    http::request::Parts,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use tracing::error;
+
+use crate::{
+    db::{
+        audit_log::{insert_audit_log_entry, NewAuditLogEntry},
+        Pool,
+    },
+    forwarded,
+    models::{AppState, UserContext},
+};
+
+/// Fields that are never written to the log, at any depth.
+const SECRET_FIELDS: &[&str] = &["key", "connection_uri", "token", "preimage"];
+
+/// What was done to which object, with its state before and after.
+pub struct AuditEvent {
+    action: &'static str,
+    target_type: &'static str,
+    target_id: Option<String>,
+    before: Option<Value>,
+    after: Option<Value>,
+}
+
+impl AuditEvent {
+    /// `action` is `<target>.<verb>`, e.g. `mint.delete`.
+    pub fn new(action: &'static str, target_type: &'static str) -> Self {
+        Self {
+            action,
+            target_type,
+            target_id: None,
+            before: None,
+            after: None,
+        }
+    }
+
+    pub fn target(mut self, id: impl ToString) -> Self {
+        self.target_id = Some(id.to_string());
+        self
+    }
+
+    pub fn before(mut self, state: impl Serialize) -> Self {
+        self.before = serde_json::to_value(state).ok().map(redact);
+        self
+    }
+
+    pub fn after(mut self, state: impl Serialize) -> Self {
+        self.after = serde_json::to_value(state).ok().map(redact);
+        self
+    }
+}
+
+fn redact(mut value: Value) -> Value {
+    match &mut value {
+        Value::Object(map) => {
+            for (name, field) in map.iter_mut() {
+                if SECRET_FIELDS.contains(&name.as_str()) {
+                    *field = Value::String("[redacted]".to_string());
+                } else {
+                    *field = redact(field.take());
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items.iter_mut() {
+                *item = redact(item.take());
+            }
+        }
+        _ => {}
+    }
+    value
+}
+
+/// Who is acting, for which organization and from where.
+pub struct Audit {
+    db: Pool,
+    user_ctx: UserContext,
+    source_ip: Option<String>,
+}
+
+impl FromRequestParts<Arc<AppState>> for Audit {
+    type Rejection = ExtensionRejection;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let Extension(user_ctx) =
+            Extension::<UserContext>::from_request_parts(parts, state).await?;
+        let source_ip = forwarded::client_ip(
+            &parts.headers,
+            forwarded::peer_addr(&parts.extensions),
+            &state.runtime_config().trusted_proxies,
+        );
+        Ok(Self {
+            db: state.db.clone(),
+            user_ctx,
+            source_ip: source_ip.map(|ip| ip.to_string()),
+        })
+    }
+}
+
+impl Audit {
+    /// Appends the event to the log. The action has already happened by
+    /// then, so a failed write is logged rather than returned.
+    pub async fn record(&self, event: AuditEvent) {
+        let entry = NewAuditLogEntry {
+            organization_id: self.user_ctx.organization_id,
+            actor_npub: self.user_ctx.npub.clone(),
+            action: event.action.to_string(),
+            target_type: event.target_type.to_string(),
+            target_id: event.target_id,
+            before: event.before,
+            after: event.after,
+            source_ip: self.source_ip.clone(),
+        };
+        if let Err(e) = insert_audit_log_entry(&self.db, &entry).await {
+            error!(
+                "Failed to write audit log entry {} by {}: {}",
+                entry.action, entry.actor_npub, e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn redacts_secrets_at_any_depth() {
+        let event = AuditEvent::new("nwc.create", "nwc_connection").after(json!({
+            "name": "alby",
+            "connection_uri": "nostr+walletconnect://secret",
+            "keys": [{ "key": "sk-123", "id": 1 }],
+        }));
+
+        assert_eq!(
+            event.after,
+            Some(json!({
+                "name": "alby",
+                "connection_uri": "[redacted]",
+                "keys": [{ "key": "[redacted]", "id": 1 }],
+            }))
+        );
+    }
+}
@@ -1,12 +1,10 @@
 use axum::{
     extract::{Request, State},
-    http::header::AUTHORIZATION,
+    http::{header::AUTHORIZATION, HeaderMap},
     middleware::Next,
     response::{IntoResponse, Response},
 };
-use base64::Engine;
-use nostr::{Event, Kind, ToBech32};
-use std::time::{SystemTime, UNIX_EPOCH};
+use nostr::ToBech32;
 use tracing::{info, warn};
 
 use crate::db::api_keys::{get_api_key_by_key, update_last_used_at};
@@ -15,18 +13,25 @@ use crate::db::provider::{
     activate_provider_for_organization, get_available_providers_for_organization,
     get_default_provider_for_organization_new, set_default_provider_for_organization_new,
 };
-use crate::db::{organizations, users};
+use crate::db::{invitations, members, organizations, sessions, users, Connection};
 use crate::error::AppError;
 use crate::handlers::mints::select_preferred_keyset;
 use crate::models::{AppState, CreateOrganizationRequest, UserContext};
+use crate::nip98::{self, ReplayCache};
+use crate::permissions::Role;
 use chrono::{DateTime, Utc};
 use std::sync::Arc;
+use uuid::Uuid;
+
+/// Names the organization a request acts in, for users who belong to more
+/// than one. Without it, the session's organization or the user's default
+/// is used.
+pub const ORGANIZATION_HEADER: &str = "x-organization-id";
 
 #[derive(Clone)]
 pub struct AuthConfig {
     pub enabled: bool,
     pub max_age_seconds: u64,
-    pub whitelisted_npubs: Vec<String>,
 }
 
 impl Default for AuthConfig {
@@ -34,15 +39,26 @@ impl Default for AuthConfig {
         Self {
             enabled: false,
             max_age_seconds: 300,
-            whitelisted_npubs: vec![],
         }
     }
 }
 
+/// How the request behind a [`UserContext`] was authenticated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthMethod {
+    /// Authentication is disabled.
+    Anonymous,
+    /// A NIP-98 event signed for this request.
+    Nostr,
+    /// An access token of the given session.
+    Session(Uuid),
+}
+
 #[derive(Clone)]
 pub struct AuthState {
     pub config: AuthConfig,
     pub app_state: Arc<AppState>,
+    pub replay_cache: Arc<ReplayCache>,
 }
 
 pub async fn bearer_auth_middleware(
@@ -121,30 +137,24 @@ pub async fn nostr_auth_middleware(
     };
 
     if let Some(encoded_event) = auth_str.strip_prefix("Nostr ") {
-        let decoded_bytes = match base64::engine::general_purpose::STANDARD.decode(encoded_event) {
-            Ok(bytes) => bytes,
-            Err(_) => return AppError::Unauthorized.into_response(),
-        };
-
-        let event_json = match std::str::from_utf8(&decoded_bytes) {
-            Ok(json) => json,
-            Err(_) => return AppError::Unauthorized.into_response(),
-        };
-
-        let event: Event = match serde_json::from_str(event_json) {
-            Ok(event) => event,
-            Err(_) => return AppError::Unauthorized.into_response(),
+        let encoded_event = encoded_event.to_string();
+        let (event, mut request) = match nip98::authorize(
+            &encoded_event,
+            request,
+            auth_state.config.max_age_seconds,
+            &auth_state.replay_cache,
+            &auth_state.app_state.runtime_config().trusted_proxies,
+        )
+        .await
+        {
+            Ok(authorized) => authorized,
+            Err(err) => return err.into_response(),
         };
 
-        if let Err(err) = validate_auth_event(&event, &request, &auth_state.config) {
-            return err.into_response();
-        }
-
         // Extract user ID (public key) from the event and add it as an extension
         let user_id = event.pubkey.to_hex();
         info!("Nostr authentication successful for user: {}", user_id);
 
-        let mut request = request;
         request.extensions_mut().insert(user_id);
         return next.run(request).await;
     }
@@ -170,6 +180,7 @@ pub async fn nostr_auth_middleware_with_context(
         match create_anonymous_user_context(&auth_state.app_state).await {
             Ok(user_context) => {
                 request.extensions_mut().insert(user_context);
+                request.extensions_mut().insert(AuthMethod::Anonymous);
                 info!("Added anonymous user context (authentication disabled)");
             }
             Err(e) => {
@@ -190,151 +201,139 @@ pub async fn nostr_auth_middleware_with_context(
         Err(_) => return AppError::Unauthorized.into_response(),
     };
 
-    if !auth_str.starts_with("Nostr ") {
-        warn!(
-            "Nostr token required but {} provided",
-            if auth_str.starts_with("Bearer ") {
-                "Bearer token"
-            } else {
-                "invalid format"
-            }
-        );
-        return AppError::Unauthorized.into_response();
-    }
-
-    let encoded_event = &auth_str[6..];
-    let decoded_bytes = match base64::engine::general_purpose::STANDARD.decode(encoded_event) {
-        Ok(bytes) => bytes,
-        Err(_) => return AppError::Unauthorized.into_response(),
-    };
-
-    let event_json = match std::str::from_utf8(&decoded_bytes) {
-        Ok(json) => json,
-        Err(_) => return AppError::Unauthorized.into_response(),
-    };
-
-    let event: Event = match serde_json::from_str(event_json) {
-        Ok(event) => event,
-        Err(_) => return AppError::Unauthorized.into_response(),
+    let organization_id = match requested_organization(request.headers()) {
+        Ok(organization_id) => organization_id,
+        Err(e) => return e.into_response(),
     };
 
-    if let Err(err) = validate_auth_event(&event, &request, &auth_state.config) {
-        return err.into_response();
+    if let Some(token) = auth_str.strip_prefix("Bearer ") {
+        let (user_context, session_id) =
+            match session_user_context(&auth_state.app_state, token, organization_id).await {
+                Ok(authenticated) => authenticated,
+                Err(e) => return e.into_response(),
+            };
+        request.extensions_mut().insert(user_context);
+        request
+            .extensions_mut()
+            .insert(AuthMethod::Session(session_id));
+        return next.run(request).await;
     }
 
-    let npub = event.pubkey.to_bech32().unwrap_or_default();
+    let Some(encoded_event) = auth_str.strip_prefix("Nostr ") else {
+        warn!("Nostr event or session token required but invalid format provided");
+        return AppError::Unauthorized.into_response();
+    };
 
-    let user_context = match create_or_get_user_context(
-        &auth_state.app_state,
-        &npub,
-        &auth_state.config.whitelisted_npubs,
+    let encoded_event = encoded_event.to_string();
+    let (event, mut request) = match nip98::authorize(
+        &encoded_event,
+        request,
+        auth_state.config.max_age_seconds,
+        &auth_state.replay_cache,
+        &auth_state.app_state.runtime_config().trusted_proxies,
     )
     .await
     {
-        Ok(ctx) => ctx,
-        Err(e) => {
-            warn!("Failed to create user context: {}", e);
-            return e.into_response();
-        }
+        Ok(authorized) => authorized,
+        Err(err) => return err.into_response(),
     };
 
-    if let Err(e) =
-        ensure_organization_multimint(&auth_state.app_state, &user_context.organization_id).await
-    {
-        warn!("Failed to ensure organization multimint: {}", e);
-        return e.into_response();
-    }
+    let npub = event.pubkey.to_bech32().unwrap_or_default();
+
+    let user_context =
+        match user_context_for_npub(&auth_state.app_state, &npub, organization_id).await {
+            Ok(ctx) => ctx,
+            Err(e) => {
+                warn!("Failed to create user context: {}", e);
+                return e.into_response();
+            }
+        };
 
     request.extensions_mut().insert(user_context);
+    request.extensions_mut().insert(AuthMethod::Nostr);
 
     info!("Nostr authentication successful with user context");
     next.run(request).await
 }
 
-fn validate_auth_event(
-    event: &Event,
-    request: &Request,
-    config: &AuthConfig,
-) -> Result<(), AppError> {
-    if event.kind != Kind::Custom(27235) {
-        warn!("Invalid event kind: expected 27235, got {}", event.kind);
-        return Err(AppError::Unauthorized);
-    }
-
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-
-    let event_age = now.saturating_sub(event.created_at.as_u64());
-    if event_age > config.max_age_seconds {
-        warn!("Event too old: {} seconds", event_age);
-        return Err(AppError::Unauthorized);
+/// The context of a user who proved control of `npub`, setting them up on
+/// first sight. Acts in `organization_id` when given, else in the user's
+/// default organization.
+pub(crate) async fn user_context_for_npub(
+    app_state: &Arc<AppState>,
+    npub: &str,
+    organization_id: Option<Uuid>,
+) -> Result<UserContext, AppError> {
+    let mut user_context = create_or_get_user_context(
+        app_state,
+        npub,
+        &app_state.runtime_config().whitelisted_npubs,
+    )
+    .await?;
+    if let Some(organization_id) = organization_id {
+        user_context = switch_organization(app_state, user_context, &organization_id).await?;
     }
+    ensure_organization_multimint(app_state, &user_context.organization_id).await?;
+    Ok(user_context)
+}
 
-    if event.verify().is_err() {
-        warn!("Invalid event signature");
+/// Resolves a session access token. The signature and expiry are checked
+/// first so forged or stale tokens never reach the database; the session row
+/// is then read so revocation takes effect immediately.
+async fn session_user_context(
+    app_state: &Arc<AppState>,
+    token: &str,
+    organization_id: Option<Uuid>,
+) -> Result<(UserContext, Uuid), AppError> {
+    let Some(claims) = app_state
+        .session_tokens
+        .verify(token, Utc::now().timestamp())
+    else {
+        warn!("Invalid or expired session token");
         return Err(AppError::Unauthorized);
-    }
-
-    // Admin validation is now handled in create_or_get_user_context function
-    // This allows non-admin users to authenticate but admin status is tracked separately
-
-    let mut url_found = false;
-    let mut method_found = false;
-
-    for tag in event.tags.iter() {
-        let values = tag.clone().to_vec();
-        if values.len() >= 2 {
-            match values[0].as_str() {
-                "u" => {
-                    // TODO: FIX ME
-                    let _expected_url = format!(
-                        "{}://{}{}",
-                        request
-                            .uri()
-                            .scheme()
-                            .map(|s| s.as_str())
-                            .unwrap_or("https"),
-                        request
-                            .uri()
-                            .authority()
-                            .map(|a| a.as_str())
-                            .unwrap_or("localhost"),
-                        request.uri().path()
-                    );
+    };
 
-                    // if values[1] != expected_url {
-                    //     warn!("URL mismatch: expected {}, got {}", expected_url, values[1]);
-                    //     return Err(AppError::Unauthorized);
-                    // }
-                    url_found = true;
-                }
-                "method" => {
-                    let expected_method = request.method().as_str();
-                    if values[1] != expected_method {
-                        warn!(
-                            "Method mismatch: expected {}, got {}",
-                            expected_method, values[1]
-                        );
-                        return Err(AppError::Unauthorized);
-                    }
-                    method_found = true;
-                }
-                _ => {}
-            }
-        }
-    }
+    let session = sessions::get_active_session(&app_state.db, &claims.sid)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?
+        .filter(|session| session.npub == claims.npub && session.organization_id == claims.org)
+        .ok_or_else(|| {
+            warn!("Session {} is revoked or expired", claims.sid);
+            AppError::Unauthorized
+        })?;
 
-    if !url_found || !method_found {
-        warn!("Missing required tags (u or method)");
-        return Err(AppError::Unauthorized);
+    let organization =
+        organizations::get_organization_by_id(&app_state.db, &session.organization_id)
+            .await?
+            .ok_or_else(|| {
+                warn!(
+                    "Session {} belongs to inactive organization {}",
+                    session.id, session.organization_id
+                );
+                AppError::Unauthorized
+            })?;
+
+    // Leaving an organization revokes its sessions, so this only fails if
+    // the membership was removed some other way.
+    let member = members::get_member(&app_state.db, &organization.id, &session.npub)
+        .await?
+        .ok_or_else(|| {
+            warn!(
+                "User {} is no longer a member of organization {}",
+                session.npub, organization.id
+            );
+            AppError::Unauthorized
+        })?;
+    let is_admin = is_user_admin(&session.npub, &app_state.runtime_config().whitelisted_npubs);
+    let mut user_context = UserContext::new_with_admin_status(session.npub, organization, is_admin)
+        .with_role(member.role());
+    if let Some(organization_id) = organization_id {
+        user_context = switch_organization(app_state, user_context, &organization_id).await?;
     }
-
-    Ok(())
+    Ok((user_context, session.id))
 }
 
-async fn validate_bearer_token(db: &sqlx::PgPool, token: &str) -> Result<String, AppError> {
+async fn validate_bearer_token(db: &crate::db::Pool, token: &str) -> Result<String, AppError> {
     let api_key = match get_api_key_by_key(db, token).await {
         Ok(Some(key)) => key,
         Ok(None) => {
@@ -388,30 +387,113 @@ async fn create_or_get_user_context(
     npub: &str,
     whitelisted_npubs: &[String],
 ) -> Result<UserContext, AppError> {
-    if let Some(user) = users::get_user_by_npub(&app_state.db, npub).await? {
-        let organization =
-            organizations::get_organization_by_id(&app_state.db, &user.organization_id)
-                .await?
-                .ok_or_else(|| {
-                    warn!(
-                        "User {} has invalid organization_id: {}",
-                        npub, user.organization_id
-                    );
-                    AppError::InternalServerError
-                })?;
-        let is_admin = is_user_admin(npub, whitelisted_npubs);
-        return Ok(UserContext::new_with_admin_status(
-            npub.to_string(),
-            organization,
-            is_admin,
-        ));
+    let existing = users::get_user_by_npub(&app_state.db, npub).await?;
+
+    // Invitations are used up together with the memberships they grant.
+    let mut tx = app_state.db.begin().await?;
+    let accepted = invitations::accept_invitations(&mut tx, npub).await?;
+    let invited_user = match (&existing, accepted.last()) {
+        // Invited users join the team instead of getting an organization of
+        // their own.
+        (None, Some(invitation)) => {
+            info!(
+                "User {} not found, creating user in organization {} they were invited to",
+                npub, invitation.organization_id
+            );
+            Some(create_user(&mut tx, npub, invitation.organization_id).await?)
+        }
+        _ => None,
+    };
+    for invitation in &accepted {
+        members::add_member(
+            &mut tx,
+            &invitation.organization_id,
+            npub,
+            invitation.role(),
+        )
+        .await?;
+        info!(
+            "User {} accepted invitation {} to organization {}",
+            npub, invitation.id, invitation.organization_id
+        );
     }
+    tx.commit().await?;
+
+    let user = match existing.or(invited_user) {
+        Some(user) => user,
+        None => {
+            info!(
+                "User {} not found, creating user and new organization",
+                npub
+            );
+            let organization = create_personal_organization(app_state, npub).await?;
+            let user =
+                create_user(&mut *app_state.db.acquire().await?, npub, organization.id).await?;
+            members::set_member_role(&app_state.db, &organization.id, npub, Role::Owner).await?;
+            user
+        }
+    };
 
-    // User doesn't exist, create a new organization for them
-    info!(
-        "User {} not found, creating user and new organization",
-        npub
-    );
+    // The newest accepted invitation becomes the default, so invited users
+    // land in the team. Users removed from their default organization fall
+    // back to another one, or to a new organization of their own.
+    let mut organization_id = accepted
+        .last()
+        .map(|invitation| invitation.organization_id)
+        .unwrap_or(user.organization_id);
+    let mut member = members::get_member(&app_state.db, &organization_id, npub).await?;
+    if member.is_none() {
+        member = members::get_memberships_for_user(&app_state.db, npub)
+            .await?
+            .into_iter()
+            .next();
+    }
+    let member = match member {
+        Some(member) => member,
+        None => {
+            let organization = create_personal_organization(app_state, npub).await?;
+            members::set_member_role(&app_state.db, &organization.id, npub, Role::Owner).await?
+        }
+    };
+    if member.organization_id != user.organization_id {
+        organization_id = member.organization_id;
+        users::set_default_organization(&app_state.db, npub, &organization_id).await?;
+    }
+
+    let organization = organizations::get_organization_by_id(&app_state.db, &organization_id)
+        .await?
+        .ok_or_else(|| {
+            warn!(
+                "User {} has invalid organization_id: {}",
+                npub, organization_id
+            );
+            AppError::InternalServerError
+        })?;
+    let is_admin = is_user_admin(npub, whitelisted_npubs);
+    Ok(
+        UserContext::new_with_admin_status(npub.to_string(), organization, is_admin)
+            .with_role(member.role()),
+    )
+}
+
+async fn create_user(
+    conn: &mut Connection,
+    npub: &str,
+    organization_id: Uuid,
+) -> Result<crate::models::User, AppError> {
+    let create_user_request = crate::models::CreateUserRequest {
+        npub: npub.to_string(),
+        display_name: None,
+        email: None,
+        organization_id,
+    };
+    users::insert_user(conn, &create_user_request).await
+}
+
+async fn create_personal_organization(
+    app_state: &Arc<AppState>,
+    npub: &str,
+) -> Result<crate::models::Organization, AppError> {
     let create_org_request = CreateOrganizationRequest {
         name: format!("Organization for {}", npub),
     };
@@ -422,26 +504,58 @@ async fn create_or_get_user_context(
         organization.id, npub
     );
 
-    let create_user_request = crate::models::CreateUserRequest {
-        npub: npub.to_string(),
-        display_name: None,
-        email: None,
-        organization_id: organization.id,
-    };
-    users::create_user(&app_state.db, &create_user_request).await?;
-
     // Setup default provider and wallet for new user
     if let Err(e) = setup_first_time_user_defaults(app_state, &organization.id).await {
         warn!("Failed to setup defaults for new user {}: {}", npub, e);
         // Don't fail the entire authentication if default setup fails
     }
+    Ok(organization)
+}
 
-    let is_admin = is_user_admin(npub, whitelisted_npubs);
-    Ok(UserContext::new_with_admin_status(
-        npub.to_string(),
-        organization,
-        is_admin,
-    ))
+/// Switches the context to another organization the user belongs to.
+async fn switch_organization(
+    app_state: &Arc<AppState>,
+    user_context: UserContext,
+    organization_id: &Uuid,
+) -> Result<UserContext, AppError> {
+    if *organization_id == user_context.organization_id {
+        return Ok(user_context);
+    }
+
+    let not_a_member = || {
+        warn!(
+            "User {} is not a member of organization {}",
+            user_context.npub, organization_id
+        );
+        AppError::Forbidden("Not a member of this organization".to_string())
+    };
+    let member = members::get_member(&app_state.db, organization_id, &user_context.npub)
+        .await?
+        .ok_or_else(not_a_member)?;
+    let organization = organizations::get_organization_by_id(&app_state.db, organization_id)
+        .await?
+        .ok_or_else(not_a_member)?;
+
+    Ok(
+        UserContext::new_with_admin_status(user_context.npub, organization, user_context.is_admin)
+            .with_role(member.role()),
+    )
+}
+
+/// The organization named by the `X-Organization-Id` header, if any.
+fn requested_organization(headers: &HeaderMap) -> Result<Option<Uuid>, AppError> {
+    headers
+        .get(ORGANIZATION_HEADER)
+        .map(|value| {
+            value
+                .to_str()
+                .ok()
+                .and_then(|value| Uuid::parse_str(value.trim()).ok())
+                .ok_or_else(|| {
+                    AppError::BadRequest(format!("Invalid {} header", ORGANIZATION_HEADER))
+                })
+        })
+        .transpose()
 }
 
 pub async fn ensure_default_organization_exists(
@@ -480,12 +594,13 @@ async fn create_anonymous_user_context(app_state: &Arc<AppState>) -> Result<User
         // Don't fail authentication if multimint setup fails
     }
 
-    // Create anonymous user context (no admin privileges)
-    Ok(UserContext::new_with_admin_status(
-        "anonymous".to_string(),
-        organization,
-        false,
-    ))
+    // Create anonymous user context (no admin privileges). Without
+    // authentication there is no one to tell apart, so it may do anything in
+    // the organization.
+    Ok(
+        UserContext::new_with_admin_status("anonymous".to_string(), organization, false)
+            .with_role(Role::Owner),
+    )
 }
 
 async fn setup_first_time_user_defaults(
@@ -647,7 +762,7 @@ async fn setup_first_time_user_defaults(
                     if let Err(e) = crate::db::mint::create_mint_units(
                         &app_state.db,
                         mint.id,
-                        &[keyset.clone()],
+                        std::slice::from_ref(&keyset),
                     )
                     .await
                     {
@@ -733,7 +848,7 @@ async fn setup_first_time_user_defaults(
 }
 
 async fn find_existing_mint_for_copy(
-    db: &sqlx::PgPool,
+    db: &crate::db::Pool,
     mint_url: &str,
 ) -> Result<Option<crate::db::mint::Mint>, sqlx::Error> {
     let msat_mint = sqlx::query_as::<_, crate::db::mint::Mint>(
@@ -766,3 +881,99 @@ async fn ensure_organization_multimint(
     info!("Ensured multimint exists for organization: {}", org_id);
     Ok(())
 }
+
+#[cfg(all(test, feature = "sqlite"))]
+mod sqlite_tests {
+    use super::*;
+    use crate::db::test_pool;
+    use crate::exchange_rate::ExchangeRateService;
+    use crate::leader::JobLeases;
+    use crate::multimint_manager::MultimintManager;
+    use crate::sessions::SessionTokens;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    async fn test_state() -> Arc<AppState> {
+        let db = test_pool().await;
+        Arc::new(AppState {
+            db: db.clone(),
+            default_msats_per_request: 1,
+            multimint_manager: Arc::new(MultimintManager::new(String::new(), db.clone())),
+            search_cache: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            exchange_rates: Arc::new(ExchangeRateService::new(Vec::new(), None, 60, 60, 60)),
+            debug_token_retention: None,
+            metrics_token: None,
+            latest_migration: None,
+            in_flight: Default::default(),
+            job_leases: Arc::new(JobLeases::new(db, "test".to_string())),
+            runtime_config: Default::default(),
+            session_tokens: Arc::new(SessionTokens::new(
+                &[0; 32],
+                Duration::from_secs(60),
+                Duration::from_secs(60),
+            )),
+            nostr_connects: Default::default(),
+            nip46_attempts: Default::default(),
+            provider_probes: Default::default(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_when_removed_from_the_default_organization() {
+        let state = test_state().await;
+        let npub = format!("npub1{}", "u".repeat(58));
+        let login = || create_or_get_user_context(&state, &npub, &[]);
+
+        let personal = login().await.unwrap();
+        assert_eq!(personal.role, Role::Owner);
+
+        // An invitation moves the default to the team
+        let team = organizations::create_organization(
+            &state.db,
+            &CreateOrganizationRequest {
+                name: "team".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        let expires_at = Utc::now() + chrono::Duration::days(1);
+        invitations::create_invitation(
+            &state.db,
+            &team.id,
+            &npub,
+            Role::Member,
+            "owner",
+            expires_at,
+        )
+        .await
+        .unwrap();
+        let invited = login().await.unwrap();
+        assert_eq!(invited.organization_id, team.id);
+        assert_eq!(invited.role, Role::Member);
+
+        // Removed from it, back to the other membership
+        members::remove_member(&state.db, &team.id, &npub)
+            .await
+            .unwrap();
+        let fallback = login().await.unwrap();
+        assert_eq!(fallback.organization_id, personal.organization_id);
+        let user = users::get_user_by_npub(&state.db, &npub)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(user.organization_id, personal.organization_id);
+
+        // Removed from everything, a new organization of their own
+        members::remove_member(&state.db, &personal.organization_id, &npub)
+            .await
+            .unwrap();
+        let fresh = login().await.unwrap();
+        assert_ne!(fresh.organization_id, personal.organization_id);
+        assert_ne!(fresh.organization_id, team.id);
+        assert_eq!(fresh.role, Role::Owner);
+        assert_eq!(
+            login().await.unwrap().organization_id,
+            fresh.organization_id
+        );
+    }
+}
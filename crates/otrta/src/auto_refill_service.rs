@@ -1,5 +1,5 @@
 use std::time::Duration;
-use tokio::time::{interval, sleep};
+use tokio::time::sleep;
 use tracing::{debug, error, info, warn};
 
 use crate::{
@@ -8,23 +8,22 @@ use crate::{
         nwc::{get_enabled_mint_auto_refill_settings, update_last_refill_time},
     },
     error::AppError,
+    metrics::record_auto_refill_attempt,
     multimint_manager::MultimintManager,
     nwc_client::NwcManager,
 };
 
 pub struct AutoRefillService {
-    db_pool: sqlx::PgPool,
+    db_pool: crate::db::Pool,
     multimint_manager: std::sync::Arc<MultimintManager>,
     nwc_manager: NwcManager,
-    check_interval: Duration,
     min_refill_interval: Duration,
 }
 
 impl AutoRefillService {
     pub fn new(
-        db_pool: sqlx::PgPool,
+        db_pool: crate::db::Pool,
         multimint_manager: std::sync::Arc<MultimintManager>,
-        check_interval_seconds: u64,
         min_refill_interval_minutes: u64,
     ) -> Self {
         let nwc_manager = NwcManager::new(db_pool.clone());
@@ -33,29 +32,14 @@ impl AutoRefillService {
             db_pool,
             multimint_manager,
             nwc_manager,
-            check_interval: Duration::from_secs(check_interval_seconds),
             min_refill_interval: Duration::from_secs(min_refill_interval_minutes * 60),
         }
     }
 
-    pub async fn start(&self) {
-        info!(
-            "Starting auto-refill service with check interval: {:?}",
-            self.check_interval
-        );
-
-        let mut interval_timer = interval(self.check_interval);
-
-        loop {
-            interval_timer.tick().await;
-
-            if let Err(e) = self.check_and_refill_balances().await {
-                error!("Error in auto-refill service: {}", e);
-            }
-        }
-    }
-
-    async fn check_and_refill_balances(&self) -> Result<(), AppError> {
+    /// One pass over every enabled auto-refill setting. Runs as the
+    /// `auto_refill` scheduled job, so only one instance refills at a time;
+    /// otherwise every replica would pay the same invoice amount into the mint.
+    pub async fn check_and_refill_balances(&self) -> Result<(), AppError> {
         debug!("Checking balances for auto-refill");
 
         let settings = get_enabled_mint_auto_refill_settings(&self.db_pool).await?;
@@ -140,7 +124,10 @@ impl AutoRefillService {
             mint.mint_url, balance, setting.min_balance_threshold_msat, setting.refill_amount_msat
         );
 
-        match self.execute_refill(setting, &mint.mint_url).await {
+        let result = self.execute_refill(setting, &mint.mint_url).await;
+        record_auto_refill_attempt(&mint.mint_url, result.is_ok());
+
+        match result {
             Ok(invoice) => {
                 info!(
                     "Successfully initiated refill for mint {}: {}",
@@ -179,7 +166,7 @@ impl AutoRefillService {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, serde::Deserialize)]
 pub struct AutoRefillConfig {
     pub enabled: bool,
     pub check_interval_seconds: u64,
@@ -195,25 +182,3 @@ impl Default for AutoRefillConfig {
         }
     }
 }
-
-pub async fn start_auto_refill_service(
-    config: AutoRefillConfig,
-    db_pool: sqlx::PgPool,
-    multimint_manager: std::sync::Arc<MultimintManager>,
-) -> tokio::task::JoinHandle<()> {
-    tokio::spawn(async move {
-        if !config.enabled {
-            info!("Auto-refill service is disabled");
-            return;
-        }
-
-        let service = AutoRefillService::new(
-            db_pool,
-            multimint_manager,
-            config.check_interval_seconds,
-            config.min_refill_interval_minutes,
-        );
-
-        service.start().await;
-    })
-}
@@ -0,0 +1,210 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::db::{
+    dialect::{any_uuid, date_trunc},
+    Pool,
+};
+use uuid::Uuid;
+
+/// Dimension spend can be grouped by.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalyticsDimension {
+    Model,
+    Provider,
+    ApiKey,
+    User,
+}
+
+impl AnalyticsDimension {
+    fn column(&self) -> &'static str {
+        match self {
+            AnalyticsDimension::Model => "model",
+            AnalyticsDimension::Provider => "provider_url",
+            AnalyticsDimension::ApiKey => "api_key_id",
+            AnalyticsDimension::User => "user_id",
+        }
+    }
+
+    /// Parses a comma-separated list such as `model,provider`.
+    pub fn parse_list(value: &str) -> Result<Vec<Self>, String> {
+        let mut dimensions = Vec::new();
+        for part in value.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let dimension = part.parse()?;
+            if !dimensions.contains(&dimension) {
+                dimensions.push(dimension);
+            }
+        }
+        Ok(dimensions)
+    }
+}
+
+impl std::str::FromStr for AnalyticsDimension {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "model" => Ok(AnalyticsDimension::Model),
+            "provider" => Ok(AnalyticsDimension::Provider),
+            "api_key" => Ok(AnalyticsDimension::ApiKey),
+            "user" => Ok(AnalyticsDimension::User),
+            _ => Err(format!("Invalid analytics dimension: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeBucket {
+    Hour,
+    Day,
+    Week,
+}
+
+impl TimeBucket {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TimeBucket::Hour => "hour",
+            TimeBucket::Day => "day",
+            TimeBucket::Week => "week",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AnalyticsQuery {
+    pub group_by: Vec<AnalyticsDimension>,
+    pub bucket: Option<TimeBucket>,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+}
+
+/// Spend of one group. Amounts are in the ledger's canonical unit; groups are
+/// always split by unit since amounts in different units can't be summed.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct SpendGroup {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bucket: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    pub unit: String,
+    pub request_count: i64,
+    /// Requests the provider failed, i.e. whose payment was refunded.
+    pub error_count: i64,
+    pub spend: i64,
+    pub fiat_spend: f64,
+    pub average_cost: f64,
+    pub average_fiat_cost: f64,
+}
+
+fn build_query(query: &AnalyticsQuery) -> String {
+    let bucket = match query.bucket {
+        Some(bucket) => format!("{} AS bucket", date_trunc(bucket.as_str(), "created_at")),
+        None => "CAST(NULL AS TIMESTAMPTZ) AS bucket".to_string(),
+    };
+
+    let dimensions = [
+        AnalyticsDimension::Model,
+        AnalyticsDimension::Provider,
+        AnalyticsDimension::ApiKey,
+        AnalyticsDimension::User,
+    ]
+    .iter()
+    .map(|dimension| {
+        if query.group_by.contains(dimension) {
+            dimension.column().to_string()
+        } else {
+            format!("CAST(NULL AS TEXT) AS {}", dimension.column())
+        }
+    })
+    .collect::<Vec<_>>()
+    .join(", ");
+
+    // Positional GROUP BY: 1 is the bucket, 2-5 the dimensions, 6 the unit.
+    let mut group_by = vec!["6"];
+    if query.bucket.is_some() {
+        group_by.push("1");
+    }
+    for (position, dimension) in [
+        ("2", AnalyticsDimension::Model),
+        ("3", AnalyticsDimension::Provider),
+        ("4", AnalyticsDimension::ApiKey),
+        ("5", AnalyticsDimension::User),
+    ] {
+        if query.group_by.contains(&dimension) {
+            group_by.push(position);
+        }
+    }
+    let group_by = group_by.join(", ");
+    let api_key_id = any_uuid("api_key_id");
+
+    format!(
+        "WITH requests AS (
+             SELECT request_id,
+                    MIN(created_at) AS created_at,
+                    MIN(model) AS model,
+                    MIN(provider_url) AS provider_url,
+                    {api_key_id} AS api_key_id,
+                    MIN(user_id) AS user_id,
+                    MIN(unit) AS unit,
+                    SUM(CASE WHEN entry_type IN ('charge', 'fee') THEN amount ELSE -amount END) AS net,
+                    COALESCE(SUM(CASE WHEN entry_type IN ('charge', 'fee') THEN fiat_amount ELSE -fiat_amount END), 0.0) AS fiat_net,
+                    MAX(CASE WHEN entry_type = 'refund' THEN 1 ELSE 0 END) = 1 AS failed
+             FROM ledger_entries
+             WHERE organization_id = $1
+               AND entry_type IN ('charge', 'fee', 'change', 'refund')
+               AND created_at >= $2
+               AND created_at <= $3
+             GROUP BY request_id
+         )
+         SELECT {bucket}, {dimensions}, unit,
+                COUNT(*) AS request_count,
+                COUNT(*) FILTER (WHERE failed) AS error_count,
+                CAST(COALESCE(SUM(net), 0) AS BIGINT) AS spend,
+                COALESCE(SUM(fiat_net), 0.0) AS fiat_spend,
+                CAST(COALESCE(AVG(net), 0) AS DOUBLE PRECISION) AS average_cost,
+                COALESCE(AVG(fiat_net), 0.0) AS average_fiat_cost
+         FROM requests
+         GROUP BY {group_by}
+         ORDER BY 1 NULLS FIRST, spend DESC"
+    )
+}
+
+pub async fn get_spend_analytics(
+    pool: &Pool,
+    organization_id: &Uuid,
+    query: &AnalyticsQuery,
+) -> Result<Vec<SpendGroup>, sqlx::Error> {
+    sqlx::query_as::<_, SpendGroup>(&build_query(query))
+        .bind(organization_id)
+        .bind(query.start_date)
+        .bind(query.end_date)
+        .fetch_all(pool)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dimension_list() {
+        assert_eq!(
+            AnalyticsDimension::parse_list("model, provider,model"),
+            Ok(vec![
+                AnalyticsDimension::Model,
+                AnalyticsDimension::Provider
+            ])
+        );
+        assert_eq!(AnalyticsDimension::parse_list(""), Ok(vec![]));
+        assert!(AnalyticsDimension::parse_list("model,colour").is_err());
+    }
+}
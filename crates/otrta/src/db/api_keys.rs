@@ -1,7 +1,7 @@
+use crate::db::Pool;
 use crate::models::UserContext;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
 use uuid::Uuid;
 
 // Database row struct that matches the exact column types
@@ -74,7 +74,7 @@ pub struct ApiKeyListResponse {
 }
 
 pub async fn get_all_api_keys(
-    pool: &PgPool,
+    pool: &Pool,
     organization_id: Option<&str>,
     page: i64,
     page_size: i64,
@@ -82,8 +82,7 @@ pub async fn get_all_api_keys(
     let offset = (page - 1) * page_size;
 
     let (api_keys, total) = if let Some(org_id) = organization_id {
-        let rows = sqlx::query_as!(
-            ApiKeyRow,
+        let rows = sqlx::query_as::<_, ApiKeyRow>(
             r#"
             SELECT id, name, key, user_id, organization_id, last_used_at, expires_at, 
                    is_active, created_at, updated_at
@@ -92,27 +91,24 @@ pub async fn get_all_api_keys(
             ORDER BY created_at DESC
             LIMIT $2 OFFSET $3
             "#,
-            org_id,
-            page_size,
-            offset
         )
+        .bind(org_id)
+        .bind(page_size)
+        .bind(offset)
         .fetch_all(pool)
         .await?;
 
-        let total = sqlx::query_scalar!(
-            "SELECT COUNT(*) FROM api_keys WHERE organization_id = $1",
-            org_id
-        )
-        .fetch_one(pool)
-        .await?
-        .unwrap_or(0);
+        let total: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM api_keys WHERE organization_id = $1")
+                .bind(org_id)
+                .fetch_one(pool)
+                .await?;
 
         let api_keys = rows.into_iter().map(ApiKey::from).collect();
 
         (api_keys, total)
     } else {
-        let rows = sqlx::query_as!(
-            ApiKeyRow,
+        let rows = sqlx::query_as::<_, ApiKeyRow>(
             r#"
             SELECT id, name, key, user_id, organization_id, last_used_at, expires_at, 
                    is_active, created_at, updated_at
@@ -120,16 +116,15 @@ pub async fn get_all_api_keys(
             ORDER BY created_at DESC
             LIMIT $1 OFFSET $2
             "#,
-            page_size,
-            offset
         )
+        .bind(page_size)
+        .bind(offset)
         .fetch_all(pool)
         .await?;
 
-        let total = sqlx::query_scalar!("SELECT COUNT(*) FROM api_keys")
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM api_keys")
             .fetch_one(pool)
-            .await?
-            .unwrap_or(0);
+            .await?;
 
         let api_keys = rows.into_iter().map(ApiKey::from).collect();
 
@@ -147,36 +142,55 @@ pub async fn get_all_api_keys(
     })
 }
 
-pub async fn get_api_key_by_id(pool: &PgPool, id: &str) -> Result<Option<ApiKey>, sqlx::Error> {
+/// Every API key of the organization, newest first.
+pub async fn get_api_keys_for_organization(
+    pool: &Pool,
+    organization_id: &str,
+) -> Result<Vec<ApiKey>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, ApiKeyRow>(
+        r#"
+        SELECT id, name, key, user_id, organization_id, last_used_at, expires_at,
+               is_active, created_at, updated_at
+        FROM api_keys
+        WHERE organization_id = $1
+        ORDER BY created_at DESC
+        "#,
+    )
+    .bind(organization_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(ApiKey::from).collect())
+}
+
+pub async fn get_api_key_by_id(pool: &Pool, id: &str) -> Result<Option<ApiKey>, sqlx::Error> {
     let id_uuid = Uuid::parse_str(id).map_err(|_| sqlx::Error::RowNotFound)?;
 
-    let row = sqlx::query_as!(
-        ApiKeyRow,
+    let row = sqlx::query_as::<_, ApiKeyRow>(
         r#"
         SELECT id, name, key, user_id, organization_id, last_used_at, expires_at, 
                is_active, created_at, updated_at
         FROM api_keys
         WHERE id = $1
         "#,
-        id_uuid
     )
+    .bind(id_uuid)
     .fetch_optional(pool)
     .await?;
 
     Ok(row.map(ApiKey::from))
 }
 
-pub async fn get_api_key_by_key(pool: &PgPool, key: &str) -> Result<Option<ApiKey>, sqlx::Error> {
-    let row = sqlx::query_as!(
-        ApiKeyRow,
+pub async fn get_api_key_by_key(pool: &Pool, key: &str) -> Result<Option<ApiKey>, sqlx::Error> {
+    let row = sqlx::query_as::<_, ApiKeyRow>(
         r#"
         SELECT id, name, key, user_id, organization_id, last_used_at, expires_at, 
                is_active, created_at, updated_at
         FROM api_keys
         WHERE key = $1 AND is_active = true
         "#,
-        key
     )
+    .bind(key)
     .fetch_optional(pool)
     .await?;
 
@@ -184,7 +198,7 @@ pub async fn get_api_key_by_key(pool: &PgPool, key: &str) -> Result<Option<ApiKe
 }
 
 pub async fn create_api_key(
-    pool: &PgPool,
+    pool: &Pool,
     request: CreateApiKeyRequest,
     user_context: &UserContext,
 ) -> Result<ApiKey, sqlx::Error> {
@@ -201,21 +215,20 @@ pub async fn create_api_key(
         None
     };
 
-    let row = sqlx::query_as!(
-        ApiKeyRow,
+    let row = sqlx::query_as::<_, ApiKeyRow>(
         r#"
         INSERT INTO api_keys (name, key, user_id, organization_id, expires_at, is_active)
         VALUES ($1, $2, $3, $4, $5, $6)
         RETURNING id, name, key, user_id, organization_id, last_used_at, expires_at, 
                   is_active, created_at, updated_at
         "#,
-        request.name,
-        api_key,
-        user_context.npub,
-        user_context.organization_id.to_string(),
-        expires_at,
-        is_active
     )
+    .bind(&request.name)
+    .bind(api_key)
+    .bind(&user_context.npub)
+    .bind(user_context.organization_id.to_string())
+    .bind(expires_at)
+    .bind(is_active)
     .fetch_one(pool)
     .await?;
 
@@ -223,7 +236,7 @@ pub async fn create_api_key(
 }
 
 pub async fn update_api_key(
-    pool: &PgPool,
+    pool: &Pool,
     id: &str,
     request: UpdateApiKeyRequest,
 ) -> Result<Option<ApiKey>, sqlx::Error> {
@@ -239,71 +252,70 @@ pub async fn update_api_key(
         None
     };
 
-    let row = sqlx::query_as!(
-        ApiKeyRow,
+    let row = sqlx::query_as::<_, ApiKeyRow>(
         r#"
         UPDATE api_keys
         SET name = COALESCE($2, name),
             is_active = COALESCE($3, is_active),
             expires_at = COALESCE($4, expires_at),
-            updated_at = NOW()
+            updated_at = $5
         WHERE id = $1
         RETURNING id, name, key, user_id, organization_id, last_used_at, expires_at, 
                   is_active, created_at, updated_at
         "#,
-        id_uuid,
-        request.name,
-        request.is_active,
-        expires_at
     )
+    .bind(id_uuid)
+    .bind(&request.name)
+    .bind(request.is_active)
+    .bind(expires_at)
+    .bind(Utc::now())
     .fetch_optional(pool)
     .await?;
 
     Ok(row.map(ApiKey::from))
 }
 
-pub async fn delete_api_key(pool: &PgPool, id: &str) -> Result<bool, sqlx::Error> {
+pub async fn delete_api_key(pool: &Pool, id: &str) -> Result<bool, sqlx::Error> {
     let id_uuid = Uuid::parse_str(id).map_err(|_| sqlx::Error::RowNotFound)?;
 
-    let result = sqlx::query!("DELETE FROM api_keys WHERE id = $1", id_uuid)
+    let result = sqlx::query("DELETE FROM api_keys WHERE id = $1")
+        .bind(id_uuid)
         .execute(pool)
         .await?;
 
     Ok(result.rows_affected() > 0)
 }
 
-pub async fn update_last_used_at(pool: &PgPool, id: &str) -> Result<(), sqlx::Error> {
+pub async fn update_last_used_at(pool: &Pool, id: &str) -> Result<(), sqlx::Error> {
     let id_uuid = Uuid::parse_str(id).map_err(|_| sqlx::Error::RowNotFound)?;
 
-    sqlx::query!(
-        "UPDATE api_keys SET last_used_at = NOW() WHERE id = $1",
-        id_uuid
-    )
-    .execute(pool)
-    .await?;
+    sqlx::query("UPDATE api_keys SET last_used_at = $2 WHERE id = $1")
+        .bind(id_uuid)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
 
     Ok(())
 }
 
 pub async fn get_api_key_by_id_for_user(
-    pool: &PgPool,
+    pool: &Pool,
     id: &str,
     organization_id: &str,
 ) -> Result<Option<ApiKey>, sqlx::Error> {
     let id_uuid = Uuid::parse_str(id).map_err(|_| sqlx::Error::RowNotFound)?;
     let org_uuid = Uuid::parse_str(organization_id).map_err(|_| sqlx::Error::RowNotFound)?;
 
-    let row = sqlx::query_as!(
-        ApiKeyRow,
+    let row = sqlx::query_as::<_, ApiKeyRow>(
         r#"
         SELECT id, name, key, user_id, organization_id, last_used_at, expires_at, 
                is_active, created_at, updated_at
         FROM api_keys
         WHERE id = $1 AND organization_id = $2
         "#,
-        id_uuid,
-        org_uuid.to_string()
     )
+    .bind(id_uuid)
+    .bind(org_uuid.to_string())
     .fetch_optional(pool)
     .await?;
 
@@ -311,7 +323,7 @@ pub async fn get_api_key_by_id_for_user(
 }
 
 pub async fn update_api_key_for_user(
-    pool: &PgPool,
+    pool: &Pool,
     id: &str,
     organization_id: &str,
     request: UpdateApiKeyRequest,
@@ -329,24 +341,24 @@ pub async fn update_api_key_for_user(
         None
     };
 
-    let row = sqlx::query_as!(
-        ApiKeyRow,
+    let row = sqlx::query_as::<_, ApiKeyRow>(
         r#"
         UPDATE api_keys
         SET name = COALESCE($3, name),
             is_active = COALESCE($4, is_active),
             expires_at = COALESCE($5, expires_at),
-            updated_at = NOW()
+            updated_at = $6
         WHERE id = $1 AND organization_id = $2
         RETURNING id, name, key, user_id, organization_id, last_used_at, expires_at, 
                   is_active, created_at, updated_at
         "#,
-        id_uuid,
-        org_uuid.to_string(),
-        request.name,
-        request.is_active,
-        expires_at
     )
+    .bind(id_uuid)
+    .bind(org_uuid.to_string())
+    .bind(&request.name)
+    .bind(request.is_active)
+    .bind(expires_at)
+    .bind(Utc::now())
     .fetch_optional(pool)
     .await?;
 
@@ -354,20 +366,18 @@ pub async fn update_api_key_for_user(
 }
 
 pub async fn delete_api_key_for_user(
-    pool: &PgPool,
+    pool: &Pool,
     id: &str,
     organization_id: &str,
 ) -> Result<bool, sqlx::Error> {
     let id_uuid = Uuid::parse_str(id).map_err(|_| sqlx::Error::RowNotFound)?;
     let org_uuid = Uuid::parse_str(organization_id).map_err(|_| sqlx::Error::RowNotFound)?;
 
-    let result = sqlx::query!(
-        "DELETE FROM api_keys WHERE id = $1 AND organization_id = $2",
-        id_uuid,
-        org_uuid.to_string()
-    )
-    .execute(pool)
-    .await?;
+    let result = sqlx::query("DELETE FROM api_keys WHERE id = $1 AND organization_id = $2")
+        .bind(id_uuid)
+        .bind(org_uuid.to_string())
+        .execute(pool)
+        .await?;
 
     Ok(result.rows_affected() > 0)
 }
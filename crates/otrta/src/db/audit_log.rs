@@ -0,0 +1,110 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::db::Pool;
+use uuid::Uuid;
+
+pub const DEFAULT_AUDIT_LOG_LIMIT: i64 = 100;
+pub const MAX_AUDIT_LOG_LIMIT: i64 = 1000;
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct AuditLogEntry {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub actor_npub: String,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<String>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub source_ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewAuditLogEntry {
+    pub organization_id: Uuid,
+    pub actor_npub: String,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<String>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub source_ip: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuditLogFilter {
+    pub action: Option<String>,
+    pub actor_npub: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    /// Exclusive, so the oldest `created_at` of one page fetches the next.
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+impl AuditLogFilter {
+    fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(DEFAULT_AUDIT_LOG_LIMIT)
+            .clamp(1, MAX_AUDIT_LOG_LIMIT)
+    }
+}
+
+pub async fn insert_audit_log_entry(
+    pool: &Pool,
+    entry: &NewAuditLogEntry,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO audit_log
+            (id, organization_id, actor_npub, action, target_type, target_id, before, after, source_ip, created_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(entry.organization_id)
+    .bind(&entry.actor_npub)
+    .bind(&entry.action)
+    .bind(&entry.target_type)
+    .bind(&entry.target_id)
+    .bind(&entry.before)
+    .bind(&entry.after)
+    .bind(&entry.source_ip)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// The organization's entries matching `filter`, newest first.
+pub async fn get_audit_log(
+    pool: &Pool,
+    organization_id: &Uuid,
+    filter: &AuditLogFilter,
+) -> Result<Vec<AuditLogEntry>, sqlx::Error> {
+    sqlx::query_as::<_, AuditLogEntry>(
+        "SELECT id, organization_id, actor_npub, action, target_type, target_id, before, after, source_ip, created_at
+         FROM audit_log
+         WHERE organization_id = $1
+           AND ($2 IS NULL OR action = $2)
+           AND ($3 IS NULL OR actor_npub = $3)
+           AND ($4 IS NULL OR target_type = $4)
+           AND ($5 IS NULL OR target_id = $5)
+           AND ($6 IS NULL OR created_at >= $6)
+           AND ($7 IS NULL OR created_at < $7)
+         ORDER BY created_at DESC, id
+         LIMIT $8",
+    )
+    .bind(organization_id)
+    .bind(&filter.action)
+    .bind(&filter.actor_npub)
+    .bind(&filter.target_type)
+    .bind(&filter.target_id)
+    .bind(filter.since)
+    .bind(filter.until)
+    .bind(filter.limit())
+    .fetch_all(pool)
+    .await
+}
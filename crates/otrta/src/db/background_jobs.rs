@@ -0,0 +1,409 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+use crate::db::{dialect::add_seconds, Pool};
+use std::time::Duration;
+use uuid::Uuid;
+
+const JOB_COLUMNS: &str = "job_name, holder, lease_expires_at, last_started_at, last_finished_at,
+    last_status, last_error, last_duration_ms, run_count, default_interval_seconds,
+    interval_seconds, paused, next_run_at, trigger_requested_at, updated_at";
+
+/// Number of runs kept per job in `background_job_runs`.
+const RUN_HISTORY_LIMIT: i64 = 100;
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct BackgroundJob {
+    pub job_name: String,
+    /// Instance holding the lease, i.e. the one running this job.
+    pub holder: Option<String>,
+    pub lease_expires_at: Option<DateTime<Utc>>,
+    pub last_started_at: Option<DateTime<Utc>>,
+    pub last_finished_at: Option<DateTime<Utc>>,
+    pub last_status: Option<String>,
+    pub last_error: Option<String>,
+    pub last_duration_ms: Option<i64>,
+    pub run_count: i64,
+    pub default_interval_seconds: i32,
+    pub interval_seconds: Option<i32>,
+    pub paused: bool,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub trigger_requested_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl BackgroundJob {
+    pub fn interval(&self) -> Duration {
+        let seconds = self
+            .interval_seconds
+            .unwrap_or(self.default_interval_seconds)
+            .max(1);
+        Duration::from_secs(seconds as u64)
+    }
+
+    /// Whether the job should run now: triggered by hand, or unpaused and
+    /// past its next run.
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.trigger_requested_at.is_some()
+            || (!self.paused && self.next_run_at.is_none_or(|next| next <= now))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct BackgroundJobRun {
+    pub id: Uuid,
+    pub job_name: String,
+    pub instance_id: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub status: String,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+    pub triggered: bool,
+}
+
+/// Makes a job known to the scheduler, or updates its default interval when
+/// the configuration changes. Overrides, pause state and history set by
+/// earlier runs are kept.
+pub async fn register_job(
+    pool: &Pool,
+    job_name: &str,
+    default_interval: Duration,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!(
+        "INSERT INTO background_jobs (job_name, default_interval_seconds)
+         VALUES ($1, $2)
+         ON CONFLICT (job_name) DO UPDATE
+         SET default_interval_seconds = EXCLUDED.default_interval_seconds,
+             next_run_at = CASE
+                 WHEN background_jobs.interval_seconds IS NULL
+                  AND background_jobs.last_finished_at IS NOT NULL
+                 THEN {}
+                 ELSE background_jobs.next_run_at
+             END,
+             updated_at = $3",
+        add_seconds(
+            "background_jobs.last_finished_at",
+            "EXCLUDED.default_interval_seconds"
+        )
+    ))
+    .bind(job_name)
+    .bind(default_interval.as_secs().min(i32::MAX as u64) as i32)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_background_jobs(pool: &Pool) -> Result<Vec<BackgroundJob>, sqlx::Error> {
+    sqlx::query_as::<_, BackgroundJob>(&format!(
+        "SELECT {} FROM background_jobs ORDER BY job_name",
+        JOB_COLUMNS
+    ))
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_background_job(
+    pool: &Pool,
+    job_name: &str,
+) -> Result<Option<BackgroundJob>, sqlx::Error> {
+    sqlx::query_as::<_, BackgroundJob>(&format!(
+        "SELECT {} FROM background_jobs WHERE job_name = $1",
+        JOB_COLUMNS
+    ))
+    .bind(job_name)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Overrides a job's interval, or restores its default when `None`. The next
+/// run moves to match the new interval.
+pub async fn set_job_interval(
+    pool: &Pool,
+    job_name: &str,
+    interval_seconds: Option<i32>,
+) -> Result<Option<BackgroundJob>, sqlx::Error> {
+    sqlx::query_as::<_, BackgroundJob>(&format!(
+        "UPDATE background_jobs
+         SET interval_seconds = $2,
+             next_run_at = CASE
+                 WHEN last_finished_at IS NULL THEN next_run_at
+                 ELSE {}
+             END,
+             updated_at = $3
+         WHERE job_name = $1
+         RETURNING {}",
+        add_seconds("last_finished_at", "COALESCE($2, default_interval_seconds)"),
+        JOB_COLUMNS
+    ))
+    .bind(job_name)
+    .bind(interval_seconds)
+    .bind(Utc::now())
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn set_job_paused(
+    pool: &Pool,
+    job_name: &str,
+    paused: bool,
+) -> Result<Option<BackgroundJob>, sqlx::Error> {
+    sqlx::query_as::<_, BackgroundJob>(&format!(
+        "UPDATE background_jobs SET paused = $2, updated_at = $3
+         WHERE job_name = $1
+         RETURNING {}",
+        JOB_COLUMNS
+    ))
+    .bind(job_name)
+    .bind(paused)
+    .bind(Utc::now())
+    .fetch_optional(pool)
+    .await
+}
+
+/// Asks whichever instance leads the job to run it as soon as possible,
+/// even while paused.
+pub async fn request_job_run(
+    pool: &Pool,
+    job_name: &str,
+) -> Result<Option<BackgroundJob>, sqlx::Error> {
+    sqlx::query_as::<_, BackgroundJob>(&format!(
+        "UPDATE background_jobs SET trigger_requested_at = $2, updated_at = $2
+         WHERE job_name = $1
+         RETURNING {}",
+        JOB_COLUMNS
+    ))
+    .bind(job_name)
+    .bind(Utc::now())
+    .fetch_optional(pool)
+    .await
+}
+
+fn lease_expiry(now: DateTime<Utc>, ttl: Duration) -> DateTime<Utc> {
+    now + chrono::Duration::milliseconds(ttl.as_millis() as i64)
+}
+
+/// Takes the lease on `job_name` for `holder` if it is free, expired, or
+/// already held by `holder`, and marks a run as started. Returns whether the
+/// lease was obtained.
+pub async fn try_acquire_lease(
+    pool: &Pool,
+    job_name: &str,
+    holder: &str,
+    ttl: Duration,
+) -> Result<bool, sqlx::Error> {
+    let now = Utc::now();
+    let acquired = sqlx::query_scalar::<_, String>(
+        "INSERT INTO background_jobs (job_name, holder, lease_expires_at, last_started_at, last_status)
+         VALUES ($1, $2, $3, $4, 'running')
+         ON CONFLICT (job_name) DO UPDATE
+         SET holder = EXCLUDED.holder,
+             lease_expires_at = EXCLUDED.lease_expires_at,
+             last_started_at = $4,
+             last_status = 'running',
+             updated_at = $4
+         WHERE background_jobs.holder = EXCLUDED.holder
+            OR background_jobs.lease_expires_at IS NULL
+            OR background_jobs.lease_expires_at < $4
+         RETURNING job_name",
+    )
+    .bind(job_name)
+    .bind(holder)
+    .bind(lease_expiry(now, ttl))
+    .bind(now)
+    .fetch_optional(pool)
+    .await?;
+    Ok(acquired.is_some())
+}
+
+/// Pushes the expiry of a lease `holder` still owns. Returns false if the
+/// lease was lost in the meantime.
+pub async fn renew_lease(
+    pool: &Pool,
+    job_name: &str,
+    holder: &str,
+    ttl: Duration,
+) -> Result<bool, sqlx::Error> {
+    let now = Utc::now();
+    let result = sqlx::query(
+        "UPDATE background_jobs
+         SET lease_expires_at = $3, updated_at = $4
+         WHERE job_name = $1 AND holder = $2",
+    )
+    .bind(job_name)
+    .bind(holder)
+    .bind(lease_expiry(now, ttl))
+    .bind(now)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Records the outcome of a run and schedules the next one. The lease is
+/// kept so the same instance stays leader for its next run, and a manual
+/// trigger is cleared unless it arrived after this run started.
+pub async fn record_lease_run(
+    pool: &Pool,
+    job_name: &str,
+    holder: &str,
+    ttl: Duration,
+    error: Option<&str>,
+    duration: Duration,
+) -> Result<(), sqlx::Error> {
+    let now = Utc::now();
+    sqlx::query(&format!(
+        "UPDATE background_jobs
+         SET lease_expires_at = $3,
+             last_finished_at = $6,
+             last_status = CASE WHEN $4 IS NULL THEN 'success' ELSE 'failure' END,
+             last_error = $4,
+             last_duration_ms = $5,
+             run_count = run_count + 1,
+             next_run_at = {},
+             trigger_requested_at = CASE
+                 WHEN trigger_requested_at <= last_started_at THEN NULL
+                 ELSE trigger_requested_at
+             END,
+             updated_at = $6
+         WHERE job_name = $1 AND holder = $2",
+        add_seconds("$6", "COALESCE(interval_seconds, default_interval_seconds)")
+    ))
+    .bind(job_name)
+    .bind(holder)
+    .bind(lease_expiry(now, ttl))
+    .bind(error)
+    .bind(duration.as_millis().min(i64::MAX as u128) as i64)
+    .bind(now)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Gives up every lease `holder` owns so another instance can take over
+/// without waiting for them to expire.
+pub async fn release_leases(pool: &Pool, holder: &str) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE background_jobs
+         SET lease_expires_at = NULL,
+             last_error = CASE WHEN last_status = 'running' THEN 'interrupted by shutdown' ELSE last_error END,
+             last_status = CASE WHEN last_status = 'running' THEN 'failure' ELSE last_status END,
+             updated_at = $2
+         WHERE holder = $1",
+    )
+    .bind(holder)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Appends a run to the job's history, keeping only the most recent ones.
+pub async fn record_job_history(
+    pool: &Pool,
+    job_name: &str,
+    instance_id: &str,
+    started_at: DateTime<Utc>,
+    error: Option<&str>,
+    duration: Duration,
+    triggered: bool,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "INSERT INTO background_job_runs (job_name, instance_id, started_at, status, error, duration_ms, triggered)
+         VALUES ($1, $2, $3, CASE WHEN $4 IS NULL THEN 'success' ELSE 'failure' END, $4, $5, $6)",
+    )
+    .bind(job_name)
+    .bind(instance_id)
+    .bind(started_at)
+    .bind(error)
+    .bind(duration.as_millis().min(i64::MAX as u128) as i64)
+    .bind(triggered)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "DELETE FROM background_job_runs
+         WHERE job_name = $1
+           AND id NOT IN (
+               SELECT id FROM background_job_runs
+               WHERE job_name = $1
+               ORDER BY started_at DESC
+               LIMIT $2
+           )",
+    )
+    .bind(job_name)
+    .bind(RUN_HISTORY_LIMIT)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await
+}
+
+pub async fn get_job_runs(
+    pool: &Pool,
+    job_name: &str,
+    limit: i64,
+) -> Result<Vec<BackgroundJobRun>, sqlx::Error> {
+    sqlx::query_as::<_, BackgroundJobRun>(
+        "SELECT id, job_name, instance_id, started_at, finished_at, status, error, duration_ms, triggered
+         FROM background_job_runs
+         WHERE job_name = $1
+         ORDER BY started_at DESC
+         LIMIT $2",
+    )
+    .bind(job_name)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(paused: bool, next_run_at: Option<DateTime<Utc>>) -> BackgroundJob {
+        BackgroundJob {
+            job_name: "test".to_string(),
+            holder: None,
+            lease_expires_at: None,
+            last_started_at: None,
+            last_finished_at: None,
+            last_status: None,
+            last_error: None,
+            last_duration_ms: None,
+            run_count: 0,
+            default_interval_seconds: 300,
+            interval_seconds: None,
+            paused,
+            next_run_at,
+            trigger_requested_at: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_is_due() {
+        let now = Utc::now();
+        let later = now + chrono::Duration::minutes(5);
+
+        assert!(job(false, None).is_due(now));
+        assert!(job(false, Some(now)).is_due(now));
+        assert!(!job(false, Some(later)).is_due(now));
+        assert!(!job(true, None).is_due(now));
+
+        let mut triggered = job(true, Some(later));
+        triggered.trigger_requested_at = Some(now);
+        assert!(triggered.is_due(now));
+    }
+
+    #[test]
+    fn test_interval_override() {
+        let mut job = job(false, None);
+        assert_eq!(job.interval(), Duration::from_secs(300));
+        job.interval_seconds = Some(30);
+        assert_eq!(job.interval(), Duration::from_secs(30));
+    }
+}
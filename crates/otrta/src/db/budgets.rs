@@ -0,0 +1,388 @@
+use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::db::{dialect::FOR_UPDATE, Connection, Pool};
+use uuid::Uuid;
+
+use crate::exchange_rate::REPORTING_CURRENCY;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    Daily,
+    Weekly,
+    Monthly,
+    Total,
+}
+
+impl BudgetPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetPeriod::Daily => "daily",
+            BudgetPeriod::Weekly => "weekly",
+            BudgetPeriod::Monthly => "monthly",
+            BudgetPeriod::Total => "total",
+        }
+    }
+
+    /// Start of the period containing `now`, or `None` for lifetime budgets.
+    pub fn start(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let today = Utc
+            .with_ymd_and_hms(now.year(), now.month(), now.day(), 0, 0, 0)
+            .single()?;
+        match self {
+            BudgetPeriod::Daily => Some(today),
+            BudgetPeriod::Weekly => {
+                Some(today - Duration::days(now.weekday().num_days_from_monday() as i64))
+            }
+            BudgetPeriod::Monthly => Utc
+                .with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+                .single(),
+            BudgetPeriod::Total => None,
+        }
+    }
+}
+
+impl std::str::FromStr for BudgetPeriod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "daily" => Ok(BudgetPeriod::Daily),
+            "weekly" => Ok(BudgetPeriod::Weekly),
+            "monthly" => Ok(BudgetPeriod::Monthly),
+            "total" => Ok(BudgetPeriod::Total),
+            _ => Err(format!("Invalid budget period: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SpendingBudget {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub api_key_id: Option<Uuid>,
+    pub amount: f64,
+    pub currency: String,
+    pub period: String,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SpendingBudget {
+    pub fn period(&self) -> BudgetPeriod {
+        self.period.parse().unwrap_or(BudgetPeriod::Monthly)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetBudgetRequest {
+    pub amount: f64,
+    pub period: Option<BudgetPeriod>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetStatus {
+    #[serde(flatten)]
+    pub budget: SpendingBudget,
+    pub spent: f64,
+    pub remaining: f64,
+    pub period_start: Option<DateTime<Utc>>,
+}
+
+pub async fn upsert_budget(
+    pool: &Pool,
+    organization_id: &Uuid,
+    api_key_id: Option<&Uuid>,
+    request: &SetBudgetRequest,
+) -> Result<SpendingBudget, sqlx::Error> {
+    let period = request.period.unwrap_or(BudgetPeriod::Monthly);
+    let is_active = request.is_active.unwrap_or(true);
+
+    // The partial unique indexes can't be used as ON CONFLICT targets for
+    // both cases at once, so update first and insert when nothing matched.
+    let updated = sqlx::query_as::<_, SpendingBudget>(
+        "UPDATE spending_budgets
+         SET amount = $3, period = $4, is_active = $5, updated_at = $6
+         WHERE organization_id = $1 AND api_key_id IS NOT DISTINCT FROM $2
+         RETURNING id, organization_id, api_key_id, amount, currency, period, is_active, created_at, updated_at",
+    )
+    .bind(organization_id)
+    .bind(api_key_id)
+    .bind(request.amount)
+    .bind(period.as_str())
+    .bind(is_active)
+    .bind(Utc::now())
+    .fetch_optional(pool)
+    .await?;
+
+    if let Some(budget) = updated {
+        return Ok(budget);
+    }
+
+    sqlx::query_as::<_, SpendingBudget>(
+        "INSERT INTO spending_budgets (organization_id, api_key_id, amount, currency, period, is_active)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         RETURNING id, organization_id, api_key_id, amount, currency, period, is_active, created_at, updated_at",
+    )
+    .bind(organization_id)
+    .bind(api_key_id)
+    .bind(request.amount)
+    .bind(REPORTING_CURRENCY)
+    .bind(period.as_str())
+    .bind(is_active)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn get_budgets_for_organization(
+    pool: &Pool,
+    organization_id: &Uuid,
+) -> Result<Vec<SpendingBudget>, sqlx::Error> {
+    sqlx::query_as::<_, SpendingBudget>(
+        "SELECT id, organization_id, api_key_id, amount, currency, period, is_active, created_at, updated_at
+         FROM spending_budgets
+         WHERE organization_id = $1
+         ORDER BY api_key_id NULLS FIRST, created_at",
+    )
+    .bind(organization_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Active budgets that apply to a request: the organization's own budget and,
+/// when the request uses an API key, that key's budget.
+pub async fn get_applicable_budgets(
+    pool: &Pool,
+    organization_id: &Uuid,
+    api_key_id: Option<&Uuid>,
+) -> Result<Vec<SpendingBudget>, sqlx::Error> {
+    sqlx::query_as::<_, SpendingBudget>(
+        "SELECT id, organization_id, api_key_id, amount, currency, period, is_active, created_at, updated_at
+         FROM spending_budgets
+         WHERE organization_id = $1
+           AND is_active = TRUE
+           AND (api_key_id IS NULL OR api_key_id = $2)",
+    )
+    .bind(organization_id)
+    .bind(api_key_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn delete_budget(
+    pool: &Pool,
+    organization_id: &Uuid,
+    budget_id: &Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM spending_budgets WHERE id = $1 AND organization_id = $2")
+        .bind(budget_id)
+        .bind(organization_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// How long a reservation holds budget headroom when its request never gets
+/// as far as a charge entry, e.g. because every mint failed to pay.
+const RESERVATION_TTL_SECONDS: i64 = 120;
+
+/// Net fiat spend (charges and fees minus change and refunds) recorded in the
+/// ledger since `since`, valued at the exchange rate stored with each entry.
+/// Scoped to one API key, or to the whole organization when `api_key_id` is
+/// `None`.
+pub async fn get_fiat_spend(
+    pool: &Pool,
+    organization_id: &Uuid,
+    api_key_id: Option<&Uuid>,
+    since: Option<DateTime<Utc>>,
+) -> Result<f64, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    fiat_spend(&mut conn, organization_id, api_key_id, since, None).await
+}
+
+/// Like [`get_fiat_spend`], but entries written while no exchange rate was
+/// recorded are valued at `btc_rate` instead of being left out, so budgets
+/// don't open up during a rate outage.
+async fn fiat_spend(
+    conn: &mut Connection,
+    organization_id: &Uuid,
+    api_key_id: Option<&Uuid>,
+    since: Option<DateTime<Utc>>,
+    btc_rate: Option<f64>,
+) -> Result<f64, sqlx::Error> {
+    let spent: Option<f64> = sqlx::query_scalar(
+        "SELECT SUM(CASE WHEN entry_type IN ('charge', 'fee') THEN value ELSE -value END)
+         FROM (
+             SELECT entry_type,
+                    CASE
+                        WHEN fiat_amount IS NOT NULL THEN fiat_amount
+                        WHEN unit = 'msat'
+                            THEN CAST(amount AS DOUBLE PRECISION) / 100000000000.0 * CAST($5 AS DOUBLE PRECISION)
+                    END AS value
+             FROM ledger_entries
+             WHERE organization_id = $1
+               AND entry_type IN ('charge', 'fee', 'change', 'refund')
+               AND ($2 IS NULL OR api_key_id = $2)
+               AND ($3 IS NULL OR created_at >= $3)
+               AND (fiat_currency = $4 OR fiat_currency IS NULL)
+         ) entries",
+    )
+    .bind(organization_id)
+    .bind(api_key_id)
+    .bind(since)
+    .bind(REPORTING_CURRENCY)
+    .bind(btc_rate)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(spent.unwrap_or(0.0).max(0.0))
+}
+
+/// A budget a request would take past its limit, with what had already been
+/// spent or reserved against it.
+#[derive(Debug, Clone)]
+pub struct BudgetExceeded {
+    pub budget: SpendingBudget,
+    pub spent: f64,
+}
+
+/// Reserves `cost_msats`, valued at `btc_rate` (reporting currency per BTC),
+/// for `request_id` against every active budget that applies, or returns the
+/// first budget it would exceed. Spend the ledger couldn't value is counted
+/// at the same rate. The budget rows stay
+/// locked from the check until the reservation is written, so concurrent
+/// requests each see the others' reservations. The reservation is released
+/// when the request's charge is journaled, or by
+/// [`release_budget_reservation`].
+pub async fn reserve_budget(
+    pool: &Pool,
+    organization_id: &Uuid,
+    api_key_id: Option<&Uuid>,
+    request_id: &Uuid,
+    cost_msats: i64,
+    btc_rate: f64,
+) -> Result<Option<BudgetExceeded>, sqlx::Error> {
+    let now = Utc::now();
+    let fiat_amount = cost_msats as f64 / 100_000_000_000.0 * btc_rate;
+    let mut tx = pool.begin().await?;
+
+    // Writing first also takes SQLite's database lock, which stands in for
+    // the row locks below.
+    sqlx::query("DELETE FROM budget_reservations WHERE expires_at <= $1")
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+    let budgets = sqlx::query_as::<_, SpendingBudget>(&format!(
+        "SELECT id, organization_id, api_key_id, amount, currency, period, is_active, created_at, updated_at
+         FROM spending_budgets
+         WHERE organization_id = $1
+           AND is_active = TRUE
+           AND (api_key_id IS NULL OR api_key_id = $2){}",
+        FOR_UPDATE
+    ))
+    .bind(organization_id)
+    .bind(api_key_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if budgets.is_empty() {
+        return Ok(None);
+    }
+
+    for budget in budgets {
+        let since = budget.period().start(now);
+        let scope = budget.api_key_id.as_ref();
+        let reserved: Option<f64> = sqlx::query_scalar(
+            "SELECT SUM(fiat_amount) FROM budget_reservations
+             WHERE organization_id = $1 AND ($2 IS NULL OR api_key_id = $2)",
+        )
+        .bind(organization_id)
+        .bind(scope)
+        .fetch_one(&mut *tx)
+        .await?;
+        let spent = fiat_spend(&mut tx, organization_id, scope, since, Some(btc_rate)).await?
+            + reserved.unwrap_or(0.0);
+
+        if spent + fiat_amount > budget.amount {
+            return Ok(Some(BudgetExceeded { budget, spent }));
+        }
+    }
+
+    sqlx::query(
+        "INSERT INTO budget_reservations (request_id, organization_id, api_key_id, fiat_amount, expires_at)
+         VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(request_id)
+    .bind(organization_id)
+    .bind(api_key_id)
+    .bind(fiat_amount)
+    .bind(now + Duration::seconds(RESERVATION_TTL_SECONDS))
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(None)
+}
+
+/// Gives back the headroom held for a request that will not be charged.
+pub async fn release_budget_reservation(pool: &Pool, request_id: &Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM budget_reservations WHERE request_id = $1")
+        .bind(request_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_budget_status(
+    pool: &Pool,
+    budget: SpendingBudget,
+) -> Result<BudgetStatus, sqlx::Error> {
+    let period_start = budget.period().start(Utc::now());
+    let spent = get_fiat_spend(
+        pool,
+        &budget.organization_id,
+        budget.api_key_id.as_ref(),
+        period_start,
+    )
+    .await?;
+
+    Ok(BudgetStatus {
+        remaining: (budget.amount - spent).max(0.0),
+        spent,
+        period_start,
+        budget,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_period_start() {
+        // Thursday
+        let now = Utc.with_ymd_and_hms(2026, 10, 15, 13, 45, 0).unwrap();
+
+        assert_eq!(
+            BudgetPeriod::Daily.start(now),
+            Some(Utc.with_ymd_and_hms(2026, 10, 15, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            BudgetPeriod::Weekly.start(now),
+            Some(Utc.with_ymd_and_hms(2026, 10, 12, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            BudgetPeriod::Monthly.start(now),
+            Some(Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(BudgetPeriod::Total.start(now), None);
+    }
+}
@@ -1,8 +1,10 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::FromRow;
 use uuid::Uuid;
 
+use crate::db::Pool;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Credit {
     pub id: String,
@@ -12,6 +14,27 @@ pub struct Credit {
     pub redeemed: bool,
 }
 
+#[derive(FromRow)]
+struct CreditRow {
+    id: Uuid,
+    created_at: DateTime<Utc>,
+    token: String,
+    amount: String,
+    redeemed: bool,
+}
+
+impl From<CreditRow> for Credit {
+    fn from(row: CreditRow) -> Self {
+        Self {
+            id: row.id.to_string(),
+            created_at: row.created_at,
+            token: row.token,
+            amount: row.amount,
+            redeemed: row.redeemed,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PaginationInfo {
     pub total: i64,
@@ -26,27 +49,25 @@ pub struct CreditListResponse {
     pub pagination: PaginationInfo,
 }
 
-pub async fn add_credit(pool: &PgPool, token: &str, amount: &str) -> Result<Uuid, sqlx::Error> {
-    let rec = sqlx::query!(
+pub async fn add_credit(pool: &Pool, token: &str, amount: &str) -> Result<Uuid, sqlx::Error> {
+    sqlx::query_scalar(
         r#"
         INSERT INTO credits (id, created_at, token, amount, redeemed)
         VALUES ($1, $2, $3, $4, $5)
         RETURNING id
         "#,
-        Uuid::new_v4(),
-        Utc::now(),
-        token,
-        amount,
-        false
     )
+    .bind(Uuid::new_v4())
+    .bind(Utc::now())
+    .bind(token)
+    .bind(amount)
+    .bind(false)
     .fetch_one(pool)
-    .await?;
-
-    Ok(rec.id)
+    .await
 }
 
 pub async fn get_credits(
-    pool: &PgPool,
+    pool: &Pool,
     page: Option<i64>,
     page_size: Option<i64>,
 ) -> Result<CreditListResponse, sqlx::Error> {
@@ -67,8 +88,7 @@ pub async fn get_credits(
     let total_pages = (total + page_size - 1) / page_size;
 
     // Get paginated credits
-    let credits = sqlx::query_as!(
-        Credit,
+    let credits = sqlx::query_as::<_, CreditRow>(
         r#"
         SELECT 
             id,
@@ -80,11 +100,14 @@ pub async fn get_credits(
         ORDER BY created_at
         LIMIT $1 OFFSET $2
         "#,
-        page_size,
-        offset
     )
+    .bind(page_size)
+    .bind(offset)
     .fetch_all(pool)
-    .await?;
+    .await?
+    .into_iter()
+    .map(Credit::from)
+    .collect();
 
     Ok(CreditListResponse {
         data: credits,
@@ -0,0 +1,143 @@
+//! SQL that differs between the Postgres and SQLite backends. Everything
+//! else is written in the subset both understand.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    encode::IsNull,
+    error::BoxDynError,
+    {Decode, Encode, Type},
+};
+
+use super::Db;
+
+/// Stand-in for a NULL uuid in unique indexes, which treat NULLs as distinct.
+#[cfg(not(feature = "sqlite"))]
+pub const NIL_UUID: &str = "'00000000-0000-0000-0000-000000000000'::uuid";
+#[cfg(feature = "sqlite")]
+pub const NIL_UUID: &str = "X'00000000000000000000000000000000'";
+
+/// Suffix for a `SELECT` that locks the rows it reads until the transaction
+/// ends. SQLite has no row locks; its writers hold the whole database.
+#[cfg(not(feature = "sqlite"))]
+pub const FOR_UPDATE: &str = " FOR UPDATE";
+#[cfg(feature = "sqlite")]
+pub const FOR_UPDATE: &str = "";
+
+/// `value` is an element of the [`StringList`] expression `list`.
+#[cfg(not(feature = "sqlite"))]
+pub fn in_list(value: &str, list: &str) -> String {
+    format!("{} = ANY({})", value, list)
+}
+#[cfg(feature = "sqlite")]
+pub fn in_list(value: &str, list: &str) -> String {
+    format!("{} IN (SELECT value FROM json_each({}))", value, list)
+}
+
+/// Truncates a timestamp column to the start of its hour, day, week or month.
+#[cfg(not(feature = "sqlite"))]
+pub fn date_trunc(unit: &str, column: &str) -> String {
+    format!("date_trunc('{}', {})", unit, column)
+}
+#[cfg(feature = "sqlite")]
+pub fn date_trunc(unit: &str, column: &str) -> String {
+    // Weeks start on Monday, as in Postgres.
+    let start = match unit {
+        "hour" => format!("strftime('%Y-%m-%dT%H:00:00', {})", column),
+        "week" => format!(
+            "strftime('%Y-%m-%dT00:00:00', {}, '-' || ((CAST(strftime('%w', {}) AS INTEGER) + 6) % 7) || ' days')",
+            column, column
+        ),
+        "month" => format!("strftime('%Y-%m-01T00:00:00', {})", column),
+        _ => format!("strftime('%Y-%m-%dT00:00:00', {})", column),
+    };
+    format!("({} || '+00:00')", start)
+}
+
+/// The timestamp `column` moved forward by the integer expression `seconds`.
+#[cfg(not(feature = "sqlite"))]
+pub fn add_seconds(column: &str, seconds: &str) -> String {
+    format!("{} + make_interval(secs => {})", column, seconds)
+}
+#[cfg(feature = "sqlite")]
+pub fn add_seconds(column: &str, seconds: &str) -> String {
+    format!(
+        "strftime('%Y-%m-%dT%H:%M:%f+00:00', {}, ({}) || ' seconds')",
+        column, seconds
+    )
+}
+
+/// The text expression `expr` consists of decimal digits only.
+#[cfg(not(feature = "sqlite"))]
+pub fn is_digits(expr: &str) -> String {
+    format!("{} ~ '^[0-9]+$'", expr)
+}
+#[cfg(feature = "sqlite")]
+pub fn is_digits(expr: &str) -> String {
+    format!("({} <> '' AND {} NOT GLOB '*[^0-9]*')", expr, expr)
+}
+
+/// Any one uuid out of a group, for aggregates over a uuid column.
+#[cfg(not(feature = "sqlite"))]
+pub fn any_uuid(column: &str) -> String {
+    format!("MIN({}::text)::uuid", column)
+}
+#[cfg(feature = "sqlite")]
+pub fn any_uuid(column: &str) -> String {
+    format!("MIN({})", column)
+}
+
+/// A list of strings, stored as `TEXT[]` on Postgres and as a JSON array on
+/// SQLite, which has no array type.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StringList(pub Vec<String>);
+
+impl From<Vec<String>> for StringList {
+    fn from(values: Vec<String>) -> Self {
+        Self(values)
+    }
+}
+
+impl From<StringList> for Vec<String> {
+    fn from(list: StringList) -> Self {
+        list.0
+    }
+}
+
+#[cfg(not(feature = "sqlite"))]
+type Stored = Vec<String>;
+#[cfg(feature = "sqlite")]
+type Stored = String;
+
+impl Type<Db> for StringList {
+    fn type_info() -> <Db as sqlx::Database>::TypeInfo {
+        <Stored as Type<Db>>::type_info()
+    }
+
+    fn compatible(ty: &<Db as sqlx::Database>::TypeInfo) -> bool {
+        <Stored as Type<Db>>::compatible(ty)
+    }
+}
+
+impl<'q> Encode<'q, Db> for StringList {
+    fn encode_by_ref(
+        &self,
+        buf: &mut <Db as sqlx::Database>::ArgumentBuffer<'q>,
+    ) -> Result<IsNull, BoxDynError> {
+        #[cfg(not(feature = "sqlite"))]
+        let stored = self.0.clone();
+        #[cfg(feature = "sqlite")]
+        let stored = serde_json::to_string(&self.0)?;
+        <Stored as Encode<'q, Db>>::encode(stored, buf)
+    }
+}
+
+impl<'r> Decode<'r, Db> for StringList {
+    fn decode(value: <Db as sqlx::Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+        let stored = <Stored as Decode<'r, Db>>::decode(value)?;
+        #[cfg(not(feature = "sqlite"))]
+        return Ok(Self(stored));
+        #[cfg(feature = "sqlite")]
+        return Ok(Self(serde_json::from_str(&stored)?));
+    }
+}
@@ -0,0 +1,89 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::db::{Connection, Pool};
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ExchangeRateRecord {
+    pub id: i64,
+    pub currency: String,
+    pub rate: f64,
+    pub source: String,
+    pub fetched_at: DateTime<Utc>,
+}
+
+pub async fn insert_exchange_rate(
+    pool: &Pool,
+    currency: &str,
+    rate: f64,
+    source: &str,
+    fetched_at: DateTime<Utc>,
+) -> Result<ExchangeRateRecord, sqlx::Error> {
+    sqlx::query_as::<_, ExchangeRateRecord>(
+        "INSERT INTO exchange_rate_history (currency, rate, source, fetched_at)
+         VALUES ($1, $2, $3, $4)
+         RETURNING id, currency, rate, source, fetched_at",
+    )
+    .bind(currency)
+    .bind(rate)
+    .bind(source)
+    .bind(fetched_at)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn get_latest_exchange_rate(
+    conn: &mut Connection,
+    currency: &str,
+) -> Result<Option<ExchangeRateRecord>, sqlx::Error> {
+    sqlx::query_as::<_, ExchangeRateRecord>(
+        "SELECT id, currency, rate, source, fetched_at
+         FROM exchange_rate_history
+         WHERE currency = $1
+         ORDER BY fetched_at DESC
+         LIMIT 1",
+    )
+    .bind(currency)
+    .fetch_optional(conn)
+    .await
+}
+
+pub async fn get_exchange_rate_history(
+    pool: &Pool,
+    currency: &str,
+    since: Option<DateTime<Utc>>,
+    limit: i64,
+) -> Result<Vec<ExchangeRateRecord>, sqlx::Error> {
+    sqlx::query_as::<_, ExchangeRateRecord>(
+        "SELECT id, currency, rate, source, fetched_at
+         FROM exchange_rate_history
+         WHERE currency = $1 AND ($2 IS NULL OR fetched_at >= $2)
+         ORDER BY fetched_at DESC
+         LIMIT $3",
+    )
+    .bind(currency)
+    .bind(since)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Rate in effect at `at`: the latest one fetched at or before that time.
+pub async fn get_exchange_rate_at(
+    pool: &Pool,
+    currency: &str,
+    at: DateTime<Utc>,
+) -> Result<Option<ExchangeRateRecord>, sqlx::Error> {
+    sqlx::query_as::<_, ExchangeRateRecord>(
+        "SELECT id, currency, rate, source, fetched_at
+         FROM exchange_rate_history
+         WHERE currency = $1 AND fetched_at <= $2
+         ORDER BY fetched_at DESC
+         LIMIT 1",
+    )
+    .bind(currency)
+    .bind(at)
+    .fetch_optional(pool)
+    .await
+}
@@ -1,15 +1,16 @@
 use async_trait::async_trait;
-use sqlx::{Pool, Postgres};
 use std::fmt;
 use uuid::Uuid;
 
+use crate::db::Pool;
+
 #[async_trait]
 pub trait DbCrud<T, C, U> {
-    async fn get_by_id(pool: &Pool<Postgres>, id: &str) -> Result<Option<T>, sqlx::Error>;
-    async fn list_all(pool: &Pool<Postgres>) -> Result<Vec<T>, sqlx::Error>;
-    async fn create(pool: &Pool<Postgres>, data: &C) -> Result<T, sqlx::Error>;
-    async fn update(pool: &Pool<Postgres>, id: &str, data: &U) -> Result<T, sqlx::Error>;
-    async fn delete(pool: &Pool<Postgres>, id: &str) -> Result<bool, sqlx::Error>;
+    async fn get_by_id(pool: &Pool, id: &str) -> Result<Option<T>, sqlx::Error>;
+    async fn list_all(pool: &Pool) -> Result<Vec<T>, sqlx::Error>;
+    async fn create(pool: &Pool, data: &C) -> Result<T, sqlx::Error>;
+    async fn update(pool: &Pool, id: &str, data: &U) -> Result<T, sqlx::Error>;
+    async fn delete(pool: &Pool, id: &str) -> Result<bool, sqlx::Error>;
 }
 
 #[derive(Debug)]
@@ -45,11 +46,8 @@ pub fn generate_id(prefix: &str) -> String {
     format!("{}_{}", prefix, Uuid::new_v4().to_string().replace("-", ""))
 }
 
-pub async fn entity_exists(pool: &Pool<Postgres>, table: &str, id: &str) -> Result<bool, DbError> {
-    let query = format!(
-        "SELECT EXISTS(SELECT 1 FROM {} WHERE id = $1) as exists",
-        table
-    );
+pub async fn entity_exists(pool: &Pool, table: &str, id: &str) -> Result<bool, DbError> {
+    let query = format!("SELECT EXISTS(SELECT 1 FROM {} WHERE id = $1)", table);
 
     let result = sqlx::query_scalar::<_, bool>(&query)
         .bind(id)
@@ -60,13 +58,13 @@ pub async fn entity_exists(pool: &Pool<Postgres>, table: &str, id: &str) -> Resu
 }
 
 pub async fn entity_exists_by_field(
-    pool: &Pool<Postgres>,
+    pool: &Pool,
     table: &str,
     field: &str,
     value: &str,
 ) -> Result<bool, DbError> {
     let query = format!(
-        "SELECT EXISTS(SELECT 1 FROM {} WHERE {} = $1) as exists",
+        "SELECT EXISTS(SELECT 1 FROM {} WHERE {} = $1)",
         table, field
     );
 
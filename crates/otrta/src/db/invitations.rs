@@ -0,0 +1,148 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+use crate::db::{Connection, Pool};
+use crate::permissions::Role;
+use uuid::Uuid;
+
+const INVITATION_COLUMNS: &str =
+    "id, organization_id, npub, role, invited_by, created_at, expires_at, accepted_at, revoked_at";
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Invitation {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub npub: String,
+    pub role: String,
+    pub invited_by: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl Invitation {
+    pub fn role(&self) -> Role {
+        self.role.parse().unwrap_or(Role::Viewer)
+    }
+}
+
+/// Invites `npub`, replacing any invitation of theirs to the organization
+/// that is still open.
+pub async fn create_invitation(
+    pool: &Pool,
+    organization_id: &Uuid,
+    npub: &str,
+    role: Role,
+    invited_by: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<Invitation, sqlx::Error> {
+    let now = Utc::now();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "UPDATE organization_invitations SET revoked_at = $3
+         WHERE organization_id = $1 AND npub = $2
+           AND accepted_at IS NULL AND revoked_at IS NULL",
+    )
+    .bind(organization_id)
+    .bind(npub)
+    .bind(now)
+    .execute(&mut *tx)
+    .await?;
+
+    let invitation = sqlx::query_as::<_, Invitation>(&format!(
+        "INSERT INTO organization_invitations (id, organization_id, npub, role, invited_by, created_at, expires_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         RETURNING {}",
+        INVITATION_COLUMNS
+    ))
+    .bind(Uuid::new_v4())
+    .bind(organization_id)
+    .bind(npub)
+    .bind(role.as_str())
+    .bind(invited_by)
+    .bind(now)
+    .bind(expires_at)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(invitation)
+}
+
+pub async fn get_invitation(
+    pool: &Pool,
+    id: &Uuid,
+    organization_id: &Uuid,
+) -> Result<Option<Invitation>, sqlx::Error> {
+    sqlx::query_as::<_, Invitation>(&format!(
+        "SELECT {} FROM organization_invitations WHERE id = $1 AND organization_id = $2",
+        INVITATION_COLUMNS
+    ))
+    .bind(id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Invitations of the organization that can still be accepted.
+pub async fn get_open_invitations_for_organization(
+    pool: &Pool,
+    organization_id: &Uuid,
+) -> Result<Vec<Invitation>, sqlx::Error> {
+    sqlx::query_as::<_, Invitation>(&format!(
+        "SELECT {} FROM organization_invitations
+         WHERE organization_id = $1 AND accepted_at IS NULL AND revoked_at IS NULL
+           AND expires_at > $2
+         ORDER BY created_at DESC",
+        INVITATION_COLUMNS
+    ))
+    .bind(organization_id)
+    .bind(Utc::now())
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn revoke_invitation(
+    pool: &Pool,
+    id: &Uuid,
+    organization_id: &Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE organization_invitations SET revoked_at = $3
+         WHERE id = $1 AND organization_id = $2
+           AND accepted_at IS NULL AND revoked_at IS NULL",
+    )
+    .bind(id)
+    .bind(organization_id)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Marks every open invitation of `npub` accepted and returns them, oldest
+/// first. The caller adds the memberships on the same transaction, so an
+/// invitation is never used up without its membership.
+pub async fn accept_invitations(
+    conn: &mut Connection,
+    npub: &str,
+) -> Result<Vec<Invitation>, sqlx::Error> {
+    let now = Utc::now();
+    let mut invitations = sqlx::query_as::<_, Invitation>(&format!(
+        "UPDATE organization_invitations SET accepted_at = $2
+         WHERE npub = $1 AND accepted_at IS NULL AND revoked_at IS NULL AND expires_at > $2
+         RETURNING {}",
+        INVITATION_COLUMNS
+    ))
+    .bind(npub)
+    .bind(now)
+    .fetch_all(conn)
+    .await?;
+
+    invitations.sort_by_key(|invitation| invitation.created_at);
+    Ok(invitations)
+}
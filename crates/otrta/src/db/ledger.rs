@@ -0,0 +1,386 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::db::{Connection, Pool};
+use uuid::Uuid;
+
+use crate::db::{
+    mint::CurrencyUnit,
+    transaction::{fiat_value, TransactionContext},
+};
+use crate::exchange_rate::REPORTING_CURRENCY;
+use crate::request_id::current_request_id;
+
+/// Kind of journal entry. Charges, fees and withdrawals take ecash out of the
+/// organization wallet; change, refunds and deposits bring it back in.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryType {
+    Charge,
+    Change,
+    Refund,
+    Fee,
+    Deposit,
+    Withdrawal,
+}
+
+impl EntryType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntryType::Charge => "charge",
+            EntryType::Change => "change",
+            EntryType::Refund => "refund",
+            EntryType::Fee => "fee",
+            EntryType::Deposit => "deposit",
+            EntryType::Withdrawal => "withdrawal",
+        }
+    }
+
+    /// Sign of the posting against the wallet account.
+    fn wallet_sign(&self) -> i64 {
+        match self {
+            EntryType::Charge | EntryType::Fee | EntryType::Withdrawal => -1,
+            EntryType::Change | EntryType::Refund | EntryType::Deposit => 1,
+        }
+    }
+
+    /// Account the wallet posting is balanced against.
+    fn counter_account(&self) -> AccountType {
+        match self {
+            EntryType::Charge | EntryType::Change | EntryType::Refund => AccountType::Spend,
+            EntryType::Fee => AccountType::Fees,
+            EntryType::Deposit | EntryType::Withdrawal => AccountType::External,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountType {
+    /// Ecash held by the organization wallet.
+    Wallet,
+    /// Provider spend, attributed to an API key and/or user.
+    Spend,
+    /// Mint input fees paid on tokens sent to providers.
+    Fees,
+    /// Funds moved in or out of the wallet outside of proxied requests.
+    External,
+}
+
+impl AccountType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountType::Wallet => "wallet",
+            AccountType::Spend => "spend",
+            AccountType::Fees => "fees",
+            AccountType::External => "external",
+        }
+    }
+}
+
+/// Who and what a journal entry belongs to. Entries written with the same
+/// context share its `request_id`.
+#[derive(Debug, Clone)]
+pub struct LedgerContext<'a> {
+    pub request_id: Uuid,
+    pub organization_id: Uuid,
+    pub api_key_id: Option<&'a str>,
+    pub user_id: Option<&'a str>,
+    pub provider_url: Option<&'a str>,
+    pub model: Option<&'a str>,
+}
+
+impl<'a> LedgerContext<'a> {
+    pub fn new(organization_id: Uuid) -> Self {
+        Self {
+            request_id: Uuid::new_v4(),
+            organization_id,
+            api_key_id: None,
+            user_id: None,
+            provider_url: None,
+            model: None,
+        }
+    }
+
+    /// The same attribution for a row of the transactions table, whose
+    /// amount is in the mint's `unit`.
+    pub fn transaction_context<'b>(&'b self, unit: Option<&'b str>) -> TransactionContext<'b> {
+        TransactionContext {
+            api_key_id: self.api_key_id,
+            user_id: self.user_id,
+            provider_url: self.provider_url,
+            unit,
+            model: self.model,
+        }
+    }
+
+    fn api_key_uuid(&self) -> Option<Uuid> {
+        self.api_key_id.and_then(|id| Uuid::parse_str(id).ok())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct LedgerEntry {
+    pub id: Uuid,
+    pub request_id: Uuid,
+    pub organization_id: Uuid,
+    pub entry_type: String,
+    pub amount: i64,
+    pub unit: String,
+    pub api_key_id: Option<Uuid>,
+    pub user_id: Option<String>,
+    pub provider_url: Option<String>,
+    pub model: Option<String>,
+    pub fiat_amount: Option<f64>,
+    pub fiat_currency: Option<String>,
+    /// `X-Request-Id` of the HTTP request that wrote the entry.
+    pub http_request_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AccountBalance {
+    pub account_id: Uuid,
+    pub account_type: String,
+    pub api_key_id: Option<Uuid>,
+    pub user_id: Option<String>,
+    pub unit: String,
+    pub balance: i64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct BalanceFilter {
+    pub api_key_id: Option<Uuid>,
+    pub user_id: Option<String>,
+}
+
+/// Converts an amount in a mint unit to the ledger's canonical unit: msat for
+/// bitcoin units, the unit itself (minor units) for anything else.
+pub fn canonical_amount(amount: i64, unit: &CurrencyUnit) -> (i64, String) {
+    match unit {
+        CurrencyUnit::Sat => (amount * 1000, CurrencyUnit::Msat.to_string()),
+        CurrencyUnit::Msat => (amount, CurrencyUnit::Msat.to_string()),
+        CurrencyUnit::Custom(code) => (amount, code.clone()),
+    }
+}
+
+/// Writes a journal entry of `amount` (in the mint's `unit`) together with its
+/// balancing postings. Zero amounts are recorded without postings so free
+/// requests still show up against their request ID.
+pub async fn record_entry(
+    pool: &Pool,
+    ctx: &LedgerContext<'_>,
+    entry_type: EntryType,
+    amount: i64,
+    unit: &str,
+) -> Result<LedgerEntry, sqlx::Error> {
+    let unit = unit.parse::<CurrencyUnit>().unwrap_or(CurrencyUnit::Msat);
+    let (canonical, canonical_unit) = canonical_amount(amount.max(0), &unit);
+    let api_key_id = ctx.api_key_uuid();
+
+    let mut tx = pool.begin().await?;
+    let fiat = fiat_value(&mut tx, amount.max(0), &unit).await;
+
+    let entry = sqlx::query_as::<_, LedgerEntry>(
+        "INSERT INTO ledger_entries (request_id, organization_id, entry_type, amount, unit, api_key_id, user_id, provider_url, model, fiat_amount, fiat_currency, http_request_id)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+         RETURNING id, request_id, organization_id, entry_type, amount, unit, api_key_id, user_id, provider_url, model, fiat_amount, fiat_currency, http_request_id, created_at",
+    )
+    .bind(ctx.request_id)
+    .bind(ctx.organization_id)
+    .bind(entry_type.as_str())
+    .bind(canonical)
+    .bind(&canonical_unit)
+    .bind(api_key_id)
+    .bind(ctx.user_id)
+    .bind(ctx.provider_url)
+    .bind(ctx.model)
+    .bind(fiat.map(|(fiat_amount, _)| fiat_amount))
+    .bind(fiat.map(|_| REPORTING_CURRENCY))
+    .bind(current_request_id())
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if canonical > 0 {
+        let wallet = ensure_account(
+            &mut tx,
+            &ctx.organization_id,
+            AccountType::Wallet,
+            None,
+            None,
+            &canonical_unit,
+        )
+        .await?;
+        let counter = ensure_account(
+            &mut tx,
+            &ctx.organization_id,
+            entry_type.counter_account(),
+            api_key_id.as_ref(),
+            ctx.user_id,
+            &canonical_unit,
+        )
+        .await?;
+
+        let wallet_amount = entry_type.wallet_sign() * canonical;
+        sqlx::query(
+            "INSERT INTO ledger_postings (entry_id, account_id, amount) VALUES ($1, $2, $3), ($1, $4, $5)",
+        )
+        .bind(entry.id)
+        .bind(wallet)
+        .bind(wallet_amount)
+        .bind(counter)
+        .bind(-wallet_amount)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    // The charge now counts against budgets on its own, so its reservation
+    // goes in the same commit.
+    if entry_type == EntryType::Charge {
+        sqlx::query("DELETE FROM budget_reservations WHERE request_id = $1")
+            .bind(ctx.request_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(entry)
+}
+
+async fn ensure_account(
+    conn: &mut Connection,
+    organization_id: &Uuid,
+    account_type: AccountType,
+    api_key_id: Option<&Uuid>,
+    user_id: Option<&str>,
+    unit: &str,
+) -> Result<Uuid, sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO ledger_accounts (organization_id, account_type, api_key_id, user_id, unit)
+         VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(organization_id)
+    .bind(account_type.as_str())
+    .bind(api_key_id)
+    .bind(user_id)
+    .bind(unit)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query_scalar(
+        "SELECT id FROM ledger_accounts
+         WHERE organization_id = $1
+           AND account_type = $2
+           AND api_key_id IS NOT DISTINCT FROM $3
+           AND user_id IS NOT DISTINCT FROM $4
+           AND unit = $5",
+    )
+    .bind(organization_id)
+    .bind(account_type.as_str())
+    .bind(api_key_id)
+    .bind(user_id)
+    .bind(unit)
+    .fetch_one(&mut *conn)
+    .await
+}
+
+pub async fn get_entries_for_request(
+    pool: &Pool,
+    organization_id: &Uuid,
+    request_id: &Uuid,
+) -> Result<Vec<LedgerEntry>, sqlx::Error> {
+    sqlx::query_as::<_, LedgerEntry>(
+        "SELECT id, request_id, organization_id, entry_type, amount, unit, api_key_id, user_id, provider_url, model, fiat_amount, fiat_currency, http_request_id, created_at
+         FROM ledger_entries
+         WHERE organization_id = $1 AND request_id = $2
+         ORDER BY created_at",
+    )
+    .bind(organization_id)
+    .bind(request_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Balance of every ledger account of the organization, optionally narrowed
+/// to the accounts of one API key or user. Balances are debit-positive: the
+/// wallet account holds what the ledger believes the wallet contains, spend
+/// and fee accounts hold what has been paid out.
+pub async fn get_account_balances(
+    pool: &Pool,
+    organization_id: &Uuid,
+    filter: &BalanceFilter,
+) -> Result<Vec<AccountBalance>, sqlx::Error> {
+    sqlx::query_as::<_, AccountBalance>(
+        "SELECT a.id AS account_id, a.account_type, a.api_key_id, a.user_id, a.unit,
+                CAST(COALESCE(SUM(p.amount), 0) AS BIGINT) AS balance
+         FROM ledger_accounts a
+         LEFT JOIN ledger_postings p ON p.account_id = a.id
+         WHERE a.organization_id = $1
+           AND ($2 IS NULL OR a.api_key_id = $2)
+           AND ($3 IS NULL OR a.user_id = $3)
+         GROUP BY a.id
+         ORDER BY a.account_type, a.unit, a.api_key_id NULLS FIRST, a.user_id NULLS FIRST",
+    )
+    .bind(organization_id)
+    .bind(filter.api_key_id)
+    .bind(filter.user_id.as_deref())
+    .fetch_all(pool)
+    .await
+}
+
+/// Stores how long the provider took to answer a proxied request on the
+/// request's entries.
+pub async fn record_request_latency(
+    pool: &Pool,
+    request_id: &Uuid,
+    latency_ms: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE ledger_entries SET latency_ms = $2 WHERE request_id = $1")
+        .bind(request_id)
+        .bind(latency_ms)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Wallet account balance per canonical unit, as the ledger sees it.
+pub async fn get_wallet_balances(
+    pool: &Pool,
+    organization_id: &Uuid,
+) -> Result<Vec<(String, i64)>, sqlx::Error> {
+    sqlx::query_as::<_, (String, i64)>(
+        "SELECT a.unit, CAST(COALESCE(SUM(p.amount), 0) AS BIGINT)
+         FROM ledger_accounts a
+         LEFT JOIN ledger_postings p ON p.account_id = a.id
+         WHERE a.organization_id = $1 AND a.account_type = 'wallet'
+         GROUP BY a.unit",
+    )
+    .bind(organization_id)
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_amount() {
+        assert_eq!(
+            canonical_amount(21, &CurrencyUnit::Sat),
+            (21_000, "msat".to_string())
+        );
+        assert_eq!(
+            canonical_amount(21_000, &CurrencyUnit::Msat),
+            (21_000, "msat".to_string())
+        );
+        assert_eq!(
+            canonical_amount(150, &CurrencyUnit::Custom("usd".to_string())),
+            (150, "usd".to_string())
+        );
+    }
+}
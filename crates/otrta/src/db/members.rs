@@ -0,0 +1,189 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+use crate::db::{dialect::FOR_UPDATE, Connection, Pool};
+use crate::permissions::Role;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct OrganizationMember {
+    pub organization_id: Uuid,
+    pub npub: String,
+    pub role: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl OrganizationMember {
+    /// Unknown roles, which the schema rules out, get the least access.
+    pub fn role(&self) -> Role {
+        self.role.parse().unwrap_or(Role::Viewer)
+    }
+}
+
+/// Adds the user to the organization, or changes their role if they are
+/// already a member.
+pub async fn set_member_role(
+    pool: &Pool,
+    organization_id: &Uuid,
+    npub: &str,
+    role: Role,
+) -> Result<OrganizationMember, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    upsert_member_role(&mut conn, organization_id, npub, role).await
+}
+
+pub async fn upsert_member_role(
+    conn: &mut Connection,
+    organization_id: &Uuid,
+    npub: &str,
+    role: Role,
+) -> Result<OrganizationMember, sqlx::Error> {
+    let now = Utc::now();
+    sqlx::query_as::<_, OrganizationMember>(
+        "INSERT INTO organization_members (organization_id, npub, role, created_at, updated_at)
+         VALUES ($1, $2, $3, $4, $4)
+         ON CONFLICT (organization_id, npub) DO UPDATE SET role = $3, updated_at = $4
+         RETURNING organization_id, npub, role, created_at, updated_at",
+    )
+    .bind(organization_id)
+    .bind(npub)
+    .bind(role.as_str())
+    .bind(now)
+    .fetch_one(conn)
+    .await
+}
+
+/// Adds the user to the organization. An existing membership keeps its
+/// role.
+pub async fn add_member(
+    conn: &mut Connection,
+    organization_id: &Uuid,
+    npub: &str,
+    role: Role,
+) -> Result<(), sqlx::Error> {
+    let now = Utc::now();
+    sqlx::query(
+        "INSERT INTO organization_members (organization_id, npub, role, created_at, updated_at)
+         VALUES ($1, $2, $3, $4, $4)
+         ON CONFLICT (organization_id, npub) DO NOTHING",
+    )
+    .bind(organization_id)
+    .bind(npub)
+    .bind(role.as_str())
+    .bind(now)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_member(
+    pool: &Pool,
+    organization_id: &Uuid,
+    npub: &str,
+) -> Result<Option<OrganizationMember>, sqlx::Error> {
+    sqlx::query_as::<_, OrganizationMember>(
+        "SELECT organization_id, npub, role, created_at, updated_at
+         FROM organization_members
+         WHERE organization_id = $1 AND npub = $2",
+    )
+    .bind(organization_id)
+    .bind(npub)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn get_members(
+    pool: &Pool,
+    organization_id: &Uuid,
+) -> Result<Vec<OrganizationMember>, sqlx::Error> {
+    sqlx::query_as::<_, OrganizationMember>(
+        "SELECT organization_id, npub, role, created_at, updated_at
+         FROM organization_members
+         WHERE organization_id = $1
+         ORDER BY created_at ASC",
+    )
+    .bind(organization_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// The organizations the user belongs to, oldest membership first.
+pub async fn get_memberships_for_user(
+    pool: &Pool,
+    npub: &str,
+) -> Result<Vec<OrganizationMember>, sqlx::Error> {
+    sqlx::query_as::<_, OrganizationMember>(
+        "SELECT m.organization_id, m.npub, m.role, m.created_at, m.updated_at
+         FROM organization_members m
+         INNER JOIN organizations o ON o.id = m.organization_id
+         WHERE m.npub = $1 AND o.is_active = true
+         ORDER BY m.created_at ASC",
+    )
+    .bind(npub)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn remove_member(
+    pool: &Pool,
+    organization_id: &Uuid,
+    npub: &str,
+) -> Result<bool, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    delete_member(&mut conn, organization_id, npub).await
+}
+
+pub async fn delete_member(
+    conn: &mut Connection,
+    organization_id: &Uuid,
+    npub: &str,
+) -> Result<bool, sqlx::Error> {
+    let result =
+        sqlx::query("DELETE FROM organization_members WHERE organization_id = $1 AND npub = $2")
+            .bind(organization_id)
+            .bind(npub)
+            .execute(conn)
+            .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// The membership, locked until the transaction ends.
+pub async fn get_member_for_update(
+    conn: &mut Connection,
+    organization_id: &Uuid,
+    npub: &str,
+) -> Result<Option<OrganizationMember>, sqlx::Error> {
+    sqlx::query_as::<_, OrganizationMember>(&format!(
+        "SELECT organization_id, npub, role, created_at, updated_at
+         FROM organization_members
+         WHERE organization_id = $1 AND npub = $2{}",
+        FOR_UPDATE
+    ))
+    .bind(organization_id)
+    .bind(npub)
+    .fetch_optional(conn)
+    .await
+}
+
+/// Counts the organization's owners and locks their rows until the
+/// transaction ends, so two concurrent demotions can't each count the other.
+/// Rows are locked in one order, so such changes queue instead of
+/// deadlocking.
+pub async fn lock_owners(
+    conn: &mut Connection,
+    organization_id: &Uuid,
+) -> Result<usize, sqlx::Error> {
+    let owners: Vec<String> = sqlx::query_scalar(&format!(
+        "SELECT npub FROM organization_members
+         WHERE organization_id = $1 AND role = 'owner'
+         ORDER BY npub{}",
+        FOR_UPDATE
+    ))
+    .bind(organization_id)
+    .fetch_all(conn)
+    .await?;
+    Ok(owners.len())
+}
@@ -5,10 +5,77 @@ use sqlx::FromRow;
 use std::str::FromStr;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// A Cashu keyset unit. Bitcoin units are first-class; anything else a mint
+/// advertises (`usd`, `eur`, ...) is kept verbatim as a custom unit.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(into = "String", try_from = "String")]
 pub enum CurrencyUnit {
     Sat,
     Msat,
+    Custom(String),
+}
+
+impl CurrencyUnit {
+    pub fn is_bitcoin(&self) -> bool {
+        matches!(self, CurrencyUnit::Sat | CurrencyUnit::Msat)
+    }
+
+    /// Fiat code for pricing a custom unit against bitcoin, if any.
+    pub fn fiat_code(&self) -> Option<&str> {
+        match self {
+            CurrencyUnit::Custom(code) => Some(code.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Converts an msat amount into this unit, rounding up. Fiat units are
+    /// denominated in minor units (cents) as Cashu mints do, and need the
+    /// price of one bitcoin in that currency.
+    pub fn from_msats(&self, msats: i64, btc_price: Option<f64>) -> Option<i64> {
+        match self {
+            CurrencyUnit::Msat => Some(msats),
+            CurrencyUnit::Sat => Some((msats + 999) / 1000),
+            CurrencyUnit::Custom(_) => {
+                let price = btc_price.filter(|p| *p > 0.0)?;
+                let minor_units = msats as f64 / 100_000_000_000.0 * price * 100.0;
+                Some(minor_units.ceil() as i64)
+            }
+        }
+    }
+
+    /// Inverse of [`CurrencyUnit::from_msats`], used to compare costs across
+    /// mints denominated in different units.
+    pub fn to_msats(&self, amount: i64, btc_price: Option<f64>) -> Option<i64> {
+        match self {
+            CurrencyUnit::Msat => Some(amount),
+            CurrencyUnit::Sat => Some(amount * 1000),
+            CurrencyUnit::Custom(_) => {
+                let price = btc_price.filter(|p| *p > 0.0)?;
+                let msats = amount as f64 / 100.0 / price * 100_000_000_000.0;
+                Some(msats.ceil() as i64)
+            }
+        }
+    }
+
+    pub fn to_cdk(&self) -> cdk::nuts::CurrencyUnit {
+        match self {
+            CurrencyUnit::Sat => cdk::nuts::CurrencyUnit::Sat,
+            CurrencyUnit::Msat => cdk::nuts::CurrencyUnit::Msat,
+            CurrencyUnit::Custom(unit) => unit
+                .parse()
+                .unwrap_or_else(|_| cdk::nuts::CurrencyUnit::Custom(unit.clone())),
+        }
+    }
+}
+
+impl From<cdk::nuts::CurrencyUnit> for CurrencyUnit {
+    fn from(unit: cdk::nuts::CurrencyUnit) -> Self {
+        match unit {
+            cdk::nuts::CurrencyUnit::Sat => CurrencyUnit::Sat,
+            cdk::nuts::CurrencyUnit::Msat => CurrencyUnit::Msat,
+            other => CurrencyUnit::Custom(other.to_string().to_lowercase()),
+        }
+    }
 }
 
 impl std::fmt::Display for CurrencyUnit {
@@ -16,6 +83,7 @@ impl std::fmt::Display for CurrencyUnit {
         match self {
             CurrencyUnit::Sat => write!(f, "sat"),
             CurrencyUnit::Msat => write!(f, "msat"),
+            CurrencyUnit::Custom(unit) => write!(f, "{}", unit),
         }
     }
 }
@@ -24,14 +92,32 @@ impl FromStr for CurrencyUnit {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
+        let unit = s.trim().to_lowercase();
+        match unit.as_str() {
             "sat" => Ok(CurrencyUnit::Sat),
             "msat" => Ok(CurrencyUnit::Msat),
+            _ if !unit.is_empty() && unit.chars().all(|c| c.is_ascii_alphanumeric()) => {
+                Ok(CurrencyUnit::Custom(unit))
+            }
             _ => Err(format!("Invalid currency unit: {}", s)),
         }
     }
 }
 
+impl From<CurrencyUnit> for String {
+    fn from(unit: CurrencyUnit) -> Self {
+        unit.to_string()
+    }
+}
+
+impl TryFrom<String> for CurrencyUnit {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Mint {
     pub id: i32,
@@ -106,6 +192,7 @@ pub struct MintUnit {
     pub unit: String,
     pub keyset_id: String,
     pub active: bool,
+    pub input_fee_ppk: i64,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
@@ -122,6 +209,8 @@ pub struct KeysetInfo {
     pub id: String,
     pub unit: String,
     pub active: bool,
+    #[serde(default)]
+    pub input_fee_ppk: u64,
 }
 
 pub async fn get_all_mints(db: &Pool) -> Result<Vec<Mint>, sqlx::Error> {
@@ -198,12 +287,13 @@ pub async fn create_mint(db: &Pool, request: CreateMintRequest) -> Result<Mint,
 
     let mint = sqlx::query_as::<_, Mint>(
         "INSERT INTO mints (mint_url, currency_unit, is_active, name, organization_id, updated_at)
-         VALUES ($1, $2, TRUE, $3, NULL, NOW())
+         VALUES ($1, $2, TRUE, $3, NULL, $4)
          RETURNING id, mint_url, currency_unit, is_active, name, organization_id, created_at, updated_at",
     )
     .bind(&request.mint_url)
     .bind(&currency_unit)
     .bind(&request.name)
+    .bind(Utc::now())
     .fetch_one(db)
     .await?;
 
@@ -226,7 +316,7 @@ pub async fn update_mint(
          SET is_active = COALESCE($1, is_active),
              name = COALESCE($2, name),
              currency_unit = COALESCE($3, currency_unit),
-             updated_at = NOW()
+             updated_at = $5
          WHERE id = $4
          RETURNING id, mint_url, currency_unit, is_active, name, organization_id, created_at, updated_at",
     )
@@ -234,6 +324,7 @@ pub async fn update_mint(
     .bind(request.name)
     .bind(request.currency_unit)
     .bind(id)
+    .bind(Utc::now())
     .fetch_one(db)
     .await?;
 
@@ -254,9 +345,10 @@ pub async fn set_mint_active_status(
     id: i32,
     is_active: bool,
 ) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("UPDATE mints SET is_active = $1, updated_at = NOW() WHERE id = $2")
+    let result = sqlx::query("UPDATE mints SET is_active = $1, updated_at = $3 WHERE id = $2")
         .bind(is_active)
         .bind(id)
+        .bind(Utc::now())
         .execute(db)
         .await?;
 
@@ -324,13 +416,14 @@ pub async fn create_mint_for_organization(
 
     let mint = sqlx::query_as::<_, Mint>(
         "INSERT INTO mints (mint_url, currency_unit, is_active, name, organization_id, updated_at)
-         VALUES ($1, $2, TRUE, $3, $4, NOW())
+         VALUES ($1, $2, TRUE, $3, $4, $5)
          RETURNING id, mint_url, currency_unit, is_active, name, organization_id, created_at, updated_at",
     )
     .bind(&request.mint_url)
     .bind(&currency_unit)
     .bind(&request.name)
     .bind(organization_id)
+    .bind(Utc::now())
     .fetch_one(db)
     .await?;
 
@@ -348,7 +441,7 @@ pub async fn update_mint_for_organization(
          SET is_active = COALESCE($3, is_active),
              name = COALESCE($4, name),
              currency_unit = COALESCE($5, currency_unit),
-             updated_at = NOW()
+             updated_at = $6
          WHERE id = $1 AND (organization_id IS NULL OR organization_id = $2)
          RETURNING id, mint_url, currency_unit, is_active, name, organization_id, created_at, updated_at",
     )
@@ -357,6 +450,7 @@ pub async fn update_mint_for_organization(
     .bind(request.is_active)
     .bind(request.name)
     .bind(request.currency_unit)
+    .bind(Utc::now())
     .fetch_optional(db)
     .await?;
 
@@ -386,12 +480,13 @@ pub async fn set_mint_active_status_for_organization(
     is_active: bool,
 ) -> Result<bool, sqlx::Error> {
     let result = sqlx::query(
-        "UPDATE mints SET is_active = $3, updated_at = NOW() 
+        "UPDATE mints SET is_active = $3, updated_at = $4 
          WHERE id = $1 AND (organization_id IS NULL OR organization_id = $2)",
     )
     .bind(id)
     .bind(organization_id)
     .bind(is_active)
+    .bind(Utc::now())
     .execute(db)
     .await?;
 
@@ -402,7 +497,7 @@ pub async fn discover_mint_keysets(
     mint_url: &str,
 ) -> Result<Vec<KeysetInfo>, Box<dyn std::error::Error>> {
     let client = reqwest::Client::new();
-    let keys_url = format!("{}/v1/keys", mint_url.trim_end_matches('/'));
+    let keys_url = format!("{}/v1/keysets", mint_url.trim_end_matches('/'));
 
     let response = client
         .get(&keys_url)
@@ -434,6 +529,10 @@ pub async fn discover_mint_keysets(
                                 .get("active")
                                 .and_then(|a| a.as_bool())
                                 .unwrap_or(true),
+                            input_fee_ppk: keyset
+                                .get("input_fee_ppk")
+                                .and_then(|f| f.as_u64())
+                                .unwrap_or(0),
                         });
                     }
                 }
@@ -446,6 +545,7 @@ pub async fn discover_mint_keysets(
             id: "default".to_string(),
             unit: "msat".to_string(),
             active: true,
+            input_fee_ppk: 0,
         });
     }
 
@@ -461,18 +561,21 @@ pub async fn create_mint_units(
 
     for keyset in keysets {
         let unit = sqlx::query_as::<_, MintUnit>(
-            "INSERT INTO mint_units (mint_id, unit, keyset_id, active, updated_at)
-             VALUES ($1, $2, $3, $4, NOW())
+            "INSERT INTO mint_units (mint_id, unit, keyset_id, active, input_fee_ppk, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6)
              ON CONFLICT (mint_id, unit) DO UPDATE SET
                 keyset_id = EXCLUDED.keyset_id,
                 active = EXCLUDED.active,
-                updated_at = NOW()
-             RETURNING id, mint_id, unit, keyset_id, active, created_at, updated_at",
+                input_fee_ppk = EXCLUDED.input_fee_ppk,
+                updated_at = $6
+             RETURNING id, mint_id, unit, keyset_id, active, input_fee_ppk, created_at, updated_at",
         )
         .bind(mint_id)
         .bind(&keyset.unit)
         .bind(&keyset.id)
         .bind(keyset.active)
+        .bind(keyset.input_fee_ppk as i64)
+        .bind(Utc::now())
         .fetch_one(db)
         .await?;
 
@@ -484,7 +587,7 @@ pub async fn create_mint_units(
 
 pub async fn get_mint_units(db: &Pool, mint_id: i32) -> Result<Vec<MintUnit>, sqlx::Error> {
     let units = sqlx::query_as::<_, MintUnit>(
-        "SELECT id, mint_id, unit, keyset_id, active, created_at, updated_at
+        "SELECT id, mint_id, unit, keyset_id, active, input_fee_ppk, created_at, updated_at
          FROM mint_units
          WHERE mint_id = $1 AND active = TRUE
          ORDER BY unit",
@@ -496,6 +599,46 @@ pub async fn get_mint_units(db: &Pool, mint_id: i32) -> Result<Vec<MintUnit>, sq
     Ok(units)
 }
 
+pub async fn get_mint_unit_input_fee_ppk(
+    db: &Pool,
+    mint_id: i32,
+    unit: &str,
+) -> Result<u64, sqlx::Error> {
+    let fee: Option<i64> = sqlx::query_scalar(
+        "SELECT input_fee_ppk FROM mint_units
+         WHERE mint_id = $1 AND unit = $2 AND active = TRUE",
+    )
+    .bind(mint_id)
+    .bind(unit)
+    .fetch_optional(db)
+    .await?;
+
+    Ok(fee.unwrap_or(0).max(0) as u64)
+}
+
+/// Picks the active keyset with the lowest input fee for every unit a mint
+/// advertises. Inactive keysets are dropped so a rotated-out keyset never
+/// overwrites the current one in `mint_units`.
+pub fn select_active_keysets(keysets: &[KeysetInfo]) -> Vec<KeysetInfo> {
+    let mut by_unit: std::collections::HashMap<&str, &KeysetInfo> =
+        std::collections::HashMap::new();
+
+    for keyset in keysets.iter().filter(|k| k.active) {
+        by_unit
+            .entry(keyset.unit.as_str())
+            .and_modify(|current| {
+                if keyset.input_fee_ppk < current.input_fee_ppk {
+                    *current = keyset;
+                }
+            })
+            .or_insert(keyset);
+    }
+
+    let mut selected: Vec<KeysetInfo> = by_unit.into_values().cloned().collect();
+    selected.sort_by(|a, b| a.unit.cmp(&b.unit));
+    selected
+}
+
 pub async fn get_mint_with_units(
     db: &Pool,
     mint_id: i32,
@@ -546,3 +689,38 @@ pub async fn get_active_mints_with_units_for_organization(
 
     Ok(mints_with_units)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_currency_unit_parsing() {
+        assert_eq!("sat".parse::<CurrencyUnit>().unwrap(), CurrencyUnit::Sat);
+        assert_eq!("MSAT".parse::<CurrencyUnit>().unwrap(), CurrencyUnit::Msat);
+        assert_eq!(
+            "USD".parse::<CurrencyUnit>().unwrap(),
+            CurrencyUnit::Custom("usd".to_string())
+        );
+        assert!("".parse::<CurrencyUnit>().is_err());
+        assert_eq!(CurrencyUnit::Custom("eur".to_string()).to_string(), "eur");
+        assert_eq!(
+            CurrencyUnit::from(cdk::nuts::CurrencyUnit::Usd),
+            CurrencyUnit::Custom("usd".to_string())
+        );
+    }
+
+    #[test]
+    fn test_currency_unit_conversion() {
+        assert_eq!(CurrencyUnit::Msat.from_msats(1500, None), Some(1500));
+        assert_eq!(CurrencyUnit::Sat.from_msats(1500, None), Some(2));
+
+        let usd = CurrencyUnit::Custom("usd".to_string());
+        assert_eq!(usd.from_msats(1000, None), None);
+        // 100_000 sats at 100k USD/BTC is 100 USD = 10_000 cents
+        assert_eq!(usd.from_msats(100_000_000, Some(100_000.0)), Some(10_000));
+        // Sub-cent costs round up to a whole cent
+        assert_eq!(usd.from_msats(1000, Some(100_000.0)), Some(1));
+        assert_eq!(usd.to_msats(10_000, Some(100_000.0)), Some(100_000_000));
+    }
+}
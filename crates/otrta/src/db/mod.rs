@@ -1,16 +1,486 @@
+pub mod analytics;
 pub mod api_keys;
+pub mod audit_log;
+pub mod background_jobs;
+pub mod budgets;
 pub mod credit;
+pub mod dialect;
+pub mod exchange_rates;
 pub mod helpers;
+pub mod invitations;
+pub mod ledger;
+pub mod members;
 pub mod mint;
 pub mod model_pricing;
 pub mod models;
 pub mod nwc;
 pub mod organizations;
+pub mod payment_intents;
 pub mod provider;
+pub mod retention;
 pub mod server_config;
+pub mod sessions;
 pub mod transaction;
+pub mod usage_export;
 pub mod user_search_groups;
 pub mod user_searches;
 pub mod users;
 pub use helpers::*;
-pub type Pool = sqlx::PgPool;
+
+/// The database the crate is built against: Postgres by default, SQLite
+/// with the `sqlite` feature.
+#[cfg(not(feature = "sqlite"))]
+pub type Db = sqlx::Postgres;
+#[cfg(feature = "sqlite")]
+pub type Db = sqlx::Sqlite;
+
+pub type Pool = sqlx::Pool<Db>;
+pub type Connection = <Db as sqlx::Database>::Connection;
+
+/// A fresh in-memory database with the SQLite schema applied.
+#[cfg(all(test, feature = "sqlite"))]
+pub(crate) async fn test_pool() -> Pool {
+    // Every connection to `:memory:` is its own database, so keep exactly one.
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::migrate!("../otrta-ui/migrations-sqlite")
+        .run(&pool)
+        .await
+        .unwrap();
+    pool
+}
+
+/// Runs the SQLite schema and one query from each module against an
+/// in-memory database, since the Postgres build never compiles the SQLite
+/// dialect.
+#[cfg(all(test, feature = "sqlite"))]
+mod sqlite_tests {
+    use super::*;
+    use crate::{
+        db::{
+            dialect::{add_seconds, date_trunc, in_list, StringList},
+            ledger::{EntryType, LedgerContext},
+            mint::CreateMintRequest,
+            transaction::{TransactionDirection, TransactionType},
+        },
+        models::{CreateOrganizationRequest, CreateUserRequest},
+        permissions::Role,
+    };
+    use chrono::{Duration, TimeZone, Utc};
+    use futures_util::StreamExt;
+    use uuid::Uuid;
+
+    const NPUB: &str = "npub1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqq";
+    /// USD per BTC.
+    const RATE: f64 = 100_000.0;
+
+    #[tokio::test]
+    async fn test_dialect() {
+        let pool = test_pool().await;
+        // Thursday
+        let at = Utc.with_ymd_and_hms(2026, 10, 15, 13, 45, 30).unwrap();
+
+        for (unit, expected) in [
+            ("hour", Utc.with_ymd_and_hms(2026, 10, 15, 13, 0, 0)),
+            ("day", Utc.with_ymd_and_hms(2026, 10, 15, 0, 0, 0)),
+            ("week", Utc.with_ymd_and_hms(2026, 10, 12, 0, 0, 0)),
+            ("month", Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0)),
+        ] {
+            let truncated: chrono::DateTime<Utc> =
+                sqlx::query_scalar(&format!("SELECT {}", date_trunc(unit, "$1")))
+                    .bind(at)
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap();
+            assert_eq!(truncated, expected.unwrap(), "{}", unit);
+        }
+
+        let later: chrono::DateTime<Utc> =
+            sqlx::query_scalar(&format!("SELECT {}", add_seconds("$1", "$2")))
+                .bind(at)
+                .bind(90)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(later, at + Duration::seconds(90));
+
+        let list = StringList(vec!["a".to_string(), "b,c".to_string()]);
+        let round_trip: StringList = sqlx::query_scalar("SELECT $1")
+            .bind(list.clone())
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(round_trip, list);
+
+        for (value, expected) in [("b,c", true), ("b", false)] {
+            let found: bool = sqlx::query_scalar(&format!("SELECT {}", in_list("$1", "$2")))
+                .bind(value)
+                .bind(list.clone())
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assert_eq!(found, expected, "{}", value);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_every_module_runs_on_sqlite() {
+        let pool = test_pool().await;
+        let now = Utc::now();
+
+        let org = organizations::create_organization(
+            &pool,
+            &CreateOrganizationRequest {
+                name: "acme".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        assert!(organizations::get_organization_by_id(&pool, &org.id)
+            .await
+            .unwrap()
+            .is_some());
+
+        users::create_user(
+            &pool,
+            &CreateUserRequest {
+                npub: NPUB.to_string(),
+                display_name: None,
+                email: None,
+                organization_id: org.id,
+            },
+        )
+        .await
+        .unwrap();
+        assert!(users::get_user_by_npub(&pool, NPUB)
+            .await
+            .unwrap()
+            .is_some());
+
+        members::set_member_role(&pool, &org.id, NPUB, Role::Owner)
+            .await
+            .unwrap();
+        assert_eq!(members::get_members(&pool, &org.id).await.unwrap().len(), 1);
+        {
+            let mut tx = pool.begin().await.unwrap();
+            assert_eq!(members::lock_owners(&mut tx, &org.id).await.unwrap(), 1);
+            assert!(members::get_member_for_update(&mut tx, &org.id, NPUB)
+                .await
+                .unwrap()
+                .is_some());
+        }
+
+        invitations::create_invitation(
+            &pool,
+            &org.id,
+            NPUB,
+            Role::Member,
+            NPUB,
+            now + Duration::days(1),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            invitations::get_open_invitations_for_organization(&pool, &org.id)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        let invitation = invitations::get_open_invitations_for_organization(&pool, &org.id)
+            .await
+            .unwrap()
+            .remove(0);
+        assert!(invitations::get_invitation(&pool, &invitation.id, &org.id)
+            .await
+            .unwrap()
+            .is_some());
+
+        let session =
+            sessions::create_session(&pool, NPUB, &org.id, "hash", None, now + Duration::hours(1))
+                .await
+                .unwrap();
+        assert!(sessions::get_active_session(&pool, &session.id)
+            .await
+            .unwrap()
+            .is_some());
+
+        server_config::create_with_seed_for_organization(&pool, "seed", &org.id)
+            .await
+            .unwrap();
+        assert!(server_config::get_config_by_organization(&pool, &org.id)
+            .await
+            .unwrap()
+            .is_some());
+
+        assert!(
+            helpers::entity_exists(&pool, "organizations", &org.id.to_string())
+                .await
+                .is_ok()
+        );
+
+        let mint = mint::create_mint_for_organization(
+            &pool,
+            CreateMintRequest {
+                mint_url: "https://mint.example".to_string(),
+                currency_unit: Some("sat".to_string()),
+                name: None,
+            },
+            &org.id,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            mint::get_mint_unit_input_fee_ppk(&pool, mint.id, "sat")
+                .await
+                .unwrap(),
+            0
+        );
+
+        let provider = provider::create_custom_provider_for_organization(
+            &pool,
+            provider::CreateCustomProviderRequest {
+                name: "provider".to_string(),
+                url: "https://provider.example".to_string(),
+                mints: vec!["https://mint.example".to_string()],
+                use_onion: false,
+            },
+            &org.id,
+        )
+        .await
+        .unwrap();
+        assert_eq!(provider.mints, vec!["https://mint.example".to_string()]);
+        models::get_models_for_organization(&pool, &org.id)
+            .await
+            .unwrap();
+        model_pricing::get_model_pricing_comparison(&pool, &org.id)
+            .await
+            .unwrap();
+
+        api_keys::get_all_api_keys(&pool, Some(&org.id.to_string()), 1, 10)
+            .await
+            .unwrap();
+
+        nwc::create_nwc_connection(
+            &pool,
+            &org.id,
+            nwc::CreateNwcConnectionRequest {
+                name: "wallet".to_string(),
+                connection_uri: "nostr+walletconnect://x".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            nwc::get_nwc_connections_for_organization(&pool, &org.id)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        exchange_rates::insert_exchange_rate(&pool, "usd", RATE, "test", now)
+            .await
+            .unwrap();
+        let mut conn = pool.acquire().await.unwrap();
+        let rate = exchange_rates::get_latest_exchange_rate(&mut conn, "usd")
+            .await
+            .unwrap()
+            .unwrap();
+        drop(conn);
+        assert_eq!(rate.rate, RATE);
+
+        transaction::add_transaction(
+            &pool,
+            "cashuA",
+            "1000",
+            TransactionDirection::Outgoing,
+            TransactionType::Api,
+            &transaction::TransactionContext {
+                unit: Some("sat"),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        transaction::scrub_expired_tokens(&pool).await.unwrap();
+        credit::add_credit(&pool, "cashuA", "1000").await.unwrap();
+        credit::get_credits(&pool, None, None).await.unwrap();
+
+        // 1000 sat at 100k USD/BTC is 1 USD
+        budgets::upsert_budget(
+            &pool,
+            &org.id,
+            None,
+            &budgets::SetBudgetRequest {
+                amount: 1.5,
+                period: None,
+                is_active: None,
+            },
+        )
+        .await
+        .unwrap();
+        let first = Uuid::new_v4();
+        assert!(
+            budgets::reserve_budget(&pool, &org.id, None, &first, 1_000_000, RATE)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let exceeded =
+            budgets::reserve_budget(&pool, &org.id, None, &Uuid::new_v4(), 1_000_000, RATE)
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(exceeded.spent, 1.0);
+
+        // The charge takes over from the reservation
+        let ledger = LedgerContext {
+            request_id: first,
+            model: Some("gpt"),
+            ..LedgerContext::new(org.id)
+        };
+        payment_intents::create_payment_intent(&pool, &ledger)
+            .await
+            .unwrap();
+        ledger::record_entry(&pool, &ledger, EntryType::Charge, 1000, "sat")
+            .await
+            .unwrap();
+        let budget = budgets::get_budgets_for_organization(&pool, &org.id)
+            .await
+            .unwrap()
+            .remove(0);
+        let status = budgets::get_budget_status(&pool, budget).await.unwrap();
+        assert!((status.spent - 1.0).abs() < 1e-9);
+        assert!(
+            budgets::reserve_budget(&pool, &org.id, None, &Uuid::new_v4(), 250_000, RATE)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        // A charge journaled during a rate outage still counts
+        sqlx::query("UPDATE ledger_entries SET fiat_amount = NULL, fiat_currency = NULL")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let exceeded =
+            budgets::reserve_budget(&pool, &org.id, None, &Uuid::new_v4(), 1_000_000, RATE)
+                .await
+                .unwrap()
+                .unwrap();
+        assert!((exceeded.spent - 1.25).abs() < 1e-9);
+
+        assert_eq!(
+            ledger::get_account_balances(&pool, &org.id, &Default::default())
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        let groups = analytics::get_spend_analytics(
+            &pool,
+            &org.id,
+            &analytics::AnalyticsQuery {
+                group_by: vec![analytics::AnalyticsDimension::Model],
+                bucket: Some(analytics::TimeBucket::Day),
+                start_date: now - Duration::days(1),
+                end_date: now + Duration::days(1),
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(groups.len(), 1);
+        let filter = usage_export::UsageExportFilter::default();
+        let rows: Vec<_> = usage_export::stream_usage(&pool, &org.id, &filter)
+            .collect()
+            .await;
+        assert_eq!(rows.len(), 1);
+        assert!(rows[0].is_ok());
+
+        // Every setting, with both transaction actions, so each purge query
+        // runs. Enforcement logs and skips organizations that fail.
+        for (organization_id, action) in [
+            (None, retention::TransactionRetentionAction::Delete),
+            (
+                Some(&org.id),
+                retention::TransactionRetentionAction::Aggregate,
+            ),
+            (Some(&org.id), retention::TransactionRetentionAction::Delete),
+        ] {
+            retention::upsert_retention_policy(
+                &pool,
+                organization_id,
+                &retention::SetRetentionPolicyRequest {
+                    transaction_retention_days: Some(30),
+                    transaction_action: Some(action),
+                    search_retention_days: Some(30),
+                    credit_retention_days: Some(30),
+                },
+            )
+            .await
+            .unwrap();
+            let reports = crate::retention::enforce_retention_policies(&pool)
+                .await
+                .unwrap();
+            assert!(reports.iter().any(|r| r.organization_id == Some(org.id)));
+            assert!(reports.iter().any(|r| r.organization_id.is_none()));
+        }
+        let policy = retention::get_retention_policy(&pool, Some(&org.id))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(policy.last_enforced_at.is_some());
+
+        background_jobs::register_job(&pool, "test", std::time::Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert!(background_jobs::get_background_job(&pool, "test")
+            .await
+            .unwrap()
+            .is_some());
+
+        audit_log::insert_audit_log_entry(
+            &pool,
+            &audit_log::NewAuditLogEntry {
+                organization_id: org.id,
+                actor_npub: NPUB.to_string(),
+                action: "mint.create".to_string(),
+                target_type: "mint".to_string(),
+                target_id: Some(mint.id.to_string()),
+                before: None,
+                after: Some(serde_json::json!({ "mint_url": "https://mint.example" })),
+                source_ip: None,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            audit_log::get_audit_log(&pool, &org.id, &Default::default())
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let group = user_search_groups::create_conversation(&pool, NPUB.to_string()).await;
+        assert_eq!(
+            user_search_groups::get_search_groups(&pool, NPUB.to_string())
+                .await
+                .len(),
+            1
+        );
+        user_searches::create_pending_search(
+            &pool,
+            &"query".to_string(),
+            NPUB.to_string(),
+            group.id,
+        )
+        .await;
+    }
+}
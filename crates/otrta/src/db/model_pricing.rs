@@ -1,6 +1,8 @@
+use crate::db::Pool;
 use crate::models::{normalize_model_name, ModelRecord};
+use chrono::{Duration, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::FromRow;
 use uuid::Uuid;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -20,14 +22,26 @@ pub struct ModelPricingComparison {
     pub providers: Vec<ModelPricingProvider>,
 }
 
+#[derive(FromRow)]
+struct ModelPricingRow {
+    normalized_model_name: String,
+    provider_id: i32,
+    provider_name: String,
+    model_name: String,
+    input_cost: i64,
+    output_cost: i64,
+    min_cash_per_request: i64,
+    is_free: bool,
+}
+
 pub async fn upsert_model_pricing(
-    pool: &PgPool,
+    pool: &Pool,
     model: &ModelRecord,
     provider_name: &str,
 ) -> Result<(), sqlx::Error> {
     let normalized_name = normalize_model_name(&model.name);
 
-    sqlx::query!(
+    sqlx::query(
         r#"
         INSERT INTO model_pricing (
             normalized_model_name, provider_id, provider_name, model_name,
@@ -43,18 +57,19 @@ pub async fn upsert_model_pricing(
             output_cost = EXCLUDED.output_cost,
             min_cash_per_request = EXCLUDED.min_cash_per_request,
             is_free = EXCLUDED.is_free,
-            updated_at = NOW(),
-            last_updated = NOW()
+            updated_at = $9,
+            last_updated = $9
         "#,
-        normalized_name,
-        model.provider_id,
-        provider_name,
-        model.name,
-        model.input_cost,
-        model.output_cost,
-        model.min_cash_per_request,
-        model.is_free,
     )
+    .bind(normalized_name)
+    .bind(model.provider_id)
+    .bind(provider_name)
+    .bind(&model.name)
+    .bind(model.input_cost)
+    .bind(model.output_cost)
+    .bind(model.min_cash_per_request)
+    .bind(model.is_free)
+    .bind(Utc::now())
     .execute(pool)
     .await?;
 
@@ -62,10 +77,10 @@ pub async fn upsert_model_pricing(
 }
 
 pub async fn get_model_pricing_comparison(
-    pool: &PgPool,
+    pool: &Pool,
     organization_id: &Uuid,
 ) -> Result<Vec<ModelPricingComparison>, sqlx::Error> {
-    let records = sqlx::query!(
+    let records = sqlx::query_as::<_, ModelPricingRow>(
         r#"
         SELECT 
             mp.normalized_model_name,
@@ -75,8 +90,7 @@ pub async fn get_model_pricing_comparison(
             mp.input_cost,
             mp.output_cost,
             mp.min_cash_per_request,
-            COALESCE(mp.is_free, false) AS is_free,
-            provider_counts.provider_count
+            COALESCE(mp.is_free, false) AS is_free
         FROM model_pricing mp
         INNER JOIN providers p ON mp.provider_id = p.id
         INNER JOIN (
@@ -85,11 +99,11 @@ pub async fn get_model_pricing_comparison(
                 COUNT(DISTINCT provider_id) as provider_count
             FROM model_pricing mp2
             INNER JOIN providers p2 ON mp2.provider_id = p2.id
-            WHERE mp2.last_updated > NOW() - INTERVAL '1 day'
+            WHERE mp2.last_updated > $2
               AND (p2.organization_id IS NULL OR p2.organization_id = $1)
             GROUP BY normalized_model_name
         ) provider_counts ON mp.normalized_model_name = provider_counts.normalized_model_name
-        WHERE mp.last_updated > NOW() - INTERVAL '1 day'
+        WHERE mp.last_updated > $2
           AND (p.organization_id IS NULL OR p.organization_id = $1)
         ORDER BY provider_counts.provider_count DESC,
                  mp.normalized_model_name ASC,
@@ -97,8 +111,9 @@ pub async fn get_model_pricing_comparison(
                  COALESCE(mp.input_cost + mp.output_cost, 0),
                  mp.min_cash_per_request
         "#,
-        organization_id
     )
+    .bind(organization_id)
+    .bind(Utc::now() - Duration::days(1))
     .fetch_all(pool)
     .await?;
 
@@ -120,12 +135,12 @@ pub async fn get_model_pricing_comparison(
             input_cost: record.input_cost,
             output_cost: record.output_cost,
             min_cash_per_request: record.min_cash_per_request,
-            is_free: record.is_free.unwrap_or(false),
+            is_free: record.is_free,
         };
 
         comparisons
             .entry(record.normalized_model_name)
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(provider);
     }
 
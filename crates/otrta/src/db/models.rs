@@ -1,12 +1,89 @@
 use chrono::{DateTime, Utc};
-use sqlx::PgPool;
+use sqlx::FromRow;
 
+use crate::db::{
+    dialect::{in_list, StringList},
+    Pool,
+};
 use crate::models::{ModelRecord, ProxyModel, ProxyModelFromApi};
 
 use super::provider::get_default_provider_for_organization_new;
 
-pub async fn get_all_models(pool: &PgPool) -> Result<Vec<ModelRecord>, sqlx::Error> {
-    let models = sqlx::query!(
+#[derive(FromRow)]
+pub(crate) struct ModelRow {
+    id: uuid::Uuid,
+    provider_id: i32,
+    name: String,
+    input_cost: i64,
+    output_cost: i64,
+    min_cash_per_request: i64,
+    min_cost_per_request: Option<i64>,
+    provider: Option<String>,
+    soft_deleted: Option<bool>,
+    model_type: Option<String>,
+    description: Option<String>,
+    context_length: Option<i32>,
+    is_free: Option<bool>,
+    created_at: DateTime<Utc>,
+    updated_at: Option<DateTime<Utc>>,
+    last_seen_at: Option<DateTime<Utc>>,
+    modality: Option<String>,
+    input_modalities: Option<StringList>,
+    output_modalities: Option<StringList>,
+    tokenizer: Option<String>,
+    instruct_type: Option<String>,
+    created_timestamp: Option<i64>,
+    prompt_cost: Option<f64>,
+    completion_cost: Option<f64>,
+    request_cost: Option<f64>,
+    image_cost: Option<f64>,
+    web_search_cost: Option<f64>,
+    internal_reasoning_cost: Option<f64>,
+    max_cost: Option<f64>,
+    max_completion_tokens: Option<i32>,
+    is_moderated: Option<bool>,
+}
+
+impl From<ModelRow> for ModelRecord {
+    fn from(row: ModelRow) -> Self {
+        ModelRecord {
+            id: row.id,
+            provider_id: row.provider_id,
+            name: row.name,
+            input_cost: row.input_cost,
+            output_cost: row.output_cost,
+            min_cash_per_request: row.min_cash_per_request,
+            min_cost_per_request: row.min_cost_per_request,
+            provider: row.provider,
+            soft_deleted: row.soft_deleted.unwrap_or(false),
+            model_type: row.model_type,
+            description: row.description,
+            context_length: row.context_length,
+            is_free: row.is_free.unwrap_or(false),
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            last_seen_at: row.last_seen_at,
+            modality: row.modality,
+            input_modalities: row.input_modalities.map(Vec::from),
+            output_modalities: row.output_modalities.map(Vec::from),
+            tokenizer: row.tokenizer,
+            instruct_type: row.instruct_type,
+            created_timestamp: row.created_timestamp,
+            prompt_cost: row.prompt_cost,
+            completion_cost: row.completion_cost,
+            request_cost: row.request_cost,
+            image_cost: row.image_cost,
+            web_search_cost: row.web_search_cost,
+            internal_reasoning_cost: row.internal_reasoning_cost,
+            max_cost: row.max_cost,
+            max_completion_tokens: row.max_completion_tokens,
+            is_moderated: row.is_moderated,
+        }
+    }
+}
+
+pub async fn get_all_models(pool: &Pool) -> Result<Vec<ModelRecord>, sqlx::Error> {
+    let models = sqlx::query_as::<_, ModelRow>(
         r#"
         SELECT id, provider_id, name, input_cost, output_cost, min_cash_per_request, 
                min_cost_per_request, provider, soft_deleted, model_type, 
@@ -18,51 +95,19 @@ pub async fn get_all_models(pool: &PgPool) -> Result<Vec<ModelRecord>, sqlx::Err
         FROM models
         WHERE soft_deleted = false OR soft_deleted IS NULL
         ORDER BY name ASC
-        "#
+        "#,
     )
     .fetch_all(pool)
     .await?
     .into_iter()
-    .map(|record| ModelRecord {
-        id: record.id,
-        provider_id: record.provider_id,
-        name: record.name,
-        input_cost: record.input_cost,
-        output_cost: record.output_cost,
-        min_cash_per_request: record.min_cash_per_request,
-        min_cost_per_request: record.min_cost_per_request,
-        provider: record.provider,
-        soft_deleted: record.soft_deleted.unwrap_or(false),
-        model_type: record.model_type,
-        description: record.description,
-        context_length: record.context_length,
-        is_free: record.is_free.unwrap_or(false),
-        created_at: record.created_at,
-        updated_at: record.updated_at,
-        last_seen_at: record.last_seen_at,
-        modality: record.modality,
-        input_modalities: record.input_modalities,
-        output_modalities: record.output_modalities,
-        tokenizer: record.tokenizer,
-        instruct_type: record.instruct_type,
-        created_timestamp: record.created_timestamp,
-        prompt_cost: record.prompt_cost,
-        completion_cost: record.completion_cost,
-        request_cost: record.request_cost,
-        image_cost: record.image_cost,
-        web_search_cost: record.web_search_cost,
-        internal_reasoning_cost: record.internal_reasoning_cost,
-        max_cost: record.max_cost,
-        max_completion_tokens: record.max_completion_tokens,
-        is_moderated: record.is_moderated,
-    })
+    .map(ModelRecord::from)
     .collect();
 
     Ok(models)
 }
 
 pub async fn get_models_for_organization(
-    pool: &PgPool,
+    pool: &Pool,
     organization_id: &uuid::Uuid,
 ) -> Result<Vec<ModelRecord>, sqlx::Error> {
     // Get the default provider for this organization
@@ -76,7 +121,7 @@ pub async fn get_models_for_organization(
         }
     };
 
-    let models = sqlx::query!(
+    let models = sqlx::query_as::<_, ModelRow>(
         r#"
         SELECT id, provider_id, name, input_cost, output_cost, min_cash_per_request, 
                min_cost_per_request, provider, soft_deleted, model_type, 
@@ -89,53 +134,21 @@ pub async fn get_models_for_organization(
         WHERE provider_id = $1 AND (soft_deleted = false OR soft_deleted IS NULL)
         ORDER BY name ASC
         "#,
-        provider_id
     )
+    .bind(provider_id)
     .fetch_all(pool)
     .await?
     .into_iter()
-    .map(|record| ModelRecord {
-        id: record.id,
-        provider_id: record.provider_id,
-        name: record.name,
-        input_cost: record.input_cost,
-        output_cost: record.output_cost,
-        min_cash_per_request: record.min_cash_per_request,
-        min_cost_per_request: record.min_cost_per_request,
-        provider: record.provider,
-        soft_deleted: record.soft_deleted.unwrap_or(false),
-        model_type: record.model_type,
-        description: record.description,
-        context_length: record.context_length,
-        is_free: record.is_free.unwrap_or(false),
-        created_at: record.created_at,
-        updated_at: record.updated_at,
-        last_seen_at: record.last_seen_at,
-        modality: record.modality,
-        input_modalities: record.input_modalities,
-        output_modalities: record.output_modalities,
-        tokenizer: record.tokenizer,
-        instruct_type: record.instruct_type,
-        created_timestamp: record.created_timestamp,
-        prompt_cost: record.prompt_cost,
-        completion_cost: record.completion_cost,
-        request_cost: record.request_cost,
-        image_cost: record.image_cost,
-        web_search_cost: record.web_search_cost,
-        internal_reasoning_cost: record.internal_reasoning_cost,
-        max_cost: record.max_cost,
-        max_completion_tokens: record.max_completion_tokens,
-        is_moderated: record.is_moderated,
-    })
+    .map(ModelRecord::from)
     .collect();
 
     Ok(models)
 }
 
 pub async fn get_all_models_including_deleted(
-    pool: &PgPool,
+    pool: &Pool,
 ) -> Result<Vec<ModelRecord>, sqlx::Error> {
-    let models = sqlx::query!(
+    let models = sqlx::query_as::<_, ModelRow>(
         r#"
         SELECT id, provider_id, name, input_cost, output_cost, min_cash_per_request, 
                min_cost_per_request, provider, soft_deleted, model_type, 
@@ -146,55 +159,23 @@ pub async fn get_all_models_including_deleted(
                is_moderated
         FROM models
         ORDER BY name ASC
-        "#
+        "#,
     )
     .fetch_all(pool)
     .await?
     .into_iter()
-    .map(|record| ModelRecord {
-        id: record.id,
-        provider_id: record.provider_id,
-        name: record.name,
-        input_cost: record.input_cost,
-        output_cost: record.output_cost,
-        min_cash_per_request: record.min_cash_per_request,
-        min_cost_per_request: record.min_cost_per_request,
-        provider: record.provider,
-        soft_deleted: record.soft_deleted.unwrap_or(false),
-        model_type: record.model_type,
-        description: record.description,
-        context_length: record.context_length,
-        is_free: record.is_free.unwrap_or(false),
-        created_at: record.created_at,
-        updated_at: record.updated_at,
-        last_seen_at: record.last_seen_at,
-        modality: record.modality,
-        input_modalities: record.input_modalities,
-        output_modalities: record.output_modalities,
-        tokenizer: record.tokenizer,
-        instruct_type: record.instruct_type,
-        created_timestamp: record.created_timestamp,
-        prompt_cost: record.prompt_cost,
-        completion_cost: record.completion_cost,
-        request_cost: record.request_cost,
-        image_cost: record.image_cost,
-        web_search_cost: record.web_search_cost,
-        internal_reasoning_cost: record.internal_reasoning_cost,
-        max_cost: record.max_cost,
-        max_completion_tokens: record.max_completion_tokens,
-        is_moderated: record.is_moderated,
-    })
+    .map(ModelRecord::from)
     .collect();
 
     Ok(models)
 }
 
 pub async fn get_model(
-    pool: &PgPool,
+    pool: &Pool,
     model_name: &str,
     provider_id: i32,
 ) -> Result<Option<ModelRecord>, sqlx::Error> {
-    let record = sqlx::query!(
+    let record = sqlx::query_as::<_, ModelRow>(
         r#"
         SELECT id, provider_id, name, input_cost, output_cost, min_cash_per_request, 
                min_cost_per_request, provider, soft_deleted, model_type, 
@@ -206,52 +187,20 @@ pub async fn get_model(
         FROM models
         WHERE name = $1 AND provider_id = $2
         "#,
-        model_name,
-        provider_id
     )
+    .bind(model_name)
+    .bind(provider_id)
     .fetch_optional(pool)
     .await?;
 
-    Ok(record.map(|r| ModelRecord {
-        id: r.id,
-        provider_id: r.provider_id,
-        name: r.name,
-        input_cost: r.input_cost,
-        output_cost: r.output_cost,
-        min_cash_per_request: r.min_cash_per_request,
-        min_cost_per_request: r.min_cost_per_request,
-        provider: r.provider,
-        soft_deleted: r.soft_deleted.unwrap_or(false),
-        model_type: r.model_type,
-        description: r.description,
-        context_length: r.context_length,
-        is_free: r.is_free.unwrap_or(false),
-        created_at: r.created_at,
-        updated_at: r.updated_at,
-        last_seen_at: r.last_seen_at,
-        modality: r.modality,
-        input_modalities: r.input_modalities,
-        output_modalities: r.output_modalities,
-        tokenizer: r.tokenizer,
-        instruct_type: r.instruct_type,
-        created_timestamp: r.created_timestamp,
-        prompt_cost: r.prompt_cost,
-        completion_cost: r.completion_cost,
-        request_cost: r.request_cost,
-        image_cost: r.image_cost,
-        web_search_cost: r.web_search_cost,
-        internal_reasoning_cost: r.internal_reasoning_cost,
-        max_cost: r.max_cost,
-        max_completion_tokens: r.max_completion_tokens,
-        is_moderated: r.is_moderated,
-    }))
+    Ok(record.map(ModelRecord::from))
 }
 
-pub async fn delete_all_models(pool: &PgPool) -> Result<i64, sqlx::Error> {
-    let result = sqlx::query!(
+pub async fn delete_all_models(pool: &Pool) -> Result<i64, sqlx::Error> {
+    let result = sqlx::query(
         r#"
         DELETE FROM models
-        "#
+        "#,
     )
     .execute(pool)
     .await?;
@@ -259,17 +208,14 @@ pub async fn delete_all_models(pool: &PgPool) -> Result<i64, sqlx::Error> {
     Ok(result.rows_affected() as i64)
 }
 
-pub async fn delete_models_for_provider(
-    pool: &PgPool,
-    provider_id: i32,
-) -> Result<i64, sqlx::Error> {
-    let result = sqlx::query!(
+pub async fn delete_models_for_provider(pool: &Pool, provider_id: i32) -> Result<i64, sqlx::Error> {
+    let result = sqlx::query(
         r#"
         DELETE FROM models
         WHERE provider_id = $1
         "#,
-        provider_id
     )
+    .bind(provider_id)
     .execute(pool)
     .await?;
 
@@ -277,13 +223,13 @@ pub async fn delete_models_for_provider(
 }
 
 pub async fn upsert_model(
-    pool: &PgPool,
+    pool: &Pool,
     model: &ProxyModelFromApi,
     provider_id: i32,
 ) -> Result<ModelRecord, sqlx::Error> {
     let model_record = model.to_model_record(provider_id);
 
-    let record = sqlx::query!(
+    let record = sqlx::query_as::<_, ModelRow>(
         r#"
         INSERT INTO models (
             name, provider_id, input_cost, output_cost, min_cash_per_request, 
@@ -295,7 +241,7 @@ pub async fn upsert_model(
             is_moderated
         )
         VALUES (
-            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, NOW(), NOW(), NOW(),
+            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $28, $28, $28,
             $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27
         )
         ON CONFLICT (name, provider_id) DO UPDATE SET
@@ -310,8 +256,8 @@ pub async fn upsert_model(
             description = EXCLUDED.description,
             context_length = EXCLUDED.context_length,
             is_free = EXCLUDED.is_free,
-            updated_at = NOW(),
-            last_seen_at = NOW(),
+            updated_at = $28,
+            last_seen_at = $28,
             modality = EXCLUDED.modality,
             input_modalities = EXCLUDED.input_modalities,
             output_modalities = EXCLUDED.output_modalities,
@@ -335,74 +281,43 @@ pub async fn upsert_model(
                   web_search_cost, internal_reasoning_cost, max_cost, max_completion_tokens,
                   is_moderated
         "#,
-        model_record.name,
-        model_record.provider_id,
-        model_record.input_cost,
-        model_record.output_cost,
-        model_record.min_cash_per_request,
-        model_record.min_cash_per_request,
-        model_record.provider,
-        model_record.soft_deleted,
-        model_record.model_type,
-        model_record.description,
-        model_record.context_length,
-        model_record.is_free,
-        model_record.modality,
-        model_record.input_modalities.as_deref(),
-        model_record.output_modalities.as_deref(),
-        model_record.tokenizer,
-        model_record.instruct_type,
-        model_record.created_timestamp,
-        model_record.prompt_cost,
-        model_record.completion_cost,
-        model_record.request_cost,
-        model_record.image_cost,
-        model_record.web_search_cost,
-        model_record.internal_reasoning_cost,
-        model_record.max_cost,
-        model_record.max_completion_tokens,
-        model_record.is_moderated,
     )
+    .bind(&model_record.name)
+    .bind(model_record.provider_id)
+    .bind(model_record.input_cost)
+    .bind(model_record.output_cost)
+    .bind(model_record.min_cash_per_request)
+    .bind(model_record.min_cash_per_request)
+    .bind(&model_record.provider)
+    .bind(model_record.soft_deleted)
+    .bind(&model_record.model_type)
+    .bind(&model_record.description)
+    .bind(model_record.context_length)
+    .bind(model_record.is_free)
+    .bind(&model_record.modality)
+    .bind(model_record.input_modalities.clone().map(StringList))
+    .bind(model_record.output_modalities.clone().map(StringList))
+    .bind(&model_record.tokenizer)
+    .bind(&model_record.instruct_type)
+    .bind(model_record.created_timestamp)
+    .bind(model_record.prompt_cost)
+    .bind(model_record.completion_cost)
+    .bind(model_record.request_cost)
+    .bind(model_record.image_cost)
+    .bind(model_record.web_search_cost)
+    .bind(model_record.internal_reasoning_cost)
+    .bind(model_record.max_cost)
+    .bind(model_record.max_completion_tokens)
+    .bind(model_record.is_moderated)
+    .bind(Utc::now())
     .fetch_one(pool)
     .await?;
 
-    Ok(ModelRecord {
-        id: record.id,
-        provider_id: record.provider_id,
-        name: record.name,
-        input_cost: record.input_cost,
-        output_cost: record.output_cost,
-        min_cash_per_request: record.min_cash_per_request,
-        min_cost_per_request: record.min_cost_per_request,
-        provider: record.provider,
-        soft_deleted: record.soft_deleted.unwrap_or(false),
-        model_type: record.model_type,
-        description: record.description,
-        context_length: record.context_length,
-        is_free: record.is_free.unwrap_or(false),
-        created_at: record.created_at,
-        updated_at: record.updated_at,
-        last_seen_at: record.last_seen_at,
-        modality: record.modality,
-        input_modalities: record.input_modalities,
-        output_modalities: record.output_modalities,
-        tokenizer: record.tokenizer,
-        instruct_type: record.instruct_type,
-        created_timestamp: record.created_timestamp,
-        prompt_cost: record.prompt_cost,
-        completion_cost: record.completion_cost,
-        request_cost: record.request_cost,
-        image_cost: record.image_cost,
-        web_search_cost: record.web_search_cost,
-        internal_reasoning_cost: record.internal_reasoning_cost,
-        max_cost: record.max_cost,
-        max_completion_tokens: record.max_completion_tokens,
-        is_moderated: record.is_moderated,
-    })
+    Ok(record.into())
 }
 
 pub async fn mark_models_as_removed(
-    pool: &PgPool,
+    pool: &Pool,
     model_names: &[String],
     cutoff_time: DateTime<Utc>,
 ) -> Result<i64, sqlx::Error> {
@@ -410,15 +325,15 @@ pub async fn mark_models_as_removed(
         return Ok(0);
     }
 
-    let result = sqlx::query!(
-        r#"
-        UPDATE models 
-        SET soft_deleted = true, updated_at = NOW()
-        WHERE name = ANY($1) AND (last_seen_at IS NULL OR last_seen_at < $2)
-        "#,
-        model_names,
-        cutoff_time
-    )
+    let result = sqlx::query(&format!(
+        "UPDATE models
+         SET soft_deleted = true, updated_at = $3
+         WHERE {} AND (last_seen_at IS NULL OR last_seen_at < $2)",
+        in_list("name", "$1")
+    ))
+    .bind(StringList(model_names.to_vec()))
+    .bind(cutoff_time)
+    .bind(Utc::now())
     .execute(pool)
     .await?;
 
@@ -426,67 +341,6 @@ pub async fn mark_models_as_removed(
 }
 
 // Bitcoin price fetching with fallback
-pub async fn get_bitcoin_price_in_usd() -> Result<f64, Box<dyn std::error::Error + Send + Sync>> {
-    let client = reqwest::Client::new();
-
-    // Try CoinGecko first
-    if let Ok(response) = client
-        .get("https://api.coingecko.com/api/v3/simple/price?ids=bitcoin&vs_currencies=usd")
-        .timeout(std::time::Duration::from_secs(5))
-        .send()
-        .await
-    {
-        if let Ok(json) = response.json::<serde_json::Value>().await {
-            if let Some(price) = json
-                .get("bitcoin")
-                .and_then(|btc| btc.get("usd"))
-                .and_then(|usd| usd.as_f64())
-            {
-                return Ok(price);
-            }
-        }
-    }
-
-    // Try Coinbase as fallback
-    if let Ok(response) = client
-        .get("https://api.coinbase.com/v2/exchange-rates?currency=BTC")
-        .timeout(std::time::Duration::from_secs(5))
-        .send()
-        .await
-    {
-        if let Ok(json) = response.json::<serde_json::Value>().await {
-            if let Some(price_str) = json
-                .get("data")
-                .and_then(|data| data.get("rates"))
-                .and_then(|rates| rates.get("USD"))
-                .and_then(|usd| usd.as_str())
-            {
-                if let Ok(price) = price_str.parse::<f64>() {
-                    return Ok(price);
-                }
-            }
-        }
-    }
-
-    // Try Binance as another fallback
-    if let Ok(response) = client
-        .get("https://api.binance.com/api/v3/ticker/price?symbol=BTCUSDT")
-        .timeout(std::time::Duration::from_secs(5))
-        .send()
-        .await
-    {
-        if let Ok(json) = response.json::<serde_json::Value>().await {
-            if let Some(price_str) = json.get("price").and_then(|p| p.as_str()) {
-                if let Ok(price) = price_str.parse::<f64>() {
-                    return Ok(price);
-                }
-            }
-        }
-    }
-
-    Ok(60000.0)
-}
-
 pub fn models_to_proxy_models(models: Vec<ModelRecord>) -> Vec<ProxyModel> {
     models
         .into_iter()
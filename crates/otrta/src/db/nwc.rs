@@ -1,6 +1,8 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, PgPool};
+use sqlx::FromRow;
+
+use crate::db::Pool;
 use uuid::Uuid;
 
 use crate::error::AppError;
@@ -60,18 +62,19 @@ pub struct UpdateMintAutoRefillRequest {
 }
 
 pub async fn create_nwc_connection(
-    pool: &PgPool,
+    pool: &Pool,
     organization_id: &Uuid,
     request: CreateNwcConnectionRequest,
 ) -> Result<NwcConnection, AppError> {
     let connection = sqlx::query_as::<_, NwcConnection>(
         "INSERT INTO nwc_connections (organization_id, name, connection_uri, updated_at)
-         VALUES ($1, $2, $3, NOW())
+         VALUES ($1, $2, $3, $4)
          RETURNING id, organization_id, name, connection_uri, is_active, created_at, updated_at",
     )
     .bind(organization_id)
     .bind(&request.name)
     .bind(&request.connection_uri)
+    .bind(Utc::now())
     .fetch_one(pool)
     .await
     .map_err(|e| {
@@ -83,7 +86,7 @@ pub async fn create_nwc_connection(
 }
 
 pub async fn get_nwc_connections_for_organization(
-    pool: &PgPool,
+    pool: &Pool,
     organization_id: &Uuid,
 ) -> Result<Vec<NwcConnection>, AppError> {
     let connections = sqlx::query_as::<_, NwcConnection>(
@@ -104,7 +107,7 @@ pub async fn get_nwc_connections_for_organization(
 }
 
 pub async fn get_active_nwc_connection_for_organization(
-    pool: &PgPool,
+    pool: &Pool,
     organization_id: &Uuid,
 ) -> Result<NwcConnection, AppError> {
     let connections = sqlx::query_as::<_, NwcConnection>(
@@ -125,7 +128,7 @@ pub async fn get_active_nwc_connection_for_organization(
 }
 
 pub async fn get_active_nwc_connections_for_organization(
-    pool: &PgPool,
+    pool: &Pool,
     organization_id: &Uuid,
 ) -> Result<Vec<NwcConnection>, AppError> {
     let connections = sqlx::query_as::<_, NwcConnection>(
@@ -146,7 +149,7 @@ pub async fn get_active_nwc_connections_for_organization(
 }
 
 pub async fn get_nwc_connection_by_id(
-    pool: &PgPool,
+    pool: &Pool,
     id: &Uuid,
     organization_id: &Uuid,
 ) -> Result<Option<NwcConnection>, AppError> {
@@ -168,7 +171,7 @@ pub async fn get_nwc_connection_by_id(
 }
 
 pub async fn update_nwc_connection(
-    pool: &PgPool,
+    pool: &Pool,
     id: &Uuid,
     organization_id: &Uuid,
     request: UpdateNwcConnectionRequest,
@@ -178,7 +181,7 @@ pub async fn update_nwc_connection(
          SET name = COALESCE($3, name),
              connection_uri = COALESCE($4, connection_uri),
              is_active = COALESCE($5, is_active),
-             updated_at = NOW()
+             updated_at = $6
          WHERE id = $1 AND organization_id = $2
          RETURNING id, organization_id, name, connection_uri, is_active, created_at, updated_at",
     )
@@ -187,6 +190,7 @@ pub async fn update_nwc_connection(
     .bind(request.name)
     .bind(request.connection_uri)
     .bind(request.is_active)
+    .bind(Utc::now())
     .fetch_optional(pool)
     .await
     .map_err(|e| {
@@ -198,7 +202,7 @@ pub async fn update_nwc_connection(
 }
 
 pub async fn delete_nwc_connection(
-    pool: &PgPool,
+    pool: &Pool,
     id: &Uuid,
     organization_id: &Uuid,
 ) -> Result<bool, AppError> {
@@ -216,13 +220,13 @@ pub async fn delete_nwc_connection(
 }
 
 pub async fn create_mint_auto_refill_settings(
-    pool: &PgPool,
+    pool: &Pool,
     organization_id: &Uuid,
     request: CreateMintAutoRefillRequest,
 ) -> Result<MintAutoRefillSettings, AppError> {
     let settings = sqlx::query_as::<_, MintAutoRefillSettings>(
         "INSERT INTO mint_auto_refill_settings (mint_id, organization_id, nwc_connection_id, min_balance_threshold_msat, refill_amount_msat, updated_at)
-         VALUES ($1, $2, $3, $4, $5, NOW())
+         VALUES ($1, $2, $3, $4, $5, $6)
          RETURNING id, mint_id, organization_id, nwc_connection_id, min_balance_threshold_msat, refill_amount_msat, is_enabled, last_refill_at, created_at, updated_at",
     )
     .bind(request.mint_id)
@@ -230,6 +234,7 @@ pub async fn create_mint_auto_refill_settings(
     .bind(request.nwc_connection_id)
     .bind(request.min_balance_threshold_msat)
     .bind(request.refill_amount_msat)
+    .bind(Utc::now())
     .fetch_one(pool)
     .await
     .map_err(|e| {
@@ -241,7 +246,7 @@ pub async fn create_mint_auto_refill_settings(
 }
 
 pub async fn get_mint_auto_refill_settings_for_organization(
-    pool: &PgPool,
+    pool: &Pool,
     organization_id: &Uuid,
 ) -> Result<Vec<MintAutoRefillSettings>, AppError> {
     let settings = sqlx::query_as::<_, MintAutoRefillSettings>(
@@ -262,7 +267,7 @@ pub async fn get_mint_auto_refill_settings_for_organization(
 }
 
 pub async fn get_enabled_mint_auto_refill_settings(
-    pool: &PgPool,
+    pool: &Pool,
 ) -> Result<Vec<MintAutoRefillSettings>, AppError> {
     let settings = sqlx::query_as::<_, MintAutoRefillSettings>(
         "SELECT mars.id, mars.mint_id, mars.organization_id, mars.nwc_connection_id,
@@ -285,7 +290,7 @@ pub async fn get_enabled_mint_auto_refill_settings(
 }
 
 pub async fn get_mint_auto_refill_settings_by_mint(
-    pool: &PgPool,
+    pool: &Pool,
     mint_id: i32,
     organization_id: &Uuid,
 ) -> Result<Option<MintAutoRefillSettings>, AppError> {
@@ -307,7 +312,7 @@ pub async fn get_mint_auto_refill_settings_by_mint(
 }
 
 pub async fn update_mint_auto_refill_settings(
-    pool: &PgPool,
+    pool: &Pool,
     id: &Uuid,
     organization_id: &Uuid,
     request: UpdateMintAutoRefillRequest,
@@ -318,7 +323,7 @@ pub async fn update_mint_auto_refill_settings(
              min_balance_threshold_msat = COALESCE($4, min_balance_threshold_msat),
              refill_amount_msat = COALESCE($5, refill_amount_msat),
              is_enabled = COALESCE($6, is_enabled),
-             updated_at = NOW()
+             updated_at = $7
          WHERE id = $1 AND organization_id = $2
          RETURNING id, mint_id, organization_id, nwc_connection_id, min_balance_threshold_msat, refill_amount_msat, is_enabled, last_refill_at, created_at, updated_at",
     )
@@ -328,6 +333,7 @@ pub async fn update_mint_auto_refill_settings(
     .bind(request.min_balance_threshold_msat)
     .bind(request.refill_amount_msat)
     .bind(request.is_enabled)
+    .bind(Utc::now())
     .fetch_optional(pool)
     .await
     .map_err(|e| {
@@ -338,13 +344,14 @@ pub async fn update_mint_auto_refill_settings(
     Ok(settings)
 }
 
-pub async fn update_last_refill_time(pool: &PgPool, id: &Uuid) -> Result<bool, AppError> {
+pub async fn update_last_refill_time(pool: &Pool, id: &Uuid) -> Result<bool, AppError> {
     let result = sqlx::query(
         "UPDATE mint_auto_refill_settings
-         SET last_refill_at = NOW(), updated_at = NOW()
+         SET last_refill_at = $2, updated_at = $2
          WHERE id = $1",
     )
     .bind(id)
+    .bind(Utc::now())
     .execute(pool)
     .await
     .map_err(|e| {
@@ -356,7 +363,7 @@ pub async fn update_last_refill_time(pool: &PgPool, id: &Uuid) -> Result<bool, A
 }
 
 pub async fn delete_mint_auto_refill_settings(
-    pool: &PgPool,
+    pool: &Pool,
     id: &Uuid,
     organization_id: &Uuid,
 ) -> Result<bool, AppError> {
@@ -1,27 +1,49 @@
-use chrono::Utc;
-use sqlx::PgPool;
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
 use uuid::Uuid;
 
+use crate::db::Pool;
 use crate::error::AppError;
 use crate::models::{CreateOrganizationRequest, Organization};
 
+#[derive(FromRow)]
+struct OrganizationRow {
+    id: Uuid,
+    name: String,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+    is_active: Option<bool>,
+}
+
+impl From<OrganizationRow> for Organization {
+    fn from(row: OrganizationRow) -> Self {
+        Self {
+            id: row.id,
+            name: row.name,
+            created_at: row.created_at.unwrap_or_else(Utc::now),
+            updated_at: row.updated_at.unwrap_or_else(Utc::now),
+            is_active: row.is_active.unwrap_or(true),
+        }
+    }
+}
+
 pub async fn create_organization(
-    pool: &PgPool,
+    pool: &Pool,
     request: &CreateOrganizationRequest,
 ) -> Result<Organization, AppError> {
     let now = chrono::Utc::now();
     let id = Uuid::new_v4();
 
-    let organization = sqlx::query!(
+    let organization = sqlx::query_as::<_, OrganizationRow>(
         r#"
         INSERT INTO organizations (id, name, created_at, updated_at, is_active)
         VALUES ($1, $2, $3, $3, true)
         RETURNING id, name, created_at, updated_at, is_active
         "#,
-        id,
-        request.name,
-        now
     )
+    .bind(id)
+    .bind(&request.name)
+    .bind(now)
     .fetch_one(pool)
     .await
     .map_err(|e| {
@@ -29,29 +51,21 @@ pub async fn create_organization(
         AppError::DatabaseError(e.to_string())
     })?;
 
-    let organization = Organization {
-        id: organization.id,
-        name: organization.name,
-        created_at: organization.created_at.unwrap_or_else(chrono::Utc::now),
-        updated_at: organization.updated_at.unwrap_or_else(chrono::Utc::now),
-        is_active: organization.is_active.unwrap_or(true),
-    };
-
-    Ok(organization)
+    Ok(organization.into())
 }
 
 pub async fn get_organization_by_id(
-    pool: &PgPool,
+    pool: &Pool,
     id: &Uuid,
 ) -> Result<Option<Organization>, AppError> {
-    let row = sqlx::query!(
+    let row = sqlx::query_as::<_, OrganizationRow>(
         r#"
         SELECT id, name, created_at, updated_at, is_active
         FROM organizations
         WHERE id = $1 AND is_active = true
         "#,
-        id
     )
+    .bind(id)
     .fetch_optional(pool)
     .await
     .map_err(|e| {
@@ -59,30 +73,22 @@ pub async fn get_organization_by_id(
         AppError::DatabaseError(e.to_string())
     })?;
 
-    let organization = row.map(|r| Organization {
-        id: r.id,
-        name: r.name,
-        created_at: r.created_at.unwrap_or_else(Utc::now),
-        updated_at: r.updated_at.unwrap_or_else(Utc::now),
-        is_active: r.is_active.unwrap_or(true),
-    });
-
-    Ok(organization)
+    Ok(row.map(Organization::from))
 }
 
 pub async fn get_organization_for_user(
-    pool: &PgPool,
+    pool: &Pool,
     user_npub: &str,
 ) -> Result<Option<Organization>, AppError> {
-    let row = sqlx::query!(
+    let row = sqlx::query_as::<_, OrganizationRow>(
         r#"
         SELECT o.id, o.name, o.created_at, o.updated_at, o.is_active
         FROM organizations o
         INNER JOIN users u ON u.organization_id = o.id
         WHERE u.npub = $1 AND o.is_active = true AND u.is_active = true
         "#,
-        user_npub
     )
+    .bind(user_npub)
     .fetch_optional(pool)
     .await
     .map_err(|e| {
@@ -90,34 +96,26 @@ pub async fn get_organization_for_user(
         AppError::DatabaseError(e.to_string())
     })?;
 
-    let organization = row.map(|r| Organization {
-        id: r.id,
-        name: r.name,
-        created_at: r.created_at.unwrap_or_else(Utc::now),
-        updated_at: r.updated_at.unwrap_or_else(Utc::now),
-        is_active: r.is_active.unwrap_or(true),
-    });
-
-    Ok(organization)
+    Ok(row.map(Organization::from))
 }
 
 pub async fn get_default_organization_for_user(
-    pool: &PgPool,
+    pool: &Pool,
     user_npub: &str,
 ) -> Result<Option<Organization>, AppError> {
-    // Since users now belong to one organization, this is the same as get_organization_for_user
+    // The organization a user acts in when a request doesn't name one
     get_organization_for_user(pool, user_npub).await
 }
 
-pub async fn organization_exists(pool: &PgPool, id: &Uuid) -> Result<bool, AppError> {
-    let count = sqlx::query_scalar!(
+pub async fn organization_exists(pool: &Pool, id: &Uuid) -> Result<bool, AppError> {
+    let count: i64 = sqlx::query_scalar(
         r#"
         SELECT COUNT(*) as count
         FROM organizations
         WHERE id = $1
         "#,
-        id
     )
+    .bind(id)
     .fetch_one(pool)
     .await
     .map_err(|e| {
@@ -125,23 +123,23 @@ pub async fn organization_exists(pool: &PgPool, id: &Uuid) -> Result<bool, AppEr
         AppError::DatabaseError(e.to_string())
     })?;
 
-    Ok(count.unwrap_or(0) > 0)
+    Ok(count > 0)
 }
 
 pub async fn user_belongs_to_organization(
-    pool: &PgPool,
+    pool: &Pool,
     user_npub: &str,
     org_id: &Uuid,
 ) -> Result<bool, AppError> {
-    let count = sqlx::query_scalar!(
+    let count: i64 = sqlx::query_scalar(
         r#"
         SELECT COUNT(*) as count
-        FROM users
-        WHERE npub = $1 AND organization_id = $2 AND is_active = true
+        FROM organization_members
+        WHERE npub = $1 AND organization_id = $2
         "#,
-        user_npub,
-        org_id
     )
+    .bind(user_npub)
+    .bind(org_id)
     .fetch_one(pool)
     .await
     .map_err(|e| {
@@ -149,17 +147,17 @@ pub async fn user_belongs_to_organization(
         AppError::DatabaseError(e.to_string())
     })?;
 
-    Ok(count.unwrap_or(0) > 0)
+    Ok(count > 0)
 }
 
-pub async fn get_all_organizations(pool: &PgPool) -> Result<Vec<Organization>, AppError> {
-    let rows = sqlx::query!(
+pub async fn get_all_organizations(pool: &Pool) -> Result<Vec<Organization>, AppError> {
+    let rows = sqlx::query_as::<_, OrganizationRow>(
         r#"
         SELECT id, name, created_at, updated_at, is_active
         FROM organizations
         WHERE is_active = true
         ORDER BY created_at ASC
-        "#
+        "#,
     )
     .fetch_all(pool)
     .await
@@ -168,16 +166,5 @@ pub async fn get_all_organizations(pool: &PgPool) -> Result<Vec<Organization>, A
         AppError::DatabaseError(e.to_string())
     })?;
 
-    let organizations = rows
-        .into_iter()
-        .map(|r| Organization {
-            id: r.id,
-            name: r.name,
-            created_at: r.created_at.unwrap_or_else(Utc::now),
-            updated_at: r.updated_at.unwrap_or_else(Utc::now),
-            is_active: r.is_active.unwrap_or(true),
-        })
-        .collect();
-
-    Ok(organizations)
+    Ok(rows.into_iter().map(Organization::from).collect())
 }
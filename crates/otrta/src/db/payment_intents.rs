@@ -0,0 +1,290 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::db::Pool;
+use uuid::Uuid;
+
+use crate::db::ledger::LedgerContext;
+use crate::request_id::current_request_id;
+
+/// Progress of a proxied payment. The first four states are unfinished and
+/// picked up by recovery; the rest are final.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentIntentState {
+    Created,
+    TokenMinted,
+    UpstreamSent,
+    ResponseReceived,
+    /// The provider answered and its change, if any, was redeemed.
+    Completed,
+    /// The original token or the provider's refund was redeemed.
+    Refunded,
+    /// No token could be minted.
+    Failed,
+    /// Recovery reclaimed ecash after the request was cut off.
+    Recovered,
+    /// Recovery found every stored token already spent.
+    Spent,
+}
+
+impl PaymentIntentState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentIntentState::Created => "created",
+            PaymentIntentState::TokenMinted => "token_minted",
+            PaymentIntentState::UpstreamSent => "upstream_sent",
+            PaymentIntentState::ResponseReceived => "response_received",
+            PaymentIntentState::Completed => "completed",
+            PaymentIntentState::Refunded => "refunded",
+            PaymentIntentState::Failed => "failed",
+            PaymentIntentState::Recovered => "recovered",
+            PaymentIntentState::Spent => "spent",
+        }
+    }
+
+    pub fn is_final(&self) -> bool {
+        !matches!(
+            self,
+            PaymentIntentState::Created
+                | PaymentIntentState::TokenMinted
+                | PaymentIntentState::UpstreamSent
+                | PaymentIntentState::ResponseReceived
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct PaymentIntent {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub state: String,
+    pub mint_url: Option<String>,
+    pub unit: Option<String>,
+    pub amount: Option<i64>,
+    #[serde(skip_serializing)]
+    pub token: Option<String>,
+    #[serde(skip_serializing)]
+    pub change_token: Option<String>,
+    pub api_key_id: Option<Uuid>,
+    pub user_id: Option<String>,
+    pub provider_url: Option<String>,
+    pub model: Option<String>,
+    pub recovery_attempts: i32,
+    pub last_error: Option<String>,
+    /// `X-Request-Id` of the HTTP request that opened the intent.
+    pub http_request_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Opens an intent for the request described by `ctx`, before any token is
+/// minted for it.
+pub async fn create_payment_intent(
+    pool: &Pool,
+    ctx: &LedgerContext<'_>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO payment_intents (id, organization_id, api_key_id, user_id, provider_url, model, http_request_id)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(ctx.request_id)
+    .bind(ctx.organization_id)
+    .bind(ctx.api_key_id.and_then(|id| Uuid::parse_str(id).ok()))
+    .bind(ctx.user_id)
+    .bind(ctx.provider_url)
+    .bind(ctx.model)
+    .bind(current_request_id())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn mark_token_minted(
+    pool: &Pool,
+    id: &Uuid,
+    mint_url: &str,
+    unit: &str,
+    amount: i64,
+    token: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE payment_intents
+         SET state = 'token_minted', mint_url = $2, unit = $3, amount = $4, token = $5, updated_at = $6
+         WHERE id = $1",
+    )
+    .bind(id)
+    .bind(mint_url)
+    .bind(unit)
+    .bind(amount)
+    .bind(token)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Stores the provider's change (or refund) token before it is redeemed.
+pub async fn mark_response_received(
+    pool: &Pool,
+    id: &Uuid,
+    change_token: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE payment_intents
+         SET state = 'response_received', change_token = $2, updated_at = $3
+         WHERE id = $1",
+    )
+    .bind(id)
+    .bind(change_token)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Moves an intent to a state that carries no new data.
+pub async fn set_payment_intent_state(
+    pool: &Pool,
+    id: &Uuid,
+    state: PaymentIntentState,
+) -> Result<(), sqlx::Error> {
+    // Tokens are dropped as soon as nothing is left to reclaim.
+    sqlx::query(
+        "UPDATE payment_intents
+         SET state = $2,
+             token = CASE WHEN $3 THEN NULL ELSE token END,
+             change_token = CASE WHEN $3 THEN NULL ELSE change_token END,
+             updated_at = $4
+         WHERE id = $1",
+    )
+    .bind(id)
+    .bind(state.as_str())
+    .bind(state.is_final())
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Closes an intent as failed, leaving `reason` for an operator to act on.
+pub async fn fail_payment_intent(pool: &Pool, id: &Uuid, reason: &str) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE payment_intents
+         SET state = 'failed', token = NULL, change_token = NULL, last_error = $2, updated_at = $3
+         WHERE id = $1",
+    )
+    .bind(id)
+    .bind(reason)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Unfinished intents that haven't moved since `stale_before`, oldest first.
+/// Intents that failed recovery `max_attempts` times are left for an operator.
+pub async fn get_stale_payment_intents(
+    pool: &Pool,
+    stale_before: DateTime<Utc>,
+    max_attempts: i32,
+    limit: i64,
+) -> Result<Vec<PaymentIntent>, sqlx::Error> {
+    sqlx::query_as::<_, PaymentIntent>(
+        "SELECT id, organization_id, state, mint_url, unit, amount, token, change_token,
+                api_key_id, user_id, provider_url, model, recovery_attempts, last_error,
+                http_request_id, created_at, updated_at
+         FROM payment_intents
+         WHERE state IN ('created', 'token_minted', 'upstream_sent', 'response_received')
+           AND updated_at < $1
+           AND recovery_attempts < $2
+         ORDER BY updated_at
+         LIMIT $3",
+    )
+    .bind(stale_before)
+    .bind(max_attempts)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn record_recovery_failure(
+    pool: &Pool,
+    id: &Uuid,
+    error: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE payment_intents
+         SET recovery_attempts = recovery_attempts + 1, last_error = $2, updated_at = $3
+         WHERE id = $1",
+    )
+    .bind(id)
+    .bind(error)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+
+    async fn intent_with_tokens(pool: &Pool) -> Uuid {
+        let organization_id = Uuid::new_v4();
+        sqlx::query("INSERT INTO organizations (id, name) VALUES ($1, 'test')")
+            .bind(organization_id)
+            .execute(pool)
+            .await
+            .unwrap();
+        let ctx = LedgerContext::new(organization_id);
+        create_payment_intent(pool, &ctx).await.unwrap();
+        mark_token_minted(pool, &ctx.request_id, "https://mint", "sat", 10, "cashuA1")
+            .await
+            .unwrap();
+        mark_response_received(pool, &ctx.request_id, Some("cashuA2"))
+            .await
+            .unwrap();
+        ctx.request_id
+    }
+
+    async fn stored_tokens(pool: &Pool, id: &Uuid) -> (Option<String>, Option<String>) {
+        sqlx::query_as("SELECT token, change_token FROM payment_intents WHERE id = $1")
+            .bind(id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_final_states_drop_tokens() {
+        let pool = test_pool().await;
+
+        let id = intent_with_tokens(&pool).await;
+        set_payment_intent_state(&pool, &id, PaymentIntentState::UpstreamSent)
+            .await
+            .unwrap();
+        assert_eq!(
+            stored_tokens(&pool, &id).await,
+            (Some("cashuA1".to_string()), Some("cashuA2".to_string()))
+        );
+
+        for state in [
+            PaymentIntentState::Completed,
+            PaymentIntentState::Refunded,
+            PaymentIntentState::Failed,
+            PaymentIntentState::Recovered,
+            PaymentIntentState::Spent,
+        ] {
+            let id = intent_with_tokens(&pool).await;
+            set_payment_intent_state(&pool, &id, state).await.unwrap();
+            assert_eq!(stored_tokens(&pool, &id).await, (None, None), "{:?}", state);
+        }
+
+        let id = intent_with_tokens(&pool).await;
+        fail_payment_intent(&pool, &id, "cut off").await.unwrap();
+        assert_eq!(stored_tokens(&pool, &id).await, (None, None));
+    }
+}
@@ -1,5 +1,9 @@
-use crate::db::Pool;
-use otrta_nostr::discover_providers;
+use crate::db::{
+    dialect::{in_list, StringList, NIL_UUID},
+    Pool,
+};
+use chrono::Utc;
+use otrta_nostr::discover_providers_from_relays;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
@@ -56,6 +60,7 @@ pub struct Provider {
     pub id: i32,
     pub name: String,
     pub url: String,
+    #[sqlx(try_from = "StringList")]
     pub mints: Vec<String>,
     pub use_onion: bool,
     pub followers: i32,
@@ -88,6 +93,14 @@ pub struct ProviderWithStatus {
     pub is_editable: bool,
 }
 
+#[derive(FromRow)]
+struct ProviderStatusRow {
+    #[sqlx(flatten)]
+    provider: Provider,
+    is_active_for_org: bool,
+    is_default_for_org: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProviderListResponse {
     pub providers: Vec<Provider>,
@@ -145,7 +158,7 @@ pub struct UpdateCustomProviderRequest {
 }
 
 pub async fn get_all_providers(db: &Pool) -> Result<Vec<Provider>, sqlx::Error> {
-    let providers = sqlx::query_as::<_, Provider>(
+    let providers = sqlx::query_as::<_, Provider>(&format!(
         "SELECT
             p.id, p.name, p.url, p.mints, p.use_onion, p.followers, p.zaps,
             p.is_default, p.is_custom,
@@ -153,12 +166,13 @@ pub async fn get_all_providers(db: &Pool) -> Result<Vec<Provider>, sqlx::Error>
             p.organization_id, p.created_at, p.updated_at,
             EXISTS(
                 SELECT 1 FROM mints m
-                WHERE m.mint_url = ANY(p.mints)
+                WHERE {}
                 AND m.currency_unit = 'msat'
             ) as has_msat_support
          FROM providers p
          ORDER BY p.is_default DESC, COALESCE(p.source, 'manual') DESC, p.is_custom ASC, p.followers DESC, p.zaps DESC",
-    )
+        in_list("m.mint_url", "p.mints")
+    ))
     .fetch_all(db)
     .await?;
 
@@ -166,19 +180,20 @@ pub async fn get_all_providers(db: &Pool) -> Result<Vec<Provider>, sqlx::Error>
 }
 
 pub async fn get_default_provider(db: &Pool) -> Result<Option<Provider>, sqlx::Error> {
-    let provider = sqlx::query_as::<_, Provider>(
+    let provider = sqlx::query_as::<_, Provider>(&format!(
         "SELECT
             p.id, p.name, p.url, p.mints, p.use_onion, p.followers, p.zaps,
             p.is_default, p.is_custom, COALESCE(p.source, 'manual') as source,
             p.organization_id, p.created_at, p.updated_at,
             EXISTS(
                 SELECT 1 FROM mints m
-                WHERE m.mint_url = ANY(p.mints)
+                WHERE {}
                 AND m.currency_unit = 'msat'
             ) as has_msat_support
          FROM providers p
          WHERE p.is_default = TRUE",
-    )
+        in_list("m.mint_url", "p.mints")
+    ))
     .fetch_optional(db)
     .await?;
 
@@ -186,19 +201,20 @@ pub async fn get_default_provider(db: &Pool) -> Result<Option<Provider>, sqlx::E
 }
 
 pub async fn get_provider_by_id(db: &Pool, id: i32) -> Result<Option<Provider>, sqlx::Error> {
-    let provider = sqlx::query_as::<_, Provider>(
+    let provider = sqlx::query_as::<_, Provider>(&format!(
         "SELECT
             p.id, p.name, p.url, p.mints, p.use_onion, p.followers, p.zaps,
             p.is_default, p.is_custom, COALESCE(p.source, 'manual') as source,
             p.organization_id, p.created_at, p.updated_at,
             EXISTS(
                 SELECT 1 FROM mints m
-                WHERE m.mint_url = ANY(p.mints)
+                WHERE {}
                 AND m.currency_unit = 'msat'
             ) as has_msat_support
          FROM providers p
          WHERE p.id = $1",
-    )
+        in_list("m.mint_url", "p.mints")
+    ))
     .bind(id)
     .fetch_optional(db)
     .await?;
@@ -224,12 +240,11 @@ pub async fn create_custom_provider(
     request: CreateCustomProviderRequest,
 ) -> Result<Provider, ProviderError> {
     // Check if a provider with this URL already exists for the global scope (organization_id = NULL)
-    let existing_provider = sqlx::query!(
-        "SELECT id FROM providers WHERE url = $1 AND organization_id IS NULL",
-        request.url
-    )
-    .fetch_optional(db)
-    .await?;
+    let existing_provider =
+        sqlx::query("SELECT id FROM providers WHERE url = $1 AND organization_id IS NULL")
+            .bind(&request.url)
+            .fetch_optional(db)
+            .await?;
 
     if existing_provider.is_some() {
         return Err(ProviderError::DuplicateUrl(
@@ -239,13 +254,14 @@ pub async fn create_custom_provider(
 
     let provider = sqlx::query_as::<_, Provider>(
         "INSERT INTO providers (name, url, mints, use_onion, followers, zaps, is_custom, source, organization_id, updated_at)
-         VALUES ($1, $2, $3, $4, 0, 0, TRUE, 'manual', NULL, NOW())
+         VALUES ($1, $2, $3, $4, 0, 0, TRUE, 'manual', NULL, $5)
          RETURNING id, name, url, mints, use_onion, followers, zaps, is_default, is_custom, COALESCE(source, 'manual') as source, organization_id, created_at, updated_at, false as has_msat_support"
     )
     .bind(&request.name)
     .bind(&request.url)
-    .bind(&request.mints)
+    .bind(StringList(request.mints.clone()))
     .bind(request.use_onion)
+    .bind(Utc::now())
     .fetch_one(db)
     .await?;
 
@@ -253,17 +269,17 @@ pub async fn create_custom_provider(
 }
 
 pub async fn delete_custom_provider(db: &Pool, id: i32) -> Result<bool, sqlx::Error> {
-    let provider_check = sqlx::query!(
+    let provider_check = sqlx::query_as::<_, (i32, String, Option<bool>, Option<Uuid>)>(
         "SELECT id, name, is_custom, organization_id FROM providers WHERE id = $1",
-        id
     )
+    .bind(id)
     .fetch_optional(db)
     .await?;
 
-    if let Some(provider) = provider_check {
+    if let Some((id, name, is_custom, organization_id)) = provider_check {
         eprintln!(
             "Provider found: id={}, name={}, is_custom={:?}, organization_id={:?}",
-            provider.id, provider.name, provider.is_custom, provider.organization_id
+            id, name, is_custom, organization_id
         );
     } else {
         eprintln!("Provider {} not found", id);
@@ -293,25 +309,27 @@ pub async fn upsert_provider(
     followers: i32,
     zaps: i32,
 ) -> Result<Provider, sqlx::Error> {
-    let provider = sqlx::query_as::<_, Provider>(
+    let provider = sqlx::query_as::<_, Provider>(&format!(
         "INSERT INTO providers (name, url, mints, use_onion, followers, zaps, is_custom, source, organization_id, updated_at)
-         VALUES ($1, $2, $3, $4, $5, $6, FALSE, 'manual', NULL, NOW())
-         ON CONFLICT (url, COALESCE(organization_id, '00000000-0000-0000-0000-000000000000'::uuid))
+         VALUES ($1, $2, $3, $4, $5, $6, FALSE, 'manual', NULL, $7)
+         ON CONFLICT (url, COALESCE(organization_id, {}))
          DO UPDATE SET
             name = EXCLUDED.name,
             mints = EXCLUDED.mints,
             use_onion = EXCLUDED.use_onion,
             followers = EXCLUDED.followers,
             zaps = EXCLUDED.zaps,
-            updated_at = NOW()
-         RETURNING id, name, url, mints, use_onion, followers, zaps, is_default, is_custom, COALESCE(source, 'manual') as source, organization_id, created_at, updated_at, false as has_msat_support"
-    )
+            updated_at = $7
+         RETURNING id, name, url, mints, use_onion, followers, zaps, is_default, is_custom, COALESCE(source, 'manual') as source, organization_id, created_at, updated_at, false as has_msat_support",
+        NIL_UUID
+    ))
     .bind(name)
     .bind(url)
-    .bind(&mints)
+    .bind(StringList(mints))
     .bind(use_onion)
     .bind(followers)
     .bind(zaps)
+    .bind(Utc::now())
     .fetch_one(db)
     .await?;
 
@@ -320,18 +338,19 @@ pub async fn upsert_provider(
 
 pub async fn refresh_providers_from_nostr_global(
     db: &Pool,
+    relays: Vec<String>,
 ) -> Result<RefreshProvidersResponse, Box<dyn std::error::Error>> {
     let mut providers_updated = 0;
     let mut providers_added = 0;
 
-    let nostr_providers = match discover_providers().await {
+    let nostr_providers = match discover_providers_from_relays(relays).await {
         Ok(providers) => providers,
         Err(e) => {
             eprintln!("Failed to discover providers from Nostr: {}", e);
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("Failed to discover providers: {}", e),
-            )));
+            return Err(Box::new(std::io::Error::other(format!(
+                "Failed to discover providers: {}",
+                e
+            ))));
         }
     };
 
@@ -343,16 +362,17 @@ pub async fn refresh_providers_from_nostr_global(
 
             let is_onion_url = url.contains(".onion");
 
-            let existing_provider = sqlx::query_as::<_, Provider>(
+            let existing_provider = sqlx::query_as::<_, Provider>(&format!(
                 "SELECT id, name, url, mints, use_onion, followers, zaps, is_default, is_custom,
                     COALESCE(source, 'manual') as source, organization_id, created_at, updated_at,
                     EXISTS(
                         SELECT 1 FROM mints m
-                        WHERE m.mint_url = ANY(mints)
+                        WHERE {}
                         AND m.currency_unit = 'msat'
                     ) as has_msat_support
                  FROM providers WHERE url = $1 AND source = 'nostr'",
-            )
+                in_list("m.mint_url", "mints")
+            ))
             .bind(url)
             .fetch_optional(db)
             .await?;
@@ -372,15 +392,16 @@ pub async fn refresh_providers_from_nostr_global(
                         use_onion = $3, 
                         followers = $4, 
                         zaps = $5, 
-                        updated_at = NOW() 
+                        updated_at = $7 
                      WHERE id = $6",
                 )
                 .bind(&provider_name)
-                .bind(&nostr_provider.mints)
+                .bind(StringList(nostr_provider.mints.clone()))
                 .bind(is_onion_url)
                 .bind(nostr_provider.followers)
                 .bind(nostr_provider.zaps)
                 .bind(provider.id)
+                .bind(Utc::now())
                 .execute(db)
                 .await?;
 
@@ -395,17 +416,18 @@ pub async fn refresh_providers_from_nostr_global(
                     nostr_provider.name.clone()
                 };
 
-                let _created_provider = sqlx::query!(
+                let _created_provider = sqlx::query(
                     "INSERT INTO providers (name, url, mints, use_onion, followers, zaps, is_default, is_custom, source, created_at, updated_at)
-                     VALUES ($1, $2, $3, $4, $5, $6, FALSE, FALSE, 'nostr', NOW(), NOW())
+                     VALUES ($1, $2, $3, $4, $5, $6, FALSE, FALSE, 'nostr', $7, $7)
                      RETURNING id",
-                    &provider_name,
-                    url,
-                    &nostr_provider.mints,
-                    is_onion_url,
-                    nostr_provider.followers,
-                    nostr_provider.zaps
                 )
+                .bind(&provider_name)
+                .bind(url)
+                .bind(StringList(nostr_provider.mints.clone()))
+                .bind(is_onion_url)
+                .bind(nostr_provider.followers)
+                .bind(nostr_provider.zaps)
+                .bind(Utc::now())
                 .fetch_one(db)
                 .await?;
 
@@ -428,19 +450,20 @@ pub async fn refresh_providers_from_nostr_global(
 pub async fn refresh_providers_from_nostr(
     db: &Pool,
     organization_id: &Uuid,
+    relays: Vec<String>,
 ) -> Result<RefreshProvidersResponse, Box<dyn std::error::Error>> {
     let mut providers_updated = 0;
     let mut providers_added = 0;
     let mut newly_created_provider_ids: Vec<i32> = Vec::new();
 
-    let nostr_providers = match discover_providers().await {
+    let nostr_providers = match discover_providers_from_relays(relays).await {
         Ok(providers) => providers,
         Err(e) => {
             eprintln!("Failed to discover providers from Nostr: {}", e);
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("Failed to discover providers: {}", e),
-            )));
+            return Err(Box::new(std::io::Error::other(format!(
+                "Failed to discover providers: {}",
+                e
+            ))));
         }
     };
 
@@ -452,16 +475,17 @@ pub async fn refresh_providers_from_nostr(
 
             let is_onion_url = url.contains(".onion");
 
-            let existing_provider = sqlx::query_as::<_, Provider>(
+            let existing_provider = sqlx::query_as::<_, Provider>(&format!(
                 "SELECT id, name, url, mints, use_onion, followers, zaps, is_default, is_custom,
                     COALESCE(source, 'manual') as source, organization_id, created_at, updated_at,
                     EXISTS(
                         SELECT 1 FROM mints m
-                        WHERE m.mint_url = ANY(mints)
+                        WHERE {}
                         AND m.currency_unit = 'msat'
                     ) as has_msat_support
                  FROM providers WHERE url = $1 AND source = 'nostr'",
-            )
+                in_list("m.mint_url", "mints")
+            ))
             .bind(url)
             .fetch_optional(db)
             .await?;
@@ -481,15 +505,16 @@ pub async fn refresh_providers_from_nostr(
                         use_onion = $3, 
                         followers = $4, 
                         zaps = $5, 
-                        updated_at = NOW() 
+                        updated_at = $7 
                      WHERE id = $6",
                 )
                 .bind(&provider_name)
-                .bind(&nostr_provider.mints)
+                .bind(StringList(nostr_provider.mints.clone()))
                 .bind(is_onion_url)
                 .bind(nostr_provider.followers)
                 .bind(nostr_provider.zaps)
                 .bind(provider.id)
+                .bind(Utc::now())
                 .execute(db)
                 .await?;
 
@@ -504,22 +529,23 @@ pub async fn refresh_providers_from_nostr(
                     nostr_provider.name.clone()
                 };
 
-                let created_provider = sqlx::query!(
+                let created_provider_id = sqlx::query_scalar::<_, i32>(
                     "INSERT INTO providers (name, url, mints, use_onion, followers, zaps, is_default, is_custom, source, created_at, updated_at)
-                     VALUES ($1, $2, $3, $4, $5, $6, FALSE, FALSE, 'nostr', NOW(), NOW())
+                     VALUES ($1, $2, $3, $4, $5, $6, FALSE, FALSE, 'nostr', $7, $7)
                      RETURNING id",
-                    &provider_name,
-                    url,
-                    &nostr_provider.mints,
-                    is_onion_url,
-                    nostr_provider.followers,
-                    nostr_provider.zaps
                 )
+                .bind(&provider_name)
+                .bind(url)
+                .bind(StringList(nostr_provider.mints.clone()))
+                .bind(is_onion_url)
+                .bind(nostr_provider.followers)
+                .bind(nostr_provider.zaps)
+                .bind(Utc::now())
                 .fetch_one(db)
                 .await?;
 
                 providers_added += 1;
-                newly_created_provider_ids.push(created_provider.id);
+                newly_created_provider_ids.push(created_provider_id);
             }
         }
     }
@@ -547,18 +573,19 @@ pub async fn get_providers_for_organization(
     db: &Pool,
     organization_id: &Uuid,
 ) -> Result<Vec<Provider>, sqlx::Error> {
-    let providers = sqlx::query_as::<_, Provider>(
+    let providers = sqlx::query_as::<_, Provider>(&format!(
         "SELECT id, name, url, mints, use_onion, followers, zaps, is_default, is_custom,
             COALESCE(source, 'manual') as source, organization_id, created_at, updated_at,
             EXISTS(
                 SELECT 1 FROM mints m
-                WHERE m.mint_url = ANY(mints)
+                WHERE {}
                 AND m.currency_unit = 'msat'
             ) as has_msat_support
          FROM providers
          WHERE organization_id IS NULL OR organization_id = $1
          ORDER BY is_default DESC, is_custom ASC, followers DESC, zaps DESC",
-    )
+        in_list("m.mint_url", "mints")
+    ))
     .bind(organization_id)
     .fetch_all(db)
     .await?;
@@ -570,17 +597,18 @@ pub async fn get_default_provider_for_organization(
     db: &Pool,
     organization_id: &Uuid,
 ) -> Result<Option<Provider>, sqlx::Error> {
-    let provider = sqlx::query_as::<_, Provider>(
+    let provider = sqlx::query_as::<_, Provider>(&format!(
         "SELECT id, name, url, mints, use_onion, followers, zaps, is_default, is_custom,
             COALESCE(source, 'manual') as source, organization_id, created_at, updated_at,
             EXISTS(
                 SELECT 1 FROM mints m
-                WHERE m.mint_url = ANY(mints)
+                WHERE {}
                 AND m.currency_unit = 'msat'
             ) as has_msat_support
          FROM providers
          WHERE is_default = TRUE AND (organization_id IS NULL OR organization_id = $1)",
-    )
+        in_list("m.mint_url", "mints")
+    ))
     .bind(organization_id)
     .fetch_optional(db)
     .await?;
@@ -593,19 +621,20 @@ pub async fn get_provider_by_id_for_organization(
     id: i32,
     organization_id: &Uuid,
 ) -> Result<Option<Provider>, sqlx::Error> {
-    let provider = sqlx::query_as::<_, Provider>(
+    let provider = sqlx::query_as::<_, Provider>(&format!(
         "SELECT
             p.id, p.name, p.url, p.mints, p.use_onion, p.followers, p.zaps,
             p.is_default, p.is_custom, p.organization_id, p.created_at, p.updated_at, p.source,
             EXISTS(
                 SELECT 1 FROM mints m
-                WHERE m.mint_url = ANY(p.mints)
+                WHERE {}
                 AND m.organization_id = $2
                 AND m.currency_unit = 'msat'
             ) as has_msat_support
          FROM providers p
          WHERE p.id = $1 AND (p.organization_id IS NULL OR p.organization_id = $2)",
-    )
+        in_list("m.mint_url", "p.mints")
+    ))
     .bind(id)
     .bind(organization_id)
     .fetch_optional(db)
@@ -620,13 +649,12 @@ pub async fn create_custom_provider_for_organization(
     organization_id: &Uuid,
 ) -> Result<Provider, ProviderError> {
     // Check if a provider with this URL already exists for this organization
-    let existing_provider = sqlx::query!(
-        "SELECT id FROM providers WHERE url = $1 AND organization_id = $2",
-        request.url,
-        organization_id
-    )
-    .fetch_optional(db)
-    .await?;
+    let existing_provider =
+        sqlx::query("SELECT id FROM providers WHERE url = $1 AND organization_id = $2")
+            .bind(&request.url)
+            .bind(organization_id)
+            .fetch_optional(db)
+            .await?;
 
     if existing_provider.is_some() {
         return Err(ProviderError::DuplicateUrl(
@@ -636,14 +664,15 @@ pub async fn create_custom_provider_for_organization(
 
     let provider = sqlx::query_as::<_, Provider>(
         "INSERT INTO providers (name, url, mints, use_onion, followers, zaps, is_custom, source, organization_id, updated_at)
-         VALUES ($1, $2, $3, $4, 0, 0, TRUE, 'manual', $5, NOW())
+         VALUES ($1, $2, $3, $4, 0, 0, TRUE, 'manual', $5, $6)
          RETURNING id, name, url, mints, use_onion, followers, zaps, is_default, is_custom, COALESCE(source, 'manual') as source, organization_id, created_at, updated_at, false as has_msat_support"
     )
     .bind(&request.name)
     .bind(&request.url)
-    .bind(&request.mints)
+    .bind(StringList(request.mints.clone()))
     .bind(request.use_onion)
     .bind(organization_id)
+    .bind(Utc::now())
     .fetch_one(db)
     .await?;
 
@@ -659,11 +688,11 @@ pub async fn update_custom_provider(
     request: UpdateCustomProviderRequest,
 ) -> Result<Provider, ProviderError> {
     // Check if another global provider with this URL already exists (excluding the current one)
-    let existing_provider = sqlx::query!(
+    let existing_provider = sqlx::query(
         "SELECT id FROM providers WHERE url = $1 AND organization_id IS NULL AND id != $2",
-        request.url,
-        id
     )
+    .bind(&request.url)
+    .bind(id)
     .fetch_optional(db)
     .await?;
 
@@ -675,15 +704,16 @@ pub async fn update_custom_provider(
 
     let provider = sqlx::query_as::<_, Provider>(
         "UPDATE providers
-         SET name = $1, url = $2, mints = $3, use_onion = $4, updated_at = NOW()
+         SET name = $1, url = $2, mints = $3, use_onion = $4, updated_at = $6
          WHERE id = $5 AND is_custom = TRUE
          RETURNING id, name, url, mints, use_onion, followers, zaps, is_default, is_custom, COALESCE(source, 'manual') as source, organization_id, created_at, updated_at, false as has_msat_support"
     )
     .bind(&request.name)
     .bind(&request.url)
-    .bind(&request.mints)
+    .bind(StringList(request.mints.clone()))
     .bind(request.use_onion)
     .bind(id)
+    .bind(Utc::now())
     .fetch_one(db)
     .await?;
 
@@ -697,12 +727,12 @@ pub async fn update_custom_provider_for_organization(
     organization_id: &Uuid,
 ) -> Result<Provider, ProviderError> {
     // Check if another provider with this URL already exists for this organization (excluding the current one)
-    let existing_provider = sqlx::query!(
+    let existing_provider = sqlx::query(
         "SELECT id FROM providers WHERE url = $1 AND organization_id = $2 AND id != $3",
-        request.url,
-        organization_id,
-        id
     )
+    .bind(&request.url)
+    .bind(organization_id)
+    .bind(id)
     .fetch_optional(db)
     .await?;
 
@@ -714,16 +744,17 @@ pub async fn update_custom_provider_for_organization(
 
     let provider = sqlx::query_as::<_, Provider>(
         "UPDATE providers
-         SET name = $1, url = $2, mints = $3, use_onion = $4, updated_at = NOW()
+         SET name = $1, url = $2, mints = $3, use_onion = $4, updated_at = $7
          WHERE id = $5 AND is_custom = TRUE AND organization_id = $6
          RETURNING id, name, url, mints, use_onion, followers, zaps, is_default, is_custom, COALESCE(source, 'manual') as source, organization_id, created_at, updated_at, false as has_msat_support"
     )
     .bind(&request.name)
     .bind(&request.url)
-    .bind(&request.mints)
+    .bind(StringList(request.mints.clone()))
     .bind(request.use_onion)
     .bind(id)
     .bind(organization_id)
+    .bind(Utc::now())
     .fetch_one(db)
     .await?;
 
@@ -774,7 +805,7 @@ pub async fn get_available_providers_for_organization(
     organization_id: &Uuid,
     is_admin: bool,
 ) -> Result<Vec<ProviderWithStatus>, sqlx::Error> {
-    let providers = sqlx::query!(
+    let providers = sqlx::query_as::<_, ProviderStatusRow>(&format!(
         r#"
         SELECT
             p.id, p.name, p.url, p.mints, p.use_onion, p.followers, p.zaps,
@@ -783,7 +814,7 @@ pub async fn get_available_providers_for_organization(
             COALESCE(op.is_default, false) as is_default_for_org,
             EXISTS(
                 SELECT 1 FROM mints m
-                WHERE m.mint_url = ANY(p.mints)
+                WHERE {}
                 AND m.organization_id = $1
                 AND m.currency_unit = 'msat'
             ) as has_msat_support
@@ -793,41 +824,26 @@ pub async fn get_available_providers_for_organization(
         WHERE p.organization_id IS NULL OR p.organization_id = $1
         ORDER BY p.is_default DESC, p.is_custom ASC, p.followers DESC, p.zaps DESC
         "#,
-        organization_id
-    )
+        in_list("m.mint_url", "p.mints")
+    ))
+    .bind(organization_id)
     .fetch_all(db)
     .await?;
 
     let result = providers
         .into_iter()
         .map(|row| {
-            let is_custom = row.is_custom.unwrap_or(false);
-            let provider_org_id = row.organization_id;
-
             // Provider is editable if it's custom and either:
             // 1. User is admin (can edit any custom provider), or
             // 2. Provider belongs to the same organization
-            let is_editable = is_admin || (is_custom && provider_org_id == Some(*organization_id));
+            let is_editable = is_admin
+                || (row.provider.is_custom
+                    && row.provider.organization_id == Some(*organization_id));
 
             ProviderWithStatus {
-                provider: Provider {
-                    id: row.id,
-                    name: row.name,
-                    url: row.url,
-                    mints: row.mints.unwrap_or_default(),
-                    use_onion: row.use_onion.unwrap_or(false),
-                    followers: row.followers.unwrap_or(0),
-                    zaps: row.zaps.unwrap_or(0),
-                    is_default: row.is_default.unwrap_or(false),
-                    is_custom,
-                    source: row.source,
-                    organization_id: provider_org_id,
-                    created_at: row.created_at.unwrap_or_else(chrono::Utc::now),
-                    updated_at: row.updated_at.unwrap_or_else(chrono::Utc::now),
-                    has_msat_support: row.has_msat_support.unwrap_or(false),
-                },
-                is_active_for_org: row.is_active_for_org.unwrap_or(false),
-                is_default_for_org: row.is_default_for_org.unwrap_or(false),
+                provider: row.provider,
+                is_active_for_org: row.is_active_for_org,
+                is_default_for_org: row.is_default_for_org,
                 is_editable,
             }
         })
@@ -840,14 +856,14 @@ pub async fn get_active_providers_for_organization(
     db: &Pool,
     organization_id: &Uuid,
 ) -> Result<Vec<Provider>, sqlx::Error> {
-    let providers = sqlx::query_as::<_, Provider>(
+    let providers = sqlx::query_as::<_, Provider>(&format!(
         r#"
         SELECT
             p.id, p.name, p.url, p.mints, p.use_onion, p.followers, p.zaps,
             p.is_default, p.is_custom, p.organization_id, p.created_at, p.updated_at, p.source,
             EXISTS(
                 SELECT 1 FROM mints m
-                WHERE m.mint_url = ANY(p.mints)
+                WHERE {}
                 AND m.organization_id = $1
                 AND m.currency_unit = 'msat'
             ) as has_msat_support
@@ -857,7 +873,8 @@ pub async fn get_active_providers_for_organization(
         WHERE op.organization_id = $1 AND op.is_active = true
         ORDER BY op.is_default DESC, p.followers DESC, p.zaps DESC
         "#,
-    )
+        in_list("m.mint_url", "p.mints")
+    ))
     .bind(organization_id)
     .fetch_all(db)
     .await?;
@@ -869,14 +886,14 @@ pub async fn get_default_provider_for_organization_new(
     db: &Pool,
     organization_id: &Uuid,
 ) -> Result<Option<Provider>, sqlx::Error> {
-    let provider = sqlx::query_as::<_, Provider>(
+    let provider = sqlx::query_as::<_, Provider>(&format!(
         r#"
         SELECT
             p.id, p.name, p.url, p.mints, p.use_onion, p.followers, p.zaps,
             p.is_default, p.is_custom, p.organization_id, p.created_at, p.updated_at, p.source,
             EXISTS(
                 SELECT 1 FROM mints m
-                WHERE m.mint_url = ANY(p.mints)
+                WHERE {}
                 AND m.organization_id = $1
                 AND m.currency_unit = 'msat'
             ) as has_msat_support
@@ -885,7 +902,8 @@ pub async fn get_default_provider_for_organization_new(
             ON p.id = op.provider_id
         WHERE op.organization_id = $1 AND op.is_default = true AND op.is_active = true
         "#,
-    )
+        in_list("m.mint_url", "p.mints")
+    ))
     .bind(organization_id)
     .fetch_optional(db)
     .await?;
@@ -899,11 +917,11 @@ pub async fn activate_provider_for_organization(
     provider_id: i32,
 ) -> Result<OrganizationProvider, sqlx::Error> {
     // First check if the provider is available to this organization
-    let provider_exists = sqlx::query!(
+    let provider_exists = sqlx::query(
         "SELECT id FROM providers WHERE id = $1 AND (organization_id IS NULL OR organization_id = $2)",
-        provider_id,
-        organization_id
     )
+    .bind(provider_id)
+    .bind(organization_id)
     .fetch_optional(db)
     .await?;
 
@@ -915,16 +933,17 @@ pub async fn activate_provider_for_organization(
     let org_provider = sqlx::query_as::<_, OrganizationProvider>(
         r#"
         INSERT INTO organization_providers (organization_id, provider_id, is_active, updated_at)
-        VALUES ($1, $2, true, NOW())
+        VALUES ($1, $2, true, $3)
         ON CONFLICT (organization_id, provider_id)
         DO UPDATE SET
             is_active = true,
-            updated_at = NOW()
+            updated_at = $3
         RETURNING organization_id, provider_id, is_default, is_active, created_at, updated_at
         "#,
     )
     .bind(organization_id)
     .bind(provider_id)
+    .bind(Utc::now())
     .fetch_one(db)
     .await?;
 
@@ -937,26 +956,27 @@ pub async fn deactivate_provider_for_organization(
     provider_id: i32,
 ) -> Result<bool, sqlx::Error> {
     // Check if this is the default provider
-    let is_default = sqlx::query!(
+    let is_default = sqlx::query_scalar::<_, Option<bool>>(
         "SELECT is_default FROM organization_providers WHERE organization_id = $1 AND provider_id = $2",
-        organization_id,
-        provider_id
     )
+    .bind(organization_id)
+    .bind(provider_id)
     .fetch_optional(db)
     .await?;
 
-    if let Some(row) = is_default {
-        if row.is_default.unwrap_or(false) {
+    if let Some(is_default) = is_default {
+        if is_default.unwrap_or(false) {
             // Cannot deactivate the default provider
             return Err(sqlx::Error::RowNotFound); // We'll use this to indicate the constraint violation
         }
     }
 
-    let result = sqlx::query!(
-        "UPDATE organization_providers SET is_active = false, updated_at = NOW() WHERE organization_id = $1 AND provider_id = $2",
-        organization_id,
-        provider_id
+    let result = sqlx::query(
+        "UPDATE organization_providers SET is_active = false, updated_at = $3 WHERE organization_id = $1 AND provider_id = $2",
     )
+    .bind(organization_id)
+    .bind(provider_id)
+    .bind(Utc::now())
     .execute(db)
     .await?;
 
@@ -972,27 +992,26 @@ pub async fn set_default_provider_for_organization_new(
     let mut tx = db.begin().await?;
 
     // Clear existing default
-    sqlx::query!(
-        "UPDATE organization_providers SET is_default = false WHERE organization_id = $1",
-        organization_id
-    )
-    .execute(&mut *tx)
-    .await?;
+    sqlx::query("UPDATE organization_providers SET is_default = false WHERE organization_id = $1")
+        .bind(organization_id)
+        .execute(&mut *tx)
+        .await?;
 
     // Set new default (and ensure it's active)
-    let result = sqlx::query!(
+    let result = sqlx::query(
         r#"
         INSERT INTO organization_providers (organization_id, provider_id, is_default, is_active, updated_at)
-        VALUES ($1, $2, true, true, NOW())
+        VALUES ($1, $2, true, true, $3)
         ON CONFLICT (organization_id, provider_id)
         DO UPDATE SET
             is_default = true,
             is_active = true,
-            updated_at = NOW()
+            updated_at = $3
         "#,
-        organization_id,
-        provider_id
     )
+    .bind(organization_id)
+    .bind(provider_id)
+    .bind(Utc::now())
     .execute(&mut *tx)
     .await?;
 
@@ -1005,42 +1024,6 @@ pub async fn set_default_provider_for_organization_new(
     Ok(())
 }
 
-pub async fn upsert_provider_for_organization(
-    db: &Pool,
-    name: &str,
-    url: &str,
-    mints: Vec<String>,
-    use_onion: bool,
-    followers: i32,
-    zaps: i32,
-    organization_id: Option<&Uuid>,
-) -> Result<Provider, sqlx::Error> {
-    let provider = sqlx::query_as::<_, Provider>(
-        "INSERT INTO providers (name, url, mints, use_onion, followers, zaps, is_custom, source, organization_id, updated_at)
-         VALUES ($1, $2, $3, $4, $5, $6, FALSE, 'manual', $7, NOW())
-         ON CONFLICT (url, COALESCE(organization_id, '00000000-0000-0000-0000-000000000000'::uuid))
-         DO UPDATE SET
-            name = EXCLUDED.name,
-            mints = EXCLUDED.mints,
-            use_onion = EXCLUDED.use_onion,
-            followers = EXCLUDED.followers,
-            zaps = EXCLUDED.zaps,
-            updated_at = NOW()
-         RETURNING id, name, url, mints, use_onion, followers, zaps, is_default, is_custom, 'manual' as source, organization_id, created_at, updated_at, false as has_msat_support"
-    )
-    .bind(name)
-    .bind(url)
-    .bind(&mints)
-    .bind(use_onion)
-    .bind(followers)
-    .bind(zaps)
-    .bind(organization_id)
-    .fetch_one(db)
-    .await?;
-
-    Ok(provider)
-}
-
 pub async fn upsert_nostr_provider(
     db: &Pool,
     request: CreateNostrProviderRequest,
@@ -1051,25 +1034,27 @@ pub async fn upsert_nostr_provider(
         request.name
     };
 
-    let provider = sqlx::query_as::<_, Provider>(
+    let provider = sqlx::query_as::<_, Provider>(&format!(
         "INSERT INTO providers (name, url, mints, use_onion, followers, zaps, is_custom, source, organization_id, updated_at)
-         VALUES ($1, $2, $3, $4, $5, $6, FALSE, 'nostr', NULL, NOW())
-         ON CONFLICT (url, COALESCE(organization_id, '00000000-0000-0000-0000-000000000000'::uuid))
+         VALUES ($1, $2, $3, $4, $5, $6, FALSE, 'nostr', NULL, $7)
+         ON CONFLICT (url, COALESCE(organization_id, {}))
          DO UPDATE SET
             name = EXCLUDED.name,
             mints = EXCLUDED.mints,
             use_onion = EXCLUDED.use_onion,
             followers = EXCLUDED.followers,
             zaps = EXCLUDED.zaps,
-            updated_at = NOW()
-         RETURNING id, name, url, mints, use_onion, followers, zaps, is_default, is_custom, COALESCE(source, 'nostr') as source, organization_id, created_at, updated_at, false as has_msat_support"
-    )
+            updated_at = $7
+         RETURNING id, name, url, mints, use_onion, followers, zaps, is_default, is_custom, COALESCE(source, 'nostr') as source, organization_id, created_at, updated_at, false as has_msat_support",
+        NIL_UUID
+    ))
     .bind(&provider_name)
     .bind(&request.url)
-    .bind(&request.mints)
+    .bind(StringList(request.mints.clone()))
     .bind(request.use_onion)
     .bind(request.followers)
     .bind(request.zaps)
+    .bind(Utc::now())
     .fetch_one(db)
     .await?;
 
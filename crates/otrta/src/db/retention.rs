@@ -0,0 +1,311 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::db::{
+    dialect::{is_digits, NIL_UUID},
+    Pool,
+};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionRetentionAction {
+    /// Fold expired transactions into daily rollups, then delete them.
+    Aggregate,
+    /// Delete expired transactions outright.
+    Delete,
+}
+
+impl TransactionRetentionAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionRetentionAction::Aggregate => "aggregate",
+            TransactionRetentionAction::Delete => "delete",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct RetentionPolicy {
+    pub id: Uuid,
+    pub organization_id: Option<Uuid>,
+    pub transaction_retention_days: Option<i32>,
+    pub transaction_action: String,
+    pub search_retention_days: Option<i32>,
+    pub credit_retention_days: Option<i32>,
+    pub last_enforced_at: Option<DateTime<Utc>>,
+    pub last_report: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl RetentionPolicy {
+    pub fn transaction_action(&self) -> TransactionRetentionAction {
+        match self.transaction_action.as_str() {
+            "delete" => TransactionRetentionAction::Delete,
+            _ => TransactionRetentionAction::Aggregate,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetRetentionPolicyRequest {
+    pub transaction_retention_days: Option<i32>,
+    pub transaction_action: Option<TransactionRetentionAction>,
+    pub search_retention_days: Option<i32>,
+    pub credit_retention_days: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TransactionRollup {
+    pub date: NaiveDate,
+    pub api_key_id: Option<Uuid>,
+    pub user_id: Option<String>,
+    pub direction: String,
+    pub unit: String,
+    pub model: String,
+    pub transaction_count: i64,
+    pub amount: i64,
+    pub fiat_amount: f64,
+}
+
+const POLICY_COLUMNS: &str = "id, organization_id, transaction_retention_days, transaction_action, search_retention_days, credit_retention_days, last_enforced_at, last_report, created_at, updated_at";
+
+// Transactions belong to an organization through their API key, or through
+// the user for chat requests made without one.
+const ORGANIZATION_TRANSACTIONS: &str = "SELECT t.id
+     FROM transactions t
+     LEFT JOIN api_keys ak ON t.api_key_id = ak.id
+     LEFT JOIN users u ON t.user_id = u.npub
+     WHERE (ak.organization_id = $3 OR (ak.id IS NULL AND u.organization_id = $1))
+       AND t.created_at < $2";
+
+/// The organization's own policy, or the instance default when
+/// `organization_id` is `None`.
+pub async fn get_retention_policy(
+    pool: &Pool,
+    organization_id: Option<&Uuid>,
+) -> Result<Option<RetentionPolicy>, sqlx::Error> {
+    sqlx::query_as::<_, RetentionPolicy>(&format!(
+        "SELECT {} FROM retention_policies WHERE organization_id IS NOT DISTINCT FROM $1",
+        POLICY_COLUMNS
+    ))
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn upsert_retention_policy(
+    pool: &Pool,
+    organization_id: Option<&Uuid>,
+    request: &SetRetentionPolicyRequest,
+) -> Result<RetentionPolicy, sqlx::Error> {
+    let action = request
+        .transaction_action
+        .unwrap_or(TransactionRetentionAction::Aggregate);
+
+    sqlx::query_as::<_, RetentionPolicy>(&format!(
+        "INSERT INTO retention_policies (organization_id, transaction_retention_days, transaction_action, search_retention_days, credit_retention_days)
+         VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT ((COALESCE(organization_id, {})))
+         DO UPDATE SET
+             transaction_retention_days = EXCLUDED.transaction_retention_days,
+             transaction_action = EXCLUDED.transaction_action,
+             search_retention_days = EXCLUDED.search_retention_days,
+             credit_retention_days = EXCLUDED.credit_retention_days,
+             updated_at = $6
+         RETURNING {}",
+        NIL_UUID, POLICY_COLUMNS
+    ))
+    .bind(organization_id)
+    .bind(request.transaction_retention_days)
+    .bind(action.as_str())
+    .bind(request.search_retention_days)
+    .bind(request.credit_retention_days)
+    .bind(Utc::now())
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn record_retention_run(
+    pool: &Pool,
+    policy_id: &Uuid,
+    report: &serde_json::Value,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE retention_policies SET last_enforced_at = $3, last_report = $2 WHERE id = $1",
+    )
+    .bind(policy_id)
+    .bind(report)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Folds the organization's transactions older than `cutoff` into daily
+/// rollups and deletes them. Returns the number of transactions removed.
+pub async fn aggregate_transactions_before(
+    pool: &Pool,
+    organization_id: &Uuid,
+    cutoff: DateTime<Utc>,
+) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(&format!(
+        "INSERT INTO transaction_daily_rollups
+             (organization_id, date, api_key_id, user_id, direction, unit, model, transaction_count, amount, fiat_amount)
+         SELECT $1, DATE(t.created_at), t.api_key_id, t.user_id, t.direction,
+                COALESCE(t.unit, 'msat'), COALESCE(t.model, ''), COUNT(*),
+                CAST(COALESCE(SUM(CASE WHEN {} THEN CAST(t.amount AS BIGINT) ELSE 0 END), 0) AS BIGINT),
+                COALESCE(SUM(t.fiat_amount), 0)
+         FROM transactions t
+         WHERE t.id IN ({})
+         GROUP BY DATE(t.created_at), t.api_key_id, t.user_id, t.direction, COALESCE(t.unit, 'msat'), COALESCE(t.model, '')
+         ON CONFLICT (organization_id, date, COALESCE(api_key_id, {}), COALESCE(user_id, ''), direction, unit, model)
+         DO UPDATE SET
+             transaction_count = transaction_daily_rollups.transaction_count + EXCLUDED.transaction_count,
+             amount = transaction_daily_rollups.amount + EXCLUDED.amount,
+             fiat_amount = transaction_daily_rollups.fiat_amount + EXCLUDED.fiat_amount,
+             updated_at = $4",
+        is_digits("t.amount"),
+        ORGANIZATION_TRANSACTIONS,
+        NIL_UUID
+    ))
+    .bind(organization_id)
+    .bind(cutoff)
+    .bind(organization_id.to_string())
+    .bind(Utc::now())
+    .execute(&mut *tx)
+    .await?;
+
+    let deleted = sqlx::query(&format!(
+        "DELETE FROM transactions WHERE id IN ({})",
+        ORGANIZATION_TRANSACTIONS
+    ))
+    .bind(organization_id)
+    .bind(cutoff)
+    .bind(organization_id.to_string())
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(deleted.rows_affected())
+}
+
+pub async fn delete_transactions_before(
+    pool: &Pool,
+    organization_id: &Uuid,
+    cutoff: DateTime<Utc>,
+) -> Result<u64, sqlx::Error> {
+    let deleted = sqlx::query(&format!(
+        "DELETE FROM transactions WHERE id IN ({})",
+        ORGANIZATION_TRANSACTIONS
+    ))
+    .bind(organization_id)
+    .bind(cutoff)
+    .bind(organization_id.to_string())
+    .execute(pool)
+    .await?;
+
+    Ok(deleted.rows_affected())
+}
+
+/// Deletes searches (including scraped source content) made by members of the
+/// organization before `cutoff`.
+pub async fn purge_searches_before(
+    pool: &Pool,
+    organization_id: &Uuid,
+    cutoff: DateTime<Utc>,
+) -> Result<u64, sqlx::Error> {
+    let deleted = sqlx::query(
+        "DELETE FROM user_searches
+         WHERE user_id IN (SELECT npub FROM users WHERE organization_id = $1)
+           AND created_at < $2",
+    )
+    .bind(organization_id)
+    .bind(cutoff)
+    .execute(pool)
+    .await?;
+
+    Ok(deleted.rows_affected())
+}
+
+/// Deletes the organization's payment intents that reached a final state
+/// before `cutoff`. Their tokens were cleared when they finished, so only
+/// request metadata goes; unfinished intents are left for recovery.
+pub async fn delete_final_payment_intents_before(
+    pool: &Pool,
+    organization_id: &Uuid,
+    cutoff: DateTime<Utc>,
+) -> Result<u64, sqlx::Error> {
+    let deleted = sqlx::query(
+        "DELETE FROM payment_intents
+         WHERE organization_id = $1
+           AND state IN ('completed', 'refunded', 'failed', 'recovered', 'spent')
+           AND updated_at < $2",
+    )
+    .bind(organization_id)
+    .bind(cutoff)
+    .execute(pool)
+    .await?;
+
+    Ok(deleted.rows_affected())
+}
+
+/// Deletes redeemed credits created before `cutoff`. Unredeemed credits still
+/// hold spendable tokens and are never purged.
+pub async fn delete_redeemed_credits_before(
+    pool: &Pool,
+    cutoff: DateTime<Utc>,
+) -> Result<u64, sqlx::Error> {
+    let deleted = sqlx::query("DELETE FROM credits WHERE redeemed = TRUE AND created_at < $1")
+        .bind(cutoff)
+        .execute(pool)
+        .await?;
+
+    Ok(deleted.rows_affected())
+}
+
+pub async fn get_transaction_rollups(
+    pool: &Pool,
+    organization_id: &Uuid,
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+) -> Result<Vec<TransactionRollup>, sqlx::Error> {
+    sqlx::query_as::<_, TransactionRollup>(
+        "SELECT date, api_key_id, user_id, CAST(direction AS TEXT) AS direction, unit, model,
+                transaction_count, amount, fiat_amount
+         FROM transaction_daily_rollups
+         WHERE organization_id = $1
+           AND ($2 IS NULL OR date >= $2)
+           AND ($3 IS NULL OR date <= $3)
+         ORDER BY date, api_key_id NULLS FIRST, direction",
+    )
+    .bind(organization_id)
+    .bind(start_date)
+    .bind(end_date)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_organization_ids(pool: &Pool) -> Result<Vec<Uuid>, sqlx::Error> {
+    sqlx::query_scalar("SELECT id FROM organizations ORDER BY created_at")
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn delete_retention_policy(
+    pool: &Pool,
+    organization_id: &Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM retention_policies WHERE organization_id = $1")
+        .bind(organization_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
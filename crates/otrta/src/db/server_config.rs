@@ -1,9 +1,10 @@
 use chrono::{DateTime, Utc};
-use sqlx::PgPool;
+use sqlx::FromRow;
 
+use crate::db::Pool;
 use crate::models::ServerConfig;
 
-#[derive(Debug)]
+#[derive(Debug, FromRow)]
 pub struct ServerConfigRecord {
     pub id: String,
     pub endpoint: String,
@@ -14,61 +15,35 @@ pub struct ServerConfigRecord {
     pub organization_id: uuid::Uuid,
 }
 
-pub async fn get_all_configs(pool: &PgPool) -> Result<Vec<ServerConfigRecord>, sqlx::Error> {
-    let configs = sqlx::query!(
+pub async fn get_all_configs(pool: &Pool) -> Result<Vec<ServerConfigRecord>, sqlx::Error> {
+    sqlx::query_as::<_, ServerConfigRecord>(
         r#"
         SELECT id, endpoint, api_key, created_at, updated_at, seed, organization_id
         FROM server_config
-        "#
+        "#,
     )
     .fetch_all(pool)
-    .await?
-    .into_iter()
-    .map(|record| ServerConfigRecord {
-        id: record.id,
-        endpoint: record.endpoint,
-        api_key: record.api_key,
-        created_at: record.created_at,
-        updated_at: record.updated_at,
-        seed: record.seed,
-        organization_id: record.organization_id,
-    })
-    .collect();
-
-    Ok(configs)
+    .await
 }
 
-pub async fn get_default_config(pool: &PgPool) -> Result<Option<ServerConfigRecord>, sqlx::Error> {
-    let record = sqlx::query!(
+pub async fn get_default_config(pool: &Pool) -> Result<Option<ServerConfigRecord>, sqlx::Error> {
+    sqlx::query_as::<_, ServerConfigRecord>(
         r#"
         SELECT id, endpoint, api_key, created_at, updated_at, seed, organization_id
         FROM server_config
         ORDER BY created_at DESC
         LIMIT 1
-        "#
+        "#,
     )
     .fetch_optional(pool)
-    .await?;
-
-    match record {
-        Some(r) => Ok(Some(ServerConfigRecord {
-            id: r.id,
-            endpoint: r.endpoint,
-            api_key: r.api_key,
-            created_at: r.created_at,
-            updated_at: r.updated_at,
-            seed: r.seed,
-            organization_id: r.organization_id,
-        })),
-        None => Ok(None),
-    }
+    .await
 }
 
 pub async fn get_config_by_organization(
-    pool: &PgPool,
+    pool: &Pool,
     organization_id: &uuid::Uuid,
 ) -> Result<Option<ServerConfigRecord>, sqlx::Error> {
-    let record = sqlx::query!(
+    sqlx::query_as::<_, ServerConfigRecord>(
         r#"
         SELECT id, endpoint, api_key, created_at, updated_at, seed, organization_id
         FROM server_config
@@ -76,27 +51,14 @@ pub async fn get_config_by_organization(
         ORDER BY created_at DESC
         LIMIT 1
         "#,
-        organization_id
     )
+    .bind(organization_id)
     .fetch_optional(pool)
-    .await?;
-
-    match record {
-        Some(r) => Ok(Some(ServerConfigRecord {
-            id: r.id,
-            endpoint: r.endpoint,
-            api_key: r.api_key,
-            created_at: r.created_at,
-            updated_at: r.updated_at,
-            seed: r.seed,
-            organization_id: r.organization_id,
-        })),
-        None => Ok(None),
-    }
+    .await
 }
 
 pub async fn create_config(
-    pool: &PgPool,
+    pool: &Pool,
     config: &ServerConfig,
     organization_id: &uuid::Uuid,
 ) -> Result<ServerConfigRecord, sqlx::Error> {
@@ -105,121 +67,74 @@ pub async fn create_config(
         uuid::Uuid::new_v4().to_string().replace("-", "")
     );
 
-    let record = sqlx::query!(
+    sqlx::query_as::<_, ServerConfigRecord>(
         r#"
         INSERT INTO server_config (id, endpoint, api_key, created_at, organization_id)
-        VALUES ($1, $2, $3, NOW(), $4)
+        VALUES ($1, $2, $3, $4, $5)
         RETURNING id, endpoint, api_key, created_at, updated_at, seed, organization_id
         "#,
-        id,
-        config.endpoint,
-        config.api_key,
-        organization_id
     )
+    .bind(id)
+    .bind(&config.endpoint)
+    .bind(&config.api_key)
+    .bind(Utc::now())
+    .bind(organization_id)
     .fetch_one(pool)
-    .await?;
-
-    Ok(ServerConfigRecord {
-        id: record.id,
-        endpoint: record.endpoint,
-        api_key: record.api_key,
-        created_at: record.created_at,
-        updated_at: record.updated_at,
-        seed: record.seed,
-        organization_id: record.organization_id,
-    })
+    .await
 }
 
 pub async fn create_config_for_organization(
-    pool: &PgPool,
+    pool: &Pool,
     config: &ServerConfig,
     organization_id: &uuid::Uuid,
 ) -> Result<ServerConfigRecord, sqlx::Error> {
-    let id = format!(
-        "config_{}",
-        uuid::Uuid::new_v4().to_string().replace("-", "")
-    );
-
-    let record = sqlx::query!(
-        r#"
-        INSERT INTO server_config (id, endpoint, api_key, created_at, organization_id)
-        VALUES ($1, $2, $3, NOW(), $4)
-        RETURNING id, endpoint, api_key, created_at, updated_at, seed, organization_id
-        "#,
-        id,
-        config.endpoint,
-        config.api_key,
-        organization_id
-    )
-    .fetch_one(pool)
-    .await?;
-
-    Ok(ServerConfigRecord {
-        id: record.id,
-        endpoint: record.endpoint,
-        api_key: record.api_key,
-        created_at: record.created_at,
-        updated_at: record.updated_at,
-        seed: record.seed,
-        organization_id: record.organization_id,
-    })
+    create_config(pool, config, organization_id).await
 }
 
 pub async fn update_config(
-    pool: &PgPool,
+    pool: &Pool,
     id: String,
     config: &ServerConfig,
 ) -> Result<ServerConfigRecord, sqlx::Error> {
-    let record = sqlx::query!(
+    sqlx::query_as::<_, ServerConfigRecord>(
         r#"
         UPDATE server_config
-        SET endpoint = $1, api_key = $2, updated_at = NOW()
-        WHERE id = $3
+        SET endpoint = $1, api_key = $2, updated_at = $3
+        WHERE id = $4
         RETURNING id, endpoint, api_key, created_at, updated_at, seed, organization_id
         "#,
-        config.endpoint,
-        config.api_key,
-        id
     )
+    .bind(&config.endpoint)
+    .bind(&config.api_key)
+    .bind(Utc::now())
+    .bind(id)
     .fetch_one(pool)
-    .await?;
-
-    Ok(ServerConfigRecord {
-        id: record.id,
-        endpoint: record.endpoint,
-        api_key: record.api_key,
-        created_at: record.created_at,
-        updated_at: record.updated_at,
-        seed: record.seed,
-        organization_id: record.organization_id,
-    })
+    .await
 }
 
-pub async fn delete_config(pool: &PgPool, id: &str) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query!(
+pub async fn delete_config(pool: &Pool, id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
         r#"
         DELETE FROM server_config
         WHERE id = $1
         "#,
-        id
     )
+    .bind(id)
     .execute(pool)
     .await?;
 
     Ok(result.rows_affected() > 0)
 }
 
-pub async fn config_exists(pool: &PgPool, id: &str) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query!(
+pub async fn config_exists(pool: &Pool, id: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
         r#"
-        SELECT EXISTS(SELECT 1 FROM server_config WHERE id = $1) as exists
+        SELECT EXISTS(SELECT 1 FROM server_config WHERE id = $1)
         "#,
-        id
     )
+    .bind(id)
     .fetch_one(pool)
-    .await?;
-
-    Ok(result.exists.unwrap_or(false))
+    .await
 }
 
 impl ServerConfigRecord {
@@ -232,36 +147,27 @@ impl ServerConfigRecord {
 }
 
 pub async fn update_seed(
-    pool: &PgPool,
+    pool: &Pool,
     seed: &str,
     organization_id: &uuid::Uuid,
 ) -> Result<ServerConfigRecord, sqlx::Error> {
-    let record = sqlx::query!(
+    sqlx::query_as::<_, ServerConfigRecord>(
         r#"
         UPDATE server_config
-        SET seed = $1, updated_at = NOW()
-        WHERE organization_id = $2
+        SET seed = $1, updated_at = $2
+        WHERE organization_id = $3
         RETURNING id, endpoint, api_key, created_at, updated_at, seed, organization_id
         "#,
-        seed,
-        organization_id
     )
+    .bind(seed)
+    .bind(Utc::now())
+    .bind(organization_id)
     .fetch_one(pool)
-    .await?;
-
-    Ok(ServerConfigRecord {
-        id: record.id,
-        endpoint: record.endpoint,
-        api_key: record.api_key,
-        created_at: record.created_at,
-        updated_at: record.updated_at,
-        seed: record.seed,
-        organization_id: record.organization_id,
-    })
+    .await
 }
 
 pub async fn create_with_seed(
-    pool: &PgPool,
+    pool: &Pool,
     seed: &str,
     organization_id: &uuid::Uuid,
 ) -> Result<ServerConfigRecord, sqlx::Error> {
@@ -270,64 +176,27 @@ pub async fn create_with_seed(
         uuid::Uuid::new_v4().to_string().replace("-", "")
     );
 
-    let record = sqlx::query!(
+    sqlx::query_as::<_, ServerConfigRecord>(
         r#"
         INSERT INTO server_config (id, endpoint, api_key, created_at, seed, organization_id)
-        VALUES ($1, $2, $3, NOW(), $4, $5)
+        VALUES ($1, $2, $3, $4, $5, $6)
         RETURNING id, endpoint, api_key, created_at, updated_at, seed, organization_id
         "#,
-        id,
-        String::from(""),
-        String::from(""),
-        seed,
-        organization_id
     )
+    .bind(id)
+    .bind("")
+    .bind("")
+    .bind(Utc::now())
+    .bind(seed)
+    .bind(organization_id)
     .fetch_one(pool)
-    .await?;
-
-    Ok(ServerConfigRecord {
-        id: record.id,
-        endpoint: record.endpoint,
-        api_key: record.api_key,
-        created_at: record.created_at,
-        updated_at: record.updated_at,
-        seed: record.seed,
-        organization_id: record.organization_id,
-    })
+    .await
 }
 
 pub async fn create_with_seed_for_organization(
-    pool: &PgPool,
+    pool: &Pool,
     seed: &str,
     organization_id: &uuid::Uuid,
 ) -> Result<ServerConfigRecord, sqlx::Error> {
-    let id = format!(
-        "config_{}",
-        uuid::Uuid::new_v4().to_string().replace("-", "")
-    );
-
-    let record = sqlx::query!(
-        r#"
-        INSERT INTO server_config (id, endpoint, api_key, created_at, seed, organization_id)
-        VALUES ($1, $2, $3, NOW(), $4, $5)
-        RETURNING id, endpoint, api_key, created_at, updated_at, seed, organization_id
-        "#,
-        id,
-        String::from(""),
-        String::from(""),
-        seed,
-        organization_id
-    )
-    .fetch_one(pool)
-    .await?;
-
-    Ok(ServerConfigRecord {
-        id: record.id,
-        endpoint: record.endpoint,
-        api_key: record.api_key,
-        created_at: record.created_at,
-        updated_at: record.updated_at,
-        seed: record.seed,
-        organization_id: record.organization_id,
-    })
+    create_with_seed(pool, seed, organization_id).await
 }
@@ -0,0 +1,185 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+use crate::db::Pool;
+use uuid::Uuid;
+
+const SESSION_COLUMNS: &str = "id, npub, organization_id, refresh_token_hash, user_agent,
+    created_at, refreshed_at, expires_at, revoked_at";
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Session {
+    pub id: Uuid,
+    pub npub: String,
+    pub organization_id: Uuid,
+    #[serde(skip_serializing)]
+    pub refresh_token_hash: String,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// When the refresh token was last exchanged, or the login time.
+    pub refreshed_at: DateTime<Utc>,
+    /// End of the refresh token's validity; pushed back by each refresh.
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+pub async fn create_session(
+    pool: &Pool,
+    npub: &str,
+    organization_id: &Uuid,
+    refresh_token_hash: &str,
+    user_agent: Option<&str>,
+    expires_at: DateTime<Utc>,
+) -> Result<Session, sqlx::Error> {
+    let now = Utc::now();
+    sqlx::query_as::<_, Session>(&format!(
+        "INSERT INTO sessions (id, npub, organization_id, refresh_token_hash, user_agent, created_at, refreshed_at, expires_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $6, $7)
+         RETURNING {}",
+        SESSION_COLUMNS
+    ))
+    .bind(Uuid::new_v4())
+    .bind(npub)
+    .bind(organization_id)
+    .bind(refresh_token_hash)
+    .bind(user_agent)
+    .bind(now)
+    .bind(expires_at)
+    .fetch_one(pool)
+    .await
+}
+
+/// The session if it is neither revoked nor expired.
+pub async fn get_active_session(pool: &Pool, id: &Uuid) -> Result<Option<Session>, sqlx::Error> {
+    sqlx::query_as::<_, Session>(&format!(
+        "SELECT {} FROM sessions WHERE id = $1 AND revoked_at IS NULL AND expires_at > $2",
+        SESSION_COLUMNS
+    ))
+    .bind(id)
+    .bind(Utc::now())
+    .fetch_optional(pool)
+    .await
+}
+
+/// Swaps the refresh token of the active session holding `old_hash` and
+/// extends it to `expires_at`. A single statement, so a token can only be
+/// exchanged once even by concurrent requests.
+pub async fn rotate_refresh_token(
+    pool: &Pool,
+    old_hash: &str,
+    new_hash: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<Option<Session>, sqlx::Error> {
+    sqlx::query_as::<_, Session>(&format!(
+        "UPDATE sessions SET refresh_token_hash = $2, refreshed_at = $3, expires_at = $4
+         WHERE refresh_token_hash = $1 AND revoked_at IS NULL AND expires_at > $3
+         RETURNING {}",
+        SESSION_COLUMNS
+    ))
+    .bind(old_hash)
+    .bind(new_hash)
+    .bind(Utc::now())
+    .bind(expires_at)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn get_active_sessions_for_user(
+    pool: &Pool,
+    npub: &str,
+) -> Result<Vec<Session>, sqlx::Error> {
+    sqlx::query_as::<_, Session>(&format!(
+        "SELECT {} FROM sessions
+         WHERE npub = $1 AND revoked_at IS NULL AND expires_at > $2
+         ORDER BY created_at DESC",
+        SESSION_COLUMNS
+    ))
+    .bind(npub)
+    .bind(Utc::now())
+    .fetch_all(pool)
+    .await
+}
+
+/// Revokes one of the user's sessions. Returns false if there was no such
+/// active session.
+pub async fn revoke_session(pool: &Pool, id: &Uuid, npub: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE sessions SET revoked_at = $3
+         WHERE id = $1 AND npub = $2 AND revoked_at IS NULL",
+    )
+    .bind(id)
+    .bind(npub)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Revokes every active session of the user and returns how many there were.
+pub async fn revoke_sessions_for_user(pool: &Pool, npub: &str) -> Result<u64, sqlx::Error> {
+    let result =
+        sqlx::query("UPDATE sessions SET revoked_at = $2 WHERE npub = $1 AND revoked_at IS NULL")
+            .bind(npub)
+            .bind(Utc::now())
+            .execute(pool)
+            .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Revokes the user's sessions in one organization, e.g. after they leave it.
+pub async fn revoke_sessions_for_membership(
+    pool: &Pool,
+    npub: &str,
+    organization_id: &Uuid,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE sessions SET revoked_at = $3
+         WHERE npub = $1 AND organization_id = $2 AND revoked_at IS NULL",
+    )
+    .bind(npub)
+    .bind(organization_id)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Moves a session to another organization and swaps its refresh token, so
+/// tokens issued for the old organization stop working.
+pub async fn switch_session_organization(
+    pool: &Pool,
+    id: &Uuid,
+    organization_id: &Uuid,
+    refresh_token_hash: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<Option<Session>, sqlx::Error> {
+    sqlx::query_as::<_, Session>(&format!(
+        "UPDATE sessions
+         SET organization_id = $2, refresh_token_hash = $3, refreshed_at = $4, expires_at = $5
+         WHERE id = $1 AND revoked_at IS NULL AND expires_at > $4
+         RETURNING {}",
+        SESSION_COLUMNS
+    ))
+    .bind(id)
+    .bind(organization_id)
+    .bind(refresh_token_hash)
+    .bind(Utc::now())
+    .bind(expires_at)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Drops sessions that can no longer be used.
+pub async fn delete_expired_sessions(pool: &Pool) -> Result<u64, sqlx::Error> {
+    let result =
+        sqlx::query("DELETE FROM sessions WHERE expires_at <= $1 OR revoked_at IS NOT NULL")
+            .bind(Utc::now())
+            .execute(pool)
+            .await?;
+
+    Ok(result.rows_affected())
+}
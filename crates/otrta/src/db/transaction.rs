@@ -1,7 +1,14 @@
-use bigdecimal::{BigDecimal, ToPrimitive};
-use chrono::{DateTime, Utc};
+use crate::db::{
+    dialect::StringList, exchange_rates::get_latest_exchange_rate, mint::CurrencyUnit, Connection,
+    Pool,
+};
+use crate::exchange_rate::REPORTING_CURRENCY;
+use crate::request_id::current_request_id;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+use std::str::FromStr;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, sqlx::Type, Serialize, Deserialize)]
@@ -46,7 +53,11 @@ impl From<String> for TransactionType {
 pub struct Transaction {
     pub id: String,
     pub created_at: DateTime<Utc>,
-    pub token: String,
+    /// Full token, only present while debug token retention is enabled.
+    pub token: Option<String>,
+    pub token_hash: Option<String>,
+    pub token_mint: Option<String>,
+    pub token_keyset_ids: Option<Vec<String>>,
     pub amount: String,
     pub direction: TransactionDirection,
     pub api_key_id: Option<String>,
@@ -55,6 +66,56 @@ pub struct Transaction {
     pub provider_url: Option<String>,
     pub unit: Option<String>,
     pub model: Option<String>,
+    pub fiat_amount: Option<f64>,
+    pub fiat_currency: Option<String>,
+    /// `X-Request-Id` of the HTTP request that caused the transaction.
+    pub request_id: Option<String>,
+}
+
+#[derive(FromRow)]
+struct TransactionRow {
+    id: Uuid,
+    created_at: DateTime<Utc>,
+    token: Option<String>,
+    token_hash: Option<String>,
+    token_mint: Option<String>,
+    token_keyset_ids: Option<StringList>,
+    amount: String,
+    direction: TransactionDirection,
+    api_key_id: Option<Uuid>,
+    user_id: Option<String>,
+    #[sqlx(rename = "type")]
+    transaction_type: TransactionType,
+    provider_url: Option<String>,
+    unit: Option<String>,
+    model: Option<String>,
+    fiat_amount: Option<f64>,
+    fiat_currency: Option<String>,
+    request_id: Option<String>,
+}
+
+impl From<TransactionRow> for Transaction {
+    fn from(row: TransactionRow) -> Self {
+        Transaction {
+            id: row.id.to_string(),
+            created_at: row.created_at,
+            token: row.token,
+            token_hash: row.token_hash,
+            token_mint: row.token_mint,
+            token_keyset_ids: row.token_keyset_ids.map(Vec::from),
+            amount: row.amount,
+            direction: row.direction,
+            api_key_id: row.api_key_id.map(|id| id.to_string()),
+            user_id: row.user_id,
+            r#type: row.transaction_type,
+            provider_url: row.provider_url,
+            unit: row.unit,
+            model: row.model,
+            fiat_amount: row.fiat_amount,
+            fiat_currency: row.fiat_currency,
+            request_id: row.request_id,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -77,6 +138,10 @@ pub struct ApiKeyStatistics {
     pub total_incoming: i64,
     pub total_outgoing: i64,
     pub total_cost: i64,
+    pub fiat_currency: String,
+    pub total_incoming_fiat: f64,
+    pub total_outgoing_fiat: f64,
+    pub total_cost_fiat: f64,
     pub daily_stats: Vec<DailyStats>,
 }
 
@@ -86,46 +151,192 @@ pub struct DailyStats {
     pub incoming: i64,
     pub outgoing: i64,
     pub cost: i64,
+    pub incoming_fiat: f64,
+    pub outgoing_fiat: f64,
+    pub cost_fiat: f64,
 }
 
+/// What is kept of a Cashu token by default: enough to match it against mint
+/// and wallet records, not enough to spend it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenFingerprint {
+    pub hash: String,
+    pub mint_url: Option<String>,
+    pub keyset_ids: Vec<String>,
+}
+
+impl TokenFingerprint {
+    /// Returns `None` for an empty token (free requests send none).
+    pub fn from_token(token: &str) -> Option<Self> {
+        let token = token.trim();
+        if token.is_empty() {
+            return None;
+        }
+
+        let mut fingerprint = Self {
+            hash: token_hash(token),
+            mint_url: None,
+            keyset_ids: Vec::new(),
+        };
+
+        if let Ok(parsed) = cdk::nuts::Token::from_str(token) {
+            fingerprint.mint_url = parsed.mint_url().ok().map(|url| url.to_string());
+            let mut keyset_ids: Vec<String> = match &parsed {
+                cdk::nuts::Token::TokenV4(v4) => {
+                    v4.token.iter().map(|t| t.keyset_id.to_string()).collect()
+                }
+                cdk::nuts::Token::TokenV3(v3) => v3
+                    .token
+                    .iter()
+                    .flat_map(|t| t.proofs.iter().map(|p| p.keyset_id.to_string()))
+                    .collect(),
+            };
+            keyset_ids.sort();
+            keyset_ids.dedup();
+            fingerprint.keyset_ids = keyset_ids;
+        }
+
+        Some(fingerprint)
+    }
+}
+
+/// Hex SHA-256 of a serialized token, matching what the scrub migration
+/// computed for existing rows.
+pub fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.trim().as_bytes()))
+}
+
+/// Who and what a transaction belongs to. `unit` is the mint unit its
+/// amount is denominated in; unset fields are stored as NULL.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TransactionContext<'a> {
+    pub api_key_id: Option<&'a str>,
+    pub user_id: Option<&'a str>,
+    pub provider_url: Option<&'a str>,
+    pub unit: Option<&'a str>,
+    pub model: Option<&'a str>,
+}
+
+#[tracing::instrument(skip_all, fields(direction = ?direction, amount = %amount))]
 pub async fn add_transaction(
-    pool: &PgPool,
+    pool: &Pool,
     token: &str,
     amount: &str,
     direction: TransactionDirection,
-    api_key_id: Option<&str>,
-    user_id: Option<&str>,
     transaction_type: TransactionType,
-    provider_url: Option<&str>,
-    unit: Option<&str>,
-    model: Option<&str>,
+    ctx: &TransactionContext<'_>,
 ) -> Result<Uuid, sqlx::Error> {
-    let rec = sqlx::query!(
+    let mut tx = pool.begin().await?;
+
+    let fiat = match amount.trim().parse::<i64>() {
+        Ok(value) => {
+            let unit = ctx
+                .unit
+                .and_then(|u| u.parse::<CurrencyUnit>().ok())
+                .unwrap_or(CurrencyUnit::Msat);
+            fiat_value(&mut tx, value, &unit).await
+        }
+        Err(_) => None,
+    };
+
+    let fingerprint = TokenFingerprint::from_token(token);
+
+    let id = sqlx::query_scalar(
         r#"
-        INSERT INTO transactions (id, created_at, token, amount, direction, api_key_id, user_id, type, provider_url, unit, model)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        INSERT INTO transactions (id, created_at, token_hash, token_mint, token_keyset_ids, amount, direction, api_key_id, user_id, type, provider_url, unit, model, fiat_amount, fiat_currency, fiat_rate, request_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
         RETURNING id
         "#,
-        Uuid::new_v4(),
-        Utc::now(),
-        token,
-        amount,
-        direction as TransactionDirection,
-        api_key_id.map(|id| Uuid::parse_str(id).ok()).flatten(),
-        user_id,
-        transaction_type as TransactionType,
-        provider_url,
-        unit,
-        model
     )
-    .fetch_one(pool)
+    .bind(Uuid::new_v4())
+    .bind(Utc::now())
+    .bind(fingerprint.as_ref().map(|f| f.hash.clone()))
+    .bind(fingerprint.as_ref().and_then(|f| f.mint_url.clone()))
+    .bind(fingerprint.as_ref().map(|f| StringList(f.keyset_ids.clone())))
+    .bind(amount)
+    .bind(direction)
+    .bind(ctx.api_key_id.and_then(|id| Uuid::parse_str(id).ok()))
+    .bind(ctx.user_id)
+    .bind(transaction_type)
+    .bind(ctx.provider_url)
+    .bind(ctx.unit)
+    .bind(ctx.model)
+    .bind(fiat.map(|(fiat_amount, _)| fiat_amount))
+    .bind(fiat.map(|_| REPORTING_CURRENCY))
+    .bind(fiat.and_then(|(_, rate)| rate))
+    .bind(current_request_id())
+    .fetch_one(&mut *tx)
     .await?;
 
-    Ok(rec.id)
+    tx.commit().await?;
+
+    Ok(id)
+}
+
+/// Values `amount` (denominated in `unit`) in the reporting currency using the
+/// latest recorded exchange rate, i.e. the rate in effect when the row is
+/// written. Returns the fiat amount and the BTC rate used, or `None` when no
+/// rate has been recorded yet. Run it on the transaction that writes the row
+/// so the rate read is the one in effect at the insert.
+pub(crate) async fn fiat_value(
+    conn: &mut Connection,
+    amount: i64,
+    unit: &CurrencyUnit,
+) -> Option<(f64, Option<f64>)> {
+    let reporting_rate = get_latest_exchange_rate(conn, REPORTING_CURRENCY)
+        .await
+        .ok()
+        .flatten()
+        .map(|record| record.rate);
+
+    if unit.fiat_code() == Some(REPORTING_CURRENCY) {
+        // Fiat mint units are minor units (cents)
+        return Some((amount as f64 / 100.0, reporting_rate));
+    }
+
+    let unit_rate = match unit.fiat_code() {
+        Some(code) => Some(get_latest_exchange_rate(conn, code).await.ok()??.rate),
+        None => None,
+    };
+    let msats = unit.to_msats(amount, unit_rate)?;
+    let rate = reporting_rate?;
+
+    Some((msats as f64 / 100_000_000_000.0 * rate, Some(rate)))
+}
+
+/// Keeps the full `token` on its transaction rows until `expires_at`. Only
+/// used when debug token retention is explicitly enabled.
+pub async fn retain_token_for_debugging(
+    pool: &Pool,
+    token: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE transactions SET token = $1, token_expires_at = $2 WHERE token_hash = $3",
+    )
+    .bind(token.trim())
+    .bind(expires_at)
+    .bind(token_hash(token))
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Drops retained tokens whose debug retention window has passed.
+pub async fn scrub_expired_tokens(pool: &Pool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE transactions SET token = NULL, token_expires_at = NULL WHERE token_expires_at <= $1",
+    )
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
 }
 
 pub async fn get_transactions(
-    pool: &PgPool,
+    pool: &Pool,
     page: Option<i64>,
     page_size: Option<i64>,
 ) -> Result<TransactionListResponse, sqlx::Error> {
@@ -134,37 +345,45 @@ pub async fn get_transactions(
 
     let offset = (page - 1) * page_size;
 
-    let total = sqlx::query_scalar(r#"SELECT COUNT(*) FROM transactions"#)
+    let total: i64 = sqlx::query_scalar(r#"SELECT COUNT(*) FROM transactions"#)
         .fetch_one(pool)
         .await
         .unwrap_or(0);
 
     let total_pages = (total + page_size - 1) / page_size;
 
-    let credits = sqlx::query_as!(
-        Transaction,
+    let credits = sqlx::query_as::<_, TransactionRow>(
         r#"
         SELECT 
             id,
             created_at,
             token,
+            token_hash,
+            token_mint,
+            token_keyset_ids,
             amount,
-            direction as "direction: TransactionDirection",
-            api_key_id::text,
-            user_id::text,
-            type as "type: TransactionType",
+            direction,
+            api_key_id,
+            user_id,
+            type,
             provider_url,
             unit,
-            model
+            model,
+            fiat_amount,
+            fiat_currency,
+            request_id
         FROM transactions
         ORDER BY created_at DESC
         LIMIT $1 OFFSET $2
         "#,
-        page_size,
-        offset
     )
+    .bind(page_size)
+    .bind(offset)
     .fetch_all(pool)
-    .await?;
+    .await?
+    .into_iter()
+    .map(Transaction::from)
+    .collect();
 
     Ok(TransactionListResponse {
         data: credits,
@@ -178,7 +397,7 @@ pub async fn get_transactions(
 }
 
 pub async fn get_transactions_for_user(
-    pool: &PgPool,
+    pool: &Pool,
     organization_id: &str,
     page: Option<i64>,
     page_size: Option<i64>,
@@ -189,48 +408,55 @@ pub async fn get_transactions_for_user(
     let offset = (page - 1) * page_size;
     let org_uuid = Uuid::parse_str(organization_id).map_err(|_| sqlx::Error::RowNotFound)?;
 
-    let total = sqlx::query_scalar!(
+    let total: i64 = sqlx::query_scalar(
         r#"
         SELECT COUNT(*) 
         FROM transactions t
         LEFT JOIN api_keys ak ON t.api_key_id = ak.id
         WHERE ak.organization_id = $1
         "#,
-        org_uuid.to_string()
     )
+    .bind(org_uuid.to_string())
     .fetch_one(pool)
-    .await?
-    .unwrap_or(0);
+    .await?;
 
     let total_pages = (total + page_size - 1) / page_size;
 
-    let transactions = sqlx::query_as!(
-        Transaction,
+    let transactions = sqlx::query_as::<_, TransactionRow>(
         r#"
         SELECT 
             t.id,
             t.created_at,
             t.token,
+            t.token_hash,
+            t.token_mint,
+            t.token_keyset_ids,
             t.amount,
-            t.direction as "direction: TransactionDirection",
-            t.api_key_id::text,
-            t.user_id::text,
-            t.type as "type: TransactionType",
+            t.direction,
+            t.api_key_id,
+            t.user_id,
+            t.type,
             t.provider_url,
             t.unit,
-            t.model
+            t.model,
+            t.fiat_amount,
+            t.fiat_currency,
+            t.request_id
         FROM transactions t
         LEFT JOIN api_keys ak ON t.api_key_id = ak.id
         WHERE ak.organization_id = $1
         ORDER BY t.created_at DESC
         LIMIT $2 OFFSET $3
         "#,
-        org_uuid.to_string(),
-        page_size,
-        offset
     )
+    .bind(org_uuid.to_string())
+    .bind(page_size)
+    .bind(offset)
     .fetch_all(pool)
-    .await?;
+    .await?
+    .into_iter()
+    .map(Transaction::from)
+    .collect();
 
     Ok(TransactionListResponse {
         data: transactions,
@@ -244,97 +470,18 @@ pub async fn get_transactions_for_user(
 }
 
 pub async fn get_api_key_statistics(
-    pool: &PgPool,
+    pool: &Pool,
     api_key_id: &str,
     start_date: Option<DateTime<Utc>>,
     end_date: Option<DateTime<Utc>>,
 ) -> Result<ApiKeyStatistics, sqlx::Error> {
-    let start_date = start_date.unwrap_or_else(|| Utc::now() - chrono::Duration::days(30));
-    let end_date = end_date.unwrap_or_else(Utc::now);
-
     let api_key_uuid = Uuid::parse_str(api_key_id).map_err(|_| sqlx::Error::RowNotFound)?;
 
-    let summary = sqlx::query!(
-        r#"
-        SELECT 
-            COALESCE(SUM(CASE WHEN direction = 'Incoming' THEN amount::bigint ELSE 0 END), 0) as total_incoming,
-            COALESCE(SUM(CASE WHEN direction = 'Outgoing' THEN amount::bigint ELSE 0 END), 0) as total_outgoing
-        FROM transactions 
-        WHERE api_key_id = $1 
-        AND created_at >= $2 
-        AND created_at <= $3
-        "#,
-        api_key_uuid,
-        start_date,
-        end_date
-    )
-    .fetch_one(pool)
-    .await?;
-
-    let daily_stats = sqlx::query!(
-        r#"
-        SELECT 
-            DATE(created_at) as date,
-            COALESCE(SUM(CASE WHEN direction = 'Incoming' THEN amount::bigint ELSE 0 END), 0) as incoming,
-            COALESCE(SUM(CASE WHEN direction = 'Outgoing' THEN amount::bigint ELSE 0 END), 0) as outgoing
-        FROM transactions 
-        WHERE api_key_id = $1 
-        AND created_at >= $2 
-        AND created_at <= $3
-        GROUP BY DATE(created_at)
-        ORDER BY date
-        "#,
-        api_key_uuid,
-        start_date,
-        end_date
-    )
-    .fetch_all(pool)
-    .await?;
-
-    let daily_stats: Vec<DailyStats> = daily_stats
-        .into_iter()
-        .map(|row| {
-            let incoming = row
-                .incoming
-                .unwrap_or(BigDecimal::from(0))
-                .to_i64()
-                .unwrap_or(0);
-            let outgoing = row
-                .outgoing
-                .unwrap_or(BigDecimal::from(0))
-                .to_i64()
-                .unwrap_or(0);
-            DailyStats {
-                date: row.date.unwrap().format("%Y-%m-%d").to_string(),
-                incoming,
-                outgoing,
-                cost: outgoing - incoming,
-            }
-        })
-        .collect();
-
-    let total_incoming = summary
-        .total_incoming
-        .unwrap_or(BigDecimal::from(0))
-        .to_i64()
-        .unwrap_or(0);
-    let total_outgoing = summary
-        .total_outgoing
-        .unwrap_or(BigDecimal::from(0))
-        .to_i64()
-        .unwrap_or(0);
-
-    Ok(ApiKeyStatistics {
-        api_key_id: api_key_id.to_string(),
-        total_incoming,
-        total_outgoing,
-        total_cost: total_outgoing - total_incoming,
-        daily_stats,
-    })
+    ledger_statistics(pool, &api_key_uuid, start_date, end_date).await
 }
 
 pub async fn get_transactions_for_user_by_user_id(
-    pool: &PgPool,
+    pool: &Pool,
     user_id: &str,
     page: Option<i64>,
     page_size: Option<i64>,
@@ -343,42 +490,47 @@ pub async fn get_transactions_for_user_by_user_id(
     let page_size = page_size.unwrap_or(10);
 
     let offset = (page - 1) * page_size;
-    let total = sqlx::query_scalar!(
-        "SELECT COUNT(*) FROM transactions WHERE user_id = $1",
-        user_id
-    )
-    .fetch_one(pool)
-    .await?
-    .unwrap_or(0);
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM transactions WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
 
     let total_pages = (total + page_size - 1) / page_size;
 
-    let transactions = sqlx::query_as!(
-        Transaction,
+    let transactions = sqlx::query_as::<_, TransactionRow>(
         r#"
         SELECT 
             id,
             created_at,
             token,
+            token_hash,
+            token_mint,
+            token_keyset_ids,
             amount,
-            direction as "direction: TransactionDirection",
-            api_key_id::text,
-            user_id::text,
-            type as "type: TransactionType",
+            direction,
+            api_key_id,
+            user_id,
+            type,
             provider_url,
             unit,
-            model
+            model,
+            fiat_amount,
+            fiat_currency,
+            request_id
         FROM transactions
         WHERE user_id = $1
         ORDER BY created_at DESC
         LIMIT $2 OFFSET $3
         "#,
-        user_id,
-        page_size,
-        offset
     )
+    .bind(user_id)
+    .bind(page_size)
+    .bind(offset)
     .fetch_all(pool)
-    .await?;
+    .await?
+    .into_iter()
+    .map(Transaction::from)
+    .collect();
 
     Ok(TransactionListResponse {
         data: transactions,
@@ -392,7 +544,7 @@ pub async fn get_transactions_for_user_by_user_id(
 }
 
 pub async fn get_all_transactions_for_user(
-    pool: &PgPool,
+    pool: &Pool,
     user_id: &str,
     organization_id: &str,
     page: Option<i64>,
@@ -404,50 +556,57 @@ pub async fn get_all_transactions_for_user(
     let offset = (page - 1) * page_size;
     let org_uuid = Uuid::parse_str(organization_id).map_err(|_| sqlx::Error::RowNotFound)?;
 
-    let total = sqlx::query_scalar!(
+    let total: i64 = sqlx::query_scalar(
         r#"
         SELECT COUNT(*) 
         FROM transactions t
         LEFT JOIN api_keys ak ON t.api_key_id = ak.id
         WHERE t.user_id = $1 OR ak.organization_id = $2
         "#,
-        user_id,
-        org_uuid.to_string()
     )
+    .bind(user_id)
+    .bind(org_uuid.to_string())
     .fetch_one(pool)
-    .await?
-    .unwrap_or(0);
+    .await?;
 
     let total_pages = (total + page_size - 1) / page_size;
 
-    let transactions = sqlx::query_as!(
-        Transaction,
+    let transactions = sqlx::query_as::<_, TransactionRow>(
         r#"
         SELECT 
             t.id,
             t.created_at,
             t.token,
+            t.token_hash,
+            t.token_mint,
+            t.token_keyset_ids,
             t.amount,
-            t.direction as "direction: TransactionDirection",
-            t.api_key_id::text,
-            t.user_id::text,
-            t.type as "type: TransactionType",
+            t.direction,
+            t.api_key_id,
+            t.user_id,
+            t.type,
             t.provider_url,
             t.unit,
-            t.model
+            t.model,
+            t.fiat_amount,
+            t.fiat_currency,
+            t.request_id
         FROM transactions t
         LEFT JOIN api_keys ak ON t.api_key_id = ak.id
         WHERE t.user_id = $1 OR ak.organization_id = $2
         ORDER BY t.created_at DESC
         LIMIT $3 OFFSET $4
         "#,
-        user_id,
-        org_uuid.to_string(),
-        page_size,
-        offset
     )
+    .bind(user_id)
+    .bind(org_uuid.to_string())
+    .bind(page_size)
+    .bind(offset)
     .fetch_all(pool)
-    .await?;
+    .await?
+    .into_iter()
+    .map(Transaction::from)
+    .collect();
 
     Ok(TransactionListResponse {
         data: transactions,
@@ -461,101 +620,118 @@ pub async fn get_all_transactions_for_user(
 }
 
 pub async fn get_api_key_statistics_for_user(
-    pool: &PgPool,
+    pool: &Pool,
     api_key_id: &str,
     organization_id: &str,
     start_date: Option<DateTime<Utc>>,
     end_date: Option<DateTime<Utc>>,
 ) -> Result<ApiKeyStatistics, sqlx::Error> {
-    let start_date = start_date.unwrap_or_else(|| Utc::now() - chrono::Duration::days(30));
-    let end_date = end_date.unwrap_or_else(Utc::now);
-
     let api_key_uuid = Uuid::parse_str(api_key_id).map_err(|_| sqlx::Error::RowNotFound)?;
     let org_uuid = Uuid::parse_str(organization_id).map_err(|_| sqlx::Error::RowNotFound)?;
 
-    sqlx::query!(
-        "SELECT id FROM api_keys WHERE id = $1 AND organization_id = $2",
-        api_key_uuid,
-        org_uuid.to_string()
-    )
-    .fetch_one(pool)
-    .await?;
+    sqlx::query("SELECT id FROM api_keys WHERE id = $1 AND organization_id = $2")
+        .bind(api_key_uuid)
+        .bind(org_uuid.to_string())
+        .fetch_one(pool)
+        .await?;
+
+    ledger_statistics(pool, &api_key_uuid, start_date, end_date).await
+}
+
+/// Spend of an API key from its ledger entries. Incoming covers change and
+/// refunds, outgoing covers charges and mint fees; integer totals are in msat
+/// and only include bitcoin-denominated entries.
+async fn ledger_statistics(
+    pool: &Pool,
+    api_key_id: &Uuid,
+    start_date: Option<DateTime<Utc>>,
+    end_date: Option<DateTime<Utc>>,
+) -> Result<ApiKeyStatistics, sqlx::Error> {
+    let start_date = start_date.unwrap_or_else(|| Utc::now() - chrono::Duration::days(30));
+    let end_date = end_date.unwrap_or_else(Utc::now);
 
-    let summary = sqlx::query!(
+    let (total_incoming, total_outgoing, total_incoming_fiat, total_outgoing_fiat) =
+        sqlx::query_as::<_, (i64, i64, f64, f64)>(
         r#"
-        SELECT 
-            COALESCE(SUM(CASE WHEN direction = 'Incoming' THEN amount::bigint ELSE 0 END), 0) as total_incoming,
-            COALESCE(SUM(CASE WHEN direction = 'Outgoing' THEN amount::bigint ELSE 0 END), 0) as total_outgoing
-        FROM transactions 
-        WHERE api_key_id = $1 
-        AND created_at >= $2 
+        SELECT
+            CAST(COALESCE(SUM(amount) FILTER (WHERE entry_type IN ('change', 'refund') AND unit = 'msat'), 0) AS BIGINT),
+            CAST(COALESCE(SUM(amount) FILTER (WHERE entry_type IN ('charge', 'fee') AND unit = 'msat'), 0) AS BIGINT),
+            COALESCE(SUM(fiat_amount) FILTER (WHERE entry_type IN ('change', 'refund')), 0.0),
+            COALESCE(SUM(fiat_amount) FILTER (WHERE entry_type IN ('charge', 'fee')), 0.0)
+        FROM ledger_entries
+        WHERE api_key_id = $1
+        AND created_at >= $2
         AND created_at <= $3
         "#,
-        api_key_uuid,
-        start_date,
-        end_date
     )
+    .bind(api_key_id)
+    .bind(start_date)
+    .bind(end_date)
     .fetch_one(pool)
     .await?;
 
-    let daily_stats = sqlx::query!(
+    let daily_stats = sqlx::query_as::<_, (NaiveDate, i64, i64, f64, f64)>(
         r#"
-        SELECT 
-            DATE(created_at) as date,
-            COALESCE(SUM(CASE WHEN direction = 'Incoming' THEN amount::bigint ELSE 0 END), 0) as incoming,
-            COALESCE(SUM(CASE WHEN direction = 'Outgoing' THEN amount::bigint ELSE 0 END), 0) as outgoing
-        FROM transactions 
-        WHERE api_key_id = $1 
-        AND created_at >= $2 
+        SELECT
+            DATE(created_at),
+            CAST(COALESCE(SUM(amount) FILTER (WHERE entry_type IN ('change', 'refund') AND unit = 'msat'), 0) AS BIGINT),
+            CAST(COALESCE(SUM(amount) FILTER (WHERE entry_type IN ('charge', 'fee') AND unit = 'msat'), 0) AS BIGINT),
+            COALESCE(SUM(fiat_amount) FILTER (WHERE entry_type IN ('change', 'refund')), 0.0),
+            COALESCE(SUM(fiat_amount) FILTER (WHERE entry_type IN ('charge', 'fee')), 0.0)
+        FROM ledger_entries
+        WHERE api_key_id = $1
+        AND created_at >= $2
         AND created_at <= $3
         GROUP BY DATE(created_at)
-        ORDER BY date
+        ORDER BY 1
         "#,
-        api_key_uuid,
-        start_date,
-        end_date
     )
+    .bind(api_key_id)
+    .bind(start_date)
+    .bind(end_date)
     .fetch_all(pool)
     .await?;
 
-    let daily_stats: Vec<DailyStats> = daily_stats
+    let daily_stats = daily_stats
         .into_iter()
-        .map(|row| {
-            let incoming = row
-                .incoming
-                .unwrap_or(BigDecimal::from(0))
-                .to_i64()
-                .unwrap_or(0);
-            let outgoing = row
-                .outgoing
-                .unwrap_or(BigDecimal::from(0))
-                .to_i64()
-                .unwrap_or(0);
-            DailyStats {
-                date: row.date.unwrap().format("%Y-%m-%d").to_string(),
+        .map(
+            |(date, incoming, outgoing, incoming_fiat, outgoing_fiat)| DailyStats {
+                date: date.format("%Y-%m-%d").to_string(),
                 incoming,
                 outgoing,
                 cost: outgoing - incoming,
-            }
-        })
+                incoming_fiat,
+                outgoing_fiat,
+                cost_fiat: outgoing_fiat - incoming_fiat,
+            },
+        )
         .collect();
 
-    let total_incoming = summary
-        .total_incoming
-        .unwrap_or(BigDecimal::from(0))
-        .to_i64()
-        .unwrap_or(0);
-    let total_outgoing = summary
-        .total_outgoing
-        .unwrap_or(BigDecimal::from(0))
-        .to_i64()
-        .unwrap_or(0);
-
     Ok(ApiKeyStatistics {
         api_key_id: api_key_id.to_string(),
         total_incoming,
         total_outgoing,
         total_cost: total_outgoing - total_incoming,
+        fiat_currency: REPORTING_CURRENCY.to_string(),
+        total_incoming_fiat,
+        total_outgoing_fiat,
+        total_cost_fiat: total_outgoing_fiat - total_incoming_fiat,
         daily_stats,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_fingerprint() {
+        assert_eq!(TokenFingerprint::from_token("  "), None);
+
+        let fingerprint = TokenFingerprint::from_token(" not-a-token ").unwrap();
+        assert_eq!(fingerprint.hash, token_hash("not-a-token"));
+        assert_eq!(fingerprint.hash.len(), 64);
+        assert_eq!(fingerprint.mint_url, None);
+        assert!(fingerprint.keyset_ids.is_empty());
+    }
+}
@@ -0,0 +1,179 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use futures_util::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use std::sync::LazyLock;
+
+use crate::db::{dialect::any_uuid, Pool};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UsageExportFilter {
+    pub start_date: Option<NaiveDate>,
+    /// Inclusive.
+    pub end_date: Option<NaiveDate>,
+    pub api_key_id: Option<Uuid>,
+    pub user_id: Option<String>,
+    pub model: Option<String>,
+    pub provider_url: Option<String>,
+}
+
+impl UsageExportFilter {
+    fn start(&self) -> Option<DateTime<Utc>> {
+        self.start_date
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|dt| dt.and_utc())
+    }
+
+    fn end(&self) -> Option<DateTime<Utc>> {
+        self.end_date
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|dt| dt.and_utc() + Duration::days(1))
+    }
+}
+
+/// One proxied request, summed from its ledger entries. Amounts are in the
+/// ledger's canonical unit.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct UsageRow {
+    pub request_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub api_key_id: Option<Uuid>,
+    pub user_id: Option<String>,
+    pub model: Option<String>,
+    pub provider_url: Option<String>,
+    pub unit: String,
+    /// Sent to the provider, excluding mint fees.
+    pub cost: i64,
+    pub fee: i64,
+    /// Change and refunds returned by the provider.
+    pub change: i64,
+    pub net: i64,
+    pub fiat_net: Option<f64>,
+    pub fiat_currency: Option<String>,
+    pub latency_ms: Option<i32>,
+}
+
+impl UsageRow {
+    pub const CSV_HEADER: &'static [&'static str] = &[
+        "request_id",
+        "created_at",
+        "api_key_id",
+        "user_id",
+        "model",
+        "provider_url",
+        "unit",
+        "cost",
+        "fee",
+        "change",
+        "net",
+        "fiat_net",
+        "fiat_currency",
+        "latency_ms",
+    ];
+
+    pub fn csv_fields(&self) -> Vec<String> {
+        fn opt<T: ToString>(value: &Option<T>) -> String {
+            value.as_ref().map(ToString::to_string).unwrap_or_default()
+        }
+
+        vec![
+            self.request_id.to_string(),
+            self.created_at.to_rfc3339(),
+            opt(&self.api_key_id),
+            opt(&self.user_id),
+            opt(&self.model),
+            opt(&self.provider_url),
+            self.unit.clone(),
+            self.cost.to_string(),
+            self.fee.to_string(),
+            self.change.to_string(),
+            self.net.to_string(),
+            opt(&self.fiat_net),
+            opt(&self.fiat_currency),
+            opt(&self.latency_ms),
+        ]
+    }
+}
+
+static USAGE_QUERY: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        "SELECT request_id,
+                MIN(created_at) AS created_at,
+                {} AS api_key_id,
+                MIN(user_id) AS user_id,
+                MIN(model) AS model,
+                MIN(provider_url) AS provider_url,
+                MIN(unit) AS unit,
+                CAST(COALESCE(SUM(amount) FILTER (WHERE entry_type = 'charge'), 0) AS BIGINT) AS cost,
+                CAST(COALESCE(SUM(amount) FILTER (WHERE entry_type = 'fee'), 0) AS BIGINT) AS fee,
+                CAST(COALESCE(SUM(amount) FILTER (WHERE entry_type IN ('change', 'refund')), 0) AS BIGINT) AS change,
+                CAST(COALESCE(SUM(CASE WHEN entry_type IN ('charge', 'fee') THEN amount ELSE -amount END), 0) AS BIGINT) AS net,
+                SUM(CASE WHEN entry_type IN ('charge', 'fee') THEN fiat_amount ELSE -fiat_amount END) AS fiat_net,
+                MIN(fiat_currency) AS fiat_currency,
+                MAX(latency_ms) AS latency_ms
+         FROM ledger_entries
+         WHERE organization_id = $1
+           AND entry_type IN ('charge', 'fee', 'change', 'refund')
+           AND ($2 IS NULL OR created_at >= $2)
+           AND ($3 IS NULL OR created_at < $3)
+           AND ($4 IS NULL OR api_key_id = $4)
+           AND ($5 IS NULL OR user_id = $5)
+           AND ($6 IS NULL OR model = $6)
+           AND ($7 IS NULL OR provider_url = $7)
+         GROUP BY request_id
+         ORDER BY MIN(created_at), request_id",
+        any_uuid("api_key_id")
+    )
+});
+
+/// Streams one row per proxied request of the organization, oldest first,
+/// without buffering the result set.
+pub fn stream_usage<'a>(
+    pool: &'a Pool,
+    organization_id: &'a Uuid,
+    filter: &'a UsageExportFilter,
+) -> BoxStream<'a, Result<UsageRow, sqlx::Error>> {
+    sqlx::query_as::<_, UsageRow>(&USAGE_QUERY)
+        .bind(organization_id)
+        .bind(filter.start())
+        .bind(filter.end())
+        .bind(filter.api_key_id)
+        .bind(filter.user_id.as_deref())
+        .bind(filter.model.as_deref())
+        .bind(filter.provider_url.as_deref())
+        .fetch(pool)
+}
+
+/// Quotes a CSV field when it contains a delimiter, quote or line break.
+pub fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub fn csv_line<S: AsRef<str>>(fields: &[S]) -> String {
+    let mut line = fields
+        .iter()
+        .map(|field| csv_escape(field.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push('\n');
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_line_escaping() {
+        assert_eq!(
+            csv_line(&["plain", "with,comma", "with \"quote\"", "multi\nline"]),
+            "plain,\"with,comma\",\"with \"\"quote\"\"\",\"multi\nline\"\n"
+        );
+    }
+}
@@ -1,8 +1,11 @@
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::FromRow;
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use crate::db::Pool;
+
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
 pub struct UserSearchGroup {
     pub id: Uuid,
     pub user_id: String,
@@ -11,16 +14,15 @@ pub struct UserSearchGroup {
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
-pub async fn create_conversation(pool: &PgPool, user_id: String) -> UserSearchGroup {
-    let group = sqlx::query_as!(
-        UserSearchGroup,
+pub async fn create_conversation(pool: &Pool, user_id: String) -> UserSearchGroup {
+    let group = sqlx::query_as::<_, UserSearchGroup>(
         r#"
         INSERT INTO user_search_groups (user_id, name)
         VALUES ($1, 'new Conversation')
         RETURNING id, user_id, name, created_at, updated_at
         "#,
-        user_id
     )
+    .bind(user_id)
     .fetch_one(pool)
     .await
     .unwrap();
@@ -28,44 +30,41 @@ pub async fn create_conversation(pool: &PgPool, user_id: String) -> UserSearchGr
     group
 }
 
-pub async fn get_search_groups(pool: &PgPool, user_id: String) -> Vec<UserSearchGroup> {
-    sqlx::query_as!(
-        UserSearchGroup,
+pub async fn get_search_groups(pool: &Pool, user_id: String) -> Vec<UserSearchGroup> {
+    sqlx::query_as::<_, UserSearchGroup>(
         r#"
         SELECT id, user_id, name, created_at, updated_at
         FROM user_search_groups
         WHERE user_id = $1
         ORDER BY created_at DESC
         "#,
-        user_id
     )
+    .bind(user_id)
     .fetch_all(pool)
     .await
     .unwrap()
 }
 
 pub async fn get_search_group(
-    pool: &PgPool,
+    pool: &Pool,
     user_id: String,
     group_id: Uuid,
 ) -> Result<UserSearchGroup, sqlx::Error> {
-    sqlx::query_as!(
-        UserSearchGroup,
+    sqlx::query_as::<_, UserSearchGroup>(
         r#"
         SELECT id, user_id, name, created_at, updated_at
         FROM user_search_groups
         WHERE user_id = $1 AND id = $2
         "#,
-        user_id,
-        group_id
     )
+    .bind(user_id)
+    .bind(group_id)
     .fetch_one(pool)
     .await
 }
 
-pub async fn get_search_group_latest(pool: &PgPool, user_id: String) -> Option<UserSearchGroup> {
-    sqlx::query_as!(
-        UserSearchGroup,
+pub async fn get_search_group_latest(pool: &Pool, user_id: String) -> Option<UserSearchGroup> {
+    sqlx::query_as::<_, UserSearchGroup>(
         r#"
         SELECT id, user_id, name, created_at, updated_at
         FROM user_search_groups
@@ -73,42 +72,43 @@ pub async fn get_search_group_latest(pool: &PgPool, user_id: String) -> Option<U
         ORDER BY created_at DESC
         LIMIT 1
         "#,
-        user_id
     )
+    .bind(user_id)
     .fetch_optional(pool)
     .await
     .unwrap()
 }
 
-pub async fn update_search_group_name(pool: &PgPool, user_id: String, group_id: Uuid, name: &str) {
-    sqlx::query!(
+pub async fn update_search_group_name(pool: &Pool, user_id: String, group_id: Uuid, name: &str) {
+    sqlx::query(
         r#"
         UPDATE user_search_groups
-        SET name = $3, updated_at = NOW()
+        SET name = $3, updated_at = $4
         WHERE user_id = $1 AND id = $2
         "#,
-        user_id,
-        group_id,
-        name
     )
+    .bind(user_id)
+    .bind(group_id)
+    .bind(name)
+    .bind(Utc::now())
     .execute(pool)
     .await
     .unwrap();
 }
 
 pub async fn delete_search_group(
-    pool: &PgPool,
+    pool: &Pool,
     user_id: String,
     group_id: Uuid,
 ) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query!(
+    let result = sqlx::query(
         r#"
         DELETE FROM user_search_groups
         WHERE user_id = $1 AND id = $2
         "#,
-        user_id,
-        group_id
     )
+    .bind(user_id)
+    .bind(group_id)
     .execute(pool)
     .await?;
 
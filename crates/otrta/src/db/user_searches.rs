@@ -1,10 +1,11 @@
 use serde::{Deserialize, Serialize};
-use sqlx::{types::Json, PgPool};
+use sqlx::{types::Json, FromRow};
 use uuid::Uuid;
 
 use crate::db::user_search_groups::{
     create_conversation, get_search_group, get_search_group_latest, update_search_group_name,
 };
+use crate::db::Pool;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct UserSearchWithGroupId {
@@ -31,7 +32,7 @@ pub struct SearchSourceMetadata {
     pub description: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
 pub struct UserSearch {
     pub id: Uuid,
     pub user_id: String,
@@ -61,7 +62,7 @@ impl From<SearchResponse> for Search {
 }
 
 pub async fn create_pending_search(
-    db_pool: &PgPool,
+    db_pool: &Pool,
     query: &String,
     user_id: String,
     group_id: Uuid,
@@ -80,20 +81,20 @@ pub async fn create_pending_search(
         sources: None,
     };
 
-    sqlx::query!(
+    sqlx::query(
         r#"
         INSERT INTO user_searches (id, user_id, user_search_group_id, created_at, name, search, status, started_at)
         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
         "#,
-        search_id,
-        user_id,
-        group.id,
-        chrono::Utc::now(),
-        query,
-        sqlx::types::Json(empty_search) as sqlx::types::Json<Search>,
-        "pending",
-        Option::<chrono::NaiveDateTime>::None,
     )
+    .bind(search_id)
+    .bind(user_id)
+    .bind(group.id)
+    .bind(chrono::Utc::now())
+    .bind(query)
+    .bind(sqlx::types::Json(empty_search))
+    .bind("pending")
+    .bind(Option::<chrono::NaiveDateTime>::None)
     .execute(db_pool)
     .await
     .expect("Failed to insert pending search");
@@ -102,91 +103,89 @@ pub async fn create_pending_search(
 }
 
 pub async fn update_search_status(
-    db_pool: &PgPool,
+    db_pool: &Pool,
     search_id: Uuid,
     status: &str,
     started_at: Option<chrono::NaiveDateTime>,
     completed_at: Option<chrono::NaiveDateTime>,
     error_message: Option<&str>,
 ) {
-    sqlx::query!(
+    sqlx::query(
         r#"
         UPDATE user_searches 
         SET status = $1, started_at = $2, completed_at = $3, error_message = $4
         WHERE id = $5
         "#,
-        status,
-        started_at,
-        completed_at,
-        error_message,
-        search_id,
     )
+    .bind(status)
+    .bind(started_at)
+    .bind(completed_at)
+    .bind(error_message)
+    .bind(search_id)
     .execute(db_pool)
     .await
     .expect("Failed to update search status");
 }
 
-pub async fn complete_search(db_pool: &PgPool, search_id: Uuid, search_response: SearchResponse) {
+pub async fn complete_search(db_pool: &Pool, search_id: Uuid, search_response: SearchResponse) {
     let search_data: Search = search_response.into();
 
-    sqlx::query!(
+    sqlx::query(
         r#"
         UPDATE user_searches 
         SET search = $1, status = $2, completed_at = $3
         WHERE id = $4
         "#,
-        sqlx::types::Json(search_data) as sqlx::types::Json<Search>,
-        "completed",
-        chrono::Utc::now().naive_utc(),
-        search_id,
     )
+    .bind(sqlx::types::Json(search_data))
+    .bind("completed")
+    .bind(chrono::Utc::now().naive_utc())
+    .bind(search_id)
     .execute(db_pool)
     .await
     .expect("Failed to complete search");
 }
 
 pub async fn get_search_by_id(
-    db_pool: &PgPool,
+    db_pool: &Pool,
     search_id: Uuid,
     user_id: &str,
 ) -> Option<UserSearch> {
-    sqlx::query_as!(
-        UserSearch,
+    sqlx::query_as::<_, UserSearch>(
         r#"
-        SELECT id, user_id, user_search_group_id, created_at, name, search as "search: Json<Search>",
+        SELECT id, user_id, user_search_group_id, created_at, name, search,
                status, started_at, completed_at, error_message 
                
         FROM user_searches 
         WHERE id = $1 AND user_id = $2
         "#,
-        search_id,
-        user_id,
     )
+    .bind(search_id)
+    .bind(user_id)
     .fetch_optional(db_pool)
     .await
     .expect("Failed to fetch search by id")
 }
 
-pub async fn get_pending_searches(db_pool: &PgPool, user_id: &str) -> Vec<UserSearch> {
-    sqlx::query_as!(
-        UserSearch,
+pub async fn get_pending_searches(db_pool: &Pool, user_id: &str) -> Vec<UserSearch> {
+    sqlx::query_as::<_, UserSearch>(
         r#"
-        SELECT id, user_id, user_search_group_id, created_at, name, search as "search: Json<Search>",
+        SELECT id, user_id, user_search_group_id, created_at, name, search,
                status, started_at, completed_at, error_message
                
         FROM user_searches 
         WHERE user_id = $1 AND status IN ('pending', 'processing')
         ORDER BY created_at DESC
         "#,
-        user_id,
     )
+    .bind(user_id)
     .fetch_all(db_pool)
     .await
     .expect("Failed to fetch pending searches")
 }
 
 pub async fn insert_search(
-    db_pool: &PgPool,
+    db_pool: &Pool,
     query: &String,
     search_response: SearchResponse,
     user_id: String,
@@ -203,20 +202,20 @@ pub async fn insert_search(
     let search_id = Uuid::new_v4();
     let search_data: Search = search_response.into();
 
-    sqlx::query!(
+    sqlx::query(
         r#"
         INSERT INTO user_searches (id, user_id, user_search_group_id, created_at, name, search, status, completed_at)
         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
         "#,
-        search_id,
-        user_id,
-        group.id,
-        chrono::Utc::now(),
-        query,
-        sqlx::types::Json(search_data) as sqlx::types::Json<Search>,
-        "completed",
-        Some(chrono::Utc::now().naive_utc()),
     )
+    .bind(search_id)
+    .bind(user_id)
+    .bind(group.id)
+    .bind(chrono::Utc::now())
+    .bind(query)
+    .bind(sqlx::types::Json(search_data))
+    .bind("completed")
+    .bind(Some(chrono::Utc::now().naive_utc()))
     .execute(db_pool)
     .await
     .expect("Failed to insert search");
@@ -225,28 +224,27 @@ pub async fn insert_search(
 }
 
 pub async fn get_searches(
-    db_pool: &PgPool,
+    db_pool: &Pool,
     group_id: Option<Uuid>,
     user_id: String,
 ) -> UserSearchWithGroupId {
     match group_id {
         Some(id) => {
-            let search = sqlx::query_as!(
-                UserSearch,
+            let search = sqlx::query_as::<_, UserSearch>(
                 r#"
-                    SELECT id, user_id, user_search_group_id, created_at, name, search as "search: Json<Search>",
+                    SELECT id, user_id, user_search_group_id, created_at, name, search,
                            status, started_at, completed_at, error_message
                            
                     FROM user_searches
                     WHERE user_search_group_id = $1 AND user_id = $2
                     ORDER BY created_at DESC
                 "#,
-                id,
-                user_id
             )
-                .fetch_all(db_pool)
-                .await
-                .unwrap();
+            .bind(id)
+            .bind(user_id)
+            .fetch_all(db_pool)
+            .await
+            .unwrap();
             UserSearchWithGroupId {
                 group_id: id,
                 user_search: search,
@@ -259,22 +257,21 @@ pub async fn get_searches(
                 None => create_conversation(db_pool, user_id.clone()).await,
             };
 
-            let search = sqlx::query_as!(
-                UserSearch,
+            let search = sqlx::query_as::<_, UserSearch>(
                 r#"
-                     SELECT id, user_id, user_search_group_id, created_at, name, search as "search: Json<Search>",
+                     SELECT id, user_id, user_search_group_id, created_at, name, search,
                             status, started_at, completed_at, error_message
                             
                      FROM user_searches
                      WHERE user_search_group_id = $1 AND user_id = $2
                      ORDER BY created_at DESC
                  "#,
-                 group.id,
-                 user_id
             )
-                .fetch_all(db_pool)
-                .await
-                .unwrap();
+            .bind(group.id)
+            .bind(user_id)
+            .fetch_all(db_pool)
+            .await
+            .unwrap();
 
             UserSearchWithGroupId {
                 group_id: group.id,
@@ -284,59 +281,58 @@ pub async fn get_searches(
     }
 }
 
-pub async fn delete_search(db_pool: &PgPool, user_id: String, group_id: Uuid, id: Uuid) {
-    sqlx::query!(
+pub async fn delete_search(db_pool: &Pool, user_id: String, group_id: Uuid, id: Uuid) {
+    sqlx::query(
         r#"
         DELETE FROM user_searches
         WHERE id = $1 AND user_search_group_id = $2 AND user_id = $3
         "#,
-        id,
-        group_id,
-        user_id,
     )
+    .bind(id)
+    .bind(group_id)
+    .bind(user_id)
     .execute(db_pool)
     .await
     .unwrap();
 }
 
-pub async fn get_search(db_pool: &PgPool, group_id: Uuid, id: Uuid, user_id: String) -> UserSearch {
-    sqlx::query_as!(
-                UserSearch,
-                r#"
-                    SELECT id, user_id, user_search_group_id, created_at, name, search as "search: Json<Search>",
+pub async fn get_search(db_pool: &Pool, group_id: Uuid, id: Uuid, user_id: String) -> UserSearch {
+    sqlx::query_as::<_, UserSearch>(
+        r#"
+                    SELECT id, user_id, user_search_group_id, created_at, name, search,
                            status, started_at, completed_at, error_message
                            
                     FROM user_searches
                     WHERE user_search_group_id = $1 AND id = $2 AND user_id = $3
                     ORDER BY created_at DESC
                 "#,
-                group_id,
-                id,
-                user_id
-            )
-                .fetch_one(db_pool)
-                .await
-                .unwrap()
+    )
+    .bind(group_id)
+    .bind(id)
+    .bind(user_id)
+    .fetch_one(db_pool)
+    .await
+    .unwrap()
 }
 
 pub async fn retry_search(
-    db_pool: &PgPool,
+    db_pool: &Pool,
     search_response: SearchResponse,
     id: Uuid,
     user_id: String,
     group_id: Uuid,
 ) -> UserSearch {
-    sqlx::query!(
+    sqlx::query(
         r#"
                     UPDATE user_searches
                     SET search = $4
                     WHERE user_search_group_id = $1 AND id = $2 AND user_id = $3
                 "#,
-        group_id,
-        id,
-        user_id,
-        sqlx::types::Json(search_response.into()) as Json<Search>
     )
+    .bind(group_id)
+    .bind(id)
+    .bind(&user_id)
+    .bind(Json::<Search>(search_response.into()))
     .execute(db_pool)
     .await
     .unwrap();
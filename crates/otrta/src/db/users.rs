@@ -1,54 +1,83 @@
-use chrono::Utc;
-use sqlx::PgPool;
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
 
+use crate::db::{Connection, Pool};
 use crate::error::AppError;
 use crate::models::{CreateUserRequest, User};
 
-pub async fn create_user(pool: &PgPool, request: &CreateUserRequest) -> Result<User, AppError> {
+#[derive(FromRow)]
+struct UserRow {
+    npub: String,
+    display_name: Option<String>,
+    email: Option<String>,
+    organization_id: uuid::Uuid,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+    last_login_at: Option<DateTime<Utc>>,
+    is_active: Option<bool>,
+}
+
+impl From<UserRow> for User {
+    fn from(row: UserRow) -> Self {
+        Self {
+            npub: row.npub,
+            display_name: row.display_name,
+            email: row.email,
+            organization_id: row.organization_id,
+            created_at: row.created_at.unwrap_or_else(Utc::now),
+            updated_at: row.updated_at.unwrap_or_else(Utc::now),
+            last_login_at: row.last_login_at,
+            is_active: row.is_active.unwrap_or(true),
+        }
+    }
+}
+
+pub async fn create_user(pool: &Pool, request: &CreateUserRequest) -> Result<User, AppError> {
+    let mut conn = pool
+        .acquire()
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+    insert_user(&mut conn, request).await
+}
+
+/// [`create_user`] on a connection or transaction the caller holds.
+pub async fn insert_user(
+    conn: &mut Connection,
+    request: &CreateUserRequest,
+) -> Result<User, AppError> {
     let now = chrono::Utc::now();
 
-    let row = sqlx::query!(
+    let row = sqlx::query_as::<_, UserRow>(
         r#"
         INSERT INTO users (npub, display_name, email, organization_id, created_at, updated_at, is_active)
         VALUES ($1, $2, $3, $4, $5, $5, true)
         RETURNING npub, display_name, email, organization_id, created_at, updated_at, last_login_at, is_active
         "#,
-        request.npub,
-        request.display_name,
-        request.email,
-        request.organization_id,
-        now
     )
-    .fetch_one(pool)
+    .bind(&request.npub)
+    .bind(&request.display_name)
+    .bind(&request.email)
+    .bind(request.organization_id)
+    .bind(now)
+    .fetch_one(conn)
     .await
     .map_err(|e| {
         tracing::error!("Failed to create user: {}", e);
         AppError::DatabaseError(e.to_string())
     })?;
 
-    let user = User {
-        npub: row.npub,
-        display_name: row.display_name,
-        email: row.email,
-        organization_id: row.organization_id,
-        created_at: row.created_at.unwrap_or_else(Utc::now),
-        updated_at: row.updated_at.unwrap_or_else(Utc::now),
-        last_login_at: row.last_login_at,
-        is_active: row.is_active.unwrap_or(true),
-    };
-
-    Ok(user)
+    Ok(row.into())
 }
 
-pub async fn get_user_by_npub(pool: &PgPool, npub: &str) -> Result<Option<User>, AppError> {
-    let row = sqlx::query!(
+pub async fn get_user_by_npub(pool: &Pool, npub: &str) -> Result<Option<User>, AppError> {
+    let row = sqlx::query_as::<_, UserRow>(
         r#"
         SELECT npub, display_name, email, organization_id, created_at, updated_at, last_login_at, is_active
         FROM users
         WHERE npub = $1 AND is_active = true
         "#,
-        npub
     )
+    .bind(npub)
     .fetch_optional(pool)
     .await
     .map_err(|e| {
@@ -56,32 +85,21 @@ pub async fn get_user_by_npub(pool: &PgPool, npub: &str) -> Result<Option<User>,
         AppError::DatabaseError(e.to_string())
     })?;
 
-    let user = row.map(|r| User {
-        npub: r.npub,
-        display_name: r.display_name,
-        email: r.email,
-        organization_id: r.organization_id,
-        created_at: r.created_at.unwrap_or_else(Utc::now),
-        updated_at: r.updated_at.unwrap_or_else(Utc::now),
-        last_login_at: r.last_login_at,
-        is_active: r.is_active.unwrap_or(true),
-    });
-
-    Ok(user)
+    Ok(row.map(User::from))
 }
 
-pub async fn update_last_login(pool: &PgPool, npub: &str) -> Result<(), AppError> {
+pub async fn update_last_login(pool: &Pool, npub: &str) -> Result<(), AppError> {
     let now = chrono::Utc::now();
 
-    sqlx::query!(
+    sqlx::query(
         r#"
         UPDATE users 
         SET last_login_at = $1, updated_at = $1
         WHERE npub = $2
         "#,
-        now,
-        npub
     )
+    .bind(now)
+    .bind(npub)
     .execute(pool)
     .await
     .map_err(|e| {
@@ -92,15 +110,35 @@ pub async fn update_last_login(pool: &PgPool, npub: &str) -> Result<(), AppError
     Ok(())
 }
 
-pub async fn user_exists(pool: &PgPool, npub: &str) -> Result<bool, AppError> {
-    let count = sqlx::query_scalar!(
+/// Sets the organization the user acts in when a request doesn't name one.
+pub async fn set_default_organization(
+    pool: &Pool,
+    npub: &str,
+    organization_id: &uuid::Uuid,
+) -> Result<(), AppError> {
+    sqlx::query("UPDATE users SET organization_id = $1, updated_at = $2 WHERE npub = $3")
+        .bind(organization_id)
+        .bind(chrono::Utc::now())
+        .bind(npub)
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to set default organization: {}", e);
+            AppError::DatabaseError(e.to_string())
+        })?;
+
+    Ok(())
+}
+
+pub async fn user_exists(pool: &Pool, npub: &str) -> Result<bool, AppError> {
+    let count: i64 = sqlx::query_scalar(
         r#"
         SELECT COUNT(*) as count
         FROM users
         WHERE npub = $1
         "#,
-        npub
     )
+    .bind(npub)
     .fetch_one(pool)
     .await
     .map_err(|e| {
@@ -108,7 +146,7 @@ pub async fn user_exists(pool: &PgPool, npub: &str) -> Result<bool, AppError> {
         AppError::DatabaseError(e.to_string())
     })?;
 
-    Ok(count.unwrap_or(0) > 0)
+    Ok(count > 0)
 }
 
 pub fn validate_npub(npub: &str) -> Result<(), AppError> {
@@ -10,6 +10,7 @@ use std::fmt;
 pub enum AppError {
     NotFound,
     Unauthorized,
+    Forbidden(String),
     InternalServerError,
     ValidationError(String),
     DatabaseError(String),
@@ -21,6 +22,7 @@ impl fmt::Display for AppError {
         match self {
             AppError::NotFound => write!(f, "Resource not found"),
             AppError::Unauthorized => write!(f, "Unauthorized"),
+            AppError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
             AppError::InternalServerError => write!(f, "Internal server error"),
             AppError::ValidationError(msg) => write!(f, "Validation error: {}", msg),
             AppError::DatabaseError(msg) => write!(f, "Database error: {}", msg),
@@ -34,6 +36,7 @@ impl IntoResponse for AppError {
         let (status, error_message) = match &self {
             AppError::NotFound => (StatusCode::NOT_FOUND, "Resource not found"),
             AppError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized"),
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg.as_str()),
             AppError::InternalServerError => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
             }
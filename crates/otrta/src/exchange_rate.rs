@@ -0,0 +1,721 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock};
+use tracing::{info, warn};
+
+use crate::db::{
+    exchange_rates::{get_latest_exchange_rate, insert_exchange_rate},
+    Pool,
+};
+
+pub type ExchangeRateError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Fiat currency that transaction values, statistics and budgets are
+/// reported in.
+pub const REPORTING_CURRENCY: &str = "usd";
+
+/// Longest time a failed refresh is remembered before the providers are
+/// asked again. Shorter cache TTLs shorten it to match.
+const FAILURE_BACKOFF_SECONDS: i64 = 30;
+
+/// A source for the price of one bitcoin in a fiat currency.
+#[async_trait]
+pub trait ExchangeRateProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Price of one BTC in `currency` (lowercase ISO code, e.g. `usd`).
+    async fn fetch_btc_price(&self, currency: &str) -> Result<f64, ExchangeRateError>;
+}
+
+pub struct CoinGeckoProvider {
+    client: reqwest::Client,
+}
+
+pub struct CoinbaseProvider {
+    client: reqwest::Client,
+}
+
+pub struct BinanceProvider {
+    client: reqwest::Client,
+}
+
+/// Fixed rates, either configured inline or loaded from a JSON file such as
+/// `{"usd": 65000.0, "eur": 60000.0}`. Meant for offline and test setups.
+pub struct StaticRateProvider {
+    rates: HashMap<String, f64>,
+}
+
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap_or_default()
+}
+
+impl CoinGeckoProvider {
+    pub fn new() -> Self {
+        Self {
+            client: http_client(),
+        }
+    }
+}
+
+impl Default for CoinGeckoProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ExchangeRateProvider for CoinGeckoProvider {
+    fn name(&self) -> &str {
+        "coingecko"
+    }
+
+    async fn fetch_btc_price(&self, currency: &str) -> Result<f64, ExchangeRateError> {
+        let json: serde_json::Value = self
+            .client
+            .get(format!(
+                "https://api.coingecko.com/api/v3/simple/price?ids=bitcoin&vs_currencies={}",
+                currency
+            ))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        json.get("bitcoin")
+            .and_then(|btc| btc.get(currency))
+            .and_then(|price| price.as_f64())
+            .ok_or_else(|| format!("coingecko returned no BTC/{} price", currency).into())
+    }
+}
+
+impl CoinbaseProvider {
+    pub fn new() -> Self {
+        Self {
+            client: http_client(),
+        }
+    }
+}
+
+impl Default for CoinbaseProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ExchangeRateProvider for CoinbaseProvider {
+    fn name(&self) -> &str {
+        "coinbase"
+    }
+
+    async fn fetch_btc_price(&self, currency: &str) -> Result<f64, ExchangeRateError> {
+        let json: serde_json::Value = self
+            .client
+            .get("https://api.coinbase.com/v2/exchange-rates?currency=BTC")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let price = json
+            .get("data")
+            .and_then(|data| data.get("rates"))
+            .and_then(|rates| rates.get(currency.to_uppercase()))
+            .and_then(|price| price.as_str())
+            .ok_or_else(|| format!("coinbase returned no BTC/{} price", currency))?;
+
+        Ok(price.parse::<f64>()?)
+    }
+}
+
+impl BinanceProvider {
+    pub fn new() -> Self {
+        Self {
+            client: http_client(),
+        }
+    }
+}
+
+impl Default for BinanceProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ExchangeRateProvider for BinanceProvider {
+    fn name(&self) -> &str {
+        "binance"
+    }
+
+    async fn fetch_btc_price(&self, currency: &str) -> Result<f64, ExchangeRateError> {
+        // USD is quoted against USDT on Binance
+        let symbol = match currency {
+            "usd" => "BTCUSDT".to_string(),
+            other => format!("BTC{}", other.to_uppercase()),
+        };
+
+        let json: serde_json::Value = self
+            .client
+            .get(format!(
+                "https://api.binance.com/api/v3/ticker/price?symbol={}",
+                symbol
+            ))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let price = json
+            .get("price")
+            .and_then(|p| p.as_str())
+            .ok_or_else(|| format!("binance returned no {} price", symbol))?;
+
+        Ok(price.parse::<f64>()?)
+    }
+}
+
+impl StaticRateProvider {
+    pub fn new(rates: HashMap<String, f64>) -> Self {
+        Self {
+            rates: rates
+                .into_iter()
+                .map(|(currency, rate)| (currency.to_lowercase(), rate))
+                .collect(),
+        }
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, ExchangeRateError> {
+        let contents = std::fs::read_to_string(path)?;
+        let rates: HashMap<String, f64> = serde_json::from_str(&contents)?;
+        Ok(Self::new(rates))
+    }
+
+    /// Parses `usd=65000,eur=60000`.
+    pub fn from_spec(spec: &str) -> Result<Self, ExchangeRateError> {
+        let mut rates = HashMap::new();
+        for pair in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (currency, rate) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid static rate '{}', expected currency=rate", pair))?;
+            rates.insert(currency.trim().to_string(), rate.trim().parse::<f64>()?);
+        }
+        Ok(Self::new(rates))
+    }
+}
+
+#[async_trait]
+impl ExchangeRateProvider for StaticRateProvider {
+    fn name(&self) -> &str {
+        "static"
+    }
+
+    async fn fetch_btc_price(&self, currency: &str) -> Result<f64, ExchangeRateError> {
+        self.rates
+            .get(currency)
+            .copied()
+            .ok_or_else(|| format!("No static BTC/{} rate configured", currency).into())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExchangeRate {
+    pub currency: String,
+    pub rate: f64,
+    pub source: String,
+    pub fetched_at: DateTime<Utc>,
+    pub age_seconds: i64,
+    pub stale: bool,
+}
+
+/// Names accepted in [`ExchangeRateConfig::providers`].
+pub const PROVIDER_NAMES: &[&str] = &["coingecko", "coinbase", "binance", "static"];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExchangeRateConfig {
+    /// Comma-separated provider names tried in order:
+    /// `coingecko`, `coinbase`, `binance`, `static`.
+    pub providers: String,
+    /// Inline static rates, e.g. `usd=65000,eur=60000`.
+    pub static_rates: Option<String>,
+    /// JSON file with static rates, e.g. `{"usd": 65000}`.
+    pub static_rates_file: Option<String>,
+    pub cache_ttl_seconds: u64,
+    /// Rates older than this are reported as stale.
+    pub max_age_seconds: u64,
+    /// Rates older than this are never used to price payments.
+    pub max_stale_seconds: u64,
+}
+
+impl Default for ExchangeRateConfig {
+    fn default() -> Self {
+        Self {
+            providers: "coingecko,coinbase,binance".to_string(),
+            static_rates: None,
+            static_rates_file: None,
+            cache_ttl_seconds: 300,
+            max_age_seconds: 3600,
+            max_stale_seconds: 86400,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct CachedRate {
+    rate: f64,
+    source: String,
+    fetched_at: DateTime<Utc>,
+}
+
+#[derive(Clone)]
+struct FailedRefresh {
+    error: String,
+    failed_at: DateTime<Utc>,
+}
+
+pub struct ExchangeRateService {
+    providers: Vec<Box<dyn ExchangeRateProvider>>,
+    cache: RwLock<HashMap<String, CachedRate>>,
+    failures: RwLock<HashMap<String, FailedRefresh>>,
+    refreshes: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+    db: Option<Pool>,
+    cache_ttl: chrono::Duration,
+    max_age: chrono::Duration,
+    max_stale: chrono::Duration,
+}
+
+impl ExchangeRateService {
+    pub fn new(
+        providers: Vec<Box<dyn ExchangeRateProvider>>,
+        db: Option<Pool>,
+        cache_ttl_seconds: u64,
+        max_age_seconds: u64,
+        max_stale_seconds: u64,
+    ) -> Self {
+        Self {
+            providers,
+            cache: RwLock::new(HashMap::new()),
+            failures: RwLock::new(HashMap::new()),
+            refreshes: Mutex::new(HashMap::new()),
+            db,
+            cache_ttl: chrono::Duration::seconds(cache_ttl_seconds as i64),
+            max_age: chrono::Duration::seconds(max_age_seconds as i64),
+            max_stale: chrono::Duration::seconds(max_stale_seconds as i64),
+        }
+    }
+
+    pub fn from_config(config: &ExchangeRateConfig, db: Option<Pool>) -> Self {
+        let mut providers: Vec<Box<dyn ExchangeRateProvider>> = Vec::new();
+
+        for name in config.providers.split(',').map(|p| p.trim().to_lowercase()) {
+            match name.as_str() {
+                "coingecko" => providers.push(Box::new(CoinGeckoProvider::new())),
+                "coinbase" => providers.push(Box::new(CoinbaseProvider::new())),
+                "binance" => providers.push(Box::new(BinanceProvider::new())),
+                "static" => {
+                    let provider = match (&config.static_rates_file, &config.static_rates) {
+                        (Some(path), _) => StaticRateProvider::from_file(path),
+                        (None, Some(spec)) => StaticRateProvider::from_spec(spec),
+                        (None, None) => Err("static provider needs static rates".into()),
+                    };
+                    match provider {
+                        Ok(provider) => providers.push(Box::new(provider)),
+                        Err(e) => warn!("Ignoring static exchange rate provider: {}", e),
+                    }
+                }
+                "" => {}
+                other => warn!("Unknown exchange rate provider: {}", other),
+            }
+        }
+
+        info!(
+            "Exchange rate providers: {}",
+            providers
+                .iter()
+                .map(|p| p.name())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        Self::new(
+            providers,
+            db,
+            config.cache_ttl_seconds,
+            config.max_age_seconds,
+            config.max_stale_seconds,
+        )
+    }
+
+    /// Returns the BTC price in `currency`. Fresh cached rates are served
+    /// directly; otherwise providers are queried in order, with concurrent
+    /// callers for the same currency sharing one refresh. If every provider
+    /// fails, the last known rate (cached or from history) is returned with
+    /// `stale` set, and the providers are left alone for a short backoff.
+    /// An error means no rate has ever been seen.
+    pub async fn get_rate(&self, currency: &str) -> Result<ExchangeRate, ExchangeRateError> {
+        let currency = currency.to_lowercase();
+
+        if let Some(rate) = self.fresh_from_cache(&currency).await {
+            return Ok(rate);
+        }
+
+        let refresh = self
+            .refreshes
+            .lock()
+            .await
+            .entry(currency.clone())
+            .or_default()
+            .clone();
+        let _refreshing = refresh.lock().await;
+
+        // Whoever held the lock before us may have refreshed the rate already
+        if let Some(rate) = self.fresh_from_cache(&currency).await {
+            return Ok(rate);
+        }
+
+        let now = Utc::now();
+        let cached = self.cache.read().await.get(&currency).cloned();
+
+        let failed = self.failures.read().await.get(&currency).cloned();
+        if let Some(failed) = failed {
+            if now - failed.failed_at < self.failure_backoff() {
+                return self
+                    .last_known(&currency, cached, failed.error.into())
+                    .await;
+            }
+        }
+
+        match self.fetch_from_providers(&currency).await {
+            Ok(fresh) => {
+                self.failures.write().await.remove(&currency);
+                self.cache
+                    .write()
+                    .await
+                    .insert(currency.clone(), fresh.clone());
+                if let Some(db) = &self.db {
+                    if let Err(e) = insert_exchange_rate(
+                        db,
+                        &currency,
+                        fresh.rate,
+                        &fresh.source,
+                        fresh.fetched_at,
+                    )
+                    .await
+                    {
+                        warn!("Failed to store BTC/{} rate: {}", currency, e);
+                    }
+                }
+                Ok(self.to_exchange_rate(&currency, &fresh, now))
+            }
+            Err(e) => {
+                warn!("All exchange rate providers failed for {}: {}", currency, e);
+                self.failures.write().await.insert(
+                    currency.clone(),
+                    FailedRefresh {
+                        error: e.to_string(),
+                        failed_at: Utc::now(),
+                    },
+                );
+                self.last_known(&currency, cached, e).await
+            }
+        }
+    }
+
+    /// Like [`get_rate`](Self::get_rate), but refuses a last known rate
+    /// older than the stale cutoff: pricing a payment on it could over- or
+    /// underpay by however far the market has moved since.
+    pub async fn get_payment_rate(
+        &self,
+        currency: &str,
+    ) -> Result<ExchangeRate, ExchangeRateError> {
+        let rate = self.get_rate(currency).await?;
+        if rate.age_seconds > self.max_stale.num_seconds() {
+            return Err(format!(
+                "BTC/{} rate from {} is {}s old, past the {}s cutoff",
+                rate.currency,
+                rate.source,
+                rate.age_seconds,
+                self.max_stale.num_seconds()
+            )
+            .into());
+        }
+        Ok(rate)
+    }
+
+    /// Rates currently held in the in-memory cache.
+    pub async fn cached_rates(&self) -> Vec<ExchangeRate> {
+        let now = Utc::now();
+        let mut rates: Vec<ExchangeRate> = self
+            .cache
+            .read()
+            .await
+            .iter()
+            .map(|(currency, cached)| self.to_exchange_rate(currency, cached, now))
+            .collect();
+        rates.sort_by(|a, b| a.currency.cmp(&b.currency));
+        rates
+    }
+
+    async fn fresh_from_cache(&self, currency: &str) -> Option<ExchangeRate> {
+        let now = Utc::now();
+        let cache = self.cache.read().await;
+        let cached = cache.get(currency)?;
+        (now - cached.fetched_at < self.cache_ttl)
+            .then(|| self.to_exchange_rate(currency, cached, now))
+    }
+
+    fn failure_backoff(&self) -> chrono::Duration {
+        self.cache_ttl
+            .min(chrono::Duration::seconds(FAILURE_BACKOFF_SECONDS))
+    }
+
+    /// The last known rate flagged stale, or `error` if there is none.
+    async fn last_known(
+        &self,
+        currency: &str,
+        cached: Option<CachedRate>,
+        error: ExchangeRateError,
+    ) -> Result<ExchangeRate, ExchangeRateError> {
+        let last_known = match cached {
+            Some(cached) => Some(cached),
+            None => self.latest_from_history(currency).await,
+        };
+
+        match last_known {
+            Some(last_known) => {
+                let mut rate = self.to_exchange_rate(currency, &last_known, Utc::now());
+                rate.stale = true;
+                Ok(rate)
+            }
+            None => Err(error),
+        }
+    }
+
+    async fn fetch_from_providers(&self, currency: &str) -> Result<CachedRate, ExchangeRateError> {
+        let mut errors = Vec::new();
+
+        for provider in &self.providers {
+            match provider.fetch_btc_price(currency).await {
+                Ok(rate) if rate.is_finite() && rate > 0.0 => {
+                    return Ok(CachedRate {
+                        rate,
+                        source: provider.name().to_string(),
+                        fetched_at: Utc::now(),
+                    });
+                }
+                Ok(rate) => errors.push(format!("{}: invalid rate {}", provider.name(), rate)),
+                Err(e) => errors.push(format!("{}: {}", provider.name(), e)),
+            }
+        }
+
+        if errors.is_empty() {
+            return Err("No exchange rate providers configured".into());
+        }
+        Err(errors.join("; ").into())
+    }
+
+    async fn latest_from_history(&self, currency: &str) -> Option<CachedRate> {
+        let db = self.db.as_ref()?;
+        let latest = match db.acquire().await {
+            Ok(mut conn) => get_latest_exchange_rate(&mut conn, currency).await,
+            Err(e) => Err(e),
+        };
+        match latest {
+            Ok(record) => record.map(|record| CachedRate {
+                rate: record.rate,
+                source: record.source,
+                fetched_at: record.fetched_at,
+            }),
+            Err(e) => {
+                warn!("Failed to load BTC/{} rate history: {}", currency, e);
+                None
+            }
+        }
+    }
+
+    fn to_exchange_rate(
+        &self,
+        currency: &str,
+        cached: &CachedRate,
+        now: DateTime<Utc>,
+    ) -> ExchangeRate {
+        let age = now - cached.fetched_at;
+        ExchangeRate {
+            currency: currency.to_string(),
+            rate: cached.rate,
+            source: cached.source.clone(),
+            fetched_at: cached.fetched_at,
+            age_seconds: age.num_seconds(),
+            stale: age > self.max_age,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    struct FlakyProvider {
+        fail: Arc<AtomicBool>,
+    }
+
+    struct CountingProvider {
+        fail: bool,
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl ExchangeRateProvider for CountingProvider {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        async fn fetch_btc_price(&self, _currency: &str) -> Result<f64, ExchangeRateError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            if self.fail {
+                Err("unavailable".into())
+            } else {
+                Ok(50_000.0)
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ExchangeRateProvider for FlakyProvider {
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        async fn fetch_btc_price(&self, _currency: &str) -> Result<f64, ExchangeRateError> {
+            if self.fail.load(Ordering::SeqCst) {
+                Err("unavailable".into())
+            } else {
+                Ok(50_000.0)
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_static_provider_spec() {
+        let provider = StaticRateProvider::from_spec("USD=65000, eur=60000").unwrap();
+        assert_eq!(provider.fetch_btc_price("usd").await.unwrap(), 65000.0);
+        assert_eq!(provider.fetch_btc_price("eur").await.unwrap(), 60000.0);
+        assert!(provider.fetch_btc_price("gbp").await.is_err());
+        assert!(StaticRateProvider::from_spec("usd").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_stale_rate_instead_of_faking() {
+        let fail = Arc::new(AtomicBool::new(true));
+        let service = ExchangeRateService::new(
+            vec![Box::new(FlakyProvider { fail: fail.clone() })],
+            None,
+            0,
+            3600,
+            86400,
+        );
+
+        // Nothing known yet: an error, not a made-up number
+        assert!(service.get_rate("usd").await.is_err());
+
+        fail.store(false, Ordering::SeqCst);
+        let rate = service.get_rate("usd").await.unwrap();
+        assert_eq!(rate.rate, 50_000.0);
+        assert_eq!(rate.source, "flaky");
+        assert!(!rate.stale);
+
+        // Providers down again: the last known rate comes back flagged stale
+        fail.store(true, Ordering::SeqCst);
+        let rate = service.get_rate("usd").await.unwrap();
+        assert_eq!(rate.rate, 50_000.0);
+        assert!(rate.stale);
+    }
+
+    #[tokio::test]
+    async fn test_refuses_to_price_payments_past_the_stale_cutoff() {
+        let fail = Arc::new(AtomicBool::new(false));
+        let service = ExchangeRateService::new(
+            vec![Box::new(FlakyProvider { fail: fail.clone() })],
+            None,
+            0,
+            3600,
+            86400,
+        );
+        assert!(service.get_payment_rate("usd").await.is_ok());
+
+        // Age the cached rate past the cutoff, then take the providers down
+        service
+            .cache
+            .write()
+            .await
+            .get_mut("usd")
+            .unwrap()
+            .fetched_at -= chrono::Duration::days(2);
+        fail.store(true, Ordering::SeqCst);
+
+        let rate = service.get_rate("usd").await.unwrap();
+        assert!(rate.stale);
+        assert!(service.get_payment_rate("usd").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_callers_share_one_refresh() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = ExchangeRateService::new(
+            vec![Box::new(CountingProvider {
+                fail: false,
+                calls: calls.clone(),
+            })],
+            None,
+            300,
+            3600,
+            86400,
+        );
+
+        let rates = futures_util::future::join_all((0..8).map(|_| service.get_rate("usd"))).await;
+        assert!(rates.iter().all(|rate| rate.is_ok()));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_backs_off_after_a_failed_refresh() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = ExchangeRateService::new(
+            vec![Box::new(CountingProvider {
+                fail: true,
+                calls: calls.clone(),
+            })],
+            None,
+            300,
+            3600,
+            86400,
+        );
+
+        assert!(service.get_rate("usd").await.is_err());
+        assert!(service.get_rate("usd").await.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Once the backoff has passed the providers are asked again
+        service
+            .failures
+            .write()
+            .await
+            .get_mut("usd")
+            .unwrap()
+            .failed_at -= chrono::Duration::seconds(FAILURE_BACKOFF_SECONDS);
+        assert!(service.get_rate("usd").await.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
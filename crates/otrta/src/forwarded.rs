@@ -0,0 +1,122 @@
+//! `X-Forwarded-*` headers are only believed when the request came from a
+//! reverse proxy listed in `application.trusted_proxies`. Anyone else could
+//! claim any client address, scheme or host with them.
+
+use axum::extract::ConnectInfo;
+use axum::http::{Extensions, HeaderMap};
+use ipnet::IpNet;
+use std::net::{IpAddr, SocketAddr};
+
+/// Addresses and networks of the reverse proxies in front of the server.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TrustedProxies(Vec<IpNet>);
+
+impl TrustedProxies {
+    /// Parses IP addresses and CIDR networks such as `10.0.0.0/8`.
+    pub fn parse<S: AsRef<str>>(entries: &[S]) -> Result<Self, String> {
+        entries
+            .iter()
+            .map(|entry| {
+                let entry = entry.as_ref().trim();
+                entry
+                    .parse::<IpNet>()
+                    .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                    .map_err(|_| format!("{:?} is not an IP address or network", entry))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+
+    /// Whether forwarded headers from `peer` are believed.
+    pub fn trusts(&self, peer: Option<SocketAddr>) -> bool {
+        peer.is_some_and(|peer| {
+            let ip = peer.ip().to_canonical();
+            self.0.iter().any(|net| net.contains(&ip))
+        })
+    }
+}
+
+/// The address the request's connection came from.
+pub fn peer_addr(extensions: &Extensions) -> Option<SocketAddr> {
+    extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| *addr)
+}
+
+/// The client address: the last hop of `X-Forwarded-For`, the one the proxy
+/// added itself, when the peer is a trusted proxy, and the peer otherwise.
+pub fn client_ip(
+    headers: &HeaderMap,
+    peer: Option<SocketAddr>,
+    trusted_proxies: &TrustedProxies,
+) -> Option<IpAddr> {
+    let forwarded = trusted_proxies
+        .trusts(peer)
+        .then(|| {
+            headers
+                .get("x-forwarded-for")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.rsplit(',').next())
+                .and_then(|value| value.trim().parse::<IpAddr>().ok())
+        })
+        .flatten();
+    forwarded.or_else(|| peer.map(|addr| addr.ip().to_canonical()))
+}
+
+/// First value of a forwarded header, i.e. what the client-facing proxy set.
+pub fn first_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trusts_listed_addresses_and_networks() {
+        let proxies = TrustedProxies::parse(&["10.0.0.0/8", "192.168.1.5", "::1"]).unwrap();
+        let peer = |addr: &str| Some(addr.parse::<SocketAddr>().unwrap());
+
+        assert!(proxies.trusts(peer("10.1.2.3:4000")));
+        assert!(proxies.trusts(peer("192.168.1.5:4000")));
+        assert!(proxies.trusts(peer("[::1]:4000")));
+        assert!(proxies.trusts(peer("[::ffff:10.0.0.1]:4000")));
+        assert!(!proxies.trusts(peer("192.168.1.6:4000")));
+        assert!(!proxies.trusts(None));
+        assert!(!TrustedProxies::default().trusts(peer("10.1.2.3:4000")));
+
+        assert!(TrustedProxies::parse(&["10.0.0.0/33"]).is_err());
+        assert!(TrustedProxies::parse(&["proxy.internal"]).is_err());
+    }
+
+    #[test]
+    fn client_ip_takes_the_proxy_hop_only_from_trusted_proxies() {
+        let proxies = TrustedProxies::parse(&["10.0.0.2"]).unwrap();
+        let client = |forwarded_for: &str, peer: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert("x-forwarded-for", forwarded_for.parse().unwrap());
+            let peer = Some(peer.parse::<SocketAddr>().unwrap());
+            client_ip(&headers, peer, &proxies).map(|ip| ip.to_string())
+        };
+        let chain = "1.2.3.4, 203.0.113.7";
+
+        assert_eq!(
+            client(chain, "10.0.0.2:4000").as_deref(),
+            Some("203.0.113.7")
+        );
+        assert_eq!(
+            client(chain, "198.51.100.9:4000").as_deref(),
+            Some("198.51.100.9")
+        );
+        assert_eq!(client_ip(&HeaderMap::new(), None, &proxies), None);
+        assert_eq!(
+            client("not an address", "10.0.0.2:4000").as_deref(),
+            Some("10.0.0.2")
+        );
+    }
+}
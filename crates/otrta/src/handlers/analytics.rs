@@ -0,0 +1,86 @@
+use crate::{
+    db::analytics::{
+        get_spend_analytics, AnalyticsDimension, AnalyticsQuery, SpendGroup, TimeBucket,
+    },
+    exchange_rate::REPORTING_CURRENCY,
+    models::{AppState, UserContext},
+};
+use axum::{
+    extract::{Extension, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::sync::Arc;
+
+#[derive(Debug, Deserialize)]
+pub struct SpendAnalyticsParams {
+    /// Comma-separated dimensions: model, provider, api_key, user.
+    pub group_by: Option<String>,
+    pub bucket: Option<TimeBucket>,
+    pub start_date: Option<DateTime<Utc>>,
+    pub end_date: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct SpendAnalyticsResponse {
+    pub group_by: Vec<AnalyticsDimension>,
+    pub bucket: Option<TimeBucket>,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    pub fiat_currency: String,
+    pub groups: Vec<SpendGroup>,
+}
+
+pub async fn get_spend_analytics_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Query(params): Query<SpendAnalyticsParams>,
+) -> Result<Json<SpendAnalyticsResponse>, (StatusCode, Json<serde_json::Value>)> {
+    let group_by = AnalyticsDimension::parse_list(params.group_by.as_deref().unwrap_or(""))
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": {
+                        "message": e,
+                        "type": "validation_error"
+                    }
+                })),
+            )
+        })?;
+
+    let query = AnalyticsQuery {
+        group_by,
+        bucket: params.bucket,
+        start_date: params
+            .start_date
+            .unwrap_or_else(|| Utc::now() - chrono::Duration::days(30)),
+        end_date: params.end_date.unwrap_or_else(Utc::now),
+    };
+
+    match get_spend_analytics(&state.db, &user_ctx.organization_id, &query).await {
+        Ok(groups) => Ok(Json(SpendAnalyticsResponse {
+            group_by: query.group_by,
+            bucket: query.bucket,
+            start_date: query.start_date,
+            end_date: query.end_date,
+            fiat_currency: REPORTING_CURRENCY.to_string(),
+            groups,
+        })),
+        Err(e) => {
+            eprintln!("Failed to get spend analytics: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": {
+                        "message": "Failed to retrieve spend analytics",
+                        "type": "database_error"
+                    }
+                })),
+            ))
+        }
+    }
+}
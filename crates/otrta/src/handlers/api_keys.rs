@@ -1,4 +1,5 @@
 use crate::{
+    audit::{Audit, AuditEvent},
     handlers::credits::PaginationParams,
     models::{AppState, UserContext},
 };
@@ -82,6 +83,7 @@ pub async fn get_api_key_handler(
 pub async fn create_api_key_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
     Json(request): Json<crate::db::api_keys::CreateApiKeyRequest>,
 ) -> Result<Json<crate::db::api_keys::ApiKey>, (StatusCode, Json<serde_json::Value>)> {
     if request.name.is_empty() {
@@ -97,7 +99,16 @@ pub async fn create_api_key_handler(
     }
 
     match crate::db::api_keys::create_api_key(&state.db, request, &user_ctx).await {
-        Ok(api_key) => Ok(Json(api_key)),
+        Ok(api_key) => {
+            audit
+                .record(
+                    AuditEvent::new("api_key.create", "api_key")
+                        .target(&api_key.id)
+                        .after(&api_key),
+                )
+                .await;
+            Ok(Json(api_key))
+        }
         Err(e) => {
             eprintln!("Error creating API key: {}", e);
             if e.to_string().contains("unique constraint") {
@@ -128,9 +139,18 @@ pub async fn create_api_key_handler(
 pub async fn update_api_key_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
     Path(id): Path<String>,
     Json(request): Json<crate::db::api_keys::UpdateApiKeyRequest>,
 ) -> Result<Json<crate::db::api_keys::ApiKey>, (StatusCode, Json<serde_json::Value>)> {
+    let before = crate::db::api_keys::get_api_key_by_id_for_user(
+        &state.db,
+        &id,
+        &user_ctx.organization_id.to_string(),
+    )
+    .await
+    .ok()
+    .flatten();
     match crate::db::api_keys::update_api_key_for_user(
         &state.db,
         &id,
@@ -139,7 +159,17 @@ pub async fn update_api_key_handler(
     )
     .await
     {
-        Ok(Some(api_key)) => Ok(Json(api_key)),
+        Ok(Some(api_key)) => {
+            audit
+                .record(
+                    AuditEvent::new("api_key.update", "api_key")
+                        .target(&id)
+                        .before(before)
+                        .after(&api_key),
+                )
+                .await;
+            Ok(Json(api_key))
+        }
         Ok(None) => Err((
             StatusCode::NOT_FOUND,
             Json(json!({
@@ -167,8 +197,17 @@ pub async fn update_api_key_handler(
 pub async fn delete_api_key_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
     Path(id): Path<String>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let before = crate::db::api_keys::get_api_key_by_id_for_user(
+        &state.db,
+        &id,
+        &user_ctx.organization_id.to_string(),
+    )
+    .await
+    .ok()
+    .flatten();
     match crate::db::api_keys::delete_api_key_for_user(
         &state.db,
         &id,
@@ -176,9 +215,18 @@ pub async fn delete_api_key_handler(
     )
     .await
     {
-        Ok(true) => Ok(Json(json!({
-            "message": "API key deleted successfully"
-        }))),
+        Ok(true) => {
+            audit
+                .record(
+                    AuditEvent::new("api_key.delete", "api_key")
+                        .target(&id)
+                        .before(before),
+                )
+                .await;
+            Ok(Json(json!({
+                "message": "API key deleted successfully"
+            })))
+        }
         Ok(false) => Err((
             StatusCode::NOT_FOUND,
             Json(json!({
@@ -0,0 +1,41 @@
+use crate::{
+    db::audit_log::{get_audit_log, AuditLogEntry, AuditLogFilter},
+    models::{AppState, UserContext},
+};
+use axum::{
+    extract::{Extension, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Serialize;
+use serde_json::{self, json};
+use std::sync::Arc;
+
+#[derive(Serialize)]
+pub struct AuditLogResponse {
+    pub entries: Vec<AuditLogEntry>,
+}
+
+/// The organization's audit log, newest first, filtered by action, actor,
+/// target and time range.
+pub async fn get_audit_log_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Query(filter): Query<AuditLogFilter>,
+) -> Result<Json<AuditLogResponse>, (StatusCode, Json<serde_json::Value>)> {
+    match get_audit_log(&state.db, &user_ctx.organization_id, &filter).await {
+        Ok(entries) => Ok(Json(AuditLogResponse { entries })),
+        Err(e) => {
+            eprintln!("Failed to get audit log: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": {
+                        "message": "Failed to get audit log",
+                        "type": "database_error"
+                    }
+                })),
+            ))
+        }
+    }
+}
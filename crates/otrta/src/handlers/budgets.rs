@@ -0,0 +1,165 @@
+use crate::{
+    db::budgets::{
+        delete_budget, get_budget_status, get_budgets_for_organization, upsert_budget,
+        BudgetStatus, SetBudgetRequest,
+    },
+    models::{AppState, UserContext},
+};
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Serialize;
+use serde_json::{self, json};
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Serialize)]
+pub struct BudgetListResponse {
+    pub budgets: Vec<BudgetStatus>,
+}
+
+fn validate_budget_request(
+    request: &SetBudgetRequest,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    if !request.amount.is_finite() || request.amount < 0.0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": {
+                    "message": "Budget amount must be a non-negative number",
+                    "type": "validation_error"
+                }
+            })),
+        ));
+    }
+    Ok(())
+}
+
+fn budget_db_error(action: &str, e: sqlx::Error) -> (StatusCode, Json<serde_json::Value>) {
+    eprintln!("Failed to {}: {}", action, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({
+            "error": {
+                "message": format!("Failed to {}", action),
+                "type": "internal_server_error"
+            }
+        })),
+    )
+}
+
+pub async fn get_budgets_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+) -> Result<Json<BudgetListResponse>, (StatusCode, Json<serde_json::Value>)> {
+    let budgets = get_budgets_for_organization(&state.db, &user_ctx.organization_id)
+        .await
+        .map_err(|e| budget_db_error("get budgets", e))?;
+
+    let mut statuses = Vec::with_capacity(budgets.len());
+    for budget in budgets {
+        statuses.push(
+            get_budget_status(&state.db, budget)
+                .await
+                .map_err(|e| budget_db_error("compute budget spend", e))?,
+        );
+    }
+
+    Ok(Json(BudgetListResponse { budgets: statuses }))
+}
+
+pub async fn set_organization_budget_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Json(request): Json<SetBudgetRequest>,
+) -> Result<Json<BudgetStatus>, (StatusCode, Json<serde_json::Value>)> {
+    validate_budget_request(&request)?;
+
+    let budget = upsert_budget(&state.db, &user_ctx.organization_id, None, &request)
+        .await
+        .map_err(|e| budget_db_error("set organization budget", e))?;
+
+    get_budget_status(&state.db, budget)
+        .await
+        .map(Json)
+        .map_err(|e| budget_db_error("compute budget spend", e))
+}
+
+pub async fn set_api_key_budget_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Path(api_key_id): Path<String>,
+    Json(request): Json<SetBudgetRequest>,
+) -> Result<Json<BudgetStatus>, (StatusCode, Json<serde_json::Value>)> {
+    validate_budget_request(&request)?;
+
+    let api_key = match crate::db::api_keys::get_api_key_by_id_for_user(
+        &state.db,
+        &api_key_id,
+        &user_ctx.organization_id.to_string(),
+    )
+    .await
+    {
+        Ok(Some(api_key)) => api_key,
+        Ok(None) | Err(sqlx::Error::RowNotFound) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(json!({
+                    "error": {
+                        "message": "API key not found",
+                        "type": "not_found"
+                    }
+                })),
+            ));
+        }
+        Err(e) => return Err(budget_db_error("get API key", e)),
+    };
+
+    let api_key_uuid = Uuid::parse_str(&api_key.id).map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": {
+                    "message": "Invalid API key ID",
+                    "type": "validation_error"
+                }
+            })),
+        )
+    })?;
+
+    let budget = upsert_budget(
+        &state.db,
+        &user_ctx.organization_id,
+        Some(&api_key_uuid),
+        &request,
+    )
+    .await
+    .map_err(|e| budget_db_error("set API key budget", e))?;
+
+    get_budget_status(&state.db, budget)
+        .await
+        .map(Json)
+        .map_err(|e| budget_db_error("compute budget spend", e))
+}
+
+pub async fn delete_budget_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    match delete_budget(&state.db, &user_ctx.organization_id, &id).await {
+        Ok(true) => Ok(Json(json!({ "success": true }))),
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": {
+                    "message": "Budget not found",
+                    "type": "not_found"
+                }
+            })),
+        )),
+        Err(e) => Err(budget_db_error("delete budget", e)),
+    }
+}
@@ -18,6 +18,7 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use serde_json::{self, json};
 use std::sync::Arc;
@@ -380,17 +381,18 @@ pub async fn save_search_handler(
 
     let search_id = uuid::Uuid::new_v4();
 
-    match sqlx::query!(
+    match sqlx::query(
         r#"
         INSERT INTO user_searches (id, name, search, user_search_group_id, user_id, created_at)
-        VALUES ($1, $2, $3, $4, $5, NOW())
+        VALUES ($1, $2, $3, $4, $5, $6)
         "#,
-        search_id,
-        request.encrypted_query,
-        search_data,
-        group_id,
-        user_id,
     )
+    .bind(search_id)
+    .bind(&request.encrypted_query)
+    .bind(&search_data)
+    .bind(group_id)
+    .bind(user_id)
+    .bind(Utc::now())
     .execute(&state.db)
     .await
     {
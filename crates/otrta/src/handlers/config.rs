@@ -4,6 +4,7 @@ use crate::{
         Pool,
     },
     models::{AppState, ServerConfig},
+    onion::tor_socks_proxy,
 };
 use axum::{extract::State, http::StatusCode, Json};
 use serde_json::json;
@@ -61,13 +62,12 @@ pub async fn tor_health_check(
 
     Ok(Json(json!({
         "tor_proxy_available": tor_available,
-        "proxy_url": std::env::var("TOR_SOCKS_PROXY").unwrap_or_else(|_| "socks5://127.0.0.1:9050".to_string())
+        "proxy_url": tor_socks_proxy()
     })))
 }
 
 async fn validate_tor_availability() -> bool {
-    let tor_proxy_url =
-        std::env::var("TOR_SOCKS_PROXY").unwrap_or_else(|_| "socks5://127.0.0.1:9050".to_string());
+    let tor_proxy_url = tor_socks_proxy();
 
     reqwest::Client::builder()
         .proxy(reqwest::Proxy::all(&tor_proxy_url).unwrap())
@@ -0,0 +1,39 @@
+use crate::{
+    handlers::metrics::has_metrics_token,
+    health::{check_readiness, HealthStatus},
+    models::AppState,
+};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use std::sync::Arc;
+
+/// Liveness probe: answers as long as the process serves requests, without
+/// touching any dependency, so an outage elsewhere never restarts the pod.
+pub async fn healthz_handler() -> Json<serde_json::Value> {
+    Json(json!({
+        "status": HealthStatus::Ok,
+        "version": env!("CARGO_PKG_VERSION"),
+    }))
+}
+
+/// Readiness probe: 200 while ok or degraded, 503 once a critical check fails.
+/// Only names and statuses are shown, unless the request carries the metrics
+/// token.
+pub async fn readyz_handler(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    let report = check_readiness(&state).await;
+    let status = if report.status == HealthStatus::Fail {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+    if has_metrics_token(&state, &headers) {
+        (status, Json(report)).into_response()
+    } else {
+        (status, Json(report.summary())).into_response()
+    }
+}
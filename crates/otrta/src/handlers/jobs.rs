@@ -0,0 +1,214 @@
+use crate::{
+    db::background_jobs::{
+        get_background_job, get_background_jobs, get_job_runs, request_job_run, set_job_interval,
+        set_job_paused, BackgroundJob, BackgroundJobRun,
+    },
+    models::{AppState, UserContext},
+    scheduler::MIN_JOB_INTERVAL,
+};
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::sync::Arc;
+
+#[derive(Serialize)]
+pub struct JobStatus {
+    #[serde(flatten)]
+    pub job: BackgroundJob,
+    /// The override if set, else the job's default.
+    pub effective_interval_seconds: u64,
+}
+
+impl From<BackgroundJob> for JobStatus {
+    fn from(job: BackgroundJob) -> Self {
+        Self {
+            effective_interval_seconds: job.interval().as_secs(),
+            job,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct JobStatusResponse {
+    /// Instance that answered, to compare against each job's `holder`.
+    pub instance_id: String,
+    pub jobs: Vec<JobStatus>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateJobRequest {
+    /// New interval, or null to go back to the job's default.
+    pub interval_seconds: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct JobRunsQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct JobRunsResponse {
+    pub runs: Vec<BackgroundJobRun>,
+}
+
+fn require_admin(user_ctx: &UserContext) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    if !user_ctx.is_admin {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": {
+                    "message": "Only administrators can manage background jobs",
+                    "type": "forbidden"
+                }
+            })),
+        ));
+    }
+    Ok(())
+}
+
+fn job_not_found(name: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!({
+            "error": {
+                "message": format!("Unknown background job: {}", name),
+                "type": "not_found"
+            }
+        })),
+    )
+}
+
+fn job_db_error(action: &str, e: sqlx::Error) -> (StatusCode, Json<serde_json::Value>) {
+    eprintln!("Failed to {}: {}", action, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({
+            "error": {
+                "message": format!("Failed to {}", action),
+                "type": "database_error"
+            }
+        })),
+    )
+}
+
+/// Schedule, leases and last run of every background job, as recorded by
+/// whichever instance ran each job.
+pub async fn get_jobs_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+) -> Result<Json<JobStatusResponse>, (StatusCode, Json<serde_json::Value>)> {
+    require_admin(&user_ctx)?;
+
+    let jobs = get_background_jobs(&state.db)
+        .await
+        .map_err(|e| job_db_error("retrieve background jobs", e))?;
+
+    Ok(Json(JobStatusResponse {
+        instance_id: state.job_leases.instance_id().to_string(),
+        jobs: jobs.into_iter().map(JobStatus::from).collect(),
+    }))
+}
+
+pub async fn get_job_runs_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Path(name): Path<String>,
+    Query(query): Query<JobRunsQuery>,
+) -> Result<Json<JobRunsResponse>, (StatusCode, Json<serde_json::Value>)> {
+    require_admin(&user_ctx)?;
+
+    if get_background_job(&state.db, &name)
+        .await
+        .map_err(|e| job_db_error("retrieve background job", e))?
+        .is_none()
+    {
+        return Err(job_not_found(&name));
+    }
+
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    get_job_runs(&state.db, &name, limit)
+        .await
+        .map(|runs| Json(JobRunsResponse { runs }))
+        .map_err(|e| job_db_error("retrieve background job runs", e))
+}
+
+pub async fn update_job_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Path(name): Path<String>,
+    Json(request): Json<UpdateJobRequest>,
+) -> Result<Json<JobStatus>, (StatusCode, Json<serde_json::Value>)> {
+    require_admin(&user_ctx)?;
+
+    if request
+        .interval_seconds
+        .is_some_and(|seconds| (seconds as i64) < MIN_JOB_INTERVAL.as_secs() as i64)
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": {
+                    "message": format!(
+                        "Job interval must be at least {} seconds",
+                        MIN_JOB_INTERVAL.as_secs()
+                    ),
+                    "type": "validation_error"
+                }
+            })),
+        ));
+    }
+
+    set_job_interval(&state.db, &name, request.interval_seconds)
+        .await
+        .map_err(|e| job_db_error("update background job", e))?
+        .map(|job| Json(job.into()))
+        .ok_or_else(|| job_not_found(&name))
+}
+
+pub async fn pause_job_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Path(name): Path<String>,
+) -> Result<Json<JobStatus>, (StatusCode, Json<serde_json::Value>)> {
+    require_admin(&user_ctx)?;
+
+    set_job_paused(&state.db, &name, true)
+        .await
+        .map_err(|e| job_db_error("pause background job", e))?
+        .map(|job| Json(job.into()))
+        .ok_or_else(|| job_not_found(&name))
+}
+
+pub async fn resume_job_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Path(name): Path<String>,
+) -> Result<Json<JobStatus>, (StatusCode, Json<serde_json::Value>)> {
+    require_admin(&user_ctx)?;
+
+    set_job_paused(&state.db, &name, false)
+        .await
+        .map_err(|e| job_db_error("resume background job", e))?
+        .map(|job| Json(job.into()))
+        .ok_or_else(|| job_not_found(&name))
+}
+
+/// Queues an immediate run. The leading instance picks it up on its next
+/// scheduler poll, within a few seconds, even if the job is paused.
+pub async fn trigger_job_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Path(name): Path<String>,
+) -> Result<(StatusCode, Json<JobStatus>), (StatusCode, Json<serde_json::Value>)> {
+    require_admin(&user_ctx)?;
+
+    request_job_run(&state.db, &name)
+        .await
+        .map_err(|e| job_db_error("trigger background job", e))?
+        .map(|job| (StatusCode::ACCEPTED, Json(job.into())))
+        .ok_or_else(|| job_not_found(&name))
+}
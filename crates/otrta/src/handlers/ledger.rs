@@ -0,0 +1,162 @@
+use crate::{
+    db::ledger::{
+        get_account_balances, get_entries_for_request, record_entry, AccountBalance, BalanceFilter,
+        EntryType, LedgerContext, LedgerEntry,
+    },
+    db::mint::CurrencyUnit,
+    models::{AppState, UserContext},
+    reconciliation::{reconcile, ReconciliationReport},
+};
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Serialize;
+use serde_json::{self, json};
+use std::{str::FromStr, sync::Arc};
+use uuid::Uuid;
+
+#[derive(Serialize)]
+pub struct LedgerBalancesResponse {
+    pub balances: Vec<AccountBalance>,
+}
+
+#[derive(Serialize)]
+pub struct LedgerRequestResponse {
+    pub request_id: Uuid,
+    pub entries: Vec<LedgerEntry>,
+}
+
+pub async fn get_ledger_balances_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Query(filter): Query<BalanceFilter>,
+) -> Result<Json<LedgerBalancesResponse>, (StatusCode, Json<serde_json::Value>)> {
+    match get_account_balances(&state.db, &user_ctx.organization_id, &filter).await {
+        Ok(balances) => Ok(Json(LedgerBalancesResponse { balances })),
+        Err(e) => {
+            eprintln!("Error getting ledger balances: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": {
+                        "message": "Failed to get ledger balances",
+                        "type": "internal_server_error"
+                    }
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn get_ledger_request_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Path(request_id): Path<Uuid>,
+) -> Result<Json<LedgerRequestResponse>, (StatusCode, Json<serde_json::Value>)> {
+    match get_entries_for_request(&state.db, &user_ctx.organization_id, &request_id).await {
+        Ok(entries) if entries.is_empty() => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": {
+                    "message": "No ledger entries for this request",
+                    "type": "not_found"
+                }
+            })),
+        )),
+        Ok(entries) => Ok(Json(LedgerRequestResponse {
+            request_id,
+            entries,
+        })),
+        Err(e) => {
+            eprintln!("Error getting ledger entries: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": {
+                        "message": "Failed to get ledger entries",
+                        "type": "internal_server_error"
+                    }
+                })),
+            ))
+        }
+    }
+}
+
+pub async fn get_ledger_reconciliation_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+) -> Result<Json<ReconciliationReport>, (StatusCode, Json<serde_json::Value>)> {
+    let wallet = match state
+        .multimint_manager
+        .get_or_create_multimint(&user_ctx.organization_id)
+        .await
+    {
+        Ok(wallet) => wallet,
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(
+                    json!({"error": {"message": "Failed to get organization wallet", "type": "wallet_error"}}),
+                ),
+            ))
+        }
+    };
+
+    match reconcile(&state.db, &wallet, &user_ctx.organization_id).await {
+        Ok(report) => Ok(Json(report)),
+        Err(e) => {
+            eprintln!("Error reconciling ledger: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": {
+                        "message": "Failed to reconcile ledger with wallet",
+                        "type": "internal_server_error"
+                    }
+                })),
+            ))
+        }
+    }
+}
+
+/// Books ecash that entered or left the organization wallet outside of a
+/// proxied request (token redeemed or sent by a user). `amount` overrides the
+/// token's face value, e.g. with what was actually received after fees.
+pub(crate) async fn record_token_movement(
+    state: &AppState,
+    user_ctx: &UserContext,
+    entry_type: EntryType,
+    token: &str,
+    amount: Option<u64>,
+) {
+    let Ok(parsed) = cdk::nuts::Token::from_str(token) else {
+        return;
+    };
+    let Some(amount) = amount.or_else(|| parsed.value().ok().map(u64::from)) else {
+        return;
+    };
+    let unit: CurrencyUnit = parsed.unit().unwrap_or(cdk::nuts::CurrencyUnit::Sat).into();
+
+    let ledger = LedgerContext {
+        user_id: Some(&user_ctx.npub),
+        ..LedgerContext::new(user_ctx.organization_id)
+    };
+
+    if let Err(e) = record_entry(
+        &state.db,
+        &ledger,
+        entry_type,
+        amount as i64,
+        &unit.to_string(),
+    )
+    .await
+    {
+        eprintln!(
+            "Failed to record {} ledger entry: {}",
+            entry_type.as_str(),
+            e
+        );
+    }
+}
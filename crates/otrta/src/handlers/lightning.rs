@@ -1,4 +1,7 @@
-use crate::models::{AppState, TopupMintResponse, UserContext};
+use crate::{
+    audit::{Audit, AuditEvent},
+    models::{AppState, TopupMintResponse, UserContext},
+};
 use axum::{
     extract::{Extension, Path, State},
     http::StatusCode,
@@ -78,6 +81,7 @@ pub struct CreateLightningInvoiceResponse {
 pub async fn create_lightning_payment_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
     Json(payload): Json<CreateLightningPaymentRequest>,
 ) -> Result<Json<CreateLightningPaymentResponse>, (StatusCode, Json<serde_json::Value>)> {
     let org_wallet = match state
@@ -189,6 +193,18 @@ pub async fn create_lightning_payment_handler(
                 quote.id,
                 u64::from(quote.amount)
             );
+            audit
+                .record(
+                    AuditEvent::new("lightning.quote_payment", "melt_quote")
+                        .target(&quote.id)
+                        .after(json!({
+                            "invoice": payload.invoice,
+                            "amount": u64::from(quote.amount),
+                            "fee_reserve": u64::from(quote.fee_reserve),
+                            "mint_url": payload.mint_url,
+                        })),
+                )
+                .await;
 
             Ok(Json(CreateLightningPaymentResponse {
                 success: true,
@@ -327,6 +343,7 @@ pub async fn check_lightning_payment_status_with_mint_handler(
 pub async fn complete_lightning_topup_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
     Path(quote_id): Path<String>,
 ) -> Result<Json<TopupMintResponse>, (StatusCode, Json<serde_json::Value>)> {
     let org_wallet = match state
@@ -367,7 +384,7 @@ pub async fn complete_lightning_topup_handler(
     };
 
     for (mint_url, _) in balances {
-        let wallet_key = WalletKey::new(mint_url, unit.clone());
+        let wallet_key = WalletKey::new(mint_url.clone(), unit.clone());
         if let Some(wallet) = org_wallet
             .inner()
             .cdk_wallet()
@@ -377,6 +394,17 @@ pub async fn complete_lightning_topup_handler(
             // Try to execute the melt directly
             match wallet.melt(&quote_id).await {
                 Ok(melt_result) => {
+                    audit
+                        .record(
+                            AuditEvent::new("lightning.pay", "melt_quote")
+                                .target(&quote_id)
+                                .after(json!({
+                                    "amount": u64::from(melt_result.amount),
+                                    "fee_paid": u64::from(melt_result.fee_paid),
+                                    "mint_url": mint_url.to_string(),
+                                })),
+                        )
+                        .await;
                     return Ok(Json(TopupMintResponse {
                         success: true,
                         message: format!(
@@ -569,7 +597,7 @@ pub async fn check_lightning_payment_nwc(
         }
     };
 
-    let status = wallet.check_mint_quote(&quote_id).await.unwrap();
+    let status = wallet.check_mint_quote(quote_id).await.unwrap();
 
     Ok(Json(PaymentStatusResponse {
         quote_id: quote_id.to_string(),
@@ -0,0 +1,42 @@
+use crate::{
+    metrics::{metrics, update_wallet_balances},
+    models::AppState,
+};
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+/// Prometheus scrape endpoint.
+pub async fn metrics_handler(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    if state.metrics_token.is_some() && !has_metrics_token(&state, &headers) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    update_wallet_balances(&state.multimint_manager).await;
+
+    match metrics().render() {
+        Ok(body) => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+            body,
+        )
+            .into_response(),
+        Err(e) => {
+            eprintln!("Failed to render metrics: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Whether the request carries the configured metrics token as a bearer
+/// token. Always false when none is configured.
+pub(crate) fn has_metrics_token(state: &AppState, headers: &HeaderMap) -> bool {
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    state.metrics_token.is_some() && provided == state.metrics_token.as_deref()
+}
@@ -1,4 +1,5 @@
 use crate::{
+    audit::{Audit, AuditEvent},
     db::mint::{
         create_mint_for_organization, create_mint_units, delete_mint_for_organization,
         discover_mint_keysets, get_active_mints_for_organization,
@@ -23,14 +24,16 @@ pub fn select_preferred_keyset(
 ) -> Option<crate::db::mint::KeysetInfo> {
     if let Some(keyset) = keysets
         .iter()
-        .find(|k| k.active && k.unit.to_lowercase() == "msat")
+        .filter(|k| k.active && k.unit.to_lowercase() == "msat")
+        .min_by_key(|k| k.input_fee_ppk)
     {
         return Some(keyset.clone());
     }
 
     if let Some(keyset) = keysets
         .iter()
-        .find(|k| k.active && k.unit.to_lowercase() == "sat")
+        .filter(|k| k.active && k.unit.to_lowercase() == "sat")
+        .min_by_key(|k| k.input_fee_ppk)
     {
         return Some(keyset.clone());
     }
@@ -158,6 +161,7 @@ pub async fn get_mint_handler(
 pub async fn create_mint_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
     Json(request): Json<CreateMintRequest>,
 ) -> Result<Json<Mint>, (StatusCode, Json<serde_json::Value>)> {
     let wallet = match state
@@ -223,7 +227,9 @@ pub async fn create_mint_handler(
 
             if let Some(keyset) = selected_keyset {
                 // Only create mint units for the selected keyset
-                if let Err(e) = create_mint_units(&state.db, mint.id, &[keyset.clone()]).await {
+                if let Err(e) =
+                    create_mint_units(&state.db, mint.id, std::slice::from_ref(&keyset)).await
+                {
                     eprintln!("Failed to create mint units for mint {}: {}", mint.id, e);
                 }
 
@@ -241,6 +247,13 @@ pub async fn create_mint_handler(
                 );
             }
 
+            audit
+                .record(
+                    AuditEvent::new("mint.create", "mint")
+                        .target(mint.id)
+                        .after(&mint),
+                )
+                .await;
             Ok(Json(mint))
         }
         Err(e) => {
@@ -277,11 +290,26 @@ pub async fn create_mint_handler(
 pub async fn update_mint_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
     Path(id): Path<i32>,
     Json(request): Json<UpdateMintRequest>,
 ) -> Result<Json<Mint>, (StatusCode, Json<serde_json::Value>)> {
+    let before = get_mint_by_id_for_organization(&state.db, id, &user_ctx.organization_id)
+        .await
+        .ok()
+        .flatten();
     match update_mint_for_organization(&state.db, id, &user_ctx.organization_id, request).await {
-        Ok(Some(mint)) => Ok(Json(mint)),
+        Ok(Some(mint)) => {
+            audit
+                .record(
+                    AuditEvent::new("mint.update", "mint")
+                        .target(id)
+                        .before(before)
+                        .after(&mint),
+                )
+                .await;
+            Ok(Json(mint))
+        }
         Ok(None) => Err((
             StatusCode::NOT_FOUND,
             Json(json!({
@@ -309,11 +337,23 @@ pub async fn update_mint_handler(
 pub async fn delete_mint_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
     Path(id): Path<i32>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let before = get_mint_by_id_for_organization(&state.db, id, &user_ctx.organization_id)
+        .await
+        .ok()
+        .flatten();
     match delete_mint_for_organization(&state.db, id, &user_ctx.organization_id).await {
         Ok(deleted) => {
             if deleted {
+                audit
+                    .record(
+                        AuditEvent::new("mint.delete", "mint")
+                            .target(id)
+                            .before(before),
+                    )
+                    .await;
                 Ok(Json(json!({
                     "success": true,
                     "message": "Mint deleted successfully"
@@ -348,6 +388,7 @@ pub async fn delete_mint_handler(
 pub async fn set_mint_active_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
     Path(id): Path<i32>,
     Json(request): Json<serde_json::Value>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
@@ -366,6 +407,13 @@ pub async fn set_mint_active_handler(
     {
         Ok(updated) => {
             if updated {
+                audit
+                    .record(
+                        AuditEvent::new("mint.set_active", "mint")
+                            .target(id)
+                            .after(json!({ "is_active": is_active })),
+                    )
+                    .await;
                 Ok(Json(json!({
                     "success": true,
                     "message": format!("Mint {} successfully", if is_active { "activated" } else { "deactivated" })
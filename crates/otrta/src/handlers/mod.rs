@@ -1,25 +1,51 @@
+pub mod analytics;
 pub mod api_keys;
+pub mod audit_log;
+pub mod budgets;
 pub mod chat;
 pub mod config;
 pub mod credits;
+pub mod health;
+pub mod jobs;
+pub mod ledger;
 pub mod lightning;
+pub mod metrics;
 pub mod mints;
 pub mod models;
 pub mod multimint;
+pub mod nip46;
 pub mod nwc;
+pub mod organizations;
 pub mod providers;
+pub mod rates;
+pub mod retention;
+pub mod sessions;
+pub mod usage_export;
 pub mod users;
 pub mod wallet;
 
+pub use analytics::*;
 pub use api_keys::*;
+pub use audit_log::*;
+pub use budgets::*;
 pub use chat::*;
 pub use config::*;
 pub use credits::*;
+pub use health::*;
+pub use jobs::*;
+pub use ledger::*;
 pub use lightning::*;
+pub use metrics::*;
 pub use mints::*;
 pub use models::*;
 pub use multimint::*;
+pub use nip46::*;
 pub use nwc::*;
+pub use organizations::*;
 pub use providers::*;
+pub use rates::*;
+pub use retention::*;
+pub use sessions::*;
+pub use usage_export::*;
 pub use users::*;
 pub use wallet::*;
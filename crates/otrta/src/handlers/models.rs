@@ -4,13 +4,13 @@ use crate::{
         upsert_model,
     },
     db::{
-        model_pricing::ModelPricingComparison, organizations::get_all_organizations,
-        provider::get_active_providers_for_organization,
+        dialect::StringList, model_pricing::ModelPricingComparison,
+        organizations::get_all_organizations, provider::get_active_providers_for_organization,
     },
     models::{AppState, ModelListResponse, ProxyModel, RefreshModelsResponse, UserContext},
     onion::{
         construct_url_with_protocol, create_onion_client, get_onion_error_message,
-        log_onion_timing, start_onion_timing,
+        log_request_timing, start_request_timer,
     },
 };
 use axum::{
@@ -19,6 +19,7 @@ use axum::{
     response::Response,
     Json,
 };
+use chrono::Utc;
 use serde_json::{self, json};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -144,7 +145,7 @@ pub async fn refresh_models_internal(
         Err(e) => return Err(e),
     };
 
-    let start_time = start_onion_timing(&endpoint_url);
+    let start_time = start_request_timer();
     println!("Making request to: {}", endpoint_url);
 
     let proxy_models_response = match client
@@ -154,7 +155,7 @@ pub async fn refresh_models_internal(
         .await
     {
         Ok(response) => {
-            log_onion_timing(start_time, &endpoint_url, "models");
+            log_request_timing(start_time, &endpoint_url, "models");
             println!("Response status: {}", response.status());
             response
         }
@@ -330,7 +331,7 @@ pub async fn refresh_models_background(
 
         for provider in providers.iter() {
             // Get all models for this provider
-            let models = match sqlx::query!(
+            let models = match sqlx::query_as::<_, crate::db::models::ModelRow>(
                 r#"
                 SELECT id, provider_id, name, input_cost, output_cost, min_cash_per_request, 
                        min_cost_per_request, provider, soft_deleted, model_type, 
@@ -342,8 +343,8 @@ pub async fn refresh_models_background(
                 FROM models
                 WHERE provider_id = $1 AND (soft_deleted = false OR soft_deleted IS NULL)
                 "#,
-                provider.id
             )
+            .bind(provider.id)
             .fetch_all(&state.db)
             .await
             {
@@ -360,39 +361,7 @@ pub async fn refresh_models_background(
 
             // Update pricing for each model
             for model_record in models {
-                let model = crate::models::ModelRecord {
-                    id: model_record.id,
-                    provider_id: model_record.provider_id,
-                    name: model_record.name,
-                    input_cost: model_record.input_cost,
-                    output_cost: model_record.output_cost,
-                    min_cash_per_request: model_record.min_cash_per_request,
-                    min_cost_per_request: model_record.min_cost_per_request,
-                    provider: model_record.provider,
-                    soft_deleted: model_record.soft_deleted.unwrap_or(false),
-                    model_type: model_record.model_type,
-                    description: model_record.description,
-                    context_length: model_record.context_length,
-                    is_free: model_record.is_free.unwrap_or(false),
-                    created_at: model_record.created_at,
-                    updated_at: model_record.updated_at,
-                    last_seen_at: model_record.last_seen_at,
-                    modality: model_record.modality,
-                    input_modalities: model_record.input_modalities,
-                    output_modalities: model_record.output_modalities,
-                    tokenizer: model_record.tokenizer,
-                    instruct_type: model_record.instruct_type,
-                    created_timestamp: model_record.created_timestamp,
-                    prompt_cost: model_record.prompt_cost,
-                    completion_cost: model_record.completion_cost,
-                    request_cost: model_record.request_cost,
-                    image_cost: model_record.image_cost,
-                    web_search_cost: model_record.web_search_cost,
-                    internal_reasoning_cost: model_record.internal_reasoning_cost,
-                    max_cost: model_record.max_cost,
-                    max_completion_tokens: model_record.max_completion_tokens,
-                    is_moderated: model_record.is_moderated,
-                };
+                let model = crate::models::ModelRecord::from(model_record);
 
                 if let Err(e) = crate::db::model_pricing::upsert_model_pricing(
                     &state.db,
@@ -483,7 +452,7 @@ async fn refresh_models_for_provider(
         Err(e) => return Err(e),
     };
 
-    let start_time = start_onion_timing(&endpoint_url);
+    let start_time = start_request_timer();
 
     let proxy_models_response = match client
         .get(&endpoint_url)
@@ -492,7 +461,7 @@ async fn refresh_models_for_provider(
         .await
     {
         Ok(response) => {
-            log_onion_timing(start_time, &endpoint_url, "models");
+            log_request_timing(start_time, &endpoint_url, "models");
             response
         }
         Err(e) => {
@@ -525,7 +494,8 @@ async fn refresh_models_for_provider(
     };
 
     // Delete existing models for this provider within the transaction
-    let deleted_count = match sqlx::query!("DELETE FROM models WHERE provider_id = $1", provider.id)
+    let deleted_count = match sqlx::query("DELETE FROM models WHERE provider_id = $1")
+        .bind(provider.id)
         .execute(&mut *transaction)
         .await
     {
@@ -545,7 +515,7 @@ async fn refresh_models_for_provider(
     for proxy_model in &proxy_models_data.data {
         let model_record = proxy_model.to_model_record(provider.id);
 
-        let result = sqlx::query!(
+        let result = sqlx::query(
             r#"
             INSERT INTO models (
                 name, provider_id, input_cost, output_cost, min_cash_per_request, 
@@ -557,38 +527,39 @@ async fn refresh_models_for_provider(
                 is_moderated
             )
             VALUES (
-                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, NOW(), NOW(), NOW(),
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $28, $28, $28,
                 $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27
             )
             "#,
-            model_record.name,
-            model_record.provider_id,
-            model_record.input_cost,
-            model_record.output_cost,
-            model_record.min_cash_per_request,
-            model_record.min_cash_per_request,
-            model_record.provider,
-            model_record.soft_deleted,
-            model_record.model_type,
-            model_record.description,
-            model_record.context_length,
-            model_record.is_free,
-            model_record.modality,
-            model_record.input_modalities.as_deref(),
-            model_record.output_modalities.as_deref(),
-            model_record.tokenizer,
-            model_record.instruct_type,
-            model_record.created_timestamp,
-            model_record.prompt_cost,
-            model_record.completion_cost,
-            model_record.request_cost,
-            model_record.image_cost,
-            model_record.web_search_cost,
-            model_record.internal_reasoning_cost,
-            model_record.max_cost,
-            model_record.max_completion_tokens,
-            model_record.is_moderated,
         )
+        .bind(&model_record.name)
+        .bind(model_record.provider_id)
+        .bind(model_record.input_cost)
+        .bind(model_record.output_cost)
+        .bind(model_record.min_cash_per_request)
+        .bind(model_record.min_cash_per_request)
+        .bind(&model_record.provider)
+        .bind(model_record.soft_deleted)
+        .bind(&model_record.model_type)
+        .bind(&model_record.description)
+        .bind(model_record.context_length)
+        .bind(model_record.is_free)
+        .bind(&model_record.modality)
+        .bind(model_record.input_modalities.clone().map(StringList))
+        .bind(model_record.output_modalities.clone().map(StringList))
+        .bind(&model_record.tokenizer)
+        .bind(&model_record.instruct_type)
+        .bind(model_record.created_timestamp)
+        .bind(model_record.prompt_cost)
+        .bind(model_record.completion_cost)
+        .bind(model_record.request_cost)
+        .bind(model_record.image_cost)
+        .bind(model_record.web_search_cost)
+        .bind(model_record.internal_reasoning_cost)
+        .bind(model_record.max_cost)
+        .bind(model_record.max_completion_tokens)
+        .bind(model_record.is_moderated)
+        .bind(Utc::now())
         .execute(&mut *transaction)
         .await;
 
@@ -635,7 +606,7 @@ async fn refresh_models_for_provider(
         }
     };
 
-    let models = match sqlx::query!(
+    let models = match sqlx::query_as::<_, crate::db::models::ModelRow>(
         r#"
         SELECT id, provider_id, name, input_cost, output_cost, min_cash_per_request, 
                min_cost_per_request, provider, soft_deleted, model_type, 
@@ -647,8 +618,8 @@ async fn refresh_models_for_provider(
         FROM models
         WHERE provider_id = $1 AND (soft_deleted = false OR soft_deleted IS NULL)
         "#,
-        provider.id
     )
+    .bind(provider.id)
     .fetch_all(db)
     .await
     {
@@ -662,39 +633,7 @@ async fn refresh_models_for_provider(
     };
 
     for model_record in models {
-        let model = crate::models::ModelRecord {
-            id: model_record.id,
-            provider_id: model_record.provider_id,
-            name: model_record.name,
-            input_cost: model_record.input_cost,
-            output_cost: model_record.output_cost,
-            min_cash_per_request: model_record.min_cash_per_request,
-            min_cost_per_request: model_record.min_cost_per_request,
-            provider: model_record.provider,
-            soft_deleted: model_record.soft_deleted.unwrap_or(false),
-            model_type: model_record.model_type,
-            description: model_record.description,
-            context_length: model_record.context_length,
-            is_free: model_record.is_free.unwrap_or(false),
-            created_at: model_record.created_at,
-            updated_at: model_record.updated_at,
-            last_seen_at: model_record.last_seen_at,
-            modality: model_record.modality,
-            input_modalities: model_record.input_modalities,
-            output_modalities: model_record.output_modalities,
-            tokenizer: model_record.tokenizer,
-            instruct_type: model_record.instruct_type,
-            created_timestamp: model_record.created_timestamp,
-            prompt_cost: model_record.prompt_cost,
-            completion_cost: model_record.completion_cost,
-            request_cost: model_record.request_cost,
-            image_cost: model_record.image_cost,
-            web_search_cost: model_record.web_search_cost,
-            internal_reasoning_cost: model_record.internal_reasoning_cost,
-            max_cost: model_record.max_cost,
-            max_completion_tokens: model_record.max_completion_tokens,
-            is_moderated: model_record.is_moderated,
-        };
+        let model = crate::models::ModelRecord::from(model_record);
 
         if let Err(e) =
             crate::db::model_pricing::upsert_model_pricing(db, &model, &provider.name).await
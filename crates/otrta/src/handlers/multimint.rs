@@ -1,4 +1,7 @@
 use crate::{
+    audit::{Audit, AuditEvent},
+    db::ledger::EntryType,
+    handlers::ledger::record_token_movement,
     models::{
         AppState, MintBalance, MintUnitBalance, MintWithBalances, MultimintBalanceResponse,
         MultimintSendTokenRequest, MultimintSendTokenResponse, TransferBetweenMintsRequest,
@@ -103,6 +106,7 @@ pub async fn get_multimint_balance_handler(
 pub async fn send_multimint_token_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
     Json(payload): Json<MultimintSendTokenRequest>,
 ) -> Result<Json<MultimintSendTokenResponse>, (StatusCode, Json<serde_json::Value>)> {
     let wallet = match state
@@ -121,18 +125,33 @@ pub async fn send_multimint_token_handler(
         }
     };
 
+    let withdrawal = json!({
+        "amount": payload.amount,
+        "preferred_mint": payload.preferred_mint,
+        "split_across_mints": payload.split_across_mints.unwrap_or(false),
+    });
     let send_options = LocalMultimintSendOptions {
         preferred_mint: payload.preferred_mint,
         split_across_mints: payload.split_across_mints.unwrap_or(false),
         ..Default::default()
     };
 
-    match wallet.send_simple(payload.amount, send_options).await {
-        Ok(token) => Ok(Json(MultimintSendTokenResponse {
-            tokens: token,
-            success: true,
-            message: Some("Token generated successfully".to_string()),
-        })),
+    match wallet
+        .send_simple(payload.amount, send_options)
+        .await
+        .map_err(|e| e.to_string())
+    {
+        Ok(token) => {
+            record_token_movement(&state, &user_ctx, EntryType::Withdrawal, &token, None).await;
+            audit
+                .record(AuditEvent::new("multimint.send", "wallet").after(withdrawal))
+                .await;
+            Ok(Json(MultimintSendTokenResponse {
+                tokens: token,
+                success: true,
+                message: Some("Token generated successfully".to_string()),
+            }))
+        }
         Err(e) => {
             eprintln!("Failed to send multimint token: {:?}", e);
             Err((
@@ -151,6 +170,7 @@ pub async fn send_multimint_token_handler(
 pub async fn transfer_between_mints_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
     Json(payload): Json<TransferBetweenMintsRequest>,
 ) -> Result<Json<TransferBetweenMintsResponse>, (StatusCode, Json<serde_json::Value>)> {
     let wallet = match state
@@ -198,16 +218,28 @@ pub async fn transfer_between_mints_handler(
     match wallet
         .transfer_between_mints(&payload.from_mint, &payload.to_mint, payload.amount)
         .await
+        .map_err(|e| e.to_string())
     {
-        Ok(_) => Ok(Json(TransferBetweenMintsResponse {
-            success: true,
-            message: format!(
-                "Successfully transferred {} msats from {} to {}",
-                payload.amount, payload.from_mint, payload.to_mint
-            ),
-        })),
+        Ok(_) => {
+            audit
+                .record(
+                    AuditEvent::new("multimint.transfer", "wallet").after(json!({
+                        "amount": payload.amount,
+                        "from_mint": payload.from_mint,
+                        "to_mint": payload.to_mint,
+                    })),
+                )
+                .await;
+            Ok(Json(TransferBetweenMintsResponse {
+                success: true,
+                message: format!(
+                    "Successfully transferred {} msats from {} to {}",
+                    payload.amount, payload.from_mint, payload.to_mint
+                ),
+            }))
+        }
         Err(e) => {
-            eprintln!("Failed to transfer between mints: {:?}", e);
+            eprintln!("Failed to transfer between mints: {}", e);
             Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(json!({
@@ -0,0 +1,152 @@
+use crate::{
+    auth::user_context_for_npub,
+    forwarded,
+    handlers::sessions::{start_session, SessionTokenResponse},
+    models::AppState,
+    nip46::{self, Nip46Error, RemoteSigner, MAX_RELAYS, PENDING_CONNECT_TTL, SIGNER_TIMEOUT},
+    nip98::request_url,
+};
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{header::USER_AGENT, HeaderMap, StatusCode, Uri},
+    Json,
+};
+use nostr::{RelayUrl, ToBech32};
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Instant,
+};
+
+#[derive(Serialize)]
+pub struct NostrConnectResponse {
+    /// Handed to the remote signer, e.g. as a QR code.
+    pub uri: String,
+    pub expires_in: u64,
+}
+
+#[derive(Deserialize)]
+pub struct Nip46LoginRequest {
+    /// A `bunker://` URI from the signer or a `nostrconnect://` URI from
+    /// `/api/auth/nip46/nostrconnect`.
+    pub uri: String,
+}
+
+fn nip46_error(e: Nip46Error) -> (StatusCode, Json<serde_json::Value>) {
+    let (status, error_type) = match &e {
+        Nip46Error::InvalidUri(_) => (StatusCode::BAD_REQUEST, "invalid_request"),
+        Nip46Error::Rejected(_) => (StatusCode::UNAUTHORIZED, "unauthorized"),
+        Nip46Error::Timeout { .. } => (StatusCode::GATEWAY_TIMEOUT, "signer_timeout"),
+        Nip46Error::TooManyRequests(_) => (StatusCode::TOO_MANY_REQUESTS, "rate_limited"),
+        Nip46Error::Relay(_) | Nip46Error::InvalidResponse(_) => {
+            (StatusCode::BAD_GATEWAY, "signer_error")
+        }
+    };
+    eprintln!("NIP-46 login failed: {}", e);
+    (
+        status,
+        Json(json!({
+            "error": {
+                "message": e.to_string(),
+                "type": error_type
+            }
+        })),
+    )
+}
+
+/// The caller's address, once it is within its NIP-46 attempt budget.
+fn admit(
+    state: &AppState,
+    headers: &HeaderMap,
+    peer: SocketAddr,
+) -> Result<IpAddr, (StatusCode, Json<serde_json::Value>)> {
+    let client = forwarded::client_ip(headers, Some(peer), &state.runtime_config().trusted_proxies)
+        .unwrap_or(peer.ip());
+    if !state.nip46_attempts.allow(client, Instant::now()) {
+        return Err(nip46_error(Nip46Error::TooManyRequests(
+            "too many remote signer logins, try again in a minute".to_string(),
+        )));
+    }
+    Ok(client)
+}
+
+fn configured_relays(state: &AppState) -> Vec<RelayUrl> {
+    state
+        .runtime_config()
+        .nostr_relays
+        .iter()
+        .filter_map(|relay| RelayUrl::parse(relay).ok())
+        .collect()
+}
+
+/// Hands out a `nostrconnect://` URI on the configured relays for a remote
+/// signer to connect through, then passed to the login.
+pub async fn nostr_connect_handler(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Result<Json<NostrConnectResponse>, (StatusCode, Json<serde_json::Value>)> {
+    let client = admit(&state, &headers, peer)?;
+    let mut relays = configured_relays(&state);
+    relays.truncate(MAX_RELAYS);
+
+    let (signer, uri) = RemoteSigner::nostr_connect(&relays, "otrta", SIGNER_TIMEOUT)
+        .await
+        .map_err(nip46_error)?;
+    state
+        .nostr_connects
+        .insert(signer, client)
+        .map_err(nip46_error)?;
+
+    Ok(Json(NostrConnectResponse {
+        uri,
+        expires_in: PENDING_CONNECT_TTL.as_secs(),
+    }))
+}
+
+/// Starts a session for the user whose remote signer signs a login
+/// challenge. Waits for the signer, which may ask its user for approval.
+pub async fn nip46_login_handler(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    uri: Uri,
+    Json(request): Json<Nip46LoginRequest>,
+) -> Result<Json<SessionTokenResponse>, (StatusCode, Json<serde_json::Value>)> {
+    admit(&state, &headers, peer)?;
+    let mut signer = nip46::connect(
+        &request.uri,
+        &state.nostr_connects,
+        &configured_relays(&state),
+        SIGNER_TIMEOUT,
+    )
+    .await
+    .map_err(nip46_error)?;
+    let trust_forwarded = state.runtime_config().trusted_proxies.trusts(Some(peer));
+    let url = request_url(&headers, &uri, trust_forwarded);
+    let signed = nip46::sign_login_challenge(&mut signer, &url).await;
+    signer.shutdown().await;
+    let npub = signed.map_err(nip46_error)?.to_bech32().unwrap_or_default();
+
+    let user_ctx = user_context_for_npub(&state, &npub, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to create user context for {}: {}", npub, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": {
+                        "message": "Failed to set up user",
+                        "type": "internal_error"
+                    }
+                })),
+            )
+        })?;
+
+    let user_agent = headers
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok());
+    start_session(&state, &user_ctx, user_agent).await.map(Json)
+}
@@ -9,13 +9,15 @@ use uuid::Uuid;
 use std::sync::Arc;
 
 use crate::{
+    audit::{Audit, AuditEvent},
     db::nwc::{
         create_mint_auto_refill_settings, create_nwc_connection, delete_mint_auto_refill_settings,
         delete_nwc_connection, get_active_nwc_connection_for_organization,
         get_mint_auto_refill_settings_by_mint, get_mint_auto_refill_settings_for_organization,
         get_nwc_connection_by_id, get_nwc_connections_for_organization,
         update_mint_auto_refill_settings, update_nwc_connection, CreateMintAutoRefillRequest,
-        CreateNwcConnectionRequest, UpdateMintAutoRefillRequest, UpdateNwcConnectionRequest,
+        CreateNwcConnectionRequest, MintAutoRefillSettings, UpdateMintAutoRefillRequest,
+        UpdateNwcConnectionRequest,
     },
     error::AppError,
     models::{AppState, UserContext},
@@ -61,6 +63,7 @@ pub struct PayInvoiceResponse {
 pub async fn create_nwc_connection_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_context): Extension<UserContext>,
+    audit: Audit,
     Json(request): Json<CreateNwcConnectionRequest>,
 ) -> Result<Json<crate::db::nwc::NwcConnection>, AppError> {
     let nwc_manager = NwcManager::new(state.db.clone());
@@ -69,6 +72,13 @@ pub async fn create_nwc_connection_handler(
 
     let connection =
         create_nwc_connection(&state.db, &user_context.organization_id, request).await?;
+    audit
+        .record(
+            AuditEvent::new("nwc.create", "nwc_connection")
+                .target(connection.id)
+                .after(&connection),
+        )
+        .await;
 
     Ok(Json(connection))
 }
@@ -99,6 +109,7 @@ pub async fn get_nwc_connection_handler(
 pub async fn update_nwc_connection_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_context): Extension<UserContext>,
+    audit: Audit,
     Path(connection_id): Path<Uuid>,
     Json(request): Json<UpdateNwcConnectionRequest>,
 ) -> Result<Json<crate::db::nwc::NwcConnection>, AppError> {
@@ -107,6 +118,8 @@ pub async fn update_nwc_connection_handler(
         nwc_manager.test_connection(connection_uri).await?;
     }
 
+    let before =
+        get_nwc_connection_by_id(&state.db, &connection_id, &user_context.organization_id).await?;
     let connection = update_nwc_connection(
         &state.db,
         &connection_id,
@@ -115,6 +128,14 @@ pub async fn update_nwc_connection_handler(
     )
     .await?
     .ok_or(AppError::NotFound)?;
+    audit
+        .record(
+            AuditEvent::new("nwc.update", "nwc_connection")
+                .target(connection_id)
+                .before(before)
+                .after(&connection),
+        )
+        .await;
 
     Ok(Json(connection))
 }
@@ -122,12 +143,22 @@ pub async fn update_nwc_connection_handler(
 pub async fn delete_nwc_connection_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_context): Extension<UserContext>,
+    audit: Audit,
     Path(connection_id): Path<Uuid>,
 ) -> Result<StatusCode, AppError> {
+    let before =
+        get_nwc_connection_by_id(&state.db, &connection_id, &user_context.organization_id).await?;
     let deleted =
         delete_nwc_connection(&state.db, &connection_id, &user_context.organization_id).await?;
 
     if deleted {
+        audit
+            .record(
+                AuditEvent::new("nwc.delete", "nwc_connection")
+                    .target(connection_id)
+                    .before(before),
+            )
+            .await;
         Ok(StatusCode::NO_CONTENT)
     } else {
         Err(AppError::NotFound)
@@ -149,13 +180,34 @@ pub async fn test_nwc_connection_handler(
     }))
 }
 
+async fn find_auto_refill_settings(
+    state: &AppState,
+    user_context: &UserContext,
+    settings_id: &Uuid,
+) -> Result<Option<MintAutoRefillSettings>, AppError> {
+    let settings =
+        get_mint_auto_refill_settings_for_organization(&state.db, &user_context.organization_id)
+            .await?;
+    Ok(settings
+        .into_iter()
+        .find(|settings| settings.id == *settings_id))
+}
+
 pub async fn create_mint_auto_refill_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_context): Extension<UserContext>,
+    audit: Audit,
     Json(request): Json<CreateMintAutoRefillRequest>,
 ) -> Result<Json<crate::db::nwc::MintAutoRefillSettings>, AppError> {
     let settings =
         create_mint_auto_refill_settings(&state.db, &user_context.organization_id, request).await?;
+    audit
+        .record(
+            AuditEvent::new("auto_refill.create", "auto_refill")
+                .target(settings.id)
+                .after(&settings),
+        )
+        .await;
 
     Ok(Json(settings))
 }
@@ -187,9 +239,11 @@ pub async fn get_mint_auto_refill_by_mint_handler(
 pub async fn update_mint_auto_refill_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_context): Extension<UserContext>,
+    audit: Audit,
     Path(settings_id): Path<Uuid>,
     Json(request): Json<UpdateMintAutoRefillRequest>,
 ) -> Result<Json<crate::db::nwc::MintAutoRefillSettings>, AppError> {
+    let before = find_auto_refill_settings(&state, &user_context, &settings_id).await?;
     let settings = update_mint_auto_refill_settings(
         &state.db,
         &settings_id,
@@ -198,6 +252,14 @@ pub async fn update_mint_auto_refill_handler(
     )
     .await?
     .ok_or(AppError::NotFound)?;
+    audit
+        .record(
+            AuditEvent::new("auto_refill.update", "auto_refill")
+                .target(settings_id)
+                .before(before)
+                .after(&settings),
+        )
+        .await;
 
     Ok(Json(settings))
 }
@@ -205,13 +267,22 @@ pub async fn update_mint_auto_refill_handler(
 pub async fn delete_mint_auto_refill_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_context): Extension<UserContext>,
+    audit: Audit,
     Path(settings_id): Path<Uuid>,
 ) -> Result<StatusCode, AppError> {
+    let before = find_auto_refill_settings(&state, &user_context, &settings_id).await?;
     let deleted =
         delete_mint_auto_refill_settings(&state.db, &settings_id, &user_context.organization_id)
             .await?;
 
     if deleted {
+        audit
+            .record(
+                AuditEvent::new("auto_refill.delete", "auto_refill")
+                    .target(settings_id)
+                    .before(before),
+            )
+            .await;
         Ok(StatusCode::NO_CONTENT)
     } else {
         Err(AppError::NotFound)
@@ -221,6 +292,7 @@ pub async fn delete_mint_auto_refill_handler(
 pub async fn pay_invoice_with_nwc_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_context): Extension<UserContext>,
+    audit: Audit,
     Json(request): Json<PayInvoiceRequest>,
 ) -> Result<Json<PayInvoiceResponse>, AppError> {
     let nwc_manager = NwcManager::new(state.db.clone());
@@ -240,11 +312,24 @@ pub async fn pay_invoice_with_nwc_handler(
         )
         .await
     {
-        Ok(preimage) => Ok(Json(PayInvoiceResponse {
-            success: true,
-            preimage: Some(preimage),
-            error: None,
-        })),
+        Ok(preimage) => {
+            audit
+                .record(
+                    AuditEvent::new("nwc.pay", "melt_quote")
+                        .target(&request.quote_id)
+                        .after(serde_json::json!({
+                            "invoice": request.invoice,
+                            "mint_url": request.mint_url,
+                            "nwc_connection_id": connection.id,
+                        })),
+                )
+                .await;
+            Ok(Json(PayInvoiceResponse {
+                success: true,
+                preimage: Some(preimage),
+                error: None,
+            }))
+        }
         Err(e) => Ok(Json(PayInvoiceResponse {
             success: false,
             preimage: None,
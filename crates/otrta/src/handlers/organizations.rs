@@ -0,0 +1,342 @@
+use crate::{
+    audit::{Audit, AuditEvent},
+    db::{
+        invitations::{
+            create_invitation, get_invitation, get_open_invitations_for_organization,
+            revoke_invitation, Invitation,
+        },
+        members::{
+            delete_member, get_member, get_member_for_update, get_members, lock_owners,
+            upsert_member_role, OrganizationMember,
+        },
+        sessions::revoke_sessions_for_membership,
+        users::validate_npub,
+        Connection, Db,
+    },
+    models::{AppState, UserContext},
+    permissions::Role,
+};
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::sync::Arc;
+use uuid::Uuid;
+
+const DEFAULT_INVITATION_DAYS: i64 = 7;
+const MAX_INVITATION_DAYS: i64 = 30;
+
+#[derive(Deserialize)]
+pub struct CreateInvitationRequest {
+    pub npub: String,
+    pub role: Role,
+    /// Days until the invitation lapses; 7 by default, at most 30.
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateMemberRequest {
+    pub role: Role,
+}
+
+#[derive(Serialize)]
+pub struct MemberListResponse {
+    pub members: Vec<OrganizationMember>,
+}
+
+#[derive(Serialize)]
+pub struct InvitationListResponse {
+    pub invitations: Vec<Invitation>,
+}
+
+type HandlerError = (StatusCode, Json<serde_json::Value>);
+
+fn organization_db_error(action: &str, e: sqlx::Error) -> HandlerError {
+    eprintln!("Failed to {}: {}", action, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({
+            "error": {
+                "message": format!("Failed to {}", action),
+                "type": "database_error"
+            }
+        })),
+    )
+}
+
+fn organization_error(status: StatusCode, error_type: &str, message: &str) -> HandlerError {
+    (
+        status,
+        Json(json!({
+            "error": {
+                "message": message,
+                "type": error_type
+            }
+        })),
+    )
+}
+
+fn require_can_assign(user_ctx: &UserContext, role: Role) -> Result<(), HandlerError> {
+    if user_ctx.role.can_assign(role) {
+        Ok(())
+    } else {
+        Err(organization_error(
+            StatusCode::FORBIDDEN,
+            "forbidden",
+            &format!("The {} role can't manage {}s", user_ctx.role, role),
+        ))
+    }
+}
+
+/// Locks the organization's owners, then the membership, until the
+/// transaction ends, so the last-owner check still holds when the change
+/// commits. Returns the member and the number of owners.
+async fn lock_member(
+    conn: &mut Connection,
+    organization_id: &Uuid,
+    npub: &str,
+) -> Result<(OrganizationMember, usize), HandlerError> {
+    let owners = lock_owners(conn, organization_id)
+        .await
+        .map_err(|e| organization_db_error("count owners", e))?;
+    let member = get_member_for_update(conn, organization_id, npub)
+        .await
+        .map_err(|e| organization_db_error("retrieve member", e))?
+        .ok_or_else(|| {
+            organization_error(StatusCode::NOT_FOUND, "not_found", "Member not found")
+        })?;
+    Ok((member, owners))
+}
+
+/// Refuses to take the owner role from the organization's last owner.
+fn require_other_owner(member: &OrganizationMember, owners: usize) -> Result<(), HandlerError> {
+    if member.role() == Role::Owner && owners <= 1 {
+        return Err(organization_error(
+            StatusCode::CONFLICT,
+            "conflict",
+            "An organization needs at least one owner",
+        ));
+    }
+    Ok(())
+}
+
+/// Removes the membership locked by [`lock_member`], then ends the sessions
+/// that act in it.
+async fn end_membership(
+    state: &AppState,
+    mut tx: sqlx::Transaction<'_, Db>,
+    member: &OrganizationMember,
+) -> Result<(), HandlerError> {
+    delete_member(&mut tx, &member.organization_id, &member.npub)
+        .await
+        .map_err(|e| organization_db_error("remove member", e))?;
+    tx.commit()
+        .await
+        .map_err(|e| organization_db_error("remove member", e))?;
+    revoke_sessions_for_membership(&state.db, &member.npub, &member.organization_id)
+        .await
+        .map_err(|e| organization_db_error("revoke sessions", e))?;
+    Ok(())
+}
+
+pub async fn get_members_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+) -> Result<Json<MemberListResponse>, HandlerError> {
+    let members = get_members(&state.db, &user_ctx.organization_id)
+        .await
+        .map_err(|e| organization_db_error("retrieve members", e))?;
+    Ok(Json(MemberListResponse { members }))
+}
+
+pub async fn update_member_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
+    Path(npub): Path<String>,
+    Json(request): Json<UpdateMemberRequest>,
+) -> Result<Json<OrganizationMember>, HandlerError> {
+    let mut tx = state
+        .db
+        .begin()
+        .await
+        .map_err(|e| organization_db_error("update member", e))?;
+    let (member, owners) = lock_member(&mut tx, &user_ctx.organization_id, &npub).await?;
+    require_can_assign(&user_ctx, member.role())?;
+    require_can_assign(&user_ctx, request.role)?;
+    if request.role != Role::Owner {
+        require_other_owner(&member, owners)?;
+    }
+
+    let updated = upsert_member_role(&mut tx, &user_ctx.organization_id, &npub, request.role)
+        .await
+        .map_err(|e| organization_db_error("update member", e))?;
+    tx.commit()
+        .await
+        .map_err(|e| organization_db_error("update member", e))?;
+    audit
+        .record(
+            AuditEvent::new("member.update", "member")
+                .target(&npub)
+                .before(&member)
+                .after(&updated),
+        )
+        .await;
+    Ok(Json(updated))
+}
+
+pub async fn remove_member_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
+    Path(npub): Path<String>,
+) -> Result<StatusCode, HandlerError> {
+    let mut tx = state
+        .db
+        .begin()
+        .await
+        .map_err(|e| organization_db_error("remove member", e))?;
+    let (member, owners) = lock_member(&mut tx, &user_ctx.organization_id, &npub).await?;
+    require_can_assign(&user_ctx, member.role())?;
+    require_other_owner(&member, owners)?;
+
+    end_membership(&state, tx, &member).await?;
+    audit
+        .record(
+            AuditEvent::new("member.remove", "member")
+                .target(&npub)
+                .before(&member),
+        )
+        .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Leaves the organization the request acts in. The next login falls back to
+/// another of the user's organizations.
+pub async fn leave_organization_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
+) -> Result<StatusCode, HandlerError> {
+    let mut tx = state
+        .db
+        .begin()
+        .await
+        .map_err(|e| organization_db_error("leave organization", e))?;
+    let (member, owners) = lock_member(&mut tx, &user_ctx.organization_id, &user_ctx.npub).await?;
+    require_other_owner(&member, owners)?;
+
+    end_membership(&state, tx, &member).await?;
+    audit
+        .record(
+            AuditEvent::new("member.leave", "member")
+                .target(&member.npub)
+                .before(&member),
+        )
+        .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_invitations_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+) -> Result<Json<InvitationListResponse>, HandlerError> {
+    let invitations = get_open_invitations_for_organization(&state.db, &user_ctx.organization_id)
+        .await
+        .map_err(|e| organization_db_error("retrieve invitations", e))?;
+    Ok(Json(InvitationListResponse { invitations }))
+}
+
+/// Invites an npub, who joins with the given role on their next login.
+pub async fn create_invitation_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
+    Json(request): Json<CreateInvitationRequest>,
+) -> Result<Json<Invitation>, HandlerError> {
+    let npub = request.npub.trim();
+    validate_npub(npub).map_err(|e| {
+        organization_error(StatusCode::BAD_REQUEST, "invalid_request", &e.to_string())
+    })?;
+    require_can_assign(&user_ctx, request.role)?;
+
+    let days = request.expires_in_days.unwrap_or(DEFAULT_INVITATION_DAYS);
+    if !(1..=MAX_INVITATION_DAYS).contains(&days) {
+        return Err(organization_error(
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+            &format!(
+                "expires_in_days must be between 1 and {}",
+                MAX_INVITATION_DAYS
+            ),
+        ));
+    }
+
+    if get_member(&state.db, &user_ctx.organization_id, npub)
+        .await
+        .map_err(|e| organization_db_error("retrieve member", e))?
+        .is_some()
+    {
+        return Err(organization_error(
+            StatusCode::CONFLICT,
+            "conflict",
+            "Already a member of this organization",
+        ));
+    }
+
+    let invitation = create_invitation(
+        &state.db,
+        &user_ctx.organization_id,
+        npub,
+        request.role,
+        &user_ctx.npub,
+        Utc::now() + Duration::days(days),
+    )
+    .await
+    .map_err(|e| organization_db_error("create invitation", e))?;
+    audit
+        .record(
+            AuditEvent::new("invitation.create", "invitation")
+                .target(invitation.id)
+                .after(&invitation),
+        )
+        .await;
+    Ok(Json(invitation))
+}
+
+/// Revokes an open invitation. Like creating one, this needs a role that
+/// may assign the invitation's role.
+pub async fn revoke_invitation_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, HandlerError> {
+    let not_found =
+        || organization_error(StatusCode::NOT_FOUND, "not_found", "Invitation not found");
+    let invitation = get_invitation(&state.db, &id, &user_ctx.organization_id)
+        .await
+        .map_err(|e| organization_db_error("retrieve invitation", e))?
+        .ok_or_else(not_found)?;
+    require_can_assign(&user_ctx, invitation.role())?;
+
+    if revoke_invitation(&state.db, &id, &user_ctx.organization_id)
+        .await
+        .map_err(|e| organization_db_error("revoke invitation", e))?
+    {
+        audit
+            .record(
+                AuditEvent::new("invitation.revoke", "invitation")
+                    .target(id)
+                    .before(&invitation),
+            )
+            .await;
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(not_found())
+    }
+}
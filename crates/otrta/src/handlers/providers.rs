@@ -1,4 +1,5 @@
 use crate::{
+    audit::{Audit, AuditEvent},
     db::{
         mint::{
             create_mint_for_organization, create_mint_units, discover_mint_keysets,
@@ -18,6 +19,7 @@ use crate::{
     },
     handlers::{models::refresh_models_internal, select_preferred_keyset},
     models::{AppState, UserContext},
+    onion::tor_socks_proxy,
 };
 use axum::{
     extract::{Extension, Path, State},
@@ -51,8 +53,7 @@ fn is_valid_provider_url(url: &str, use_onion: bool) -> bool {
 }
 
 async fn validate_tor_availability() -> bool {
-    let tor_proxy_url =
-        std::env::var("TOR_SOCKS_PROXY").unwrap_or_else(|_| "socks5://127.0.0.1:9050".to_string());
+    let tor_proxy_url = tor_socks_proxy();
 
     reqwest::Client::builder()
         .proxy(reqwest::Proxy::all(&tor_proxy_url).unwrap())
@@ -101,7 +102,8 @@ async fn auto_create_mints_for_provider(
 
             match create_mint_for_organization(db, mint_request, organization_id).await {
                 Ok(mint) => {
-                    if let Err(e) = create_mint_units(db, mint.id, &[selected_keyset.clone()]).await
+                    if let Err(e) =
+                        create_mint_units(db, mint.id, std::slice::from_ref(&selected_keyset)).await
                     {
                         eprintln!("Failed to create mint units for mint {}: {}", mint.id, e);
                     }
@@ -196,15 +198,21 @@ pub async fn get_active_providers(
 pub async fn activate_provider(
     State(state): State<Arc<AppState>>,
     Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
     Path(provider_id): Path<i32>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     match activate_provider_for_organization(&state.db, &user_ctx.organization_id, provider_id)
         .await
     {
-        Ok(_) => Ok(Json(json!({
-            "success": true,
-            "message": "Provider activated successfully"
-        }))),
+        Ok(_) => {
+            audit
+                .record(AuditEvent::new("provider.activate", "provider").target(provider_id))
+                .await;
+            Ok(Json(json!({
+                "success": true,
+                "message": "Provider activated successfully"
+            })))
+        }
         Err(sqlx::Error::RowNotFound) => Err((
             StatusCode::NOT_FOUND,
             Json(json!({
@@ -232,15 +240,21 @@ pub async fn activate_provider(
 pub async fn deactivate_provider(
     State(state): State<Arc<AppState>>,
     Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
     Path(provider_id): Path<i32>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     match deactivate_provider_for_organization(&state.db, &user_ctx.organization_id, provider_id)
         .await
     {
-        Ok(true) => Ok(Json(json!({
-            "success": true,
-            "message": "Provider deactivated successfully"
-        }))),
+        Ok(true) => {
+            audit
+                .record(AuditEvent::new("provider.deactivate", "provider").target(provider_id))
+                .await;
+            Ok(Json(json!({
+                "success": true,
+                "message": "Provider deactivated successfully"
+            })))
+        }
         Ok(false) => Err((
             StatusCode::NOT_FOUND,
             Json(json!({
@@ -309,7 +323,13 @@ pub async fn refresh_providers(
     State(state): State<Arc<AppState>>,
     Extension(user_ctx): Extension<UserContext>,
 ) -> Result<Json<RefreshProvidersResponse>, (StatusCode, Json<serde_json::Value>)> {
-    match refresh_providers_from_nostr(&state.db, &user_ctx.organization_id).await {
+    match refresh_providers_from_nostr(
+        &state.db,
+        &user_ctx.organization_id,
+        state.runtime_config().nostr_relays,
+    )
+    .await
+    {
         Ok(response) => Ok(Json(response)),
         Err(e) => {
             eprintln!("Failed to refresh providers: {}", e);
@@ -329,6 +349,7 @@ pub async fn refresh_providers(
 pub async fn create_custom_provider_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
     Json(request): Json<CreateCustomProviderRequest>,
 ) -> Result<Json<crate::db::provider::Provider>, (StatusCode, Json<serde_json::Value>)> {
     if request.name.trim().is_empty() {
@@ -405,6 +426,13 @@ pub async fn create_custom_provider_handler(
                     provider.id, e
                 );
             }
+            audit
+                .record(
+                    AuditEvent::new("provider.create", "provider")
+                        .target(provider.id)
+                        .after(&provider),
+                )
+                .await;
             Ok(Json(provider))
         }
         Err(ProviderError::DuplicateUrl(msg)) => Err((
@@ -434,6 +462,7 @@ pub async fn create_custom_provider_handler(
 pub async fn update_custom_provider_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
     Path(id): Path<i32>,
     Json(request): Json<UpdateCustomProviderRequest>,
 ) -> Result<Json<crate::db::provider::Provider>, (StatusCode, Json<serde_json::Value>)> {
@@ -477,6 +506,10 @@ pub async fn update_custom_provider_handler(
         ));
     }
 
+    let before = get_provider_by_id_for_organization(&state.db, id, &user_ctx.organization_id)
+        .await
+        .ok()
+        .flatten();
     let result = if user_ctx.is_admin {
         update_custom_provider(&state.db, id, request).await
     } else {
@@ -485,7 +518,17 @@ pub async fn update_custom_provider_handler(
     };
 
     match result {
-        Ok(provider) => Ok(Json(provider)),
+        Ok(provider) => {
+            audit
+                .record(
+                    AuditEvent::new("provider.update", "provider")
+                        .target(id)
+                        .before(before)
+                        .after(&provider),
+                )
+                .await;
+            Ok(Json(provider))
+        }
         Err(ProviderError::DuplicateUrl(msg)) => Err((
             StatusCode::CONFLICT,
             Json(json!({
@@ -522,8 +565,13 @@ pub async fn update_custom_provider_handler(
 pub async fn delete_custom_provider_handler(
     State(state): State<Arc<AppState>>,
     Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
     Path(id): Path<i32>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let before = get_provider_by_id_for_organization(&state.db, id, &user_ctx.organization_id)
+        .await
+        .ok()
+        .flatten();
     let result = if user_ctx.is_admin {
         delete_custom_provider(&state.db, id).await
     } else {
@@ -533,6 +581,13 @@ pub async fn delete_custom_provider_handler(
     match result {
         Ok(deleted) => {
             if deleted {
+                audit
+                    .record(
+                        AuditEvent::new("provider.delete", "provider")
+                            .target(id)
+                            .before(before),
+                    )
+                    .await;
                 Ok(Json(json!({
                     "success": true,
                     "message": "Custom provider deleted successfully"
@@ -588,6 +643,7 @@ pub async fn get_default_provider_handler(
 pub async fn set_provider_default(
     State(state): State<Arc<AppState>>,
     Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
     Path(id): Path<i32>,
 ) -> Result<Json<crate::db::provider::Provider>, (StatusCode, Json<serde_json::Value>)> {
     // First, get the provider to access its mints
@@ -634,9 +690,24 @@ pub async fn set_provider_default(
         );
     }
 
+    let previous_default =
+        get_default_provider_for_organization_new(&state.db, &user_ctx.organization_id)
+            .await
+            .ok()
+            .flatten();
     match set_default_provider_for_organization_new(&state.db, &user_ctx.organization_id, id).await
     {
         Ok(_) => {
+            audit
+                .record(
+                    AuditEvent::new("provider.set_default", "provider")
+                        .target(id)
+                        .before(previous_default.map(|provider| {
+                            json!({ "default_provider_id": provider.id, "url": provider.url })
+                        }))
+                        .after(json!({ "default_provider_id": provider.id, "url": provider.url })),
+                )
+                .await;
             if let Err(e) = refresh_models_internal(&state.db, &user_ctx.organization_id).await {
                 eprintln!(
                     "Warning: Failed to refresh models after setting default provider: {}",
@@ -728,11 +799,13 @@ mod tests {
                 id: "keyset1".to_string(),
                 unit: "sat".to_string(),
                 active: true,
+                input_fee_ppk: 0,
             },
             KeysetInfo {
                 id: "keyset2".to_string(),
                 unit: "msat".to_string(),
                 active: true,
+                input_fee_ppk: 0,
             },
         ];
         let selected = select_preferred_keyset(&keysets);
@@ -745,11 +818,13 @@ mod tests {
                 id: "keyset1".to_string(),
                 unit: "sat".to_string(),
                 active: true,
+                input_fee_ppk: 0,
             },
             KeysetInfo {
                 id: "keyset2".to_string(),
                 unit: "usd".to_string(),
                 active: true,
+                input_fee_ppk: 0,
             },
         ];
         let selected = select_preferred_keyset(&keysets);
@@ -762,28 +837,50 @@ mod tests {
                 id: "keyset1".to_string(),
                 unit: "msat".to_string(),
                 active: false,
+                input_fee_ppk: 0,
             },
             KeysetInfo {
                 id: "keyset2".to_string(),
                 unit: "usd".to_string(),
                 active: true,
+                input_fee_ppk: 0,
             },
         ];
         let selected = select_preferred_keyset(&keysets);
         assert!(selected.is_some());
         assert_eq!(selected.unwrap().unit, "msat");
 
+        // Test with several active msat keysets (cheapest input fee wins)
+        let keysets = vec![
+            KeysetInfo {
+                id: "keyset1".to_string(),
+                unit: "msat".to_string(),
+                active: true,
+                input_fee_ppk: 100,
+            },
+            KeysetInfo {
+                id: "keyset2".to_string(),
+                unit: "msat".to_string(),
+                active: true,
+                input_fee_ppk: 0,
+            },
+        ];
+        let selected = select_preferred_keyset(&keysets);
+        assert_eq!(selected.unwrap().id, "keyset2");
+
         // Test with no suitable keysets
         let keysets = vec![
             KeysetInfo {
                 id: "keyset1".to_string(),
                 unit: "usd".to_string(),
                 active: true,
+                input_fee_ppk: 0,
             },
             KeysetInfo {
                 id: "keyset2".to_string(),
                 unit: "eur".to_string(),
                 active: false,
+                input_fee_ppk: 0,
             },
         ];
         let selected = select_preferred_keyset(&keysets);
@@ -0,0 +1,121 @@
+use crate::{
+    db::exchange_rates::{get_exchange_rate_history, ExchangeRateRecord},
+    exchange_rate::ExchangeRate,
+    models::AppState,
+};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+pub struct RatesParams {
+    /// Comma-separated currencies, defaults to `usd`.
+    pub currency: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RateHistoryParams {
+    pub currency: Option<String>,
+    pub since: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct RatesResponse {
+    pub rates: Vec<ExchangeRate>,
+    pub unavailable: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct RateHistoryResponse {
+    pub currency: String,
+    pub history: Vec<ExchangeRateRecord>,
+}
+
+pub async fn get_exchange_rates_handler(
+    State(state): State<Arc<AppState>>,
+    params: Query<RatesParams>,
+) -> Result<Json<RatesResponse>, (StatusCode, Json<serde_json::Value>)> {
+    let currencies = params.currency.as_deref().unwrap_or("usd");
+
+    let mut rates = Vec::new();
+    let mut unavailable = Vec::new();
+
+    for currency in currencies
+        .split(',')
+        .map(|c| c.trim().to_lowercase())
+        .filter(|c| !c.is_empty())
+    {
+        match state.exchange_rates.get_rate(&currency).await {
+            Ok(rate) => rates.push(rate),
+            Err(e) => {
+                eprintln!("No BTC/{} exchange rate available: {}", currency, e);
+                unavailable.push(currency);
+            }
+        }
+    }
+
+    if rates.is_empty() && !unavailable.is_empty() {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({
+                "error": {
+                    "message": format!("No exchange rate available for: {}", unavailable.join(", ")),
+                    "type": "exchange_rate_error"
+                }
+            })),
+        ));
+    }
+
+    Ok(Json(RatesResponse { rates, unavailable }))
+}
+
+pub async fn get_exchange_rate_history_handler(
+    State(state): State<Arc<AppState>>,
+    params: Query<RateHistoryParams>,
+) -> Result<Json<RateHistoryResponse>, (StatusCode, Json<serde_json::Value>)> {
+    let currency = params
+        .currency
+        .as_deref()
+        .unwrap_or("usd")
+        .trim()
+        .to_lowercase();
+
+    let since = match params.since.as_deref() {
+        Some(since) => match chrono::DateTime::parse_from_rfc3339(since) {
+            Ok(dt) => Some(dt.with_timezone(&chrono::Utc)),
+            Err(_) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({
+                        "error": {
+                            "message": "since must be an RFC 3339 timestamp",
+                            "type": "validation_error"
+                        }
+                    })),
+                ));
+            }
+        },
+        None => None,
+    };
+
+    let limit = params.limit.unwrap_or(100).clamp(1, 1000);
+
+    match get_exchange_rate_history(&state.db, &currency, since, limit).await {
+        Ok(history) => Ok(Json(RateHistoryResponse { currency, history })),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": {
+                    "message": format!("Failed to load exchange rate history: {}", e),
+                    "type": "database_error"
+                }
+            })),
+        )),
+    }
+}
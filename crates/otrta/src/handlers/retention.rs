@@ -0,0 +1,192 @@
+use crate::{
+    db::retention::{
+        delete_retention_policy, get_retention_policy, get_transaction_rollups,
+        upsert_retention_policy, RetentionPolicy, SetRetentionPolicyRequest, TransactionRollup,
+    },
+    models::{AppState, UserContext},
+    retention::{enforce_retention_policies, RetentionReport},
+};
+use axum::{
+    extract::{Extension, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::sync::Arc;
+
+#[derive(Serialize)]
+pub struct RetentionPolicyResponse {
+    /// The organization's own policy, if it has one.
+    pub policy: Option<RetentionPolicy>,
+    /// Instance default applied when the organization has no policy.
+    pub default_policy: Option<RetentionPolicy>,
+}
+
+#[derive(Serialize)]
+pub struct RetentionRunResponse {
+    pub reports: Vec<RetentionReport>,
+}
+
+#[derive(Deserialize)]
+pub struct RollupQuery {
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+#[derive(Serialize)]
+pub struct RollupListResponse {
+    pub rollups: Vec<TransactionRollup>,
+}
+
+fn validate_retention_request(
+    request: &SetRetentionPolicyRequest,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    let days = [
+        request.transaction_retention_days,
+        request.search_retention_days,
+        request.credit_retention_days,
+    ];
+    if days.iter().flatten().any(|days| *days <= 0) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": {
+                    "message": "Retention periods must be a positive number of days",
+                    "type": "validation_error"
+                }
+            })),
+        ));
+    }
+    Ok(())
+}
+
+fn require_admin(user_ctx: &UserContext) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    if !user_ctx.is_admin {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": {
+                    "message": "Only administrators can manage instance retention",
+                    "type": "forbidden"
+                }
+            })),
+        ));
+    }
+    Ok(())
+}
+
+fn retention_db_error(
+    action: &str,
+    e: impl std::fmt::Display,
+) -> (StatusCode, Json<serde_json::Value>) {
+    eprintln!("Failed to {}: {}", action, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({
+            "error": {
+                "message": format!("Failed to {}", action),
+                "type": "internal_server_error"
+            }
+        })),
+    )
+}
+
+pub async fn get_retention_policy_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+) -> Result<Json<RetentionPolicyResponse>, (StatusCode, Json<serde_json::Value>)> {
+    let policy = get_retention_policy(&state.db, Some(&user_ctx.organization_id))
+        .await
+        .map_err(|e| retention_db_error("get retention policy", e))?;
+    let default_policy = get_retention_policy(&state.db, None)
+        .await
+        .map_err(|e| retention_db_error("get default retention policy", e))?;
+
+    Ok(Json(RetentionPolicyResponse {
+        policy,
+        default_policy,
+    }))
+}
+
+pub async fn set_retention_policy_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Json(request): Json<SetRetentionPolicyRequest>,
+) -> Result<Json<RetentionPolicy>, (StatusCode, Json<serde_json::Value>)> {
+    validate_retention_request(&request)?;
+
+    // Credits aren't scoped to an organization; only the default policy
+    // can purge them.
+    let request = SetRetentionPolicyRequest {
+        credit_retention_days: None,
+        ..request
+    };
+
+    upsert_retention_policy(&state.db, Some(&user_ctx.organization_id), &request)
+        .await
+        .map(Json)
+        .map_err(|e| retention_db_error("set retention policy", e))
+}
+
+pub async fn delete_retention_policy_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
+    match delete_retention_policy(&state.db, &user_ctx.organization_id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": {
+                    "message": "Organization has no retention policy",
+                    "type": "not_found"
+                }
+            })),
+        )),
+        Err(e) => Err(retention_db_error("delete retention policy", e)),
+    }
+}
+
+pub async fn set_default_retention_policy_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Json(request): Json<SetRetentionPolicyRequest>,
+) -> Result<Json<RetentionPolicy>, (StatusCode, Json<serde_json::Value>)> {
+    require_admin(&user_ctx)?;
+    validate_retention_request(&request)?;
+
+    upsert_retention_policy(&state.db, None, &request)
+        .await
+        .map(Json)
+        .map_err(|e| retention_db_error("set default retention policy", e))
+}
+
+pub async fn run_retention_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+) -> Result<Json<RetentionRunResponse>, (StatusCode, Json<serde_json::Value>)> {
+    require_admin(&user_ctx)?;
+
+    enforce_retention_policies(&state.db)
+        .await
+        .map(|reports| Json(RetentionRunResponse { reports }))
+        .map_err(|e| retention_db_error("enforce retention policies", e))
+}
+
+pub async fn get_transaction_rollups_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Query(query): Query<RollupQuery>,
+) -> Result<Json<RollupListResponse>, (StatusCode, Json<serde_json::Value>)> {
+    get_transaction_rollups(
+        &state.db,
+        &user_ctx.organization_id,
+        query.start_date,
+        query.end_date,
+    )
+    .await
+    .map(|rollups| Json(RollupListResponse { rollups }))
+    .map_err(|e| retention_db_error("get transaction rollups", e))
+}
@@ -0,0 +1,316 @@
+use crate::{
+    auth::AuthMethod,
+    db::{
+        members::get_member,
+        sessions::{
+            create_session, get_active_sessions_for_user, revoke_session, rotate_refresh_token,
+            switch_session_organization, Session,
+        },
+        users::{set_default_organization, update_last_login},
+    },
+    models::{AppState, UserContext},
+    sessions::{hash_refresh_token, new_refresh_token, SessionClaims},
+};
+use axum::{
+    extract::{Extension, Path, State},
+    http::{header::USER_AGENT, HeaderMap, StatusCode},
+    Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Serialize)]
+pub struct SessionTokenResponse {
+    pub session_id: Uuid,
+    /// Sent as `Authorization: Bearer <access_token>` on management requests.
+    pub access_token: String,
+    pub token_type: &'static str,
+    pub expires_in: u64,
+    /// Exchanged at `/api/auth/refresh` for a new pair of tokens; each one
+    /// works once.
+    pub refresh_token: String,
+    pub refresh_expires_in: u64,
+}
+
+#[derive(Deserialize)]
+pub struct RefreshSessionRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct SwitchOrganizationRequest {
+    pub organization_id: Uuid,
+}
+
+#[derive(Serialize)]
+pub struct SessionInfo {
+    #[serde(flatten)]
+    pub session: Session,
+    /// Whether this is the session the request was made with.
+    pub current: bool,
+}
+
+#[derive(Serialize)]
+pub struct SessionListResponse {
+    pub sessions: Vec<SessionInfo>,
+}
+
+fn session_db_error(action: &str, e: sqlx::Error) -> (StatusCode, Json<serde_json::Value>) {
+    eprintln!("Failed to {}: {}", action, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({
+            "error": {
+                "message": format!("Failed to {}", action),
+                "type": "database_error"
+            }
+        })),
+    )
+}
+
+fn token_response(
+    state: &AppState,
+    session: &Session,
+    refresh_token: String,
+) -> SessionTokenResponse {
+    let tokens = &state.session_tokens;
+    let access_token = tokens.sign(&SessionClaims {
+        sid: session.id,
+        npub: session.npub.clone(),
+        org: session.organization_id,
+        exp: Utc::now().timestamp() + tokens.access_ttl.as_secs() as i64,
+    });
+
+    SessionTokenResponse {
+        session_id: session.id,
+        access_token,
+        token_type: "Bearer",
+        expires_in: tokens.access_ttl.as_secs(),
+        refresh_token,
+        refresh_expires_in: tokens.refresh_ttl.as_secs(),
+    }
+}
+
+/// Starts a session for the signer of the request's NIP-98 event.
+pub async fn login_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Extension(auth_method): Extension<AuthMethod>,
+    headers: HeaderMap,
+) -> Result<Json<SessionTokenResponse>, (StatusCode, Json<serde_json::Value>)> {
+    if auth_method != AuthMethod::Nostr {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": {
+                    "message": "Sessions can only be started with a NIP-98 signed request",
+                    "type": "invalid_request"
+                }
+            })),
+        ));
+    }
+
+    let user_agent = headers
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok());
+    start_session(&state, &user_ctx, user_agent).await.map(Json)
+}
+
+/// Creates a session for a user who has just proven control of their key.
+pub(crate) async fn start_session(
+    state: &AppState,
+    user_ctx: &UserContext,
+    user_agent: Option<&str>,
+) -> Result<SessionTokenResponse, (StatusCode, Json<serde_json::Value>)> {
+    let refresh_token = new_refresh_token();
+    let expires_at = Utc::now() + state.session_tokens.refresh_ttl;
+    let session = create_session(
+        &state.db,
+        &user_ctx.npub,
+        &user_ctx.organization_id,
+        &hash_refresh_token(&refresh_token),
+        user_agent,
+        expires_at,
+    )
+    .await
+    .map_err(|e| session_db_error("create session", e))?;
+
+    if let Err(e) = update_last_login(&state.db, &user_ctx.npub).await {
+        eprintln!("Failed to record login of {}: {}", user_ctx.npub, e);
+    }
+
+    Ok(token_response(state, &session, refresh_token))
+}
+
+/// Exchanges a refresh token for new tokens. Needs no other credentials, as
+/// the access token has usually expired by then.
+pub async fn refresh_session_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<RefreshSessionRequest>,
+) -> Result<Json<SessionTokenResponse>, (StatusCode, Json<serde_json::Value>)> {
+    let refresh_token = new_refresh_token();
+    let expires_at = Utc::now() + state.session_tokens.refresh_ttl;
+    let session = rotate_refresh_token(
+        &state.db,
+        &hash_refresh_token(&request.refresh_token),
+        &hash_refresh_token(&refresh_token),
+        expires_at,
+    )
+    .await
+    .map_err(|e| session_db_error("refresh session", e))?
+    .ok_or_else(|| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": {
+                    "message": "Invalid or expired refresh token",
+                    "type": "unauthorized"
+                }
+            })),
+        )
+    })?;
+
+    Ok(Json(token_response(&state, &session, refresh_token)))
+}
+
+/// Revokes the session the request was made with.
+pub async fn logout_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Extension(auth_method): Extension<AuthMethod>,
+) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
+    let AuthMethod::Session(session_id) = auth_method else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": {
+                    "message": "The request was not made with a session token",
+                    "type": "invalid_request"
+                }
+            })),
+        ));
+    };
+
+    revoke_session(&state.db, &session_id, &user_ctx.npub)
+        .await
+        .map_err(|e| session_db_error("revoke session", e))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Moves the session to another of the user's organizations and returns new
+/// tokens for it. The organization also becomes the default for new logins.
+pub async fn switch_organization_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Extension(auth_method): Extension<AuthMethod>,
+    Json(request): Json<SwitchOrganizationRequest>,
+) -> Result<Json<SessionTokenResponse>, (StatusCode, Json<serde_json::Value>)> {
+    let AuthMethod::Session(session_id) = auth_method else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": {
+                    "message": "Only sessions can switch organization; signed requests name it in the X-Organization-Id header",
+                    "type": "invalid_request"
+                }
+            })),
+        ));
+    };
+
+    if get_member(&state.db, &request.organization_id, &user_ctx.npub)
+        .await
+        .map_err(|e| session_db_error("retrieve membership", e))?
+        .is_none()
+    {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": {
+                    "message": "Not a member of this organization",
+                    "type": "forbidden"
+                }
+            })),
+        ));
+    }
+
+    let refresh_token = new_refresh_token();
+    let expires_at = Utc::now() + state.session_tokens.refresh_ttl;
+    let session = switch_session_organization(
+        &state.db,
+        &session_id,
+        &request.organization_id,
+        &hash_refresh_token(&refresh_token),
+        expires_at,
+    )
+    .await
+    .map_err(|e| session_db_error("switch organization", e))?
+    .ok_or_else(|| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": {
+                    "message": "Session expired",
+                    "type": "unauthorized"
+                }
+            })),
+        )
+    })?;
+
+    if let Err(e) =
+        set_default_organization(&state.db, &user_ctx.npub, &request.organization_id).await
+    {
+        eprintln!(
+            "Failed to set default organization of {}: {}",
+            user_ctx.npub, e
+        );
+    }
+
+    Ok(Json(token_response(&state, &session, refresh_token)))
+}
+
+pub async fn get_sessions_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Extension(auth_method): Extension<AuthMethod>,
+) -> Result<Json<SessionListResponse>, (StatusCode, Json<serde_json::Value>)> {
+    let sessions = get_active_sessions_for_user(&state.db, &user_ctx.npub)
+        .await
+        .map_err(|e| session_db_error("retrieve sessions", e))?;
+
+    Ok(Json(SessionListResponse {
+        sessions: sessions
+            .into_iter()
+            .map(|session| SessionInfo {
+                current: auth_method == AuthMethod::Session(session.id),
+                session,
+            })
+            .collect(),
+    }))
+}
+
+/// Revokes one of the caller's sessions, e.g. on a lost device.
+pub async fn revoke_session_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
+    if revoke_session(&state.db, &id, &user_ctx.npub)
+        .await
+        .map_err(|e| session_db_error("revoke session", e))?
+    {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": {
+                    "message": "Session not found",
+                    "type": "not_found"
+                }
+            })),
+        ))
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{
+    db::usage_export::{csv_line, stream_usage, UsageExportFilter, UsageRow},
+    models::{AppState, UserContext},
+};
+use axum::{
+    body::Body,
+    extract::{Extension, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use futures_util::{stream, StreamExt};
+use serde::Deserialize;
+use std::{io, sync::Arc};
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Jsonl,
+}
+
+impl ExportFormat {
+    fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Jsonl => "application/x-ndjson",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+        }
+    }
+
+    fn format_row(&self, row: &UsageRow) -> Result<String, io::Error> {
+        match self {
+            ExportFormat::Csv => Ok(csv_line(&row.csv_fields())),
+            ExportFormat::Jsonl => {
+                let mut line = serde_json::to_string(row).map_err(io::Error::other)?;
+                line.push('\n');
+                Ok(line)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportFormatQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+// Rows buffered between the database cursor and the client.
+const EXPORT_BUFFER_ROWS: usize = 256;
+
+/// Streams the organization's usage, one row per proxied request. Rows are
+/// read from a database cursor and written as they arrive, so large ranges
+/// never sit in memory.
+pub async fn export_usage_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(user_ctx): Extension<UserContext>,
+    Query(format): Query<ExportFormatQuery>,
+    Query(filter): Query<UsageExportFilter>,
+) -> Response {
+    let format = format.format;
+    let (tx, rx) = mpsc::channel::<Result<String, io::Error>>(EXPORT_BUFFER_ROWS);
+
+    let db = state.db.clone();
+    let organization_id = user_ctx.organization_id;
+    tokio::spawn(async move {
+        if format == ExportFormat::Csv && tx.send(Ok(csv_line(UsageRow::CSV_HEADER))).await.is_err()
+        {
+            return;
+        }
+
+        let mut rows = stream_usage(&db, &organization_id, &filter);
+        while let Some(row) = rows.next().await {
+            let line = match row {
+                Ok(row) => format.format_row(&row),
+                Err(e) => {
+                    eprintln!("Usage export failed: {}", e);
+                    Err(io::Error::other(e))
+                }
+            };
+            let failed = line.is_err();
+            // The client went away; stop reading.
+            if tx.send(line).await.is_err() || failed {
+                return;
+            }
+        }
+    });
+
+    let body = Body::from_stream(stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|line| (line, rx))
+    }));
+
+    let filename = format!(
+        "usage-{}.{}",
+        chrono::Utc::now().format("%Y%m%d%H%M%S"),
+        format.extension()
+    );
+
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
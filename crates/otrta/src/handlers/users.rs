@@ -1,19 +1,40 @@
-use crate::models::{AppState, Organization, User};
+use crate::{
+    db::{members, organizations, users},
+    models::{AppState, Organization, User, UserContext},
+    permissions::Role,
+};
 use axum::{
-    extract::{Path, State},
+    extract::{Extension, State},
     http::StatusCode,
     Json,
 };
+use serde::Serialize;
 use serde_json::{self, json};
 use std::sync::Arc;
 
+#[derive(Serialize)]
+pub struct UserProfileResponse {
+    pub user: User,
+    /// The organization the request acted in.
+    pub organization: Organization,
+    pub role: Role,
+    pub is_admin: bool,
+}
+
+#[derive(Serialize)]
+pub struct UserOrganization {
+    #[serde(flatten)]
+    pub organization: Organization,
+    pub role: Role,
+    /// Whether this is the organization the request acted in.
+    pub current: bool,
+}
+
 pub async fn get_user_profile_handler(
     State(app_state): State<Arc<AppState>>,
-    Path(npub): Path<String>,
-) -> Result<Json<User>, (StatusCode, Json<serde_json::Value>)> {
-    use crate::db::users;
-
-    let user = match users::get_user_by_npub(&app_state.db, &npub).await {
+    Extension(user_ctx): Extension<UserContext>,
+) -> Result<Json<UserProfileResponse>, (StatusCode, Json<serde_json::Value>)> {
+    let user = match users::get_user_by_npub(&app_state.db, &user_ctx.npub).await {
         Ok(Some(user)) => user,
         Ok(None) => {
             return Err((
@@ -38,30 +59,49 @@ pub async fn get_user_profile_handler(
         }
     };
 
-    Ok(Json(user))
+    Ok(Json(UserProfileResponse {
+        user,
+        organization: user_ctx.organization,
+        role: user_ctx.role,
+        is_admin: user_ctx.is_admin,
+    }))
 }
 
+/// The organizations the caller belongs to, to pick one to act in.
 pub async fn get_user_organizations_handler(
     State(app_state): State<Arc<AppState>>,
-    Path(npub): Path<String>,
-) -> Result<Json<Vec<Organization>>, (StatusCode, Json<serde_json::Value>)> {
-    use crate::db::organizations;
-
-    let organizations = match organizations::get_organization_for_user(&app_state.db, &npub).await {
-        Ok(Some(org)) => vec![org],
-        Ok(None) => vec![],
-        Err(e) => {
-            tracing::error!("Failed to get user organization: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "get_organization_failed",
-                    "message": "Failed to get user organizations",
-                    "type": "internal_server_error"
-                })),
-            ));
-        }
+    Extension(user_ctx): Extension<UserContext>,
+) -> Result<Json<Vec<UserOrganization>>, (StatusCode, Json<serde_json::Value>)> {
+    let failed = |e: &dyn std::fmt::Display| {
+        tracing::error!("Failed to get user organizations: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": "get_organization_failed",
+                "message": "Failed to get user organizations",
+                "type": "internal_server_error"
+            })),
+        )
     };
 
+    let memberships = members::get_memberships_for_user(&app_state.db, &user_ctx.npub)
+        .await
+        .map_err(|e| failed(&e))?;
+    let mut organizations = Vec::with_capacity(memberships.len());
+    for member in memberships {
+        let Some(organization) =
+            organizations::get_organization_by_id(&app_state.db, &member.organization_id)
+                .await
+                .map_err(|e| failed(&e))?
+        else {
+            continue;
+        };
+        organizations.push(UserOrganization {
+            current: organization.id == user_ctx.organization_id,
+            role: member.role(),
+            organization,
+        });
+    }
+
     Ok(Json(organizations))
 }
@@ -1,4 +1,7 @@
 use crate::{
+    audit::{Audit, AuditEvent},
+    db::ledger::EntryType,
+    handlers::ledger::record_token_movement,
     models::{
         AppState, SendTokenRequest, SendTokenResponse, Token, TokenRedeemResponse, UserContext,
     },
@@ -85,8 +88,16 @@ pub async fn redeem_token(
         }
 
         if let Some(mint_wallet) = wallet.get_wallet_for_mint(&mint_url.to_string()).await {
-            match mint_wallet.receive(token).await {
+            match mint_wallet.receive(token).await.map_err(|e| e.to_string()) {
                 Ok(amount) => {
+                    record_token_movement(
+                        &state,
+                        &user_ctx,
+                        EntryType::Deposit,
+                        token,
+                        amount.to_string().parse().ok(),
+                    )
+                    .await;
                     return Json(TokenRedeemResponse {
                         amount: Some(amount.to_string()),
                         success: true,
@@ -97,7 +108,7 @@ pub async fn redeem_token(
                     });
                 }
                 Err(e) => {
-                    let error_message = get_user_friendly_wallet_error_message(&e.to_string());
+                    let error_message = get_user_friendly_wallet_error_message(&e);
                     return Json(TokenRedeemResponse {
                         amount: None,
                         success: false,
@@ -108,14 +119,24 @@ pub async fn redeem_token(
         }
     }
 
-    match wallet.receive(token).await {
-        Ok(amount) => Json(TokenRedeemResponse {
-            amount: Some(amount.to_string()),
-            success: true,
-            message: Some("Token redeemed successfully".to_string()),
-        }),
+    match wallet.receive(token).await.map_err(|e| e.to_string()) {
+        Ok(amount) => {
+            record_token_movement(
+                &state,
+                &user_ctx,
+                EntryType::Deposit,
+                token,
+                amount.parse().ok(),
+            )
+            .await;
+            Json(TokenRedeemResponse {
+                amount: Some(amount.to_string()),
+                success: true,
+                message: Some("Token redeemed successfully".to_string()),
+            })
+        }
         Err(e) => {
-            let error_message = get_user_friendly_wallet_error_message(&e.to_string());
+            let error_message = get_user_friendly_wallet_error_message(&e);
             Json(TokenRedeemResponse {
                 amount: None,
                 success: false,
@@ -161,6 +182,7 @@ pub async fn get_pendings(
 pub async fn send_token(
     State(state): State<Arc<AppState>>,
     Extension(user_ctx): Extension<UserContext>,
+    audit: Audit,
     Json(payload): Json<SendTokenRequest>,
 ) -> Result<Json<SendTokenResponse>, (StatusCode, Json<serde_json::Value>)> {
     let wallet = match state
@@ -179,6 +201,7 @@ pub async fn send_token(
         }
     };
 
+    let withdrawal = json!({ "amount": payload.amount, "mint_url": payload.mint_url });
     match wallet
         .send_simple(
             payload.amount as u64,
@@ -188,9 +211,14 @@ pub async fn send_token(
             },
         )
         .await
+        .map_err(|e| e.to_string())
     {
         Ok(response) => {
             eprintln!("DEBUG: Successfully generated token");
+            record_token_movement(&state, &user_ctx, EntryType::Withdrawal, &response, None).await;
+            audit
+                .record(AuditEvent::new("wallet.send", "wallet").after(withdrawal))
+                .await;
             Ok(Json(SendTokenResponse {
                 token: response,
                 success: true,
@@ -261,23 +289,24 @@ pub async fn get_wallet_debug_info(
     let mints_list = org_wallet.list_mints().await;
     debug_info.insert("configured_mints".to_string(), json!(mints_list));
 
-    for unit in [CurrencyUnit::Sat, CurrencyUnit::Msat] {
+    let mut units = vec![CurrencyUnit::Sat, CurrencyUnit::Msat];
+    for wallet in org_wallet.inner().cdk_wallet().get_wallets().await {
+        if !units.contains(&wallet.unit) {
+            units.push(wallet.unit);
+        }
+    }
+
+    for unit in units {
         match org_wallet.inner().cdk_wallet().get_balances(&unit).await {
             Ok(balances) => {
                 let balances_map: std::collections::HashMap<String, u64> = balances
                     .into_iter()
                     .map(|(mint_url, amount)| (mint_url.to_string(), u64::from(amount)))
                     .collect();
-                debug_info.insert(
-                    format!("balances_{:?}", unit).to_lowercase(),
-                    json!(balances_map),
-                );
+                debug_info.insert(format!("balances_{}", unit), json!(balances_map));
             }
             Err(e) => {
-                debug_info.insert(
-                    format!("balances_{:?}_error", unit).to_lowercase(),
-                    json!(e.to_string()),
-                );
+                debug_info.insert(format!("balances_{}_error", unit), json!(e.to_string()));
             }
         }
     }
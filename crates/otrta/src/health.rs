@@ -0,0 +1,450 @@
+use chrono::{DateTime, Utc};
+use futures_util::future::join_all;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, RwLock},
+    time::Duration,
+    time::Instant,
+};
+use tokio::{net::TcpStream, time::timeout};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    db::{provider::get_default_provider_for_organization_new, retention::get_organization_ids},
+    models::AppState,
+    onion::{construct_url_with_protocol, create_onion_client, is_onion_url, tor_socks_proxy},
+};
+
+// Probes usually time out after a few seconds, so every check is bounded.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Providers are probed in the background this often rather than on every
+/// readiness request, which anyone can make.
+pub const PROVIDER_PROBE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Ok,
+    Degraded,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthCheck {
+    pub name: &'static str,
+    pub status: HealthStatus,
+    /// Whether a failure of this check takes the instance out of rotation.
+    pub critical: bool,
+    pub duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadinessReport {
+    pub status: HealthStatus,
+    pub checked_at: DateTime<Utc>,
+    pub checks: Vec<HealthCheck>,
+}
+
+/// What unauthenticated callers see: no messages or details, which can hold
+/// organization IDs, provider URLs and error strings.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadinessSummary {
+    pub status: HealthStatus,
+    pub checked_at: DateTime<Utc>,
+    pub checks: Vec<CheckSummary>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckSummary {
+    pub name: &'static str,
+    pub status: HealthStatus,
+}
+
+impl ReadinessReport {
+    pub fn summary(&self) -> ReadinessSummary {
+        ReadinessSummary {
+            status: self.status,
+            checked_at: self.checked_at,
+            checks: self
+                .checks
+                .iter()
+                .map(|check| CheckSummary {
+                    name: check.name,
+                    status: check.status,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Latest outcome of probing every organization's default provider.
+#[derive(Default)]
+pub struct ProviderProbes {
+    latest: RwLock<Option<ProviderProbeResults>>,
+}
+
+#[derive(Clone)]
+struct ProviderProbeResults {
+    checked_at: DateTime<Utc>,
+    /// Probed URL and its HTTP status, or why it couldn't be reached.
+    results: Vec<(String, Result<u16, String>)>,
+    /// Set when the providers couldn't be listed.
+    error: Option<String>,
+}
+
+struct CheckOutcome {
+    status: HealthStatus,
+    message: Option<String>,
+    details: Option<serde_json::Value>,
+}
+
+impl CheckOutcome {
+    fn ok() -> Self {
+        Self {
+            status: HealthStatus::Ok,
+            message: None,
+            details: None,
+        }
+    }
+
+    fn fail(message: impl Into<String>) -> Self {
+        Self {
+            status: HealthStatus::Fail,
+            message: Some(message.into()),
+            details: None,
+        }
+    }
+
+    fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+async fn run_check<F>(name: &'static str, critical: bool, check: F) -> HealthCheck
+where
+    F: Future<Output = CheckOutcome>,
+{
+    let started = Instant::now();
+    let outcome = timeout(CHECK_TIMEOUT, check)
+        .await
+        .unwrap_or_else(|_| CheckOutcome::fail("check timed out"));
+
+    HealthCheck {
+        name,
+        status: outcome.status,
+        critical,
+        duration_ms: started.elapsed().as_millis() as u64,
+        message: outcome.message,
+        details: outcome.details,
+    }
+}
+
+/// Overall status: a failing critical check fails readiness, anything else
+/// that isn't ok only degrades it.
+pub fn overall_status(checks: &[HealthCheck]) -> HealthStatus {
+    checks
+        .iter()
+        .map(|check| match check.status {
+            HealthStatus::Fail if !check.critical => HealthStatus::Degraded,
+            status => status,
+        })
+        .max()
+        .unwrap_or(HealthStatus::Ok)
+}
+
+async fn check_database(state: &AppState) -> CheckOutcome {
+    match sqlx::query_scalar::<_, i32>("SELECT 1")
+        .fetch_one(&state.db)
+        .await
+    {
+        Ok(_) => CheckOutcome::ok(),
+        Err(e) => CheckOutcome::fail(format!("database unreachable: {}", e)),
+    }
+}
+
+async fn check_migrations(state: &AppState) -> CheckOutcome {
+    let applied = match sqlx::query_as::<_, (i64, bool)>(
+        "SELECT version, success FROM _sqlx_migrations ORDER BY version",
+    )
+    .fetch_all(&state.db)
+    .await
+    {
+        Ok(applied) => applied,
+        Err(e) => return CheckOutcome::fail(format!("failed to read migrations: {}", e)),
+    };
+
+    let current = applied.iter().map(|(version, _)| *version).max();
+    let details = serde_json::json!({
+        "current": current,
+        "expected": state.latest_migration,
+    });
+
+    if let Some((version, _)) = applied.iter().find(|(_, success)| !success) {
+        return CheckOutcome::fail(format!("migration {} did not complete", version))
+            .with_details(details);
+    }
+
+    match (current, state.latest_migration) {
+        (Some(current), Some(expected)) if current < expected => {
+            CheckOutcome::fail("migrations pending").with_details(details)
+        }
+        (None, Some(_)) => CheckOutcome::fail("no migrations applied").with_details(details),
+        (Some(current), Some(expected)) if current > expected => CheckOutcome {
+            status: HealthStatus::Degraded,
+            message: Some("database schema is newer than this binary".to_string()),
+            details: Some(details),
+        },
+        _ => CheckOutcome::ok().with_details(details),
+    }
+}
+
+/// `host:port` of the configured Tor SOCKS proxy.
+pub fn tor_proxy_address(proxy_url: &str) -> Option<String> {
+    let url = reqwest::Url::parse(proxy_url).ok()?;
+    Some(format!(
+        "{}:{}",
+        url.host_str()?,
+        url.port().unwrap_or(9050)
+    ))
+}
+
+async fn check_tor() -> CheckOutcome {
+    let proxy_url = tor_socks_proxy();
+    let Some(address) = tor_proxy_address(&proxy_url) else {
+        return CheckOutcome::fail(format!("invalid Tor SOCKS proxy: {}", proxy_url));
+    };
+
+    match TcpStream::connect(&address).await {
+        Ok(_) => CheckOutcome::ok(),
+        Err(e) => CheckOutcome::fail(format!("SOCKS proxy {} unreachable: {}", address, e)),
+    }
+    .with_details(serde_json::json!({ "proxy": address }))
+}
+
+/// Counts wallets without loading or creating any, which would write seeds
+/// and wallet files on every probe.
+async fn check_wallets(state: &AppState, organization_ids: &[Uuid]) -> CheckOutcome {
+    let manager = &state.multimint_manager;
+    let loaded = organization_ids
+        .iter()
+        .filter(|org_id| manager.is_loaded(org_id))
+        .count();
+    let on_disk = organization_ids
+        .iter()
+        .filter(|org_id| manager.has_wallet(org_id))
+        .count();
+
+    CheckOutcome::ok().with_details(serde_json::json!({
+        "organizations": organization_ids.len(),
+        "loaded": loaded,
+        "on_disk": on_disk,
+    }))
+}
+
+async fn probe_provider(url: &str, use_onion: bool) -> Result<u16, String> {
+    let client = create_onion_client(url, use_onion, Some(CHECK_TIMEOUT.as_secs()))?;
+    // Any HTTP answer means the provider is reachable; the base path need not
+    // be a valid route.
+    let response = client
+        .get(construct_url_with_protocol(url, ""))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(response.status().as_u16())
+}
+
+/// Probes every organization's default provider and stores the results for
+/// the readiness check.
+pub async fn refresh_provider_probes(state: &AppState) {
+    let organization_ids = get_organization_ids(&state.db).await.unwrap_or_default();
+    let mut providers = HashMap::new();
+    let mut error = None;
+    for org_id in &organization_ids {
+        match get_default_provider_for_organization_new(&state.db, org_id).await {
+            Ok(Some(provider)) => {
+                providers.insert(provider.url.clone(), provider.use_onion);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                error = Some(format!("failed to load providers: {}", e));
+                break;
+            }
+        }
+    }
+
+    let results = join_all(providers.iter().map(|(url, use_onion)| async move {
+        let result = probe_provider(url, *use_onion).await;
+        (url.clone(), result)
+    }))
+    .await;
+
+    *state.provider_probes.latest.write().unwrap() = Some(ProviderProbeResults {
+        checked_at: Utc::now(),
+        results,
+        error,
+    });
+}
+
+/// Refreshes the provider probes every [`PROVIDER_PROBE_INTERVAL`], starting
+/// right away.
+pub async fn probe_providers_periodically(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(PROVIDER_PROBE_INTERVAL);
+    loop {
+        interval.tick().await;
+        if timeout(PROVIDER_PROBE_INTERVAL, refresh_provider_probes(&state))
+            .await
+            .is_err()
+        {
+            warn!(
+                "Provider probes did not finish within {:?}",
+                PROVIDER_PROBE_INTERVAL
+            );
+        }
+    }
+}
+
+async fn check_providers(state: &AppState) -> CheckOutcome {
+    let Some(latest) = state.provider_probes.latest.read().unwrap().clone() else {
+        return CheckOutcome {
+            status: HealthStatus::Degraded,
+            message: Some("providers not probed yet".to_string()),
+            details: None,
+        };
+    };
+    if let Some(error) = latest.error {
+        return CheckOutcome::fail(error);
+    }
+
+    let unreachable = latest
+        .results
+        .iter()
+        .filter(|(_, result)| result.is_err())
+        .count();
+    let providers: Vec<_> = latest
+        .results
+        .into_iter()
+        .map(|(url, result)| match result {
+            Ok(status) => serde_json::json!({
+                "url": url,
+                "onion": is_onion_url(&url),
+                "reachable": true,
+                "status": status,
+            }),
+            Err(error) => serde_json::json!({
+                "url": url,
+                "onion": is_onion_url(&url),
+                "reachable": false,
+                "error": error,
+            }),
+        })
+        .collect();
+    let details = serde_json::json!({
+        "checked_at": latest.checked_at,
+        "providers": providers,
+    });
+
+    if unreachable == 0 {
+        CheckOutcome::ok().with_details(details)
+    } else {
+        CheckOutcome::fail(format!("{} default provider(s) unreachable", unreachable))
+            .with_details(details)
+    }
+}
+
+/// Runs every readiness check concurrently. Only the database and the schema are
+/// critical; Tor, wallets and providers can be down for some organizations
+/// without the instance being useless, so they degrade the report instead.
+/// Nothing here writes or calls out beyond the Tor proxy; providers are read
+/// from the background probes.
+pub async fn check_readiness(state: &AppState) -> ReadinessReport {
+    let organization_ids = get_organization_ids(&state.db).await.unwrap_or_default();
+
+    let checks = futures_util::join!(
+        run_check("database", true, check_database(state)),
+        run_check("migrations", true, check_migrations(state)),
+        run_check("tor", false, check_tor()),
+        run_check("wallets", false, check_wallets(state, &organization_ids)),
+        run_check("providers", false, check_providers(state)),
+    );
+    let checks = vec![checks.0, checks.1, checks.2, checks.3, checks.4];
+
+    ReadinessReport {
+        status: overall_status(&checks),
+        checked_at: Utc::now(),
+        checks,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(status: HealthStatus, critical: bool) -> HealthCheck {
+        HealthCheck {
+            name: "test",
+            status,
+            critical,
+            duration_ms: 0,
+            message: None,
+            details: None,
+        }
+    }
+
+    #[test]
+    fn test_overall_status() {
+        assert_eq!(overall_status(&[]), HealthStatus::Ok);
+        assert_eq!(
+            overall_status(&[
+                check(HealthStatus::Ok, true),
+                check(HealthStatus::Fail, false)
+            ]),
+            HealthStatus::Degraded
+        );
+        assert_eq!(
+            overall_status(&[
+                check(HealthStatus::Fail, true),
+                check(HealthStatus::Ok, false)
+            ]),
+            HealthStatus::Fail
+        );
+    }
+
+    #[test]
+    fn test_summary_leaves_out_details() {
+        let mut failing = check(HealthStatus::Fail, false);
+        failing.message = Some("wallet for 0b6c… failed".to_string());
+        failing.details = Some(serde_json::json!({ "url": "http://provider.onion" }));
+        let report = ReadinessReport {
+            status: HealthStatus::Degraded,
+            checked_at: Utc::now(),
+            checks: vec![failing],
+        };
+
+        let summary = serde_json::to_value(report.summary()).unwrap();
+        assert_eq!(
+            summary["checks"],
+            serde_json::json!([{ "name": "test", "status": "fail" }])
+        );
+    }
+
+    #[test]
+    fn test_tor_proxy_address() {
+        assert_eq!(
+            tor_proxy_address("socks5h://127.0.0.1:9050"),
+            Some("127.0.0.1:9050".to_string())
+        );
+        assert_eq!(
+            tor_proxy_address("socks5://tor"),
+            Some("tor:9050".to_string())
+        );
+        assert_eq!(tor_proxy_address("not a url"), None);
+    }
+}
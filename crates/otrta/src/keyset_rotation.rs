@@ -0,0 +1,123 @@
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+use crate::{
+    db::{
+        mint::{
+            create_mint_units, discover_mint_keysets, get_active_mints, get_mint_units,
+            select_active_keysets,
+        },
+        Pool,
+    },
+    error::AppError,
+    multimint_manager::MultimintManager,
+};
+
+#[derive(Debug, Default, Clone, serde::Serialize)]
+pub struct KeysetRotationSummary {
+    pub mints_checked: usize,
+    pub keysets_rotated: usize,
+    pub fees_updated: usize,
+    pub organizations_swapped: usize,
+    pub amount_swapped: u64,
+}
+
+/// Refreshes the stored keyset and input fee of every active mint, then swaps
+/// any proofs the organization wallets still hold under rotated-out keysets.
+pub async fn rotate_keysets(
+    db: &Pool,
+    multimint_manager: &MultimintManager,
+) -> Result<KeysetRotationSummary, AppError> {
+    let mut summary = KeysetRotationSummary::default();
+    let mut organizations = HashSet::new();
+
+    for mint in get_active_mints(db).await? {
+        summary.mints_checked += 1;
+
+        let keysets = match discover_mint_keysets(&mint.mint_url).await {
+            Ok(keysets) => keysets,
+            Err(e) => {
+                warn!("Failed to fetch keysets for mint {}: {}", mint.mint_url, e);
+                continue;
+            }
+        };
+
+        // discover_mint_keysets falls back to a placeholder when the mint
+        // returns nothing usable; never rotate onto it.
+        if keysets.iter().all(|k| k.id == "default") {
+            continue;
+        }
+
+        let stored_units = get_mint_units(db, mint.id).await?;
+        let active_keysets = select_active_keysets(&keysets);
+
+        let mut changed = Vec::new();
+        for stored in &stored_units {
+            let Some(active) = active_keysets.iter().find(|k| k.unit == stored.unit) else {
+                warn!(
+                    "Mint {} no longer has an active {} keyset",
+                    mint.mint_url, stored.unit
+                );
+                continue;
+            };
+
+            if active.id != stored.keyset_id {
+                info!(
+                    "Keyset rotated for mint {} ({}): {} -> {}",
+                    mint.mint_url, stored.unit, stored.keyset_id, active.id
+                );
+                summary.keysets_rotated += 1;
+                changed.push(active.clone());
+            } else if active.input_fee_ppk as i64 != stored.input_fee_ppk {
+                info!(
+                    "Input fee changed for mint {} ({}): {} -> {} ppk",
+                    mint.mint_url, stored.unit, stored.input_fee_ppk, active.input_fee_ppk
+                );
+                summary.fees_updated += 1;
+                changed.push(active.clone());
+            }
+        }
+
+        if !changed.is_empty() {
+            create_mint_units(db, mint.id, &changed).await?;
+        }
+
+        if keysets.iter().any(|k| !k.active) {
+            if let Some(org_id) = mint.organization_id {
+                organizations.insert(org_id);
+            }
+        }
+    }
+
+    for org_id in organizations {
+        let wallet = match multimint_manager.get_or_create_multimint(&org_id).await {
+            Ok(wallet) => wallet,
+            Err(e) => {
+                warn!("Failed to load wallet for organization {}: {}", org_id, e);
+                continue;
+            }
+        };
+
+        match wallet.swap_inactive_keyset_proofs().await {
+            Ok(swapped) if !swapped.is_empty() => {
+                for (mint_url, amount) in &swapped {
+                    info!(
+                        "Swapped {} from inactive keysets of mint {} for organization {}",
+                        amount, mint_url, org_id
+                    );
+                    summary.amount_swapped += amount;
+                }
+                summary.organizations_swapped += 1;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                warn!(
+                    "Failed to swap inactive keyset proofs for organization {}: {}",
+                    org_id, e
+                );
+            }
+        }
+    }
+
+    Ok(summary)
+}
@@ -0,0 +1,147 @@
+use std::{fmt::Display, future::Future, pin::pin, time::Duration};
+use tokio::time::{sleep, Instant};
+use tracing::{debug, error, warn};
+
+use crate::db::{
+    background_jobs::{record_lease_run, release_leases, renew_lease, try_acquire_lease},
+    Pool,
+};
+
+/// Consecutive failed renewals after which a job is stopped: by then the
+/// lease has likely expired and another instance may have started it.
+const MAX_RENEW_FAILURES: u32 = 2;
+
+/// Coordinates background jobs between instances sharing one database. Each
+/// job has its own lease, so different replicas may lead different jobs.
+pub struct JobLeases {
+    db: Pool,
+    instance_id: String,
+}
+
+/// Name this instance uses as lease holder: `INSTANCE_ID`, else the
+/// container's `HOSTNAME`, suffixed so restarts don't inherit stale leases.
+pub fn default_instance_id() -> String {
+    let base = std::env::var("INSTANCE_ID")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .unwrap_or_else(|_| "otrta".to_string());
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    format!("{}-{}", base, &suffix[..8])
+}
+
+/// Lease length for a job that runs every `interval`: long enough for the
+/// leader to miss one run before another instance takes over.
+pub fn lease_ttl(interval: Duration) -> Duration {
+    (interval * 2).max(Duration::from_secs(60))
+}
+
+impl JobLeases {
+    pub fn new(db: Pool, instance_id: String) -> Self {
+        Self { db, instance_id }
+    }
+
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    /// Runs `job` if this instance holds or can take the lease on `name`,
+    /// renewing it while the job runs. The job is cancelled with an error if
+    /// the lease is lost or can't be renewed, so two instances never run it
+    /// at once. Returns `None` when another instance is leader, or the lease
+    /// table can't be reached.
+    pub async fn run<F, T, E>(&self, name: &str, ttl: Duration, job: F) -> Option<Result<T, E>>
+    where
+        F: Future<Output = Result<T, E>>,
+        E: Display + From<String>,
+    {
+        match try_acquire_lease(&self.db, name, &self.instance_id, ttl).await {
+            Ok(true) => {}
+            Ok(false) => {
+                debug!("Skipping job {}: another instance holds the lease", name);
+                return None;
+            }
+            Err(e) => {
+                error!("Failed to acquire lease for job {}: {}", name, e);
+                return None;
+            }
+        }
+
+        let started = Instant::now();
+        let mut job = pin!(job);
+        let mut renew_failures = 0;
+        let result = loop {
+            tokio::select! {
+                result = &mut job => break result,
+                _ = sleep(ttl / 3) => {
+                    match renew_lease(&self.db, name, &self.instance_id, ttl).await {
+                        Ok(true) => renew_failures = 0,
+                        Ok(false) => {
+                            warn!("Lost lease for job {} while it was running; stopping it", name);
+                            break Err(E::from("lost the job lease".to_string()));
+                        }
+                        Err(e) => {
+                            error!("Failed to renew lease for job {}: {}", name, e);
+                            renew_failures += 1;
+                            if renew_failures >= MAX_RENEW_FAILURES {
+                                break Err(E::from(format!("could not renew the job lease: {}", e)));
+                            }
+                        }
+                    }
+                }
+            }
+        };
+
+        let error = result.as_ref().err().map(|e| e.to_string());
+        if let Err(e) = record_lease_run(
+            &self.db,
+            name,
+            &self.instance_id,
+            ttl,
+            error.as_deref(),
+            started.elapsed(),
+        )
+        .await
+        {
+            error!("Failed to record run of job {}: {}", name, e);
+        }
+
+        Some(result)
+    }
+
+    /// Hands every lease back at shutdown.
+    pub async fn release_all(&self) {
+        match release_leases(&self.db, &self.instance_id).await {
+            Ok(released) => debug!("Released {} job lease(s)", released),
+            Err(e) => error!("Failed to release job leases: {}", e),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod sqlite_tests {
+    use super::*;
+    use crate::db::test_pool;
+
+    #[tokio::test]
+    async fn test_stops_the_job_when_the_lease_is_lost() {
+        let db = test_pool().await;
+        let leases = JobLeases::new(db.clone(), "first".to_string());
+
+        let job = async {
+            // Another instance takes over while the job is running
+            sqlx::query("UPDATE background_jobs SET holder = 'second' WHERE job_name = 'job'")
+                .execute(&db)
+                .await
+                .unwrap();
+            sleep(Duration::from_secs(60)).await;
+            Ok::<(), String>(())
+        };
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(10),
+            leases.run("job", Duration::from_millis(300), job),
+        )
+        .await
+        .expect("job kept running without its lease");
+        assert!(result.unwrap().is_err());
+    }
+}
@@ -1,14 +1,31 @@
+pub mod audit;
 pub mod auth;
 pub mod auto_refill_service;
 pub mod completion;
 pub mod db;
 pub mod error;
+pub mod exchange_rate;
+pub mod forwarded;
 pub mod handlers;
+pub mod health;
+pub mod keyset_rotation;
+pub mod leader;
+pub mod metrics;
 pub mod models;
 pub mod multimint;
 pub mod multimint_manager;
+pub mod nip46;
+pub mod nip98;
 pub mod nwc_client;
 pub mod onion;
+pub mod payment_recovery;
+pub mod permissions;
 pub mod proxy;
+pub mod reconciliation;
+pub mod request_id;
+pub mod retention;
+pub mod scheduler;
 pub mod search;
+pub mod sessions;
+pub mod shutdown;
 pub mod wallet;
@@ -0,0 +1,210 @@
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use std::{
+    collections::HashMap,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use crate::multimint_manager::MultimintManager;
+
+// Upstream calls over Tor routinely take tens of seconds.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0,
+];
+
+const JOB_DURATION_BUCKETS: &[f64] = &[0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0, 900.0];
+
+pub struct Metrics {
+    registry: Registry,
+    pub proxy_requests: IntCounterVec,
+    pub upstream_latency: HistogramVec,
+    pub payment_failures: IntCounterVec,
+    pub wallet_balance: IntGaugeVec,
+    pub auto_refill_attempts: IntCounterVec,
+    pub job_duration: HistogramVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry =
+            Registry::new_custom(Some("otrta".to_string()), None).expect("valid metrics registry");
+
+        let proxy_requests = IntCounterVec::new(
+            Opts::new(
+                "proxy_requests_total",
+                "Proxied requests by model, provider and upstream status",
+            ),
+            &["model", "provider", "status"],
+        )
+        .expect("valid metric");
+        let upstream_latency = HistogramVec::new(
+            HistogramOpts::new(
+                "upstream_request_duration_seconds",
+                "Time until the provider answered, split by clearnet and onion",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+            &["network", "context"],
+        )
+        .expect("valid metric");
+        let payment_failures = IntCounterVec::new(
+            Opts::new(
+                "payment_failures_total",
+                "Failed attempts to create a payment token, by mint",
+            ),
+            &["mint"],
+        )
+        .expect("valid metric");
+        let wallet_balance = IntGaugeVec::new(
+            Opts::new(
+                "wallet_balance",
+                "Balance held across organization wallets, by mint and unit",
+            ),
+            &["mint", "unit"],
+        )
+        .expect("valid metric");
+        let auto_refill_attempts = IntCounterVec::new(
+            Opts::new(
+                "auto_refill_attempts_total",
+                "Auto-refill attempts by mint and result",
+            ),
+            &["mint", "result"],
+        )
+        .expect("valid metric");
+        let job_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "background_job_duration_seconds",
+                "Duration of background job runs",
+            )
+            .buckets(JOB_DURATION_BUCKETS.to_vec()),
+            &["job", "result"],
+        )
+        .expect("valid metric");
+
+        for collector in [
+            Box::new(proxy_requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(upstream_latency.clone()),
+            Box::new(payment_failures.clone()),
+            Box::new(wallet_balance.clone()),
+            Box::new(auto_refill_attempts.clone()),
+            Box::new(job_duration.clone()),
+        ] {
+            registry.register(collector).expect("unique metric names");
+        }
+
+        Self {
+            registry,
+            proxy_requests,
+            upstream_latency,
+            payment_failures,
+            wallet_balance,
+            auto_refill_attempts,
+            job_duration,
+        }
+    }
+
+    /// Renders every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        String::from_utf8(buffer).map_err(|e| prometheus::Error::Msg(e.to_string()))
+    }
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+/// Label for requests whose model the provider doesn't list. Model names
+/// come from the request body, so passing them through would let clients
+/// create any number of series.
+const OTHER_MODEL: &str = "other";
+
+/// Counts a proxied request. `model` must be a model the provider lists,
+/// `None` for anything else.
+pub fn record_proxy_request(model: Option<&str>, provider: &str, status: &str) {
+    metrics()
+        .proxy_requests
+        .with_label_values(&[model.unwrap_or(OTHER_MODEL), provider, status])
+        .inc();
+}
+
+pub fn record_upstream_latency(onion: bool, context: &str, duration: Duration) {
+    let network = if onion { "onion" } else { "clearnet" };
+    metrics()
+        .upstream_latency
+        .with_label_values(&[network, context])
+        .observe(duration.as_secs_f64());
+}
+
+pub fn record_payment_failure(mint_url: &str) {
+    metrics()
+        .payment_failures
+        .with_label_values(&[mint_url])
+        .inc();
+}
+
+pub fn record_auto_refill_attempt(mint_url: &str, success: bool) {
+    let result = if success { "success" } else { "failure" };
+    metrics()
+        .auto_refill_attempts
+        .with_label_values(&[mint_url, result])
+        .inc();
+}
+
+pub fn record_job_run(job: &str, started: Instant, success: bool) {
+    let result = if success { "success" } else { "failure" };
+    metrics()
+        .job_duration
+        .with_label_values(&[job, result])
+        .observe(started.elapsed().as_secs_f64());
+}
+
+/// Refreshes the wallet balance gauges from the wallets that are currently
+/// loaded. Wallets are never opened just to be measured.
+pub async fn update_wallet_balances(multimint_manager: &MultimintManager) {
+    let mut totals: HashMap<(String, String), i64> = HashMap::new();
+    for wallet in multimint_manager.cached_multimints() {
+        let Ok(balance) = wallet.get_total_balance().await else {
+            continue;
+        };
+        for mint in balance.balances_by_mint {
+            *totals
+                .entry((mint.mint_url, mint.unit.to_string()))
+                .or_insert(0) += mint.balance as i64;
+        }
+    }
+
+    let gauge = &metrics().wallet_balance;
+    gauge.reset();
+    for ((mint_url, unit), balance) in totals {
+        gauge.with_label_values(&[&mint_url, &unit]).set(balance);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_includes_recorded_metrics() {
+        record_proxy_request(Some("gpt-4o"), "https://provider.test", "200");
+        record_proxy_request(None, "https://provider.test", "200");
+        record_upstream_latency(true, "proxy", Duration::from_millis(1500));
+
+        let output = metrics().render().unwrap();
+
+        assert!(output.contains(
+            "otrta_proxy_requests_total{model=\"gpt-4o\",provider=\"https://provider.test\",status=\"200\"} 1"
+        ));
+        assert!(output.contains(
+            "otrta_proxy_requests_total{model=\"other\",provider=\"https://provider.test\",status=\"200\"} 1"
+        ));
+        assert!(output.contains(
+            "otrta_upstream_request_duration_seconds_bucket{context=\"proxy\",network=\"onion\",le=\"2.5\"} 1"
+        ));
+    }
+}
@@ -1,9 +1,17 @@
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Instant;
 
 use crate::db::mint::CurrencyUnit;
+use crate::exchange_rate::ExchangeRateService;
+use crate::forwarded::TrustedProxies;
+use crate::health::ProviderProbes;
+use crate::leader::JobLeases;
 use crate::multimint_manager::MultimintManager;
+use crate::nip46::{AttemptLimiter, PendingConnects};
+use crate::permissions::Role;
+use crate::sessions::SessionTokens;
+use crate::shutdown::InFlightRequests;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -27,10 +35,65 @@ pub struct TokenRedeemResponse {
 
 #[derive(Clone)]
 pub struct AppState {
-    pub db: sqlx::PgPool,
+    pub db: crate::db::Pool,
     pub default_msats_per_request: u32,
     pub multimint_manager: Arc<MultimintManager>,
     pub search_cache: Arc<Mutex<HashMap<String, Instant>>>,
+    pub exchange_rates: Arc<ExchangeRateService>,
+    /// When set, full Cashu tokens are kept on their transactions for this
+    /// long before being scrubbed. Off by default.
+    pub debug_token_retention: Option<std::time::Duration>,
+    /// Bearer token required to scrape `/metrics`. Open when unset.
+    pub metrics_token: Option<String>,
+    /// Newest migration bundled with the binary, compared against the
+    /// database by `/readyz`.
+    pub latest_migration: Option<i64>,
+    /// Proxied requests that shutdown waits for before exiting.
+    pub in_flight: Arc<InFlightRequests>,
+    /// Decides which instance runs each background job.
+    pub job_leases: Arc<JobLeases>,
+    /// Settings swapped in when the configuration is reloaded.
+    pub runtime_config: Arc<RwLock<RuntimeConfig>>,
+    /// Signs the session tokens handed out at login.
+    pub session_tokens: Arc<SessionTokens>,
+    /// Remote signers waiting to connect through a handed-out
+    /// `nostrconnect://` URI.
+    pub nostr_connects: Arc<PendingConnects>,
+    /// NIP-46 logins and Nostr Connect URIs recently started per client.
+    pub nip46_attempts: Arc<AttemptLimiter>,
+    /// Default provider reachability, refreshed in the background for
+    /// `/readyz`.
+    pub provider_probes: Arc<ProviderProbes>,
+}
+
+/// The part of the configuration that can change without a restart.
+#[derive(Clone, Debug)]
+pub struct RuntimeConfig {
+    /// Npubs granted admin rights.
+    pub whitelisted_npubs: Vec<String>,
+    /// Relays queried for provider announcements.
+    pub nostr_relays: Vec<String>,
+    /// Reverse proxies whose `X-Forwarded-*` headers are believed.
+    pub trusted_proxies: TrustedProxies,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            whitelisted_npubs: Vec::new(),
+            nostr_relays: otrta_nostr::DEFAULT_RELAYS
+                .iter()
+                .map(|relay| relay.to_string())
+                .collect(),
+            trusted_proxies: TrustedProxies::default(),
+        }
+    }
+}
+
+impl AppState {
+    pub fn runtime_config(&self) -> RuntimeConfig {
+        self.runtime_config.read().unwrap().clone()
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -415,6 +478,8 @@ pub struct UserContext {
     pub organization_id: Uuid,
     pub organization: Organization,
     pub is_admin: bool,
+    /// Role in `organization`, which decides what the user may do there.
+    pub role: Role,
 }
 
 impl UserContext {
@@ -424,6 +489,7 @@ impl UserContext {
             organization_id: organization.id,
             organization,
             is_admin: false,
+            role: Role::Viewer,
         }
     }
 
@@ -433,8 +499,14 @@ impl UserContext {
             organization_id: organization.id,
             organization,
             is_admin,
+            role: Role::Viewer,
         }
     }
+
+    pub fn with_role(mut self, role: Role) -> Self {
+        self.role = role;
+        self
+    }
 }
 
 pub fn normalize_model_name(model_name: &str) -> String {
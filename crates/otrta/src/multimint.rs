@@ -1,19 +1,15 @@
 use crate::db::mint::CurrencyUnit;
-use crate::db::transaction::{add_transaction, TransactionDirection, TransactionType};
+use crate::db::transaction::{
+    add_transaction, TransactionContext, TransactionDirection, TransactionType,
+};
 use crate::db::Pool;
 use cdk::amount::SplitTarget;
 use cdk::nuts::nut23::QuoteState;
 use cdk::{wallet::SendOptions, Amount};
 use ecash_402_wallet::multimint::{MultimintSendOptions, MultimintWallet};
 use serde::{Deserialize, Serialize};
-
-fn convert_currency_unit_from_cdk(unit: cdk::nuts::CurrencyUnit) -> CurrencyUnit {
-    match unit {
-        cdk::nuts::CurrencyUnit::Sat => CurrencyUnit::Sat,
-        cdk::nuts::CurrencyUnit::Msat => CurrencyUnit::Msat,
-        _ => CurrencyUnit::Msat,
-    }
-}
+use std::str::FromStr;
+use tracing::{debug, warn};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocalMultimintBalance {
@@ -102,7 +98,7 @@ impl MultimintWalletWrapper {
             .map(|mint_balance| LocalMintBalance {
                 mint_url: mint_balance.mint_url,
                 balance: mint_balance.balance,
-                unit: convert_currency_unit_from_cdk(mint_balance.unit),
+                unit: mint_balance.unit.into(),
                 proof_count: mint_balance.proof_count,
             })
             .collect();
@@ -154,49 +150,36 @@ impl MultimintWalletWrapper {
 
         for mint_with_units in mints_with_units {
             let mut unit_balances = Vec::new();
+            let mut mint_balance = 0u64;
+
+            for unit in mint_with_units.supported_units.iter().filter(|u| u.active) {
+                let currency_unit = unit.unit.parse::<CurrencyUnit>().ok();
+                let balance = match &currency_unit {
+                    Some(currency_unit) => match self
+                        .get_wallet_for_mint_unit(&mint_with_units.mint.mint_url, currency_unit)
+                        .await
+                    {
+                        Some(wallet) => wallet.balance().await.unwrap_or(0),
+                        None => 0,
+                    },
+                    None => 0,
+                };
+
+                unit_balances.push(MintUnitBalance {
+                    mint_id: mint_with_units.mint.id,
+                    mint_url: mint_with_units.mint.mint_url.clone(),
+                    mint_name: mint_with_units.mint.name.clone(),
+                    unit: unit.unit.clone(),
+                    balance,
+                    proof_count: 0, // TODO: Get actual proof count from wallet
+                });
 
-            // Get the total balance for this mint from the wallet
-            let (mint_balance, proof_count) = match self
-                .get_wallet_for_mint(&mint_with_units.mint.mint_url)
-                .await
-            {
-                Some(wallet) => {
-                    let balance = wallet.balance().await.unwrap_or(0);
-                    (balance, 0) // TODO: Get actual proof count from wallet
-                }
-                None => (0, 0),
-            };
-
-            // For now, assign the full mint balance to the primary unit
-            // In the future, we can enhance this to track separate balances per unit
-            if let Some(primary_unit) = mint_with_units.supported_units.first() {
-                if primary_unit.active {
-                    unit_balances.push(MintUnitBalance {
-                        mint_id: mint_with_units.mint.id,
-                        mint_url: mint_with_units.mint.mint_url.clone(),
-                        mint_name: mint_with_units.mint.name.clone(),
-                        unit: primary_unit.unit.clone(),
-                        balance: mint_balance,
-                        proof_count,
-                    });
-
-                    *balances_by_unit
-                        .entry(primary_unit.unit.clone())
-                        .or_insert(0) += mint_balance;
-                }
+                *balances_by_unit.entry(unit.unit.clone()).or_insert(0) += balance;
 
-                // Add zero balances for other supported units to show they exist
-                for unit in mint_with_units.supported_units.iter().skip(1) {
-                    if unit.active {
-                        unit_balances.push(MintUnitBalance {
-                            mint_id: mint_with_units.mint.id,
-                            mint_url: mint_with_units.mint.mint_url.clone(),
-                            mint_name: mint_with_units.mint.name.clone(),
-                            unit: unit.unit.clone(),
-                            balance: 0, // For now, only primary unit has balance
-                            proof_count: 0,
-                        });
-                    }
+                // Fiat balances are reported per unit only; adding cents to
+                // sats would make the mint total meaningless.
+                if currency_unit.is_some_and(|u| u.is_bitcoin()) {
+                    mint_balance += balance;
                 }
             }
 
@@ -205,13 +188,8 @@ impl MultimintWalletWrapper {
                 let primary_unit = &unit_balances[0];
                 legacy_balances_by_mint.push(LocalMintBalance {
                     mint_url: mint_with_units.mint.mint_url.clone(),
-                    balance: mint_balance,
-                    unit: convert_currency_unit_from_cdk(
-                        primary_unit
-                            .unit
-                            .parse()
-                            .unwrap_or(cdk::nuts::CurrencyUnit::Msat),
-                    ),
+                    balance: primary_unit.balance,
+                    unit: primary_unit.unit.parse().unwrap_or(CurrencyUnit::Msat),
                     proof_count: primary_unit.proof_count,
                 });
 
@@ -240,34 +218,21 @@ impl MultimintWalletWrapper {
         amount: u64,
         options: LocalMultimintSendOptions,
         db: &Pool,
-        api_key_id: Option<&str>,
-        user_id: Option<&str>,
-        provider_url: Option<&str>,
-        unit: Option<&str>,
-        model: Option<&str>,
+        ctx: &TransactionContext<'_>,
     ) -> Result<String, Box<dyn std::error::Error>> {
-        let send_options = MultimintSendOptions {
-            preferred_mint: options.preferred_mint,
-            ..Default::default()
-        };
-
-        let token = self.inner.send(amount, send_options).await?;
+        let token = self.send_simple(amount, options).await?;
 
         add_transaction(
             db,
             &token,
             &amount.to_string(),
             TransactionDirection::Outgoing,
-            api_key_id,
-            user_id,
-            if user_id.is_some() {
+            if ctx.user_id.is_some() {
                 TransactionType::Chat
             } else {
                 TransactionType::Api
             },
-            provider_url,
-            unit,
-            model,
+            ctx,
         )
         .await?;
 
@@ -299,18 +264,88 @@ impl MultimintWalletWrapper {
             .map(CdkWalletWrapper::new)
     }
 
+    pub async fn get_wallet_for_mint_unit(
+        &self,
+        mint_url: &str,
+        unit: &CurrencyUnit,
+    ) -> Option<CdkWalletWrapper> {
+        let mint_url = mint_url.parse::<cdk::mint_url::MintUrl>().ok()?;
+        let wallet_key = cdk::wallet::types::WalletKey::new(mint_url, unit.to_cdk());
+        self.inner
+            .cdk_wallet()
+            .get_wallet(&wallet_key)
+            .await
+            .map(CdkWalletWrapper::new)
+    }
+
     pub async fn get_wallet_for_mint_with_token(
         &self,
         mint_url: &str,
         token: &str,
     ) -> Option<CdkWalletWrapper> {
-        println!("{:?}", token);
+        debug!("Resolving wallet for token from mint {}", mint_url);
         self.inner
             .get_wallet_for_mint_with_token(mint_url, token)
             .await
             .map(CdkWalletWrapper::new)
     }
 
+    /// Checks with the token's mint whether it has already been redeemed.
+    pub async fn is_token_spent(&self, token: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let mint_url = cdk::nuts::Token::from_str(token)?.mint_url()?.to_string();
+        let wallet = self
+            .get_wallet_for_mint_with_token(&mint_url, token)
+            .await
+            .ok_or_else(|| format!("No wallet for mint {}", mint_url))?;
+        wallet.is_token_spent(token).await
+    }
+
+    /// Swaps proofs held under keysets the mint has rotated out into its
+    /// current active keyset. Returns the amount moved per mint URL.
+    pub async fn swap_inactive_keyset_proofs(
+        &self,
+    ) -> Result<Vec<(String, u64)>, Box<dyn std::error::Error>> {
+        let mut swapped = Vec::new();
+
+        for wallet in self.inner.cdk_wallet().get_wallets().await {
+            let keysets = wallet.get_mint_keysets().await?;
+            let inactive: std::collections::HashSet<_> = keysets
+                .iter()
+                .filter(|k| !k.active && k.unit == wallet.unit)
+                .map(|k| k.id)
+                .collect();
+            if inactive.is_empty() {
+                continue;
+            }
+
+            let proofs: cdk::nuts::Proofs = wallet
+                .get_unspent_proofs()
+                .await?
+                .into_iter()
+                .filter(|p| inactive.contains(&p.keyset_id))
+                .collect();
+            if proofs.is_empty() {
+                continue;
+            }
+
+            let total: u64 = proofs.iter().map(|p| u64::from(p.amount)).sum();
+            let fee: u64 = wallet.get_proofs_fee(&proofs).await?.into();
+            if total <= fee {
+                continue;
+            }
+
+            // Loads keys for the new active keyset before swapping into it
+            wallet.get_active_mint_keyset().await?;
+            wallet
+                .swap(None, SplitTarget::default(), proofs, None, false)
+                .await?;
+
+            swapped.push((wallet.mint_url.to_string(), total - fee));
+        }
+
+        Ok(swapped)
+    }
+
     pub async fn redeem_pendings(&self) -> Result<(), Box<dyn std::error::Error>> {
         self.inner.redeem_pendings().await?;
         Ok(())
@@ -326,6 +361,18 @@ impl MultimintWalletWrapper {
         amount: u64,
         options: LocalMultimintSendOptions,
     ) -> Result<String, Box<dyn std::error::Error>> {
+        // The inner multimint only resolves sat/msat wallets, so tokens in
+        // any other unit are sent from the matching cdk wallet directly.
+        if let (Some(mint_url), Some(unit)) = (&options.preferred_mint, &options.unit) {
+            if !unit.is_bitcoin() {
+                let wallet = self
+                    .get_wallet_for_mint_unit(mint_url, unit)
+                    .await
+                    .ok_or_else(|| format!("No {} wallet for mint {}", unit, mint_url))?;
+                return wallet.send(amount).await;
+            }
+        }
+
         let send_options = MultimintSendOptions {
             preferred_mint: options.preferred_mint,
             ..Default::default()
@@ -378,6 +425,19 @@ impl CdkWalletWrapper {
         Ok(amount)
     }
 
+    /// Asks the mint (NUT-07) whether every proof in `token` has been spent.
+    pub async fn is_token_spent(&self, token: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        use cdk::nuts::{State, Token};
+
+        let token = Token::from_str(token)?;
+        let keysets = self.inner.get_mint_keysets().await?;
+        let states = self
+            .inner
+            .check_proofs_spent(token.proofs(&keysets)?)
+            .await?;
+        Ok(!states.is_empty() && states.iter().all(|s| s.state == State::Spent))
+    }
+
     pub async fn send(&self, amount: u64) -> Result<String, Box<dyn std::error::Error>> {
         let amount_obj = Amount::from(amount);
         let prepared_send = self
@@ -433,7 +493,7 @@ impl CdkWalletWrapper {
         {
             Ok(_resp) => Ok(QuoteState::Paid),
             Err(e) => {
-                println!("{:?}", e);
+                warn!("Minting quote {} failed: {:?}", quote_id, e);
                 Ok(QuoteState::Pending)
             }
         }
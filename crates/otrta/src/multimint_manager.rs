@@ -11,11 +11,11 @@ use crate::multimint::MultimintWalletWrapper;
 pub struct MultimintManager {
     instances: DashMap<Uuid, Arc<MultimintWalletWrapper>>,
     base_db_path: String,
-    db_pool: sqlx::PgPool,
+    db_pool: crate::db::Pool,
 }
 
 impl MultimintManager {
-    pub fn new(base_db_path: String, db_pool: sqlx::PgPool) -> Self {
+    pub fn new(base_db_path: String, db_pool: crate::db::Pool) -> Self {
         Self {
             instances: DashMap::new(),
             base_db_path,
@@ -23,6 +23,26 @@ impl MultimintManager {
         }
     }
 
+    /// Path of the organization's wallet database, without the `.sqlite`
+    /// extension the wallet adds.
+    fn wallet_base_path(&self, org_id: &Uuid) -> String {
+        format!("{}{}", self.base_db_path, org_id)
+    }
+
+    pub fn wallet_db_path(&self, org_id: &Uuid) -> String {
+        format!("{}.sqlite", self.wallet_base_path(org_id))
+    }
+
+    /// Whether the organization's wallet has been created on disk.
+    pub fn has_wallet(&self, org_id: &Uuid) -> bool {
+        std::path::Path::new(&self.wallet_db_path(org_id)).exists()
+    }
+
+    /// Whether the organization's wallet is loaded in this process.
+    pub fn is_loaded(&self, org_id: &Uuid) -> bool {
+        self.instances.contains_key(org_id)
+    }
+
     pub async fn get_or_create_multimint(
         &self,
         org_id: &Uuid,
@@ -47,7 +67,7 @@ impl MultimintManager {
                 AppError::NotFound
             })?;
 
-        let db_path = format!("{}{}", self.base_db_path, org_id);
+        let db_path = self.wallet_base_path(org_id);
 
         use crate::db::server_config::{
             create_with_seed_for_organization, get_config_by_organization,
@@ -89,8 +109,7 @@ impl MultimintManager {
         };
 
         // Check if wallet files already exist (preserving existing balances and mints)
-        let wallet_db_path = format!("{}.sqlite", &db_path);
-        let wallet_exists = std::path::Path::new(&wallet_db_path).exists();
+        let wallet_exists = self.has_wallet(org_id);
 
         let wallet = if wallet_exists {
             info!(
@@ -181,6 +200,14 @@ impl MultimintManager {
         self.instances.get(org_id).map(|v| v.clone())
     }
 
+    /// Wallets of every organization loaded so far.
+    pub fn cached_multimints(&self) -> Vec<Arc<MultimintWalletWrapper>> {
+        self.instances
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
     pub async fn preload_multimint(&self, org_id: &Uuid) -> Result<(), AppError> {
         if self.get_cached_multimint(org_id).await.is_none() {
             self.get_or_create_multimint(org_id).await?;
@@ -0,0 +1,769 @@
+//! NIP-46 remote signing ("Nostr Connect") for users whose keys live in a
+//! bunker rather than a browser extension. The server acts as the client: it
+//! reaches the signer over relays with a throwaway key, learns the user's
+//! public key and has the signer sign a login challenge.
+
+use nostr::nips::nip46::{NostrConnectMessage, NostrConnectRequest, NostrConnectURI};
+use nostr::nips::{nip04, nip44};
+use nostr::{
+    Event, EventBuilder, Filter, JsonUtil, Keys, Kind, PublicKey, RelayUrl, Tag, Timestamp,
+};
+use nostr_sdk::{Client, RelayPoolNotification};
+use rand::RngCore;
+use std::{
+    collections::HashMap,
+    fmt,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{info, warn};
+
+/// How long to wait for each answer from the signer, which may first ask its
+/// user to approve the request.
+pub const SIGNER_TIMEOUT: Duration = Duration::from_secs(60);
+/// Nostr Connect URIs that no signer has been connected through are dropped
+/// after this long.
+pub const PENDING_CONNECT_TTL: Duration = Duration::from_secs(600);
+const MAX_PENDING_CONNECTS: usize = 256;
+const MAX_PENDING_CONNECTS_PER_CLIENT: usize = 4;
+/// Each client address may start this many logins or Nostr Connect URIs per
+/// window, since every one opens relay connections.
+const MAX_ATTEMPTS_PER_WINDOW: u32 = 10;
+const ATTEMPT_WINDOW: Duration = Duration::from_secs(60);
+/// Caps the relay connections a single login makes.
+pub const MAX_RELAYS: usize = 5;
+const RELAY_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub enum Nip46Error {
+    InvalidUri(String),
+    Relay(String),
+    /// The signer did not answer in time. Some signers first answer with a
+    /// URL where the user has to approve the request.
+    Timeout {
+        auth_url: Option<String>,
+    },
+    Rejected(String),
+    InvalidResponse(String),
+    TooManyRequests(String),
+}
+
+impl fmt::Display for Nip46Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Nip46Error::InvalidUri(e) => write!(f, "Invalid Nostr Connect URI: {}", e),
+            Nip46Error::Relay(e) => write!(f, "Relay error: {}", e),
+            Nip46Error::Timeout {
+                auth_url: Some(url),
+            } => write!(
+                f,
+                "Remote signer did not answer; approve the request at {}",
+                url
+            ),
+            Nip46Error::Timeout { auth_url: None } => write!(f, "Remote signer did not answer"),
+            Nip46Error::Rejected(e) => write!(f, "Remote signer rejected the request: {}", e),
+            Nip46Error::InvalidResponse(e) => write!(f, "Invalid remote signer response: {}", e),
+            Nip46Error::TooManyRequests(e) => write!(f, "Too many requests: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Nip46Error {}
+
+/// A connection to a remote signer through its relays.
+pub struct RemoteSigner {
+    client: Client,
+    keys: Keys,
+    /// Unknown until a signer answers a Nostr Connect URI.
+    signer: Option<PublicKey>,
+    /// Expected back from a signer connecting through a Nostr Connect URI.
+    connect_secret: Option<String>,
+    notifications: broadcast::Receiver<RelayPoolNotification>,
+    timeout: Duration,
+}
+
+impl RemoteSigner {
+    async fn open(relays: &[RelayUrl], timeout: Duration) -> Result<Self, Nip46Error> {
+        if relays.is_empty() {
+            return Err(Nip46Error::InvalidUri("no relay given".to_string()));
+        }
+        if relays.len() > MAX_RELAYS {
+            return Err(Nip46Error::InvalidUri(format!(
+                "at most {} relays are supported",
+                MAX_RELAYS
+            )));
+        }
+
+        let keys = Keys::generate();
+        let client = Client::default();
+        for relay in relays {
+            client
+                .add_relay(relay.clone())
+                .await
+                .map_err(|e| Nip46Error::Relay(e.to_string()))?;
+        }
+        client.connect().await;
+        client.wait_for_connection(RELAY_CONNECT_TIMEOUT).await;
+        if !client
+            .relays()
+            .await
+            .values()
+            .any(|relay| relay.is_connected())
+        {
+            client.shutdown().await;
+            return Err(Nip46Error::Relay(
+                "could not connect to any relay".to_string(),
+            ));
+        }
+
+        // Subscribed before anything is sent: Nostr Connect events are
+        // ephemeral, so relays don't keep them for late subscribers.
+        let notifications = client.notifications();
+        let filter = Filter::new()
+            .kind(Kind::NostrConnect)
+            .pubkey(keys.public_key())
+            .since(Timestamp::now());
+        if let Err(e) = client.subscribe(filter, None).await {
+            client.shutdown().await;
+            return Err(Nip46Error::Relay(e.to_string()));
+        }
+
+        Ok(Self {
+            client,
+            keys,
+            signer: None,
+            connect_secret: None,
+            notifications,
+            timeout,
+        })
+    }
+
+    /// Connects to the signer named by a `bunker://` URI.
+    pub async fn bunker(uri: &NostrConnectURI, timeout: Duration) -> Result<Self, Nip46Error> {
+        let NostrConnectURI::Bunker {
+            remote_signer_public_key,
+            relays,
+            secret,
+        } = uri
+        else {
+            return Err(Nip46Error::InvalidUri(
+                "expected a bunker:// URI".to_string(),
+            ));
+        };
+
+        let mut signer = Self::open(relays, timeout).await?;
+        signer.signer = Some(*remote_signer_public_key);
+        let result = signer
+            .call(NostrConnectRequest::Connect {
+                public_key: *remote_signer_public_key,
+                secret: secret.clone(),
+            })
+            .await;
+
+        match result {
+            // Signers answer with "ack" or echo the secret.
+            Ok(result) if result == "ack" || Some(result.as_str()) == secret.as_deref() => {
+                Ok(signer)
+            }
+            Ok(result) => {
+                signer.shutdown().await;
+                Err(Nip46Error::InvalidResponse(format!(
+                    "unexpected connect result {:?}",
+                    result
+                )))
+            }
+            Err(e) => {
+                signer.shutdown().await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Starts listening on `relays` for a signer and returns the
+    /// `nostrconnect://` URI the user hands to it.
+    pub async fn nostr_connect(
+        relays: &[RelayUrl],
+        app_name: &str,
+        timeout: Duration,
+    ) -> Result<(Self, String), Nip46Error> {
+        let mut signer = Self::open(relays, timeout).await?;
+
+        let mut secret = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut secret);
+        let secret = hex::encode(secret);
+
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        for relay in relays {
+            query.append_pair("relay", relay.as_str());
+        }
+        query.append_pair("secret", &secret);
+        query.append_pair("name", app_name);
+        // Older signers read the app name from here.
+        query.append_pair(
+            "metadata",
+            &serde_json::json!({ "name": app_name }).to_string(),
+        );
+        let uri = format!(
+            "nostrconnect://{}?{}",
+            signer.keys.public_key().to_hex(),
+            query.finish()
+        );
+
+        signer.connect_secret = Some(secret);
+        Ok((signer, uri))
+    }
+
+    /// Waits for a signer to connect through the Nostr Connect URI. Only a
+    /// signer that read the URI knows its secret, so a bare "ack" from
+    /// whoever else is watching the relay is ignored.
+    pub async fn wait_for_connect(&mut self) -> Result<(), Nip46Error> {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let (author, message) = self.next_message(deadline).await?;
+            if let NostrConnectMessage::Response {
+                result: Some(result),
+                ..
+            } = message
+            {
+                if Some(&result) == self.connect_secret.as_ref() {
+                    info!("Remote signer {} connected", author);
+                    self.signer = Some(author);
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Public key of the client side, which a `nostrconnect://` URI names.
+    pub fn client_public_key(&self) -> PublicKey {
+        self.keys.public_key()
+    }
+
+    pub async fn get_public_key(&mut self) -> Result<PublicKey, Nip46Error> {
+        let result = self.call(NostrConnectRequest::GetPublicKey).await?;
+        PublicKey::from_hex(&result).map_err(|e| Nip46Error::InvalidResponse(e.to_string()))
+    }
+
+    pub async fn sign_event(
+        &mut self,
+        unsigned: nostr::UnsignedEvent,
+    ) -> Result<Event, Nip46Error> {
+        let result = self.call(NostrConnectRequest::SignEvent(unsigned)).await?;
+        Event::from_json(result).map_err(|e| Nip46Error::InvalidResponse(e.to_string()))
+    }
+
+    pub async fn shutdown(self) {
+        self.client.shutdown().await;
+    }
+
+    /// Sends a request and returns the raw result.
+    async fn call(&mut self, request: NostrConnectRequest) -> Result<String, Nip46Error> {
+        let signer = self
+            .signer
+            .ok_or_else(|| Nip46Error::InvalidResponse("no signer connected".to_string()))?;
+        let message = NostrConnectMessage::request(&request);
+        let content = nip44::encrypt(
+            self.keys.secret_key(),
+            &signer,
+            message.as_json(),
+            nip44::Version::V2,
+        )
+        .map_err(|e| Nip46Error::InvalidResponse(e.to_string()))?;
+        let event = EventBuilder::new(Kind::NostrConnect, content)
+            .tag(Tag::public_key(signer))
+            .sign_with_keys(&self.keys)
+            .map_err(|e| Nip46Error::InvalidResponse(e.to_string()))?;
+        self.client
+            .send_event(&event)
+            .await
+            .map_err(|e| Nip46Error::Relay(e.to_string()))?;
+
+        let deadline = Instant::now() + self.timeout;
+        let mut auth_url = None;
+        loop {
+            let reply = match self.next_message(deadline).await {
+                Ok((_, reply)) => reply,
+                Err(Nip46Error::Timeout { .. }) => return Err(Nip46Error::Timeout { auth_url }),
+                Err(e) => return Err(e),
+            };
+            let NostrConnectMessage::Response { id, result, error } = reply else {
+                continue;
+            };
+            if id != message.id() {
+                continue;
+            }
+
+            if result.as_deref() == Some("auth_url") {
+                info!("Remote signer asks for approval at {:?}", error);
+                auth_url = error;
+                continue;
+            }
+            if let Some(error) = error.filter(|error| !error.is_empty()) {
+                return Err(Nip46Error::Rejected(error));
+            }
+            return result.ok_or_else(|| Nip46Error::InvalidResponse("empty result".to_string()));
+        }
+    }
+
+    /// Next Nostr Connect message addressed to us, with its author.
+    async fn next_message(
+        &mut self,
+        deadline: Instant,
+    ) -> Result<(PublicKey, NostrConnectMessage), Nip46Error> {
+        loop {
+            let notification =
+                match tokio::time::timeout_at(deadline.into(), self.notifications.recv()).await {
+                    Err(_) => return Err(Nip46Error::Timeout { auth_url: None }),
+                    Ok(Err(RecvError::Lagged(_))) => continue,
+                    Ok(Err(RecvError::Closed)) => {
+                        return Err(Nip46Error::Relay("relay connections closed".to_string()))
+                    }
+                    Ok(Ok(notification)) => notification,
+                };
+
+            let RelayPoolNotification::Event { event, .. } = notification else {
+                continue;
+            };
+            if event.kind != Kind::NostrConnect
+                || self.signer.is_some_and(|signer| signer != event.pubkey)
+            {
+                continue;
+            }
+
+            // NIP-44 by current signers, NIP-04 by older ones.
+            let secret_key = self.keys.secret_key();
+            let Some(content) = nip44::decrypt(secret_key, &event.pubkey, &event.content)
+                .ok()
+                .or_else(|| nip04::decrypt(secret_key, &event.pubkey, &event.content).ok())
+            else {
+                warn!("Undecryptable Nostr Connect event from {}", event.pubkey);
+                continue;
+            };
+            match NostrConnectMessage::from_json(content) {
+                Ok(message) => return Ok((event.pubkey, message)),
+                Err(e) => warn!("Malformed Nostr Connect message: {}", e),
+            }
+        }
+    }
+}
+
+/// Connects to the signer of `uri`: a `bunker://` URI from the user, or a
+/// `nostrconnect://` URI previously handed out and held in `pending`.
+/// Bunker URIs may only name `allowed_relays`, so callers can't make the
+/// server open connections to arbitrary hosts.
+pub async fn connect(
+    uri: &str,
+    pending: &PendingConnects,
+    allowed_relays: &[RelayUrl],
+    timeout: Duration,
+) -> Result<RemoteSigner, Nip46Error> {
+    let parsed =
+        NostrConnectURI::parse(uri.trim()).map_err(|e| Nip46Error::InvalidUri(e.to_string()))?;
+    match parsed {
+        NostrConnectURI::Bunker { ref relays, .. } => {
+            if let Some(relay) = relays.iter().find(|relay| !allowed_relays.contains(relay)) {
+                return Err(Nip46Error::InvalidUri(format!(
+                    "relay {} is not one of the configured relays",
+                    relay
+                )));
+            }
+            RemoteSigner::bunker(&parsed, timeout).await
+        }
+        NostrConnectURI::Client { public_key, .. } => {
+            let mut signer = pending.take(&public_key).ok_or_else(|| {
+                Nip46Error::InvalidUri("unknown or expired nostrconnect:// URI".to_string())
+            })?;
+            match signer.wait_for_connect().await {
+                Ok(()) => Ok(signer),
+                Err(e) => {
+                    signer.shutdown().await;
+                    Err(e)
+                }
+            }
+        }
+    }
+}
+
+/// Has the signer sign a kind 27235 challenge for `url` and returns the
+/// user's public key once the signature checks out.
+pub async fn sign_login_challenge(
+    signer: &mut RemoteSigner,
+    url: &str,
+) -> Result<PublicKey, Nip46Error> {
+    let user = signer.get_public_key().await?;
+
+    let mut nonce = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let tags = [
+        ["u", url],
+        ["method", "POST"],
+        ["challenge", &hex::encode(nonce)],
+    ]
+    .into_iter()
+    .map(|tag| Tag::parse(tag).map_err(|e| Nip46Error::InvalidResponse(e.to_string())))
+    .collect::<Result<Vec<_>, _>>()?;
+    let mut challenge = EventBuilder::new(Kind::HttpAuth, "").tags(tags).build(user);
+    let challenge_id = challenge.id();
+
+    let event = signer.sign_event(challenge).await?;
+    if event.id != challenge_id || event.pubkey != user || event.verify().is_err() {
+        return Err(Nip46Error::Rejected(
+            "the signed challenge does not match".to_string(),
+        ));
+    }
+    Ok(user)
+}
+
+/// Signers listening on handed-out `nostrconnect://` URIs, by client key.
+/// Per process, so the login has to reach the instance that issued the URI.
+#[derive(Default)]
+pub struct PendingConnects {
+    inner: Mutex<HashMap<PublicKey, PendingConnect>>,
+}
+
+struct PendingConnect {
+    signer: RemoteSigner,
+    issued: Instant,
+    /// Address of the caller the URI was handed to.
+    requester: IpAddr,
+}
+
+impl PendingConnects {
+    pub fn insert(&self, signer: RemoteSigner, requester: IpAddr) -> Result<(), Nip46Error> {
+        let mut inner = self.inner.lock().unwrap();
+        let now = Instant::now();
+        let expired: Vec<PublicKey> = inner
+            .iter()
+            .filter(|(_, pending)| now.duration_since(pending.issued) > PENDING_CONNECT_TTL)
+            .map(|(key, _)| *key)
+            .collect();
+        for key in expired {
+            if let Some(pending) = inner.remove(&key) {
+                tokio::spawn(pending.signer.shutdown());
+            }
+        }
+
+        let from_requester = inner
+            .values()
+            .filter(|pending| pending.requester == requester)
+            .count();
+        if from_requester >= MAX_PENDING_CONNECTS_PER_CLIENT {
+            tokio::spawn(signer.shutdown());
+            return Err(Nip46Error::TooManyRequests(
+                "too many pending Nostr Connect logins from this address".to_string(),
+            ));
+        }
+        if inner.len() >= MAX_PENDING_CONNECTS {
+            tokio::spawn(signer.shutdown());
+            return Err(Nip46Error::TooManyRequests(
+                "too many pending Nostr Connect logins".to_string(),
+            ));
+        }
+        inner.insert(
+            signer.client_public_key(),
+            PendingConnect {
+                signer,
+                issued: now,
+                requester,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn take(&self, client: &PublicKey) -> Option<RemoteSigner> {
+        let pending = self.inner.lock().unwrap().remove(client)?;
+        if pending.issued.elapsed() > PENDING_CONNECT_TTL {
+            tokio::spawn(pending.signer.shutdown());
+            return None;
+        }
+        Some(pending.signer)
+    }
+}
+
+/// Counts NIP-46 logins and Nostr Connect URIs per client address in fixed
+/// windows. Per process, like [`PendingConnects`].
+#[derive(Default)]
+pub struct AttemptLimiter {
+    inner: Mutex<HashMap<IpAddr, (Instant, u32)>>,
+}
+
+impl AttemptLimiter {
+    /// Records an attempt from `client`. Returns false once it has used up
+    /// its attempts for the current window.
+    pub fn allow(&self, client: IpAddr, now: Instant) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        inner.retain(|_, (started, _)| now.duration_since(*started) < ATTEMPT_WINDOW);
+        let (_, attempts) = inner.entry(client).or_insert((now, 0));
+        if *attempts >= MAX_ATTEMPTS_PER_WINDOW {
+            return false;
+        }
+        *attempts += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::{SinkExt, StreamExt};
+    use nostr::filter::MatchEventOptions;
+    use nostr::{ClientMessage, RelayMessage, SubscriptionId};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::tungstenite::Message;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+    const LOCALHOST: IpAddr = IpAddr::V4(std::net::Ipv4Addr::LOCALHOST);
+
+    /// Minimal in-memory relay: forwards every event to the subscriptions
+    /// it matches and stores nothing.
+    async fn start_relay() -> RelayUrl {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = RelayUrl::parse(&format!("ws://{}", listener.local_addr().unwrap())).unwrap();
+        let (events, _) = broadcast::channel::<Event>(64);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_relay_connection(stream, events.clone()));
+            }
+        });
+        url
+    }
+
+    async fn serve_relay_connection(stream: TcpStream, events: broadcast::Sender<Event>) {
+        let Ok(ws) = tokio_tungstenite::accept_async(stream).await else {
+            return;
+        };
+        let (mut sink, mut source) = ws.split();
+        let mut published = events.subscribe();
+        let mut subscriptions: Vec<(SubscriptionId, Filter)> = Vec::new();
+
+        loop {
+            let reply = tokio::select! {
+                message = source.next() => {
+                    let Some(Ok(Message::Text(text))) = message else {
+                        if matches!(message, Some(Ok(_))) {
+                            continue;
+                        }
+                        return;
+                    };
+                    match ClientMessage::from_json(text.as_str()) {
+                        Ok(ClientMessage::Event(event)) => {
+                            let _ = events.send(event.clone().into_owned());
+                            vec![RelayMessage::ok(event.id, true, "")]
+                        }
+                        Ok(ClientMessage::Req { subscription_id, filter }) => {
+                            let id = subscription_id.into_owned();
+                            subscriptions.push((id.clone(), filter.into_owned()));
+                            vec![RelayMessage::eose(id)]
+                        }
+                        Ok(ClientMessage::Close(id)) => {
+                            subscriptions.retain(|(sub, _)| *sub != *id);
+                            Vec::new()
+                        }
+                        _ => Vec::new(),
+                    }
+                }
+                event = published.recv() => {
+                    let Ok(event) = event else { return };
+                    subscriptions
+                        .iter()
+                        .filter(|(_, filter)| filter.match_event(&event, MatchEventOptions::new()))
+                        .map(|(id, _)| RelayMessage::event(id.clone(), event.clone()))
+                        .collect()
+                }
+            };
+            for message in reply {
+                if sink.send(Message::text(message.as_json())).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Remote signer holding `user`'s key. With `connect_to`, it first
+    /// answers that Nostr Connect URI's client instead of waiting for one.
+    async fn start_signer(
+        relay: RelayUrl,
+        signer_keys: Keys,
+        user: Keys,
+        connect_to: Option<(PublicKey, String)>,
+    ) {
+        let client = Client::default();
+        client.add_relay(relay).await.unwrap();
+        client.connect().await;
+        client.wait_for_connection(TIMEOUT).await;
+        let mut notifications = client.notifications();
+        client
+            .subscribe(
+                Filter::new()
+                    .kind(Kind::NostrConnect)
+                    .pubkey(signer_keys.public_key()),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let respond = |client: Client, keys: Keys, to: PublicKey, message: NostrConnectMessage| async move {
+            let content = nip44::encrypt(
+                keys.secret_key(),
+                &to,
+                message.as_json(),
+                nip44::Version::V2,
+            )
+            .unwrap();
+            let event = EventBuilder::new(Kind::NostrConnect, content)
+                .tag(Tag::public_key(to))
+                .sign_with_keys(&keys)
+                .unwrap();
+            client.send_event(&event).await.unwrap();
+        };
+
+        if let Some((app, secret)) = connect_to {
+            let message = NostrConnectMessage::Response {
+                id: "connect".to_string(),
+                result: Some(secret),
+                error: None,
+            };
+            respond(client.clone(), signer_keys.clone(), app, message).await;
+        }
+
+        tokio::spawn(async move {
+            while let Ok(notification) = notifications.recv().await {
+                let RelayPoolNotification::Event { event, .. } = notification else {
+                    continue;
+                };
+                let Ok(content) =
+                    nip44::decrypt(signer_keys.secret_key(), &event.pubkey, &event.content)
+                else {
+                    continue;
+                };
+                let message = NostrConnectMessage::from_json(content).unwrap();
+                let id = message.id().to_string();
+                let result = match message.to_request().unwrap() {
+                    NostrConnectRequest::Connect { .. } => "ack".to_string(),
+                    NostrConnectRequest::GetPublicKey => user.public_key().to_hex(),
+                    NostrConnectRequest::SignEvent(unsigned) => {
+                        unsigned.sign_with_keys(&user).unwrap().as_json()
+                    }
+                    _ => continue,
+                };
+                let reply = NostrConnectMessage::Response {
+                    id,
+                    result: Some(result),
+                    error: None,
+                };
+                respond(client.clone(), signer_keys.clone(), event.pubkey, reply).await;
+            }
+        });
+    }
+
+    #[tokio::test]
+    async fn logs_in_through_bunker_uri() {
+        let relay = start_relay().await;
+        let (signer_keys, user) = (Keys::generate(), Keys::generate());
+        start_signer(relay.clone(), signer_keys.clone(), user.clone(), None).await;
+
+        let uri = format!(
+            "bunker://{}?relay={}&secret=abc",
+            signer_keys.public_key().to_hex(),
+            relay
+        );
+        let relays = [relay];
+        let mut signer = connect(&uri, &PendingConnects::default(), &relays, TIMEOUT)
+            .await
+            .unwrap();
+        let pubkey =
+            sign_login_challenge(&mut signer, "http://localhost:3333/api/auth/nip46/login")
+                .await
+                .unwrap();
+        signer.shutdown().await;
+
+        assert_eq!(pubkey, user.public_key());
+    }
+
+    #[tokio::test]
+    async fn logs_in_through_nostrconnect_uri() {
+        let relay = start_relay().await;
+        let pending = PendingConnects::default();
+        let (signer, uri) =
+            RemoteSigner::nostr_connect(std::slice::from_ref(&relay), "otrta", TIMEOUT)
+                .await
+                .unwrap();
+        let app = signer.client_public_key();
+        let secret = signer.connect_secret.clone().unwrap();
+        pending.insert(signer, LOCALHOST).unwrap();
+
+        let (signer_keys, user) = (Keys::generate(), Keys::generate());
+        start_signer(relay, signer_keys, user.clone(), Some((app, secret))).await;
+
+        let mut signer = connect(&uri, &pending, &[], TIMEOUT).await.unwrap();
+        let pubkey =
+            sign_login_challenge(&mut signer, "http://localhost:3333/api/auth/nip46/login")
+                .await
+                .unwrap();
+        signer.shutdown().await;
+
+        assert_eq!(pubkey, user.public_key());
+        assert!(connect(&uri, &pending, &[], TIMEOUT).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn ignores_signers_without_the_secret() {
+        let relay = start_relay().await;
+        let pending = PendingConnects::default();
+        let (signer, uri) = RemoteSigner::nostr_connect(
+            std::slice::from_ref(&relay),
+            "otrta",
+            Duration::from_secs(1),
+        )
+        .await
+        .unwrap();
+        let app = signer.client_public_key();
+        pending.insert(signer, LOCALHOST).unwrap();
+
+        let (signer_keys, user) = (Keys::generate(), Keys::generate());
+        start_signer(relay, signer_keys, user, Some((app, "ack".to_string()))).await;
+
+        assert!(matches!(
+            connect(&uri, &pending, &[], TIMEOUT).await,
+            Err(Nip46Error::Timeout { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_uris() {
+        let pending = PendingConnects::default();
+        let unissued = format!(
+            "nostrconnect://{}?relay=ws://127.0.0.1:1&secret=abc&metadata=%7B%7D",
+            Keys::generate().public_key().to_hex()
+        );
+
+        let unlisted_relay = format!(
+            "bunker://{}?relay=ws://127.0.0.1:1",
+            Keys::generate().public_key().to_hex()
+        );
+        let allowed = [RelayUrl::parse("wss://relay.example.com").unwrap()];
+
+        for uri in ["https://example.com", &unissued, &unlisted_relay] {
+            assert!(matches!(
+                connect(uri, &pending, &allowed, TIMEOUT).await,
+                Err(Nip46Error::InvalidUri(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn limits_attempts_per_client_and_window() {
+        let limiter = AttemptLimiter::default();
+        let other = IpAddr::V4(std::net::Ipv4Addr::new(203, 0, 113, 7));
+        let start = Instant::now();
+
+        for _ in 0..MAX_ATTEMPTS_PER_WINDOW {
+            assert!(limiter.allow(LOCALHOST, start));
+        }
+        assert!(!limiter.allow(LOCALHOST, start));
+        assert!(limiter.allow(other, start));
+        assert!(limiter.allow(LOCALHOST, start + ATTEMPT_WINDOW));
+    }
+}
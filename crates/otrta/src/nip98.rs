@@ -0,0 +1,333 @@
+//! NIP-98 HTTP auth: a signed kind 27235 event in the `Authorization` header
+//! authorizes exactly one request. The event has to name this request's URL
+//! and method, carry the SHA-256 of its body, be recent, and not have been
+//! used before.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header::HOST, request::Parts, HeaderMap, Uri},
+};
+use base64::Engine;
+use nostr::{Event, EventId, Kind};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::warn;
+use url::Url;
+
+use crate::{
+    error::AppError,
+    forwarded::{self, TrustedProxies},
+};
+
+/// Bodies of Nostr-authenticated requests are buffered to hash them; the
+/// management API only takes small JSON documents.
+const MAX_SIGNED_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// Event IDs that have already authorized a request, kept until the event
+/// falls out of the validity window and would be rejected as stale anyway.
+/// Per process, so replicas behind a load balancer each keep their own.
+#[derive(Default)]
+pub struct ReplayCache {
+    inner: Mutex<ReplayCacheInner>,
+}
+
+#[derive(Default)]
+struct ReplayCacheInner {
+    seen: HashMap<EventId, u64>,
+    last_pruned: u64,
+}
+
+impl ReplayCache {
+    /// Records `id` as used until `expires_at` (Unix seconds). Returns false
+    /// if it was already recorded.
+    pub fn insert(&self, id: EventId, expires_at: u64, now: u64) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.last_pruned < now {
+            inner.seen.retain(|_, expiry| *expiry >= now);
+            inner.last_pruned = now;
+        }
+        if inner.seen.get(&id).is_some_and(|expiry| *expiry >= now) {
+            return false;
+        }
+        inner.seen.insert(id, expires_at);
+        true
+    }
+}
+
+/// Checks the `Nostr <base64 event>` credentials against the request and
+/// returns the event together with the request, whose body had to be read to
+/// hash it.
+pub async fn authorize(
+    encoded_event: &str,
+    request: Request,
+    max_age_seconds: u64,
+    replay_cache: &ReplayCache,
+    trusted_proxies: &TrustedProxies,
+) -> Result<(Event, Request), AppError> {
+    let event = decode_event(encoded_event)?;
+
+    let (parts, body) = request.into_parts();
+    let trust_forwarded = trusted_proxies.trusts(forwarded::peer_addr(&parts.extensions));
+    let body = to_bytes(body, MAX_SIGNED_BODY_BYTES).await.map_err(|e| {
+        warn!("Failed to read request body for NIP-98 check: {}", e);
+        AppError::Unauthorized
+    })?;
+
+    let now = unix_now();
+    validate_auth_event(&event, &parts, &body, max_age_seconds, now, trust_forwarded)?;
+
+    if !replay_cache.insert(event.id, event.created_at.as_u64() + max_age_seconds, now) {
+        warn!("Replayed auth event {}", event.id);
+        return Err(AppError::Unauthorized);
+    }
+
+    Ok((event, Request::from_parts(parts, Body::from(body))))
+}
+
+fn decode_event(encoded_event: &str) -> Result<Event, AppError> {
+    let decoded_bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded_event)
+        .map_err(|_| AppError::Unauthorized)?;
+    let event_json = std::str::from_utf8(&decoded_bytes).map_err(|_| AppError::Unauthorized)?;
+    serde_json::from_str(event_json).map_err(|_| AppError::Unauthorized)
+}
+
+fn validate_auth_event(
+    event: &Event,
+    parts: &Parts,
+    body: &[u8],
+    max_age_seconds: u64,
+    now: u64,
+    trust_forwarded: bool,
+) -> Result<(), AppError> {
+    if event.kind != Kind::HttpAuth {
+        warn!("Invalid event kind: expected 27235, got {}", event.kind);
+        return Err(AppError::Unauthorized);
+    }
+
+    let created_at = event.created_at.as_u64();
+    if created_at.abs_diff(now) > max_age_seconds {
+        warn!(
+            "Event outside the validity window: created {}s from now",
+            created_at as i64 - now as i64
+        );
+        return Err(AppError::Unauthorized);
+    }
+
+    let mut url = None;
+    let mut method = None;
+    let mut payload = None;
+    for tag in event.tags.iter() {
+        match tag.as_slice() {
+            [name, value, ..] if name == "u" => url = Some(value.as_str()),
+            [name, value, ..] if name == "method" => method = Some(value.as_str()),
+            [name, value, ..] if name == "payload" => payload = Some(value.as_str()),
+            _ => {}
+        }
+    }
+
+    let (Some(url), Some(method)) = (url, method) else {
+        warn!("Missing required tags (u or method)");
+        return Err(AppError::Unauthorized);
+    };
+
+    let expected_url = request_url(&parts.headers, &parts.uri, trust_forwarded);
+    if !urls_match(url, &expected_url) {
+        warn!("URL mismatch: expected {}, got {}", expected_url, url);
+        return Err(AppError::Unauthorized);
+    }
+
+    if method != parts.method.as_str() {
+        warn!(
+            "Method mismatch: expected {}, got {}",
+            parts.method.as_str(),
+            method
+        );
+        return Err(AppError::Unauthorized);
+    }
+
+    match payload {
+        Some(payload) => {
+            let expected_payload = hex::encode(Sha256::digest(body));
+            if !payload.eq_ignore_ascii_case(&expected_payload) {
+                warn!("Payload hash mismatch");
+                return Err(AppError::Unauthorized);
+            }
+        }
+        None if !body.is_empty() => {
+            warn!("Missing payload tag for a request with a body");
+            return Err(AppError::Unauthorized);
+        }
+        None => {}
+    }
+
+    // Last, since it is the expensive check.
+    if event.verify().is_err() {
+        warn!("Invalid event signature");
+        return Err(AppError::Unauthorized);
+    }
+
+    Ok(())
+}
+
+/// The absolute URL the client sent the request to. Behind a trusted reverse
+/// proxy the scheme and host come from `X-Forwarded-Proto` and
+/// `X-Forwarded-Host`; without one the server itself speaks plain HTTP.
+pub(crate) fn request_url(headers: &HeaderMap, uri: &Uri, trust_forwarded: bool) -> String {
+    let forwarded = |name: &str| {
+        trust_forwarded
+            .then(|| forwarded::first_value(headers, name))
+            .flatten()
+    };
+
+    let scheme = forwarded("x-forwarded-proto")
+        .or(uri.scheme_str())
+        .unwrap_or("http");
+    let host = forwarded("x-forwarded-host")
+        .or_else(|| headers.get(HOST).and_then(|value| value.to_str().ok()))
+        .or_else(|| uri.authority().map(|authority| authority.as_str()))
+        .unwrap_or("localhost");
+    let path_and_query = uri
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/");
+
+    format!("{}://{}{}", scheme, host, path_and_query)
+}
+
+/// Compares after parsing, so default ports and host case don't matter.
+fn urls_match(signed: &str, expected: &str) -> bool {
+    match (Url::parse(signed), Url::parse(expected)) {
+        (Ok(signed), Ok(expected)) => signed == expected,
+        _ => false,
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nostr::{EventBuilder, Keys, Tag, Timestamp};
+
+    const MAX_AGE: u64 = 60;
+    const NOW: u64 = 1_800_000_000;
+
+    fn parts(method: &str, uri: &str, headers: &[(&str, &str)]) -> Parts {
+        let mut builder = Request::builder().method(method).uri(uri);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(()).unwrap().into_parts().0
+    }
+
+    fn event(tags: &[&[&str]], created_at: u64) -> Event {
+        EventBuilder::new(Kind::HttpAuth, "")
+            .tags(tags.iter().map(|tag| Tag::parse(tag.to_vec()).unwrap()))
+            .custom_created_at(Timestamp::from(created_at))
+            .sign_with_keys(&Keys::generate())
+            .unwrap()
+    }
+
+    #[test]
+    fn accepts_matching_request() {
+        let parts = parts("GET", "/api/mints?page=2", &[("host", "localhost:3333")]);
+        let event = event(
+            &[
+                &["u", "http://localhost:3333/api/mints?page=2"],
+                &["method", "GET"],
+            ],
+            NOW,
+        );
+        assert!(validate_auth_event(&event, &parts, b"", MAX_AGE, NOW, false).is_ok());
+    }
+
+    #[test]
+    fn rejects_other_path_or_query() {
+        let parts = parts("GET", "/api/mints?page=3", &[("host", "localhost:3333")]);
+        for url in [
+            "http://localhost:3333/api/mints?page=2",
+            "http://localhost:3333/api/api-keys?page=3",
+            "http://otherhost:3333/api/mints?page=3",
+        ] {
+            let event = event(&[&["u", url], &["method", "GET"]], NOW);
+            assert!(validate_auth_event(&event, &parts, b"", MAX_AGE, NOW, false).is_err());
+        }
+    }
+
+    #[test]
+    fn uses_forwarded_scheme_and_host_from_trusted_proxies() {
+        let parts = parts(
+            "GET",
+            "/api/mints",
+            &[
+                ("host", "otrta:3333"),
+                ("x-forwarded-proto", "https"),
+                ("x-forwarded-host", "otrta.example.com, proxy.internal"),
+            ],
+        );
+        assert_eq!(
+            request_url(&parts.headers, &parts.uri, true),
+            "https://otrta.example.com/api/mints"
+        );
+        assert!(urls_match(
+            "https://OTRTA.example.com:443/api/mints",
+            &request_url(&parts.headers, &parts.uri, true)
+        ));
+
+        // From anyone else the headers are ignored
+        assert_eq!(
+            request_url(&parts.headers, &parts.uri, false),
+            "http://otrta:3333/api/mints"
+        );
+    }
+
+    #[test]
+    fn checks_payload_hash() {
+        let parts = parts("POST", "/api/mints", &[("host", "localhost:3333")]);
+        let body = br#"{"mint_url":"https://mint.example.com"}"#;
+        let hash = hex::encode(Sha256::digest(body));
+        let base = [["u", "http://localhost:3333/api/mints"], ["method", "POST"]];
+
+        let signed = event(&[&base[0], &base[1], &["payload", &hash]], NOW);
+        assert!(validate_auth_event(&signed, &parts, body, MAX_AGE, NOW, false).is_ok());
+        assert!(validate_auth_event(&signed, &parts, b"{}", MAX_AGE, NOW, false).is_err());
+
+        let unsigned_body = event(&[&base[0], &base[1]], NOW);
+        assert!(validate_auth_event(&unsigned_body, &parts, body, MAX_AGE, NOW, false).is_err());
+    }
+
+    #[test]
+    fn rejects_events_outside_window() {
+        let parts = parts("GET", "/api/mints", &[("host", "localhost:3333")]);
+        let tags: [&[&str]; 2] = [
+            &["u", "http://localhost:3333/api/mints"],
+            &["method", "GET"],
+        ];
+        for created_at in [NOW - MAX_AGE - 1, NOW + MAX_AGE + 1] {
+            let event = event(&tags, created_at);
+            assert!(validate_auth_event(&event, &parts, b"", MAX_AGE, NOW, false).is_err());
+        }
+    }
+
+    #[test]
+    fn replay_cache_rejects_reuse_until_expiry() {
+        let cache = ReplayCache::default();
+        let id = event(&[], NOW).id;
+
+        assert!(cache.insert(id, NOW + MAX_AGE, NOW));
+        assert!(!cache.insert(id, NOW + MAX_AGE, NOW + 1));
+        assert!(!cache.insert(id, NOW + MAX_AGE, NOW + MAX_AGE));
+        assert!(cache.insert(id, NOW + 2 * MAX_AGE, NOW + MAX_AGE + 1));
+    }
+}
@@ -26,7 +26,7 @@ impl NwcClient {
         let mut monitor_sub = monitor.subscribe();
         tokio::spawn(async move {
             while let Ok(notification) = monitor_sub.recv().await {
-                println!("Notification: {notification:?}");
+                debug!("NWC notification: {notification:?}");
             }
         });
 
@@ -110,11 +110,11 @@ impl NwcClient {
 }
 
 pub struct NwcManager {
-    db_pool: sqlx::PgPool,
+    db_pool: crate::db::Pool,
 }
 
 impl NwcManager {
-    pub fn new(db_pool: sqlx::PgPool) -> Self {
+    pub fn new(db_pool: crate::db::Pool) -> Self {
         Self { db_pool }
     }
 
@@ -131,7 +131,7 @@ impl NwcManager {
                     AppError::NotFound
                 })?;
 
-        println!("connection: {:?}", connection);
+        debug!("Loaded NWC connection {}", connection.id);
 
         if !connection.is_active {
             warn!("NWC connection is inactive: {}", connection_id);
@@ -191,6 +191,7 @@ impl NwcManager {
         }
     }
 
+    #[tracing::instrument(skip_all, fields(connection_id = %nwc_connection_id, mint_url = %mint_url, amount_msat = amount_msat))]
     pub async fn request_mint_refill(
         &self,
         nwc_connection_id: &uuid::Uuid,
@@ -206,6 +207,7 @@ impl NwcManager {
         client.request_payment(amount_msat, &description).await
     }
 
+    #[tracing::instrument(skip_all, fields(connection_id = %nwc_connection_id, quote_id = %quote_id))]
     pub async fn pay_invoice_with_connection(
         &self,
         state: &Arc<AppState>,
@@ -226,7 +228,7 @@ impl NwcManager {
             AppError::NotFound
         })?;
 
-        println!("{:?}", connection);
+        debug!("Testing NWC connection {}", connection.id);
         let uri = NostrWalletConnectURI::from_str(&connection.connection_uri).map_err(|e| {
             error!("Failed to parse NWC URI for testing: {}", e);
             AppError::BadRequest("Invalid NWC connection URI".to_string())
@@ -237,7 +239,7 @@ impl NwcManager {
         let mut monitor_sub = monitor.subscribe();
         tokio::spawn(async move {
             while let Ok(notification) = monitor_sub.recv().await {
-                println!("Notification: {notification:?}");
+                debug!("NWC notification: {notification:?}");
             }
         });
 
@@ -1,5 +1,28 @@
 use reqwest::Client;
-use std::time::Instant;
+use std::{sync::OnceLock, time::Instant};
+use tracing::{debug, info};
+
+use crate::metrics::record_upstream_latency;
+
+const DEFAULT_TOR_SOCKS_PROXY: &str = "socks5h://127.0.0.1:9050";
+
+static TOR_SOCKS_PROXY: OnceLock<String> = OnceLock::new();
+
+/// Sets the Tor SOCKS proxy from the loaded configuration. Changing it takes
+/// a restart, so only the first call has an effect.
+pub fn set_tor_socks_proxy(url: String) {
+    let _ = TOR_SOCKS_PROXY.set(url);
+}
+
+/// The configured Tor SOCKS proxy, else `TOR_SOCKS_PROXY`, else the local
+/// Tor default.
+pub fn tor_socks_proxy() -> String {
+    TOR_SOCKS_PROXY
+        .get()
+        .cloned()
+        .or_else(|| std::env::var("TOR_SOCKS_PROXY").ok())
+        .unwrap_or_else(|| DEFAULT_TOR_SOCKS_PROXY.to_string())
+}
 
 pub fn is_onion_url(url: &str) -> bool {
     url.contains(".onion")
@@ -22,8 +45,7 @@ pub fn construct_url_with_protocol(base_url: &str, path: &str) -> String {
 }
 
 pub fn configure_tor_proxy_url(endpoint_url: &str) -> String {
-    let tor_proxy_url =
-        std::env::var("TOR_SOCKS_PROXY").unwrap_or_else(|_| "socks5h://127.0.0.1:9050".to_string());
+    let tor_proxy_url = tor_socks_proxy();
 
     // Ensure we're using socks5h:// for onion addresses (hostname resolution through proxy)
     if endpoint_url.contains(".onion") && tor_proxy_url.starts_with("socks5://") {
@@ -46,12 +68,12 @@ pub fn configure_client_with_tor_proxy(
     if needs_tor_proxy(endpoint_url, use_onion) {
         let proxy_url = configure_tor_proxy_url(endpoint_url);
 
-        println!("Using Tor proxy URL: {}", proxy_url);
+        info!("Using Tor proxy URL: {}", proxy_url);
 
         match reqwest::Proxy::all(&proxy_url) {
             Ok(proxy) => {
                 client_builder = client_builder.proxy(proxy);
-                println!(
+                info!(
                     "Using Tor proxy for .onion request: {} (proxy: {})",
                     endpoint_url, proxy_url
                 );
@@ -86,22 +108,21 @@ pub fn create_onion_client(
         .map_err(|e| format!("Failed to build HTTP client: {}", e))
 }
 
-pub fn start_onion_timing(endpoint_url: &str) -> Option<Instant> {
-    if is_onion_url(endpoint_url) {
-        Some(Instant::now())
-    } else {
-        None
-    }
+/// Starts timing an upstream request, for [`log_request_timing`].
+pub fn start_request_timer() -> Instant {
+    Instant::now()
 }
 
-pub fn log_onion_timing(start_time: Option<Instant>, endpoint_url: &str, context: &str) {
-    if let Some(start) = start_time {
-        let duration = start.elapsed();
-        println!(
-            "Onion {} request completed in {:?}: {}",
-            context, duration, endpoint_url
-        );
-    }
+/// Records the latency of an upstream request started at `started` in the
+/// metrics, split by clearnet and onion.
+pub fn log_request_timing(started: Instant, endpoint_url: &str, context: &str) {
+    let duration = started.elapsed();
+    let onion = is_onion_url(endpoint_url);
+    record_upstream_latency(onion, context, duration);
+    debug!(
+        "{} request completed in {:?}: {}",
+        context, duration, endpoint_url
+    );
 }
 
 pub fn get_onion_error_message(
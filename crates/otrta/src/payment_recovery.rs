@@ -0,0 +1,248 @@
+use chrono::{Duration, Utc};
+use tracing::{error, info, warn};
+
+use crate::{
+    db::{
+        ledger::{record_entry, EntryType, LedgerContext},
+        payment_intents::{
+            fail_payment_intent, get_stale_payment_intents, record_recovery_failure,
+            set_payment_intent_state, PaymentIntent, PaymentIntentState,
+        },
+        transaction::{add_transaction, TransactionDirection, TransactionType},
+    },
+    error::AppError,
+    models::AppState,
+    multimint::MultimintWalletWrapper,
+};
+
+/// Intents untouched for this long belong to a request that was cut off. It
+/// comfortably exceeds the slowest upstream call over Tor.
+const STALE_AFTER_MINUTES: i64 = 15;
+
+// Failed attempts push `updated_at` forward, so this spans about half a day.
+const MAX_RECOVERY_ATTEMPTS: i32 = 48;
+
+const BATCH_SIZE: i64 = 100;
+
+#[derive(Debug, Default, Clone, serde::Serialize)]
+pub struct RecoveryReport {
+    pub examined: usize,
+    pub recovered: usize,
+    pub spent: usize,
+    pub failed: usize,
+    /// Intents whose tokens couldn't be checked yet, retried on the next pass.
+    pub unresolved: usize,
+}
+
+enum TokenOutcome {
+    Reclaimed,
+    Spent,
+    Unresolved(String),
+}
+
+async fn reclaim_token(
+    state: &AppState,
+    wallet: &MultimintWalletWrapper,
+    intent: &PaymentIntent,
+    token: &str,
+    entry_type: EntryType,
+) -> TokenOutcome {
+    let received = wallet.receive(token).await.map_err(|e| e.to_string());
+    let amount = match received {
+        Ok(amount) => amount,
+        Err(receive_error) => {
+            let spent = wallet
+                .is_token_spent(token)
+                .await
+                .map_err(|e| e.to_string());
+            return match spent {
+                Ok(true) => TokenOutcome::Spent,
+                Ok(false) => TokenOutcome::Unresolved(receive_error),
+                Err(e) => TokenOutcome::Unresolved(format!("{}; checkstate: {}", receive_error, e)),
+            };
+        }
+    };
+
+    let unit = intent.unit.as_deref().unwrap_or("sat");
+    let api_key_id = intent.api_key_id.map(|id| id.to_string());
+    let ledger = LedgerContext {
+        request_id: intent.id,
+        api_key_id: api_key_id.as_deref(),
+        user_id: intent.user_id.as_deref(),
+        provider_url: intent.provider_url.as_deref(),
+        model: intent.model.as_deref(),
+        ..LedgerContext::new(intent.organization_id)
+    };
+
+    if let Err(e) = add_transaction(
+        &state.db,
+        token,
+        &amount,
+        TransactionDirection::Incoming,
+        if intent.user_id.is_some() {
+            TransactionType::Chat
+        } else {
+            TransactionType::Api
+        },
+        &ledger.transaction_context(Some(unit)),
+    )
+    .await
+    {
+        error!(
+            "Failed to record recovered token for intent {}: {}",
+            intent.id, e
+        );
+    }
+
+    match amount.trim().parse::<i64>() {
+        Ok(amount) => {
+            if let Err(e) = record_entry(&state.db, &ledger, entry_type, amount, unit).await {
+                error!(
+                    "Failed to record ledger entry for intent {}: {}",
+                    intent.id, e
+                );
+            }
+        }
+        Err(_) => warn!(
+            "Recovered token for intent {} with invalid amount {:?}",
+            intent.id, amount
+        ),
+    }
+
+    TokenOutcome::Reclaimed
+}
+
+/// Left on intents cut off before their token was journaled. The proofs the
+/// send reserved can't be told apart from those of live requests, so they are
+/// not swept here.
+const UNJOURNALED_TOKEN: &str =
+    "cut off before its token was journaled; any proofs reserved for it are still pending in the wallet";
+
+/// The state an intent settles in once each of its stored tokens has been
+/// tried. An intent with no stored tokens never got a token journaled.
+fn settled_state(outcomes: &[TokenOutcome]) -> Result<PaymentIntentState, String> {
+    if outcomes.is_empty() {
+        return Ok(PaymentIntentState::Failed);
+    }
+
+    let unresolved: Vec<_> = outcomes
+        .iter()
+        .filter_map(|outcome| match outcome {
+            TokenOutcome::Unresolved(e) => Some(e.as_str()),
+            _ => None,
+        })
+        .collect();
+    if !unresolved.is_empty() {
+        return Err(unresolved.join("; "));
+    }
+
+    if outcomes
+        .iter()
+        .any(|outcome| matches!(outcome, TokenOutcome::Reclaimed))
+    {
+        Ok(PaymentIntentState::Recovered)
+    } else {
+        Ok(PaymentIntentState::Spent)
+    }
+}
+
+async fn recover_intent(
+    state: &AppState,
+    intent: &PaymentIntent,
+) -> Result<PaymentIntentState, String> {
+    if intent.token.is_none() && intent.change_token.is_none() {
+        return settled_state(&[]);
+    }
+
+    let wallet = state
+        .multimint_manager
+        .get_or_create_multimint(&intent.organization_id)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut outcomes = Vec::new();
+    if let Some(change_token) = &intent.change_token {
+        outcomes.push(reclaim_token(state, &wallet, intent, change_token, EntryType::Change).await);
+    }
+    if let Some(token) = &intent.token {
+        outcomes.push(reclaim_token(state, &wallet, intent, token, EntryType::Refund).await);
+    }
+
+    settled_state(&outcomes)
+}
+
+/// Settles payment intents left unfinished by a crash or a cut-off request:
+/// tokens the provider never redeemed are received back into the wallet, and
+/// intents whose tokens were all spent are closed.
+pub async fn recover_payment_intents(state: &AppState) -> Result<RecoveryReport, AppError> {
+    let stale_before = Utc::now() - Duration::minutes(STALE_AFTER_MINUTES);
+    let intents =
+        get_stale_payment_intents(&state.db, stale_before, MAX_RECOVERY_ATTEMPTS, BATCH_SIZE)
+            .await?;
+
+    let mut report = RecoveryReport {
+        examined: intents.len(),
+        ..Default::default()
+    };
+
+    for intent in &intents {
+        match recover_intent(state, intent).await {
+            Ok(outcome) => {
+                match outcome {
+                    PaymentIntentState::Recovered => report.recovered += 1,
+                    PaymentIntentState::Spent => report.spent += 1,
+                    _ => report.failed += 1,
+                }
+                info!(
+                    "Payment intent {} ({}) settled by recovery as {}",
+                    intent.id,
+                    intent.state,
+                    outcome.as_str()
+                );
+                if outcome == PaymentIntentState::Failed {
+                    warn!("Payment intent {} {}", intent.id, UNJOURNALED_TOKEN);
+                    fail_payment_intent(&state.db, &intent.id, UNJOURNALED_TOKEN).await?;
+                } else {
+                    set_payment_intent_state(&state.db, &intent.id, outcome).await?;
+                }
+            }
+            Err(e) => {
+                report.unresolved += 1;
+                warn!("Could not recover payment intent {}: {}", intent.id, e);
+                record_recovery_failure(&state.db, &intent.id, &e).await?;
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settled_state() {
+        use TokenOutcome::*;
+
+        // Created only: nothing journaled to reclaim
+        assert_eq!(settled_state(&[]), Ok(PaymentIntentState::Failed));
+
+        assert_eq!(
+            settled_state(&[Reclaimed, Spent]),
+            Ok(PaymentIntentState::Recovered)
+        );
+        assert_eq!(
+            settled_state(&[Spent, Spent]),
+            Ok(PaymentIntentState::Spent)
+        );
+        assert_eq!(
+            settled_state(&[Reclaimed, Unresolved("mint down".to_string())]),
+            Err("mint down".to_string())
+        );
+        assert_eq!(
+            settled_state(&[Unresolved("a".to_string()), Unresolved("b".to_string())]),
+            Err("a; b".to_string())
+        );
+    }
+}
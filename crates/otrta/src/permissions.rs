@@ -0,0 +1,369 @@
+//! Roles of organization members and what each may do. Every protected
+//! route maps to one [`Permission`]; routes missing from the table are
+//! refused, so new endpoints have to be classified before anyone can call
+//! them.
+
+use axum::{
+    extract::{MatchedPath, Request},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::warn;
+
+use crate::models::UserContext;
+
+/// A member's role within one organization.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Everything, including withdrawals.
+    Owner,
+    /// Everything except withdrawals.
+    Admin,
+    /// Uses models and search and manages API keys.
+    Member,
+    /// Read-only access.
+    Viewer,
+    /// Moves funds in and out of the organization's wallet.
+    Billing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// Read the organization's wallet, settings and usage.
+    View,
+    /// Run searches and keep search history.
+    UseServices,
+    /// Create, read and revoke API keys, which can spend the balance.
+    ManageApiKeys,
+    /// Add funds: redeem tokens, top up mints, pay in over Lightning.
+    Deposit,
+    /// Take funds out: send ecash, pay Lightning invoices.
+    Withdraw,
+    /// Change mints, providers, budgets, retention and NWC connections.
+    Configure,
+    /// Invite, remove and change the roles of members.
+    ManageMembers,
+    /// Read who changed settings and moved funds.
+    ViewAuditLog,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Owner => "owner",
+            Role::Admin => "admin",
+            Role::Member => "member",
+            Role::Viewer => "viewer",
+            Role::Billing => "billing",
+        }
+    }
+
+    /// Whether a member with this role may give `role` to someone, or take
+    /// it away. Admins can't hand out withdrawal rights they don't have.
+    pub fn can_assign(&self, role: Role) -> bool {
+        match self {
+            Role::Owner => true,
+            Role::Admin => matches!(role, Role::Admin | Role::Member | Role::Viewer),
+            _ => false,
+        }
+    }
+
+    pub fn allows(&self, permission: Permission) -> bool {
+        use Permission::*;
+        match self {
+            Role::Owner => true,
+            Role::Admin => permission != Withdraw,
+            Role::Member => matches!(permission, View | UseServices | ManageApiKeys | Deposit),
+            Role::Viewer => permission == View,
+            Role::Billing => matches!(permission, View | Deposit | Withdraw),
+        }
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "owner" => Ok(Role::Owner),
+            "admin" => Ok(Role::Admin),
+            "member" => Ok(Role::Member),
+            "viewer" => Ok(Role::Viewer),
+            "billing" => Ok(Role::Billing),
+            _ => Err(format!("Invalid role: {}", s)),
+        }
+    }
+}
+
+/// The permission a protected route requires, by method and route pattern.
+pub fn required_permission(method: &Method, path: &str) -> Option<Permission> {
+    use Permission::*;
+    let permission = match (method.as_str(), path) {
+        (
+            "GET",
+            "/api/openai-models"
+            | "/api/proxy/models"
+            | "/api/providers"
+            | "/api/providers/default"
+            | "/api/providers/active"
+            | "/api/providers/{id}"
+            | "/api/models/pricing-comparison"
+            | "/api/wallet/balance"
+            | "/api/wallet/pending-transactions"
+            | "/api/multimint/balance"
+            | "/api/tor/health"
+            | "/api/rates"
+            | "/api/rates/history"
+            | "/api/budgets"
+            | "/api/ledger/balances"
+            | "/api/ledger/requests/{request_id}"
+            | "/api/ledger/reconciliation"
+            | "/api/retention"
+            | "/api/retention/rollups"
+            | "/api/jobs"
+            | "/api/jobs/{name}/runs"
+            | "/api/usage/export"
+            | "/api/analytics/spend"
+            | "/api/credits"
+            | "/api/transactions"
+            | "/api/statistics/{api_key_id}"
+            | "/api/mints"
+            | "/api/mints/active"
+            | "/api/mints/active-with-units"
+            | "/api/mints/{id}"
+            | "/api/search"
+            | "/api/search/{search_id}/status"
+            | "/api/search/pending"
+            | "/api/search/groups"
+            | "/api/nwc/auto-refill"
+            | "/api/nwc/auto-refill/mint/{mint_id}"
+            | "/api/user/profile"
+            | "/api/user/organizations"
+            | "/api/organization/members",
+        ) => View,
+        // The user's own sessions and memberships, whatever their role.
+        (
+            "POST",
+            "/api/auth/login"
+            | "/api/auth/logout"
+            | "/api/auth/switch-organization"
+            | "/api/organization/leave",
+        )
+        | ("GET", "/api/auth/sessions")
+        | ("DELETE", "/api/auth/sessions/{id}") => View,
+
+        (
+            "POST",
+            "/api/search"
+            | "/api/search/temporary"
+            | "/api/search/save"
+            | "/api/search/delete"
+            | "/api/search/groups"
+            | "/api/search/groups/update"
+            | "/api/search/groups/delete",
+        ) => UseServices,
+
+        ("GET" | "PUT" | "DELETE", "/api/api-keys/{id}") | ("GET" | "POST", "/api/api-keys") => {
+            ManageApiKeys
+        }
+
+        (
+            "POST",
+            "/api/wallet/redeem"
+            | "/api/wallet/redeem-pendings"
+            | "/api/multimint/redeem"
+            | "/api/multimint/topup"
+            | "/api/lightning/create-invoice"
+            | "/api/lightning/payment-status-with-mint",
+        )
+        | ("GET", "/api/lightning/payment-status/{quote_id}") => Deposit,
+
+        (
+            "POST",
+            "/api/wallet/send"
+            | "/api/multimint/send"
+            | "/api/multimint/transfer"
+            | "/api/lightning/create-payment"
+            // Melts the quote from create-payment, paying the invoice.
+            | "/api/lightning/complete-topup/{quote_id}"
+            | "/api/nwc/connections/pay",
+        ) => Withdraw,
+
+        (
+            "POST",
+            "/api/providers"
+            | "/api/providers/{id}/set-default"
+            | "/api/providers/{id}/activate"
+            | "/api/providers/{id}/deactivate"
+            | "/api/providers/refresh"
+            | "/api/proxy/models/refresh"
+            | "/api/server-config"
+            | "/api/retention/run"
+            | "/api/jobs/{name}/pause"
+            | "/api/jobs/{name}/resume"
+            | "/api/jobs/{name}/run"
+            | "/api/mints"
+            | "/api/mints/{id}/set-active"
+            | "/api/nwc/connections"
+            | "/api/nwc/test"
+            | "/api/nwc/auto-refill",
+        )
+        | (
+            "PUT",
+            "/api/providers/{id}"
+            | "/api/budgets/organization"
+            | "/api/budgets/api-keys/{api_key_id}"
+            | "/api/retention"
+            | "/api/retention/default"
+            | "/api/jobs/{name}"
+            | "/api/mints/{id}"
+            | "/api/nwc/connections/{connection_id}"
+            | "/api/nwc/auto-refill/{settings_id}",
+        )
+        | (
+            "DELETE",
+            "/api/providers/{id}"
+            | "/api/budgets/{id}"
+            | "/api/retention"
+            | "/api/mints/{id}"
+            | "/api/nwc/connections/{connection_id}"
+            | "/api/nwc/auto-refill/{settings_id}",
+        )
+        // NWC connection strings, the upstream API key and wallet internals
+        // are secrets.
+        | (
+            "GET",
+            "/api/nwc/connections"
+            | "/api/nwc/connections/{connection_id}"
+            | "/api/server-config"
+            | "/api/debug/wallet",
+        ) => Configure,
+
+        ("GET" | "POST", "/api/organization/invitations")
+        | ("DELETE", "/api/organization/invitations/{id}")
+        | ("PUT" | "DELETE", "/api/organization/members/{npub}") => ManageMembers,
+
+        ("GET", "/api/audit-log") => ViewAuditLog,
+
+        _ => return None,
+    };
+    Some(permission)
+}
+
+fn forbidden(message: &str) -> Response {
+    (
+        StatusCode::FORBIDDEN,
+        Json(json!({
+            "error": {
+                "message": message,
+                "type": "forbidden"
+            }
+        })),
+    )
+        .into_response()
+}
+
+/// Checks the caller's role against the route. Runs after authentication,
+/// which provides the [`UserContext`].
+pub async fn permission_middleware(request: Request, next: Next) -> Response {
+    let Some(path) = request.extensions().get::<MatchedPath>() else {
+        warn!(
+            "No matched route for {} {}; refusing",
+            request.method(),
+            request.uri().path()
+        );
+        return forbidden("This endpoint is not available");
+    };
+    let Some(user_ctx) = request.extensions().get::<UserContext>() else {
+        return forbidden("No user context");
+    };
+
+    let Some(permission) = required_permission(request.method(), path.as_str()) else {
+        warn!(
+            "No permission defined for {} {}; refusing",
+            request.method(),
+            path.as_str()
+        );
+        return forbidden("This endpoint is not available");
+    };
+    if !user_ctx.role.allows(permission) {
+        warn!(
+            "{} ({}) denied {} {}",
+            user_ctx.npub,
+            user_ctx.role,
+            request.method(),
+            path.as_str()
+        );
+        return forbidden(&format!(
+            "The {} role is not allowed to do this",
+            user_ctx.role
+        ));
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn withdrawals_need_owner_or_billing() {
+        for path in [
+            "/api/wallet/send",
+            "/api/multimint/send",
+            "/api/lightning/create-payment",
+            "/api/lightning/complete-topup/{quote_id}",
+        ] {
+            let permission = required_permission(&Method::POST, path).unwrap();
+            let allowed: Vec<Role> = [
+                Role::Owner,
+                Role::Admin,
+                Role::Member,
+                Role::Viewer,
+                Role::Billing,
+            ]
+            .into_iter()
+            .filter(|role| role.allows(permission))
+            .collect();
+            assert_eq!(allowed, vec![Role::Owner, Role::Billing], "{}", path);
+        }
+    }
+
+    #[test]
+    fn viewers_only_read() {
+        let view = required_permission(&Method::GET, "/api/mints").unwrap();
+        let delete = required_permission(&Method::DELETE, "/api/mints/{id}").unwrap();
+
+        assert!(Role::Viewer.allows(view));
+        assert!(!Role::Viewer.allows(delete));
+        assert!(Role::Admin.allows(delete));
+        assert!(!Role::Member.allows(delete));
+        assert_eq!(required_permission(&Method::GET, "/api/unknown"), None);
+    }
+
+    #[test]
+    fn secrets_need_configure() {
+        for path in [
+            "/api/server-config",
+            "/api/nwc/connections",
+            "/api/debug/wallet",
+        ] {
+            let permission = required_permission(&Method::GET, path).unwrap();
+            assert_eq!(permission, Permission::Configure, "{}", path);
+            assert!(!Role::Member.allows(permission), "{}", path);
+            assert!(!Role::Viewer.allows(permission), "{}", path);
+        }
+    }
+}
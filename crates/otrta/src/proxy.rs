@@ -1,18 +1,29 @@
 use crate::{
     db::{
         api_keys::get_api_key_by_id,
-        mint::{get_mint_by_url, get_mint_by_url_for_organization},
+        budgets::{get_applicable_budgets, release_budget_reservation, reserve_budget},
+        ledger::{record_entry, record_request_latency, EntryType, LedgerContext},
+        mint::{
+            get_mint_by_url, get_mint_by_url_for_organization, get_mint_unit_input_fee_ppk,
+            CurrencyUnit,
+        },
         models::get_model,
+        payment_intents::{
+            create_payment_intent, mark_response_received, mark_token_minted,
+            set_payment_intent_state, PaymentIntentState,
+        },
         provider::{get_default_provider, get_default_provider_for_organization_new},
-        transaction::{add_transaction, TransactionDirection, TransactionType},
+        transaction::{self, add_transaction, TransactionDirection, TransactionType},
         Pool,
     },
+    exchange_rate::REPORTING_CURRENCY,
+    metrics::{record_payment_failure, record_proxy_request},
     models::*,
     onion::{
         configure_client_with_tor_proxy, construct_url_with_protocol, get_onion_error_message,
-        log_onion_timing, start_onion_timing,
+        log_request_timing, start_request_timer,
     },
-    wallet::send_with_retry,
+    wallet::{amount_with_input_fee, send_with_retry},
 };
 use axum::{
     body::Body,
@@ -26,8 +37,24 @@ use reqwest::Client;
 use serde_json::json;
 use std::io;
 use std::sync::Arc;
+use tracing::{error, info, warn, Instrument};
 use uuid::Uuid;
 
+/// Amount to send in the mint's unit for a request priced at `cost_msats`,
+/// including the swap fee the provider pays to redeem the token, and the fee
+/// part of it. Returns `None` for fiat units when no exchange rate is
+/// available.
+fn mint_cost(
+    cost_msats: i64,
+    unit: &CurrencyUnit,
+    input_fee_ppk: u64,
+    btc_price: Option<f64>,
+) -> Option<(i64, i64)> {
+    let base = unit.from_msats(cost_msats, btc_price)?.max(0);
+    let total = amount_with_input_fee(base as u64, input_fee_ppk) as i64;
+    Some((total, total - base))
+}
+
 #[derive(serde::Deserialize)]
 struct OpenAIRequest {
     model: String,
@@ -37,29 +64,25 @@ struct OpenAIRequest {
 
 async fn redeem_token_on_error(
     state: &Arc<AppState>,
-    org_id: &Uuid,
+    ledger: &LedgerContext<'_>,
     token: &str,
     mint_url: &str,
     mint_currency_unit: &str,
-    api_key_id: Option<&str>,
-    user_id: Option<&str>,
     transaction_type: TransactionType,
-    provider_url: &str,
-    model_name: Option<&str>,
     status: StatusCode,
-) {
+) -> bool {
     let wallet = match state
         .multimint_manager
-        .get_or_create_multimint(org_id)
+        .get_or_create_multimint(&ledger.organization_id)
         .await
     {
         Ok(wallet) => wallet,
         Err(e) => {
-            eprintln!(
+            error!(
                 "Failed to get wallet for token redemption: mint={}, error={}",
                 mint_url, e
             );
-            return;
+            return false;
         }
     };
 
@@ -67,7 +90,7 @@ async fn redeem_token_on_error(
 
     match receive_result {
         Ok(res) => {
-            eprintln!(
+            error!(
                 "Redeemed original token after error response: mint={}, amount={}, status={}",
                 mint_url, res, status
             );
@@ -76,27 +99,96 @@ async fn redeem_token_on_error(
                 token,
                 &res,
                 TransactionDirection::Incoming,
-                api_key_id,
-                user_id,
                 transaction_type,
-                Some(provider_url),
-                Some(mint_currency_unit),
-                model_name,
+                &ledger.transaction_context(Some(mint_currency_unit)),
             )
             .await
             {
-                eprintln!(
+                error!(
                     "Failed to record redemption transaction: mint={}, error={}",
                     mint_url, e
                 );
             }
+            retain_token_for_debugging(state, token).await;
+            record_ledger_entry(
+                &state.db,
+                ledger,
+                EntryType::Refund,
+                &res,
+                mint_currency_unit,
+            )
+            .await;
+            true
         }
         Err(e) => {
-            eprintln!(
+            error!(
                 "Failed to redeem original token after error response: mint={}, status={}, error={}",
                 mint_url, status, e
             );
+            false
+        }
+    }
+}
+
+/// Logs a failed write to the payment journal. The request goes on: the
+/// journal only matters if this process dies before the payment settles.
+fn log_journal_error(result: Result<(), sqlx::Error>, intent_id: &Uuid) {
+    if let Err(e) = result {
+        error!("Failed to update payment intent {}: {}", intent_id, e);
+    }
+}
+
+async fn advance_payment_intent(db: &Pool, intent_id: Option<&Uuid>, state: PaymentIntentState) {
+    if let Some(intent_id) = intent_id {
+        log_journal_error(
+            set_payment_intent_state(db, intent_id, state).await,
+            intent_id,
+        );
+    }
+}
+
+/// Keeps the full token on its transaction rows when debug token retention
+/// is enabled; otherwise only the fingerprint written by `add_transaction`
+/// remains.
+async fn retain_token_for_debugging(state: &AppState, token: &str) {
+    let Some(retention) = state.debug_token_retention else {
+        return;
+    };
+    let expires_at = chrono::Utc::now()
+        + chrono::Duration::from_std(retention).unwrap_or(chrono::Duration::zero());
+
+    if let Err(e) = transaction::retain_token_for_debugging(&state.db, token, expires_at).await {
+        error!("Failed to retain token for debugging: {}", e);
+    }
+}
+
+async fn record_ledger_entry(
+    db: &Pool,
+    ledger: &LedgerContext<'_>,
+    entry_type: EntryType,
+    amount: &str,
+    unit: &str,
+) {
+    let amount = match amount.trim().parse::<i64>() {
+        Ok(amount) => amount,
+        Err(_) => {
+            warn!(
+                "Not recording {} ledger entry for request {}: invalid amount {:?}",
+                entry_type.as_str(),
+                ledger.request_id,
+                amount
+            );
+            return;
         }
+    };
+
+    if let Err(e) = record_entry(db, ledger, entry_type, amount, unit).await {
+        error!(
+            "Failed to record {} ledger entry for request {}: {}",
+            entry_type.as_str(),
+            ledger.request_id,
+            e
+        );
     }
 }
 
@@ -144,25 +236,47 @@ pub async fn forward_any_request(
         &path,
         Some(body_data),
         false,
-        api_key_id,
-        None, // organization_id - will be derived from api_key_id
-        user_id,
-        transaction_type,
+        ProxyCaller {
+            api_key_id,
+            // Derived from the API key
+            organization_id: None,
+            user_id,
+            transaction_type,
+        },
     )
     .await
 }
 
+/// Who a paid request is made for: the organization that pays, directly or
+/// through an API key, and how its transactions are attributed.
+pub struct ProxyCaller<'a> {
+    pub api_key_id: Option<&'a str>,
+    pub organization_id: Option<&'a Uuid>,
+    pub user_id: Option<&'a str>,
+    pub transaction_type: TransactionType,
+}
+
+#[tracing::instrument(
+    skip_all,
+    fields(path = %path, is_streaming = is_streaming, model = tracing::field::Empty,
+        ledger_request_id = tracing::field::Empty, provider = tracing::field::Empty)
+)]
 pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
     original_headers: HeaderMap,
     state: &Arc<AppState>,
     path: &str,
     body: Option<T>,
     is_streaming: bool,
-    api_key_id: Option<&str>,
-    organization_id: Option<&Uuid>,
-    user_id: Option<&str>,
-    transaction_type: TransactionType,
+    caller: ProxyCaller<'_>,
 ) -> Response<Body> {
+    let ProxyCaller {
+        api_key_id,
+        organization_id,
+        user_id,
+        transaction_type,
+    } = caller;
+    let _in_flight = state.in_flight.start();
+
     let org_id = if let Some(org_id) = organization_id {
         *org_id
     } else if let Some(api_key_id) = api_key_id {
@@ -245,7 +359,7 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
                     ).into_response();
                 }
                 Err(e) => {
-                    eprintln!("Failed to get global default provider: {}", e);
+                    error!("Failed to get global default provider: {}", e);
                     return (
                         StatusCode::SERVICE_UNAVAILABLE,
                         Json(json!({
@@ -261,7 +375,7 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
             }
         }
         Err(e) => {
-            eprintln!(
+            error!(
                 "Failed to get default provider for organization {}: {}",
                 org_id, e
             );
@@ -280,7 +394,7 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
     };
 
     let endpoint_url = construct_url_with_protocol(&server_config.url, path);
-    println!("Constructed proxy endpoint URL: {}", endpoint_url);
+    info!("Constructed proxy endpoint URL: {}", endpoint_url);
 
     let timeout_secs = if is_streaming { 300 } else { 60 }; // 5 min for streaming, 1 min for regular
     let mut client = match crate::onion::create_onion_client(
@@ -290,7 +404,7 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
     ) {
         Ok(client) => client,
         Err(e) => {
-            eprintln!("Failed to create client with Tor proxy: {}", e);
+            error!("Failed to create client with Tor proxy: {}", e);
             return (
                 StatusCode::BAD_GATEWAY,
                 Json(json!({
@@ -320,7 +434,7 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
         ) {
             Ok(builder) => builder,
             Err(e) => {
-                eprintln!("Failed to configure Tor proxy for streaming: {}", e);
+                error!("Failed to configure Tor proxy for streaming: {}", e);
                 return (
                     StatusCode::BAD_GATEWAY,
                     Json(json!({
@@ -359,16 +473,17 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
     let model = if let Some(ref model_name) = model_name {
         (get_model(&state.db, model_name, server_config.id).await).unwrap_or_default()
     } else {
-        eprintln!("No model name provided in request");
+        warn!("No model name provided in request");
         None
     };
+    let metrics_model = model.as_ref().map(|model| model.name.clone());
 
     let is_free_model = match model.clone() {
         Some(model) => model.is_free,
         None => false,
     };
 
-    eprintln!(
+    info!(
         "Processing request for model: {:?}, is_free: {}, cost_msats: {}",
         model_name,
         is_free_model,
@@ -403,55 +518,142 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
             .into_response();
     };
 
-    // Helper function to get mint info and sort by priority (msat first)
+    // The ledger's own ID keys the payment intent and budget reservation, so
+    // it can't be the client-chosen X-Request-Id; entries and the intent
+    // record that one alongside.
+    let request_id = Uuid::new_v4();
+    if !is_free_model {
+        let api_key_uuid = api_key_id.and_then(|id| Uuid::parse_str(id).ok());
+        if let Err(response) = enforce_spending_budgets(
+            state,
+            &org_id,
+            api_key_uuid.as_ref(),
+            &request_id,
+            cost_msats,
+        )
+        .await
+        {
+            return response;
+        }
+    }
+
+    // Helper function to get mint info and sort by priority (cheapest effective cost first)
     async fn get_sorted_mints_with_info(
         db: &crate::db::Pool,
+        exchange_rates: &crate::exchange_rate::ExchangeRateService,
         mint_urls: &[String],
         org_id: &uuid::Uuid,
-    ) -> Vec<(String, String)> {
+        cost_msats: i64,
+    ) -> Vec<(String, String, i64, i64)> {
         let mut mints_with_units = Vec::new();
 
         for mint_url in mint_urls {
-            let currency_unit = match get_mint_by_url_for_organization(db, mint_url, org_id).await {
-                Ok(Some(mint)) => mint.currency_unit,
+            let mint = match get_mint_by_url_for_organization(db, mint_url, org_id).await {
+                Ok(Some(mint)) => Some(mint),
                 Ok(None) => {
                     match get_mint_by_url(db, mint_url).await {
-                        Ok(Some(mint)) => mint.currency_unit,
+                        Ok(Some(mint)) => Some(mint),
                         Ok(None) => {
-                            eprintln!("Mint not found for URL: {} (neither organization-specific nor global)", mint_url);
-                            "msat".to_string()
+                            warn!("Mint not found for URL: {} (neither organization-specific nor global)", mint_url);
+                            None
                         }
                         Err(e) => {
-                            eprintln!(
+                            error!(
                                 "Error fetching global mint info for URL: {}: {}",
                                 mint_url, e
                             );
-                            "msat".to_string()
+                            None
                         }
                     }
                 }
                 Err(e) => {
-                    eprintln!(
+                    error!(
                         "Error fetching mint info for URL: {} and organization: {}: {}",
                         mint_url, org_id, e
                     );
-                    "msat".to_string()
+                    None
+                }
+            };
+
+            let (currency_unit, input_fee_ppk) = match mint {
+                Some(mint) => {
+                    let fee = get_mint_unit_input_fee_ppk(db, mint.id, &mint.currency_unit)
+                        .await
+                        .unwrap_or_else(|e| {
+                            error!("Error fetching keyset fee for mint {}: {}", mint_url, e);
+                            0
+                        });
+                    (mint.currency_unit, fee)
                 }
+                None => ("msat".to_string(), 0),
             };
-            mints_with_units.push((mint_url.clone(), currency_unit));
+            mints_with_units.push((mint_url.clone(), currency_unit, input_fee_ppk));
         }
 
-        // Sort mints: msat first, then sat
-        mints_with_units.sort_by(|a, b| match (a.1.as_str(), b.1.as_str()) {
-            ("msat", "sat") => std::cmp::Ordering::Less,
-            ("sat", "msat") => std::cmp::Ordering::Greater,
-            _ => std::cmp::Ordering::Equal,
-        });
+        // Fiat-denominated mints are priced via the current BTC exchange rate
+        let mut btc_prices: std::collections::HashMap<String, Option<f64>> =
+            std::collections::HashMap::new();
+        let mut priced_mints = Vec::new();
+
+        for (mint_url, currency_unit, input_fee_ppk) in mints_with_units {
+            let unit = currency_unit
+                .parse::<CurrencyUnit>()
+                .unwrap_or(CurrencyUnit::Msat);
+
+            let btc_price = match unit.fiat_code() {
+                Some(code) => {
+                    if !btc_prices.contains_key(code) {
+                        let price = match exchange_rates.get_payment_rate(code).await {
+                            Ok(rate) => {
+                                if rate.stale {
+                                    warn!(
+                                        "Using stale BTC/{} rate from {} ({}s old)",
+                                        code, rate.source, rate.age_seconds
+                                    );
+                                }
+                                Some(rate.rate)
+                            }
+                            Err(e) => {
+                                error!("Failed to fetch BTC/{} price: {}", code, e);
+                                None
+                            }
+                        };
+                        btc_prices.insert(code.to_string(), price);
+                    }
+                    btc_prices[code]
+                }
+                None => None,
+            };
+
+            let Some((cost, fee)) = mint_cost(cost_msats, &unit, input_fee_ppk, btc_price) else {
+                info!(
+                    "Skipping mint {}: no exchange rate to price {} payments",
+                    mint_url, unit
+                );
+                continue;
+            };
+            let cost_in_msats = unit.to_msats(cost, btc_price).unwrap_or(i64::MAX);
+            priced_mints.push((mint_url, unit.to_string(), cost, fee, cost_in_msats));
+        }
+
+        // Sort mints by what the request actually costs in msats once swap fees,
+        // rounding and conversion are included; on a tie msat mints go first.
+        priced_mints.sort_by_key(|(_, unit, _, _, cost_in_msats)| (*cost_in_msats, unit != "msat"));
 
-        mints_with_units
+        priced_mints
+            .into_iter()
+            .map(|(mint_url, unit, cost, fee, _)| (mint_url, unit, cost, fee))
+            .collect()
     }
 
-    let sorted_mints = get_sorted_mints_with_info(&state.db, &server_config.mints, &org_id).await;
+    let sorted_mints = get_sorted_mints_with_info(
+        &state.db,
+        &state.exchange_rates,
+        &server_config.mints,
+        &org_id,
+        cost_msats,
+    )
+    .await;
     if sorted_mints.is_empty() {
         return (
             StatusCode::BAD_REQUEST,
@@ -467,23 +669,37 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
     }
 
     // Start with the first (highest priority) mint for initial cost calculation
-    let (initial_mint_url, initial_mint_currency_unit) = sorted_mints.first().unwrap();
+    let (initial_mint_url, initial_mint_currency_unit, cost, _) = sorted_mints.first().unwrap();
     let mut mint_url = initial_mint_url.clone();
     let mut mint_currency_unit = initial_mint_currency_unit.clone();
 
-    let cost = if mint_currency_unit == "sat" {
-        (cost_msats + 999) / 1000
-    } else {
-        cost_msats
-    };
-
-    eprintln!(
+    info!(
         "Cost calculation: model={:?}, cost_msats={}, mint_unit={}, final_cost={}, available_mints={}",
         model_name, cost_msats, mint_currency_unit, cost, sorted_mints.len()
     );
 
+    let span = tracing::Span::current();
+    span.record("model", model_name.as_deref().unwrap_or(""));
+    span.record("provider", server_config.url.as_str());
+
+    let ledger = LedgerContext {
+        request_id,
+        api_key_id,
+        user_id,
+        provider_url: Some(&server_config.url),
+        model: model_name.as_deref(),
+        ..LedgerContext::new(org_id)
+    };
+    span.record(
+        "ledger_request_id",
+        tracing::field::display(ledger.request_id),
+    );
+
+    // Set once the payment is journaled; free requests have nothing to track.
+    let mut payment_intent: Option<Uuid> = None;
+
     let token = if is_free_model {
-        eprintln!(
+        info!(
             "Recording free model transaction: model={:?}, provider={}, user={:?}",
             model_name, server_config.url, user_id
         );
@@ -492,18 +708,22 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
             "",
             "0",
             TransactionDirection::Outgoing,
-            api_key_id,
-            user_id,
             transaction_type.clone(),
-            Some(&server_config.url),
-            Some(&mint_currency_unit),
-            model_name.as_deref(),
+            &ledger.transaction_context(Some(&mint_currency_unit)),
         )
         .await
         .unwrap_or_else(|e| {
-            eprintln!("Failed to record free model transaction: {}", e);
+            error!("Failed to record free model transaction: {}", e);
             uuid::Uuid::new_v4()
         });
+        record_ledger_entry(
+            &state.db,
+            &ledger,
+            EntryType::Charge,
+            "0",
+            &mint_currency_unit,
+        )
+        .await;
         String::new()
     } else {
         let wallet = match state
@@ -513,7 +733,7 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
         {
             Ok(wallet) => wallet,
             Err(e) => {
-                eprintln!(
+                error!(
                     "Failed to get organization wallet for org {}: {}",
                     org_id, e
                 );
@@ -531,6 +751,14 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
             }
         };
 
+        match create_payment_intent(&state.db, &ledger).await {
+            Ok(()) => payment_intent = Some(ledger.request_id),
+            Err(e) => error!(
+                "Failed to journal payment intent for request {}: {}",
+                ledger.request_id, e
+            ),
+        }
+
         // Try each mint in priority order until payment succeeds
         let mut successful_mint_url = None;
         let mut successful_currency_unit = None;
@@ -538,14 +766,10 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
         let mut token = String::new();
         let mut _last_error = None;
 
-        for (current_mint_url, current_currency_unit) in &sorted_mints {
-            let current_cost = if current_currency_unit == "sat" {
-                (cost_msats + 999) / 1000
-            } else {
-                cost_msats
-            };
+        for (current_mint_url, current_currency_unit, current_cost, current_fee) in &sorted_mints {
+            let current_cost = *current_cost;
 
-            eprintln!(
+            info!(
                 "Attempting payment with mint: {}, unit: {}, cost: {}",
                 current_mint_url, current_currency_unit, current_cost
             );
@@ -556,20 +780,49 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
                 current_mint_url,
                 Some(3),
                 &state.db,
-                api_key_id,
-                user_id,
-                Some(&server_config.url),
-                Some(current_currency_unit),
-                model_name.as_deref(),
+                &ledger.transaction_context(Some(current_currency_unit)),
             )
             .await;
 
             match token_result {
                 Ok(success_token) => {
-                    eprintln!(
+                    info!(
                         "Payment successful with mint: {}, unit: {}, cost: {}",
                         current_mint_url, current_currency_unit, current_cost
                     );
+                    if let Some(intent_id) = &payment_intent {
+                        log_journal_error(
+                            mark_token_minted(
+                                &state.db,
+                                intent_id,
+                                current_mint_url,
+                                current_currency_unit,
+                                current_cost,
+                                &success_token,
+                            )
+                            .await,
+                            intent_id,
+                        );
+                    }
+                    retain_token_for_debugging(state, &success_token).await;
+                    record_ledger_entry(
+                        &state.db,
+                        &ledger,
+                        EntryType::Charge,
+                        &(current_cost - current_fee).to_string(),
+                        current_currency_unit,
+                    )
+                    .await;
+                    if *current_fee > 0 {
+                        record_ledger_entry(
+                            &state.db,
+                            &ledger,
+                            EntryType::Fee,
+                            &current_fee.to_string(),
+                            current_currency_unit,
+                        )
+                        .await;
+                    }
                     successful_mint_url = Some(current_mint_url.clone());
                     successful_currency_unit = Some(current_currency_unit.clone());
                     _final_cost = current_cost;
@@ -577,26 +830,49 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
                     break;
                 }
                 Err(e) => {
-                    eprintln!(
+                    error!(
                         "Payment failed with mint: {}, unit: {}, cost: {}, error: {:?}",
                         current_mint_url, current_currency_unit, current_cost, e
                     );
+                    record_payment_failure(current_mint_url);
                     _last_error = Some(e);
                     continue;
                 }
             }
         }
 
-        // If no mint succeeded, handle the failure
-        if successful_mint_url.is_none() {
-            eprintln!(
+        if let Some((url, unit)) = successful_mint_url.zip(successful_currency_unit) {
+            // Update the variables for successful payment
+            mint_url = url;
+            mint_currency_unit = unit;
+
+            info!(
+                "Final payment successful: mint={}, unit={}, cost={}",
+                mint_url, mint_currency_unit, _final_cost
+            );
+        } else {
+            // If no mint succeeded, handle the failure
+            advance_payment_intent(
+                &state.db,
+                payment_intent.as_ref(),
+                PaymentIntentState::Failed,
+            )
+            .await;
+            payment_intent = None;
+            if let Err(e) = release_budget_reservation(&state.db, &ledger.request_id).await {
+                warn!(
+                    "Failed to release budget reservation for request {}: {}",
+                    ledger.request_id, e
+                );
+            }
+            warn!(
                 "All mints exhausted. Payment token generation failed for model: {:?}, tried {} mints",
                 model_name, sorted_mints.len()
             );
 
             // If the model should be free or cost is 0, continue without payment
-            if is_free_model || cost == 0 {
-                eprintln!("Model is free or zero cost, proceeding without payment token");
+            if is_free_model || *cost == 0 {
+                info!("Model is free or zero cost, proceeding without payment token");
                 token = String::new();
             } else {
                 return (
@@ -610,11 +886,11 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
                                 "model": model_name,
                                 "cost_msats": cost_msats,
                                 "mints_tried": sorted_mints.len(),
-                                "mints": sorted_mints.iter().map(|(url, unit)| {
+                                "mints": sorted_mints.iter().map(|(url, unit, cost, _)| {
                                     json!({
                                         "url": url,
                                         "currency_unit": unit,
-                                        "cost": if unit == "sat" { (cost_msats + 999) / 1000 } else { cost_msats }
+                                        "cost": cost
                                     })
                                 }).collect::<Vec<_>>()
                             }
@@ -623,15 +899,6 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
                 )
                     .into_response();
             }
-        } else {
-            // Update the variables for successful payment
-            mint_url = successful_mint_url.unwrap();
-            mint_currency_unit = successful_currency_unit.unwrap();
-
-            eprintln!(
-                "Final payment successful: mint={}, unit={}, cost={}",
-                mint_url, mint_currency_unit, _final_cost
-            );
         }
 
         token
@@ -647,13 +914,40 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
         req_builder = req_builder.header(header::ACCEPT, accept);
     }
 
-    let start_time = start_onion_timing(&endpoint_url);
+    let request_started = start_request_timer();
+
+    advance_payment_intent(
+        &state.db,
+        payment_intent.as_ref(),
+        PaymentIntentState::UpstreamSent,
+    )
+    .await;
 
     let response = if let Ok(resp) = req_builder.send().await {
-        log_onion_timing(start_time, &endpoint_url, "proxy");
+        log_request_timing(request_started, &endpoint_url, "proxy");
+        record_proxy_request(
+            metrics_model.as_deref(),
+            &server_config.url,
+            resp.status().as_str(),
+        );
+        let latency_ms = request_started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+        if let Err(e) = record_request_latency(&state.db, &ledger.request_id, latency_ms).await {
+            error!(
+                "Failed to record latency for request {}: {}",
+                ledger.request_id, e
+            );
+        }
         let status = resp.status();
         let headers = resp.headers().clone();
 
+        if let Some(intent_id) = &payment_intent {
+            let change_token = headers.get("X-Cashu").and_then(|v| v.to_str().ok());
+            log_journal_error(
+                mark_response_received(&state.db, intent_id, change_token).await,
+                intent_id,
+            );
+        }
+
         if status != StatusCode::OK {
             if let Some(change_sats) = headers.get("X-Cashu") {
                 if let Ok(in_token) = change_sats.to_str() {
@@ -682,43 +976,58 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
 
                     match receive_result {
                         Ok(res) => {
-                            eprintln!("Received change token: amount={}", res);
+                            info!("Received change token: amount={}", res);
                             if let Err(e) = add_transaction(
                                 &state.db,
                                 in_token,
                                 &res,
                                 TransactionDirection::Incoming,
-                                api_key_id,
-                                user_id,
                                 transaction_type.clone(),
-                                Some(&server_config.url),
-                                Some(&mint_currency_unit),
-                                model_name.as_deref(),
+                                &ledger.transaction_context(Some(&mint_currency_unit)),
                             )
                             .await
                             {
-                                eprintln!("Failed to record change token transaction: {}", e);
+                                error!("Failed to record change token transaction: {}", e);
                             }
+                            retain_token_for_debugging(state, in_token).await;
+                            record_ledger_entry(
+                                &state.db,
+                                &ledger,
+                                EntryType::Refund,
+                                &res,
+                                &mint_currency_unit,
+                            )
+                            .await;
+                            advance_payment_intent(
+                                &state.db,
+                                payment_intent.as_ref(),
+                                PaymentIntentState::Refunded,
+                            )
+                            .await;
                         }
                         Err(e) => {
-                            eprintln!("Failed to receive change token: {}", e);
+                            error!("Failed to receive change token: {}", e);
                         }
                     }
                 }
-            } else if !token.is_empty() && !is_free_model {
-                redeem_token_on_error(
-                    &state,
-                    &org_id,
+            } else if !token.is_empty()
+                && !is_free_model
+                && redeem_token_on_error(
+                    state,
+                    &ledger,
                     &token,
                     &mint_url,
                     &mint_currency_unit,
-                    api_key_id,
-                    user_id,
                     transaction_type.clone(),
-                    &server_config.url,
-                    model_name.as_deref(),
                     status,
                 )
+                .await
+            {
+                advance_payment_intent(
+                    &state.db,
+                    payment_intent.as_ref(),
+                    PaymentIntentState::Refunded,
+                )
                 .await;
             }
             return (
@@ -767,7 +1076,7 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
 
                 match receive_result {
                     Ok(res) => {
-                        eprintln!(
+                        info!(
                             "Received change token on successful response: amount={}",
                             res
                         );
@@ -776,29 +1085,47 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
                             in_token,
                             &res,
                             TransactionDirection::Incoming,
-                            api_key_id,
-                            user_id,
                             transaction_type.clone(),
-                            Some(&server_config.url),
-                            Some(&mint_currency_unit),
-                            model_name.as_deref(),
+                            &ledger.transaction_context(Some(&mint_currency_unit)),
                         )
                         .await
                         {
-                            eprintln!(
+                            error!(
                                 "Failed to record successful change token transaction: {}",
                                 e
                             );
                         }
+                        retain_token_for_debugging(state, in_token).await;
+                        record_ledger_entry(
+                            &state.db,
+                            &ledger,
+                            EntryType::Change,
+                            &res,
+                            &mint_currency_unit,
+                        )
+                        .await;
+                        advance_payment_intent(
+                            &state.db,
+                            payment_intent.as_ref(),
+                            PaymentIntentState::Completed,
+                        )
+                        .await;
                     }
                     Err(e) => {
-                        eprintln!(
+                        error!(
                             "Failed to receive change token on successful response: {}",
                             e
                         );
                     }
                 }
             }
+        } else {
+            advance_payment_intent(
+                &state.db,
+                payment_intent.as_ref(),
+                PaymentIntentState::Completed,
+            )
+            .await;
         }
 
         let response_headers = response.headers_mut().unwrap();
@@ -824,18 +1151,24 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
                     .await
                 {
                     if let Err(err) = wallet.redeem_pendings().await {
-                        eprintln!("Error redeeming pendings: {:?}", err);
+                        error!("Error redeeming pendings: {:?}", err);
                     }
                 }
-            });
+            }
+            .in_current_span());
 
-            eprintln!("Error creating streaming response: {}", e);
+            error!("Error creating streaming response: {}", e);
             Response::builder()
                 .status(StatusCode::BAD_GATEWAY)
                 .body(Body::from("{\"error\":{\"message\":\"Error creating streaming response\",\"type\":\"streaming_error\",\"code\":\"stream_creation_failed\"}}".to_string()))
                 .unwrap()
         });
     } else {
+        record_proxy_request(
+            metrics_model.as_deref(),
+            &server_config.url,
+            "upstream_error",
+        );
         let wallet = match state
             .multimint_manager
             .get_or_create_multimint(&org_id)
@@ -861,7 +1194,7 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
 
         match receive_result {
             Ok(res) => {
-                eprintln!(
+                warn!(
                     "Token redemption after failed request: mint={}, amount={}",
                     mint_url, res
                 );
@@ -870,23 +1203,34 @@ pub async fn forward_request_with_payment_with_body<T: serde::Serialize>(
                     &token,
                     &res,
                     TransactionDirection::Incoming,
-                    api_key_id,
-                    user_id,
                     transaction_type,
-                    Some(&server_config.url),
-                    Some(&mint_currency_unit),
-                    model_name.as_deref(),
+                    &ledger.transaction_context(Some(&mint_currency_unit)),
                 )
                 .await
                 {
-                    eprintln!(
+                    error!(
                         "Failed to record failed request redemption transaction: {}",
                         e
                     );
                 }
+                retain_token_for_debugging(state, &token).await;
+                record_ledger_entry(
+                    &state.db,
+                    &ledger,
+                    EntryType::Refund,
+                    &res,
+                    &mint_currency_unit,
+                )
+                .await;
+                advance_payment_intent(
+                    &state.db,
+                    payment_intent.as_ref(),
+                    PaymentIntentState::Refunded,
+                )
+                .await;
             }
             Err(e) => {
-                eprintln!(
+                error!(
                     "Failed to redeem token after failed request: mint={}, error={}",
                     mint_url, e
                 );
@@ -992,7 +1336,7 @@ pub async fn forward_request(
                     ).into_response();
                 }
                 Err(e) => {
-                    eprintln!("Failed to get global default provider: {}", e);
+                    error!("Failed to get global default provider: {}", e);
                     return (
                         StatusCode::SERVICE_UNAVAILABLE,
                         Json(json!({
@@ -1008,7 +1352,7 @@ pub async fn forward_request(
             }
         }
         Err(e) => {
-            eprintln!(
+            error!(
                 "Failed to get default provider for organization {}: {}",
                 org_id, e
             );
@@ -1027,7 +1371,7 @@ pub async fn forward_request(
     };
 
     let endpoint_url = construct_url_with_protocol(&server_config.url, path);
-    println!("Constructed forward_request endpoint URL: {}", endpoint_url);
+    info!("Constructed forward_request endpoint URL: {}", endpoint_url);
 
     let timeout_secs = 60; // 1 min for regular requests
     let client = match crate::onion::create_onion_client(
@@ -1037,7 +1381,7 @@ pub async fn forward_request(
     ) {
         Ok(client) => client,
         Err(e) => {
-            eprintln!("Failed to create client with Tor proxy: {}", e);
+            error!("Failed to create client with Tor proxy: {}", e);
             return (
                 StatusCode::BAD_GATEWAY,
                 Json(json!({
@@ -1055,24 +1399,24 @@ pub async fn forward_request(
     let mut req_builder = client.get(&endpoint_url);
     req_builder = req_builder.header(header::CONTENT_TYPE, "application/json");
 
-    let start_time = start_onion_timing(&endpoint_url);
+    let start_time = start_request_timer();
 
     match req_builder.send().await {
         Ok(resp) => {
-            log_onion_timing(start_time, &endpoint_url, "forward_request");
+            log_request_timing(start_time, &endpoint_url, "forward_request");
             let status = resp.status();
             let response = Response::builder().status(status);
 
             match resp.bytes().await {
                 Ok(bytes) => response.body(Body::from(bytes)).unwrap_or_else(|e| {
-                    eprintln!("Error creating response: {}", e);
+                    error!("Error creating response: {}", e);
                     Response::builder()
                         .status(StatusCode::BAD_GATEWAY)
                         .body(Body::from("{\"error\":{\"message\":\"Error processing provider response\",\"type\":\"gateway_error\",\"code\":\"response_processing_failed\"}}".to_string()))
                         .unwrap()
                 }),
                 Err(e) => {
-                    eprintln!("Error reading response body: {}", e);
+                    error!("Error reading response body: {}", e);
                     Response::builder()
                         .status(StatusCode::BAD_GATEWAY)
                         .body(Body::from("{\"error\":{\"message\":\"Error reading response from provider\",\"type\":\"gateway_error\",\"code\":\"response_read_failed\"}}".to_string()))
@@ -1081,7 +1425,7 @@ pub async fn forward_request(
             }
         }
         Err(error) => {
-            eprintln!("Error forwarding request: {}", error);
+            error!("Error forwarding request: {}", error);
 
             let error_msg = get_onion_error_message(&error, &endpoint_url, "forward_request");
 
@@ -1096,3 +1440,99 @@ pub async fn forward_request(
         }
     }
 }
+
+/// Rejects the request when paying `cost_msats` would take the organization or
+/// the API key past an active fiat budget, and otherwise reserves the cost
+/// under `request_id` until the charge is journaled. Fails closed when budgets
+/// exist but no exchange rate is available to value the request.
+async fn enforce_spending_budgets(
+    state: &AppState,
+    org_id: &Uuid,
+    api_key_id: Option<&Uuid>,
+    request_id: &Uuid,
+    cost_msats: i64,
+) -> Result<(), Response<Body>> {
+    let lookup_failed = || {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": {
+                    "message": "Failed to check spending budget",
+                    "type": "budget_error",
+                    "code": "budget_lookup_failed"
+                }
+            })),
+        )
+            .into_response()
+    };
+
+    match get_applicable_budgets(&state.db, org_id, api_key_id).await {
+        Ok(budgets) if budgets.is_empty() => return Ok(()),
+        Ok(_) => {}
+        Err(e) => {
+            error!("Failed to load spending budgets for {}: {}", org_id, e);
+            return Err(lookup_failed());
+        }
+    }
+
+    // Refuses rates past the hard staleness cutoff, like mint pricing.
+    let rate = match state
+        .exchange_rates
+        .get_payment_rate(REPORTING_CURRENCY)
+        .await
+    {
+        Ok(rate) => rate,
+        Err(e) => {
+            warn!("No exchange rate available to enforce budgets: {}", e);
+            return Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({
+                    "error": {
+                        "message": "Spending budget cannot be checked: no exchange rate available",
+                        "type": "budget_error",
+                        "code": "exchange_rate_unavailable"
+                    }
+                })),
+            )
+                .into_response());
+        }
+    };
+
+    match reserve_budget(
+        &state.db, org_id, api_key_id, request_id, cost_msats, rate.rate,
+    )
+    .await
+    {
+        Ok(None) => Ok(()),
+        Ok(Some(exceeded)) => {
+            let budget = exceeded.budget;
+            let scope = if budget.api_key_id.is_some() {
+                "API key"
+            } else {
+                "organization"
+            };
+            Err((
+                StatusCode::PAYMENT_REQUIRED,
+                Json(json!({
+                    "error": {
+                        "message": format!(
+                            "{} {} budget of {:.2} {} exceeded ({:.2} spent)",
+                            scope,
+                            budget.period,
+                            budget.amount,
+                            budget.currency.to_uppercase(),
+                            exceeded.spent
+                        ),
+                        "type": "budget_error",
+                        "code": "budget_exceeded"
+                    }
+                })),
+            )
+                .into_response())
+        }
+        Err(e) => {
+            error!("Failed to reserve spending budget for {}: {}", org_id, e);
+            Err(lookup_failed())
+        }
+    }
+}
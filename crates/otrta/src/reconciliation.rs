@@ -0,0 +1,125 @@
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+use crate::{
+    db::{
+        ledger::{canonical_amount, get_wallet_balances},
+        Pool,
+    },
+    error::AppError,
+    multimint::MultimintWalletWrapper,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ReconciliationLine {
+    pub unit: String,
+    pub ledger_balance: i64,
+    pub wallet_balance: i64,
+    /// Wallet minus ledger. Positive means the wallet holds funds the ledger
+    /// has no record of (e.g. top-ups made before the ledger existed).
+    pub difference: i64,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ReconciliationReport {
+    pub organization_id: Uuid,
+    pub generated_at: DateTime<Utc>,
+    pub balanced: bool,
+    pub lines: Vec<ReconciliationLine>,
+}
+
+/// Compares the ledger's wallet account with what the organization wallet
+/// actually holds, per canonical unit.
+pub async fn reconcile(
+    db: &Pool,
+    wallet: &MultimintWalletWrapper,
+    organization_id: &Uuid,
+) -> Result<ReconciliationReport, AppError> {
+    let ledger: HashMap<String, i64> = get_wallet_balances(db, organization_id)
+        .await?
+        .into_iter()
+        .collect();
+
+    let balance = wallet
+        .get_total_balance()
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Failed to read wallet balance: {}", e)))?;
+
+    let mut actual = HashMap::new();
+    for mint in &balance.balances_by_mint {
+        let (amount, unit) = canonical_amount(mint.balance as i64, &mint.unit);
+        *actual.entry(unit).or_insert(0) += amount;
+    }
+
+    let lines = compare_balances(&ledger, &actual);
+
+    Ok(ReconciliationReport {
+        organization_id: *organization_id,
+        generated_at: Utc::now(),
+        balanced: lines.iter().all(|line| line.difference == 0),
+        lines,
+    })
+}
+
+fn compare_balances(
+    ledger: &HashMap<String, i64>,
+    wallet: &HashMap<String, i64>,
+) -> Vec<ReconciliationLine> {
+    let mut units = BTreeMap::new();
+    for (unit, amount) in ledger {
+        units.entry(unit.clone()).or_insert((0, 0)).0 = *amount;
+    }
+    for (unit, amount) in wallet {
+        units.entry(unit.clone()).or_insert((0, 0)).1 = *amount;
+    }
+
+    units
+        .into_iter()
+        .map(
+            |(unit, (ledger_balance, wallet_balance))| ReconciliationLine {
+                unit,
+                ledger_balance,
+                wallet_balance,
+                difference: wallet_balance - ledger_balance,
+            },
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_balances() {
+        let ledger = HashMap::from([("msat".to_string(), 5_000), ("usd".to_string(), 120)]);
+        let wallet = HashMap::from([("msat".to_string(), 7_000), ("eur".to_string(), 50)]);
+
+        let lines = compare_balances(&ledger, &wallet);
+
+        assert_eq!(
+            lines,
+            vec![
+                ReconciliationLine {
+                    unit: "eur".to_string(),
+                    ledger_balance: 0,
+                    wallet_balance: 50,
+                    difference: 50,
+                },
+                ReconciliationLine {
+                    unit: "msat".to_string(),
+                    ledger_balance: 5_000,
+                    wallet_balance: 7_000,
+                    difference: 2_000,
+                },
+                ReconciliationLine {
+                    unit: "usd".to_string(),
+                    ledger_balance: 120,
+                    wallet_balance: 0,
+                    difference: -120,
+                },
+            ]
+        );
+    }
+}
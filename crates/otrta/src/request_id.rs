@@ -0,0 +1,88 @@
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+use uuid::Uuid;
+
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// ID of the HTTP request being handled by the current task, if any. Work
+/// moved to a spawned task doesn't inherit it.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Accepts a caller-supplied request ID only if it is short, printable ASCII,
+/// so it can be logged and echoed back safely.
+fn accept_request_id(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty()
+        || value.len() > MAX_REQUEST_ID_LEN
+        || !value.bytes().all(|b| b.is_ascii_graphic())
+    {
+        return None;
+    }
+    Some(value.to_string())
+}
+
+/// Takes the request ID from `X-Request-Id` or generates one, runs the rest
+/// of the stack inside a `request` span carrying it, and echoes it back on
+/// the response.
+pub async fn request_id_middleware(request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(accept_request_id)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+
+    let mut response = REQUEST_ID
+        .scope(request_id.clone(), next.run(request).instrument(span))
+        .await;
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response
+            .headers_mut()
+            .insert(REQUEST_ID_HEADER.clone(), value);
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_request_id() {
+        assert_eq!(accept_request_id(" abc-123 "), Some("abc-123".to_string()));
+        assert_eq!(accept_request_id(""), None);
+        assert_eq!(accept_request_id("has space"), None);
+        assert_eq!(accept_request_id(&"a".repeat(129)), None);
+    }
+
+    #[tokio::test]
+    async fn test_current_request_id_is_task_scoped() {
+        assert_eq!(current_request_id(), None);
+        let id = REQUEST_ID
+            .scope("req-1".to_string(), async { current_request_id() })
+            .await;
+        assert_eq!(id, Some("req-1".to_string()));
+    }
+}
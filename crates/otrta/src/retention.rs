@@ -0,0 +1,176 @@
+use chrono::{DateTime, Duration, Utc};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{
+    db::{
+        retention::{
+            aggregate_transactions_before, delete_final_payment_intents_before,
+            delete_redeemed_credits_before, delete_transactions_before, get_organization_ids,
+            get_retention_policy, purge_searches_before, record_retention_run, RetentionPolicy,
+            TransactionRetentionAction,
+        },
+        Pool,
+    },
+    error::AppError,
+};
+
+#[derive(Debug, Default, Clone, serde::Serialize)]
+pub struct RetentionReport {
+    /// `None` for the instance-wide pass over unscoped data (credits).
+    pub organization_id: Option<Uuid>,
+    pub policy_id: Option<Uuid>,
+    pub transactions_aggregated: u64,
+    pub transactions_deleted: u64,
+    pub payment_intents_deleted: u64,
+    pub searches_purged: u64,
+    pub credits_deleted: u64,
+}
+
+impl RetentionReport {
+    pub fn is_empty(&self) -> bool {
+        self.transactions_aggregated == 0
+            && self.transactions_deleted == 0
+            && self.payment_intents_deleted == 0
+            && self.searches_purged == 0
+            && self.credits_deleted == 0
+    }
+}
+
+/// Start of the retention window for a policy setting of `days`, or `None`
+/// when the data is kept forever.
+pub fn retention_cutoff(now: DateTime<Utc>, days: Option<i32>) -> Option<DateTime<Utc>> {
+    days.filter(|days| *days > 0)
+        .map(|days| now - Duration::days(days as i64))
+}
+
+/// Applies one organization's policy (its own, or the instance default).
+/// Finished payment intents expire with transactions. Ledger entries and
+/// postings are kept: account balances and wallet reconciliation are sums
+/// over every posting, so dropping old ones would change them.
+pub async fn enforce_organization_policy(
+    db: &Pool,
+    organization_id: &Uuid,
+    policy: &RetentionPolicy,
+) -> Result<RetentionReport, AppError> {
+    let now = Utc::now();
+    let mut report = RetentionReport {
+        organization_id: Some(*organization_id),
+        policy_id: Some(policy.id),
+        ..Default::default()
+    };
+
+    if let Some(cutoff) = retention_cutoff(now, policy.transaction_retention_days) {
+        match policy.transaction_action() {
+            TransactionRetentionAction::Aggregate => {
+                report.transactions_aggregated =
+                    aggregate_transactions_before(db, organization_id, cutoff).await?;
+            }
+            TransactionRetentionAction::Delete => {
+                report.transactions_deleted =
+                    delete_transactions_before(db, organization_id, cutoff).await?;
+            }
+        }
+        report.payment_intents_deleted =
+            delete_final_payment_intents_before(db, organization_id, cutoff).await?;
+    }
+
+    if let Some(cutoff) = retention_cutoff(now, policy.search_retention_days) {
+        report.searches_purged = purge_searches_before(db, organization_id, cutoff).await?;
+    }
+
+    Ok(report)
+}
+
+/// Enforces every organization's retention policy, plus the instance default
+/// for credits, and stores the outcome on the policy rows. Organizations that
+/// fail are logged and skipped so one bad row doesn't block the rest.
+pub async fn enforce_retention_policies(db: &Pool) -> Result<Vec<RetentionReport>, AppError> {
+    let default_policy = get_retention_policy(db, None).await?;
+    let mut reports = Vec::new();
+
+    for organization_id in get_organization_ids(db).await? {
+        let own_policy = get_retention_policy(db, Some(&organization_id)).await?;
+        let Some(policy) = own_policy.as_ref().or(default_policy.as_ref()) else {
+            continue;
+        };
+
+        match enforce_organization_policy(db, &organization_id, policy).await {
+            Ok(report) => {
+                if own_policy.is_some() {
+                    record_report(db, &policy.id, &report).await;
+                }
+                reports.push(report);
+            }
+            Err(e) => {
+                error!(
+                    "Retention enforcement failed for organization {}: {}",
+                    organization_id, e
+                );
+            }
+        }
+    }
+
+    if let Some(policy) = default_policy {
+        let mut report = RetentionReport {
+            policy_id: Some(policy.id),
+            ..Default::default()
+        };
+        if let Some(cutoff) = retention_cutoff(Utc::now(), policy.credit_retention_days) {
+            report.credits_deleted = delete_redeemed_credits_before(db, cutoff).await?;
+        }
+
+        // The default row reports the totals of every organization it covered.
+        let mut totals = report.clone();
+        for covered in reports.iter().filter(|r| r.policy_id == Some(policy.id)) {
+            totals.transactions_aggregated += covered.transactions_aggregated;
+            totals.transactions_deleted += covered.transactions_deleted;
+            totals.payment_intents_deleted += covered.payment_intents_deleted;
+            totals.searches_purged += covered.searches_purged;
+        }
+        record_report(db, &policy.id, &totals).await;
+        reports.push(report);
+    }
+
+    for report in reports.iter().filter(|r| !r.is_empty()) {
+        info!(
+            "Retention removed data for {}: {} transactions aggregated, {} deleted, {} payment intents, {} searches, {} credits",
+            report
+                .organization_id
+                .map(|id| format!("organization {}", id))
+                .unwrap_or_else(|| "instance".to_string()),
+            report.transactions_aggregated,
+            report.transactions_deleted,
+            report.payment_intents_deleted,
+            report.searches_purged,
+            report.credits_deleted
+        );
+    }
+
+    Ok(reports)
+}
+
+async fn record_report(db: &Pool, policy_id: &Uuid, report: &RetentionReport) {
+    let report = serde_json::to_value(report).unwrap_or_default();
+    if let Err(e) = record_retention_run(db, policy_id, &report).await {
+        error!("Failed to record retention report: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_retention_cutoff() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+
+        assert_eq!(
+            retention_cutoff(now, Some(30)),
+            Some(Utc.with_ymd_and_hms(2026, 9, 18, 12, 0, 0).unwrap())
+        );
+        assert_eq!(retention_cutoff(now, None), None);
+        assert_eq!(retention_cutoff(now, Some(0)), None);
+    }
+}
@@ -0,0 +1,163 @@
+use chrono::Utc;
+use futures_util::future::BoxFuture;
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::time::interval;
+use tracing::{error, info, warn, Instrument};
+
+use crate::{
+    db::background_jobs::{get_background_jobs, record_job_history, register_job, BackgroundJob},
+    leader::lease_ttl,
+    metrics::record_job_run,
+    models::AppState,
+};
+
+/// How often the schedule table is checked for due or triggered jobs.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Shortest interval a job may be given, so a typo can't hammer mints or
+/// relays.
+pub const MIN_JOB_INTERVAL: Duration = Duration::from_secs(10);
+
+pub type JobResult = Result<(), String>;
+
+type JobFn = Arc<dyn Fn(Arc<AppState>) -> BoxFuture<'static, JobResult> + Send + Sync>;
+
+struct RegisteredJob {
+    default_interval: Duration,
+    run: JobFn,
+}
+
+/// Runs named jobs on the schedule kept in `background_jobs`. Intervals,
+/// pauses and manual triggers are read from the table on every poll, so
+/// changes made through the admin API apply to all instances without a
+/// restart. Each run goes through the job's lease, so only one instance
+/// runs a given job at a time.
+pub struct Scheduler {
+    state: Arc<AppState>,
+    jobs: HashMap<&'static str, RegisteredJob>,
+}
+
+impl Scheduler {
+    pub fn new(state: Arc<AppState>) -> Self {
+        Self {
+            state,
+            jobs: HashMap::new(),
+        }
+    }
+
+    /// Adds a job that runs every `default_interval` unless an admin sets
+    /// another interval.
+    pub fn register<F, Fut>(&mut self, name: &'static str, default_interval: Duration, job: F)
+    where
+        F: Fn(Arc<AppState>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = JobResult> + Send + 'static,
+    {
+        let run: JobFn = Arc::new(move |state| Box::pin(job(state)));
+        self.jobs.insert(
+            name,
+            RegisteredJob {
+                default_interval,
+                run,
+            },
+        );
+    }
+
+    /// Records the registered jobs and starts polling for due ones.
+    pub async fn start(self) -> tokio::task::JoinHandle<()> {
+        for (name, job) in &self.jobs {
+            if let Err(e) = register_job(&self.state.db, name, job.default_interval).await {
+                error!("Failed to register background job {}: {}", name, e);
+            }
+        }
+        info!("Scheduler started with {} jobs", self.jobs.len());
+
+        tokio::spawn(async move {
+            let jobs = Arc::new(self.jobs);
+            let running: Arc<Mutex<HashSet<&'static str>>> = Arc::default();
+            let mut poll = interval(POLL_INTERVAL);
+
+            loop {
+                poll.tick().await;
+
+                let rows = match get_background_jobs(&self.state.db).await {
+                    Ok(rows) => rows,
+                    Err(e) => {
+                        error!("Failed to load background job schedule: {}", e);
+                        continue;
+                    }
+                };
+
+                let now = Utc::now();
+                for row in rows {
+                    let Some((&name, _)) = jobs.get_key_value(row.job_name.as_str()) else {
+                        continue;
+                    };
+                    if !row.is_due(now) || !running.lock().unwrap().insert(name) {
+                        continue;
+                    }
+
+                    let state = Arc::clone(&self.state);
+                    let jobs = Arc::clone(&jobs);
+                    let running = Arc::clone(&running);
+                    tokio::spawn(
+                        async move {
+                            run_job(state, name, &jobs[name], row).await;
+                            running.lock().unwrap().remove(name);
+                        }
+                        .instrument(tracing::info_span!("background_job", job = name)),
+                    );
+                }
+            }
+        })
+    }
+}
+
+async fn run_job(
+    state: Arc<AppState>,
+    name: &'static str,
+    job: &RegisteredJob,
+    row: BackgroundJob,
+) {
+    let triggered = row.trigger_requested_at.is_some();
+    let started_at = Utc::now();
+    let started = Instant::now();
+
+    let Some(result) = state
+        .job_leases
+        .run(
+            name,
+            lease_ttl(row.interval()),
+            (job.run)(Arc::clone(&state)),
+        )
+        .await
+    else {
+        return;
+    };
+    let duration = started.elapsed();
+    record_job_run(name, started, result.is_ok());
+
+    let error = result.err();
+    match &error {
+        None => info!("Job {} finished in {:?}", name, duration),
+        Some(e) => warn!("Job {} failed after {:?}: {}", name, duration, e),
+    }
+
+    if let Err(e) = record_job_history(
+        &state.db,
+        name,
+        state.job_leases.instance_id(),
+        started_at,
+        error.as_deref(),
+        duration,
+        triggered,
+    )
+    .await
+    {
+        error!("Failed to record history for job {}: {}", name, e);
+    }
+}
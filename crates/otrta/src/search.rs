@@ -7,7 +7,7 @@ use crate::{
     completion::create_search_completion_request,
     db::user_searches::{SearchResponse, SearchSource, SearchSourceMetadata},
     models::AppState,
-    proxy::forward_request_with_payment_with_body,
+    proxy::{forward_request_with_payment_with_body, ProxyCaller},
 };
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -104,10 +104,12 @@ pub async fn perform_web_search_with_llm(
             "v1/chat/completions",
             Some(completion_request),
             true,
-            None,                  // api_key_id
-            Some(organization_id), // organization_id
-            user_id,
-            crate::db::transaction::TransactionType::Chat,
+            ProxyCaller {
+                api_key_id: None,
+                organization_id: Some(organization_id),
+                user_id,
+                transaction_type: crate::db::transaction::TransactionType::Chat,
+            },
         )
         .await;
 
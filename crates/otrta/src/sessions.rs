@@ -0,0 +1,137 @@
+//! Session tokens issued after a NIP-98 login, so clients sign one event
+//! instead of one per request. The access token is a short-lived, HMAC-signed
+//! set of claims; the refresh token is random, stored hashed, and replaced on
+//! every refresh.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// What an access token vouches for. `org` is where the session was started
+/// or last switched to, and is used when a request doesn't name an
+/// organization. It doesn't confine the token: `X-Organization-Id` picks any
+/// organization the user is still a member of, checked on every request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionClaims {
+    /// Session ID, looked up on every request to honour revocation.
+    pub sid: Uuid,
+    pub npub: String,
+    pub org: Uuid,
+    /// Expiry in Unix seconds.
+    pub exp: i64,
+}
+
+/// Signs and checks access tokens.
+pub struct SessionTokens {
+    key: Vec<u8>,
+    pub access_ttl: Duration,
+    pub refresh_ttl: Duration,
+}
+
+impl SessionTokens {
+    pub fn new(secret: &[u8], access_ttl: Duration, refresh_ttl: Duration) -> Self {
+        Self {
+            key: secret.to_vec(),
+            access_ttl,
+            refresh_ttl,
+        }
+    }
+
+    /// Uses a key that only this process knows, so tokens stop working on
+    /// restart and aren't accepted by other replicas.
+    pub fn with_random_secret(access_ttl: Duration, refresh_ttl: Duration) -> Self {
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+        Self::new(&secret, access_ttl, refresh_ttl)
+    }
+
+    /// Encodes the claims as `<payload>.<signature>`, both base64url.
+    pub fn sign(&self, claims: &SessionClaims) -> String {
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(claims).unwrap());
+        let signature = URL_SAFE_NO_PAD.encode(self.mac(&payload).finalize().into_bytes());
+        format!("{}.{}", payload, signature)
+    }
+
+    /// Returns the claims of an authentic token that hasn't expired by `now`
+    /// (Unix seconds).
+    pub fn verify(&self, token: &str, now: i64) -> Option<SessionClaims> {
+        let (payload, signature) = token.split_once('.')?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        self.mac(payload).verify_slice(&signature).ok()?;
+
+        let claims: SessionClaims =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()?;
+        (claims.exp > now).then_some(claims)
+    }
+
+    fn mac(&self, payload: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(payload.as_bytes());
+        mac
+    }
+}
+
+/// A fresh opaque refresh token.
+pub fn new_refresh_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// The form refresh tokens are stored and looked up in.
+pub fn hash_refresh_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_800_000_000;
+
+    fn tokens(secret: &[u8]) -> SessionTokens {
+        SessionTokens::new(secret, Duration::from_secs(900), Duration::from_secs(3600))
+    }
+
+    fn claims() -> SessionClaims {
+        SessionClaims {
+            sid: Uuid::new_v4(),
+            npub: "npub1test".to_string(),
+            org: Uuid::new_v4(),
+            exp: NOW + 900,
+        }
+    }
+
+    #[test]
+    fn round_trips_until_expiry() {
+        let tokens = tokens(b"secret");
+        let claims = claims();
+        let token = tokens.sign(&claims);
+
+        assert_eq!(tokens.verify(&token, NOW), Some(claims.clone()));
+        assert_eq!(tokens.verify(&token, claims.exp), None);
+    }
+
+    #[test]
+    fn rejects_tampered_or_foreign_tokens() {
+        let tokens = tokens(b"secret");
+        let token = tokens.sign(&claims());
+
+        let mut other = claims();
+        other.npub = "npub1other".to_string();
+        let forged_payload = tokens.sign(&other);
+        let (forged_payload, _) = forged_payload.split_once('.').unwrap();
+        let (_, signature) = token.split_once('.').unwrap();
+        let forged = format!("{}.{}", forged_payload, signature);
+
+        assert_eq!(tokens.verify(&forged, NOW), None);
+        assert_eq!(self::tokens(b"other secret").verify(&token, NOW), None);
+        assert_eq!(tokens.verify("not a token", NOW), None);
+    }
+}
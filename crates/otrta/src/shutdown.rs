@@ -0,0 +1,95 @@
+use std::{
+    pin::pin,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+
+use crate::multimint_manager::MultimintManager;
+
+/// Counts proxied requests that may be holding ecash: a token sent to a
+/// provider whose change hasn't been redeemed yet. Shutdown waits for it to
+/// reach zero.
+#[derive(Debug, Default)]
+pub struct InFlightRequests {
+    count: AtomicUsize,
+    idle: Notify,
+}
+
+/// Marks one request as in flight until dropped.
+pub struct InFlightGuard<'a> {
+    tracker: &'a InFlightRequests,
+}
+
+impl InFlightRequests {
+    pub fn start(&self) -> InFlightGuard<'_> {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard { tracker: self }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    /// Waits until no request is in flight, giving up after `deadline`.
+    /// Returns whether everything settled.
+    pub async fn wait_idle(&self, deadline: Duration) -> bool {
+        tokio::time::timeout(deadline, async {
+            loop {
+                let mut notified = pin!(self.idle.notified());
+                notified.as_mut().enable();
+                if self.count() == 0 {
+                    return;
+                }
+                notified.await;
+            }
+        })
+        .await
+        .is_ok()
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        if self.tracker.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.tracker.idle.notify_waiters();
+        }
+    }
+}
+
+/// Reclaims tokens left pending by requests that were cut off, for every
+/// wallet loaded in this process.
+pub async fn redeem_all_pendings(multimint_manager: &MultimintManager, deadline: Duration) {
+    let wallets = multimint_manager.cached_multimints();
+    info!("Redeeming pending tokens for {} wallet(s)", wallets.len());
+
+    for wallet in wallets {
+        match tokio::time::timeout(deadline, wallet.redeem_pendings()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => error!("Failed to redeem pending tokens: {}", e),
+            Err(_) => warn!("Redeeming pending tokens timed out after {:?}", deadline),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_idle() {
+        let tracker = InFlightRequests::default();
+        assert!(tracker.wait_idle(Duration::from_millis(10)).await);
+
+        let guard = tracker.start();
+        assert_eq!(tracker.count(), 1);
+        assert!(!tracker.wait_idle(Duration::from_millis(10)).await);
+
+        let (settled, _) = tokio::join!(tracker.wait_idle(Duration::from_secs(1)), async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            drop(guard);
+        });
+        assert!(settled);
+    }
+}
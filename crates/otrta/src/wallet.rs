@@ -1,6 +1,6 @@
 use crate::{
     db::{
-        transaction::{add_transaction, TransactionDirection, TransactionType},
+        transaction::{add_transaction, TransactionContext, TransactionDirection, TransactionType},
         Pool,
     },
     multimint::{LocalMultimintSendOptions, MultimintWalletWrapper},
@@ -11,39 +11,25 @@ pub enum SendAmoundResponse {
     Error(String),
 }
 
+#[tracing::instrument(skip_all, fields(mint_url = %mint_url, amount = amount))]
 pub async fn send_with_retry(
     wallet: &MultimintWalletWrapper,
     amount: i64,
     mint_url: &str,
     retries: Option<i32>,
     db: &Pool,
-    api_key_id: Option<&str>,
-    user_id: Option<&str>,
-    provider_url: Option<&str>,
-    unit: Option<&str>,
-    model: Option<&str>,
+    ctx: &TransactionContext<'_>,
 ) -> Result<String, SendAmoundResponse> {
     let retry_count = retries.unwrap_or(3);
 
     for _ in 0..retry_count {
         let option = LocalMultimintSendOptions {
             preferred_mint: Some(mint_url.to_string()),
+            unit: ctx.unit.and_then(|u| u.parse().ok()),
             ..Default::default()
         };
 
-        if let Ok(token_result) = wallet
-            .send(
-                amount as u64,
-                option,
-                db,
-                api_key_id,
-                user_id,
-                provider_url,
-                unit,
-                model,
-            )
-            .await
-        {
+        if let Ok(token_result) = wallet.send(amount as u64, option, db, ctx).await {
             return Ok(token_result);
         }
     }
@@ -66,12 +52,8 @@ pub async fn finalize_request(
             token_send,
             &sats_send.to_string(),
             TransactionDirection::Outgoing,
-            None,
-            None,
             TransactionType::Api,
-            None,
-            None,
-            None,
+            &TransactionContext::default(),
         )
         .await
         .unwrap();
@@ -81,14 +63,78 @@ pub async fn finalize_request(
             token_received,
             &res.to_string(),
             TransactionDirection::Incoming,
-            None,
-            None,
             TransactionType::Api,
-            None,
-            None,
-            None,
+            &TransactionContext::default(),
         )
         .await
         .unwrap();
     }
 }
+
+/// Estimates the swap fee a receiver pays to redeem a token of `amount`.
+///
+/// Mints charge `input_fee_ppk` per input proof, rounded up to a whole unit
+/// (NUT-02). The proof count is not known before coin selection, so this
+/// assumes the canonical power-of-two split of `amount`.
+pub fn estimate_input_fee(amount: u64, input_fee_ppk: u64) -> u64 {
+    let proofs = amount.count_ones() as u64;
+    (proofs * input_fee_ppk).div_ceil(1000)
+}
+
+/// Returns the amount to send so the receiver still nets `amount` after
+/// paying the swap fee on the token.
+pub fn amount_with_input_fee(amount: u64, input_fee_ppk: u64) -> u64 {
+    if input_fee_ppk == 0 {
+        return amount;
+    }
+
+    let mut total = amount;
+    // Adding the fee changes the split, so settle on a total that covers
+    // its own fee. `total` only grows and the fee is bounded by 64 proofs,
+    // so this ends.
+    loop {
+        let required = amount.saturating_add(estimate_input_fee(total, input_fee_ppk));
+        if required <= total {
+            return total;
+        }
+        total = required;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_fee_estimation() {
+        assert_eq!(estimate_input_fee(1000, 0), 0);
+        // 1000 = 512 + 256 + 128 + 64 + 32 + 8 -> 6 proofs
+        assert_eq!(estimate_input_fee(1000, 100), 1);
+        assert_eq!(estimate_input_fee(1000, 1000), 6);
+        assert_eq!(estimate_input_fee(0, 1000), 0);
+    }
+
+    #[test]
+    fn test_amount_with_input_fee_covers_own_fee() {
+        assert_eq!(amount_with_input_fee(1000, 0), 1000);
+
+        for (amount, ppk) in [(1000, 100), (1000, 1000), (1, 1000), (12345, 250)] {
+            let total = amount_with_input_fee(amount, ppk);
+            assert!(total >= amount);
+            assert!(total - estimate_input_fee(total, ppk) >= amount);
+        }
+
+        // Large fees reshuffle the split many times before settling.
+        for ppk in [1, 999, 5_000, 100_000] {
+            for amount in 0..4096 {
+                let total = amount_with_input_fee(amount, ppk);
+                assert!(
+                    total - estimate_input_fee(total, ppk) >= amount,
+                    "{} {}",
+                    amount,
+                    ppk
+                );
+            }
+        }
+    }
+}
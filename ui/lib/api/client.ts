@@ -1,5 +1,7 @@
 import axios, { AxiosRequestConfig, AxiosResponse, AxiosInstance } from 'axios';
 import { authStateManager } from '../auth/auth-state';
+import { QueryParams, withQuery } from './nip98';
+import { clearSession, getSessionToken } from './session';
 
 // Nostr window interface is defined in nostr-auth.ts
 
@@ -71,6 +73,7 @@ class ApiClient {
       console.log('401 Unauthorized - logging out and redirecting to login');
 
       // Clear auth since server rejected the authentication
+      clearSession();
       try {
         const { getGlobalAuthState } = await import(
           '../auth/NostrifyAuthProvider'
@@ -95,10 +98,7 @@ class ApiClient {
   }
 
   // Helper method to construct headers
-  private async getHeaders(
-    method: string,
-    path: string
-  ): Promise<Record<string, string>> {
+  private async getHeaders(): Promise<Record<string, string>> {
     const headers: Record<string, string> = {
       Accept: 'application/json',
       'Content-Type': 'application/json',
@@ -108,7 +108,7 @@ class ApiClient {
     const isAuthEnabled =
       process.env.NEXT_PUBLIC_ENABLE_AUTHENTICATION === 'true';
 
-    // Add NIP-98 authentication if enabled and authenticated
+    // Authenticate with a session token, logging in with NIP-98 when needed
     if (isAuthEnabled && typeof window !== 'undefined') {
       try {
         // Import auth state getter
@@ -118,29 +118,12 @@ class ApiClient {
         const authState = getGlobalAuthState();
 
         if (authState?.currentSigner && authState?.activeUser) {
-          console.log('Creating NIP-98 auth event for:', method, path);
-          try {
-            const auth_event = await authState.signEvent({
-              kind: 27235,
-              created_at: Math.floor(new Date().getTime() / 1000),
-              content: 'application/json',
-              tags: [
-                ['u', `${this.getBaseUrl()}${path}`],
-                ['method', method],
-              ],
-            });
-
-            if (auth_event) {
-              headers['Authorization'] =
-                `Nostr ${btoa(JSON.stringify(auth_event))}`;
-              console.log('NIP-98 auth header added successfully');
-            } else {
-              console.warn('Failed to create auth event');
-            }
-          } catch (eventError) {
-            console.error('Error creating NIP-98 auth event:', eventError);
-            throw eventError; // Re-throw to be caught by outer catch
-          }
+          const token = await getSessionToken(
+            this.getBaseUrl(),
+            authState.activeUser.pubkey,
+            authState.signEvent
+          );
+          headers['Authorization'] = `Bearer ${token}`;
         } else {
           console.warn('No auth state available:', {
             hasSigner: !!authState?.currentSigner,
@@ -148,7 +131,7 @@ class ApiClient {
           });
         }
       } catch (error) {
-        console.error('Failed to create NIP-98 authentication:', error);
+        console.error('Failed to obtain a session token:', error);
         // In case of auth error, still try the request without auth
         // This helps with debugging and might work for some endpoints
       }
@@ -181,20 +164,17 @@ class ApiClient {
   }
 
   // GET request
-  async get<T>(
-    endpoint: string,
-    params?: Record<string, string | number | boolean | undefined>
-  ): Promise<T> {
+  async get<T>(endpoint: string, params?: QueryParams): Promise<T> {
+    const url = withQuery(endpoint, params);
     const config: AxiosRequestConfig = {
-      headers: await this.getHeaders('GET', endpoint),
-      params,
+      headers: await this.getHeaders(),
     };
 
     try {
-      const fullUrl = `${this.getBaseUrl()}${endpoint}`;
+      const fullUrl = `${this.getBaseUrl()}${url}`;
       console.log(`Making GET request to ${fullUrl}`);
       const response: AxiosResponse<T> = await this.axiosInstance.get<T>(
-        endpoint,
+        url,
         config
       );
       return response.data;
@@ -218,7 +198,7 @@ class ApiClient {
   // POST request
   async post<T>(endpoint: string, data: Record<string, unknown>): Promise<T> {
     const config: AxiosRequestConfig = {
-      headers: await this.getHeaders('POST', endpoint),
+      headers: await this.getHeaders(),
     };
 
     try {
@@ -251,7 +231,7 @@ class ApiClient {
   // PUT request
   async put<T>(endpoint: string, data: Record<string, unknown>): Promise<T> {
     const config: AxiosRequestConfig = {
-      headers: await this.getHeaders('PUT', endpoint),
+      headers: await this.getHeaders(),
     };
 
     try {
@@ -274,7 +254,7 @@ class ApiClient {
   // DELETE request
   async delete<T>(endpoint: string): Promise<T> {
     const config: AxiosRequestConfig = {
-      headers: await this.getHeaders('DELETE', endpoint),
+      headers: await this.getHeaders(),
     };
 
     try {
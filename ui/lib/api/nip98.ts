@@ -0,0 +1,44 @@
+import { nip98 } from 'nostr-tools';
+
+export type QueryParams = Record<string, string | number | boolean | undefined>;
+
+// Appends params to the endpoint ourselves, so the URL we sign is exactly
+// the one sent.
+export function withQuery(endpoint: string, params?: QueryParams): string {
+  const search = new URLSearchParams();
+  for (const [key, value] of Object.entries(params ?? {})) {
+    if (value !== undefined) {
+      search.append(key, String(value));
+    }
+  }
+  const query = search.toString();
+  if (!query) {
+    return endpoint;
+  }
+  return `${endpoint}${endpoint.includes('?') ? '&' : '?'}${query}`;
+}
+
+// crypto.randomUUID is missing outside secure contexts, e.g. plain HTTP on
+// a LAN address.
+function randomNonce(): string {
+  return Array.from(crypto.getRandomValues(new Uint8Array(16)), (byte) =>
+    byte.toString(16).padStart(2, '0')
+  ).join('');
+}
+
+// Tags of a NIP-98 auth event for one request. Bodies must be sent as
+// JSON.stringify(data) so they match the payload hash.
+export function authTags(
+  url: string,
+  method: string,
+  data?: unknown
+): string[][] {
+  return [
+    ['u', url],
+    ['method', method],
+    ...(data !== undefined ? [['payload', nip98.hashPayload(data)]] : []),
+    // The server rejects reused events, and identical requests signed in
+    // the same second would otherwise share an ID.
+    ['nonce', randomNonce()],
+  ];
+}
@@ -1,5 +1,6 @@
 import axios from 'axios';
 import type { z } from 'zod';
+import { authTags, withQuery } from '../nip98';
 
 const PRODUCTION = 'production';
 
@@ -29,17 +30,19 @@ export default function api<Request, Response>({
     requestSchema.parse(requestData);
 
     async function apiCall() {
+      const isGet = method === HTTPMethod.GET;
+      const url = isGet
+        ? withQuery(path, requestData as Record<string, string>)
+        : path;
       const auth_event = await window.nostr!.signEvent({
         kind: 27235,
         created_at: Math.floor(new Date().getTime() / 1000),
         content: 'application/json',
-        tags: [
-          [
-            'u',
-            `${process.env.NEXT_PUBLIC_API_URL || 'http://localhost:3333'}${path}`,
-          ],
-          ['method', method],
-        ],
+        tags: authTags(
+          `${process.env.NEXT_PUBLIC_API_URL || 'http://localhost:3333'}${url}`,
+          method,
+          isGet ? undefined : requestData
+        ),
         // eslint-disable-next-line @typescript-eslint/no-explicit-any
       } as any);
 
@@ -50,8 +53,8 @@ export default function api<Request, Response>({
           'Content-Type': 'application/json',
         },
         method,
-        url: path,
-        [method === HTTPMethod.GET ? 'params' : 'data']: requestData,
+        url,
+        data: isGet ? undefined : JSON.stringify(requestData),
       });
 
       if (process.env.NODE_ENV === PRODUCTION) {
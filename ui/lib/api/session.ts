@@ -0,0 +1,147 @@
+import axios from 'axios';
+import type { NostrEvent } from '@nostrify/nostrify';
+import { authTags } from './nip98';
+
+// Sessions let the signer approve one login event instead of one event per
+// request. The server hands out a short-lived access token and a refresh
+// token that is replaced every time it is used.
+
+const STORAGE_KEY = 'otrta_session';
+// Refresh a little early so a token doesn't expire in flight.
+const EXPIRY_MARGIN_MS = 30_000;
+
+interface StoredSession {
+  baseUrl: string;
+  pubkey: string;
+  accessToken: string;
+  accessExpiresAt: number;
+  refreshToken: string;
+}
+
+interface SessionTokenResponse {
+  access_token: string;
+  expires_in: number;
+  refresh_token: string;
+}
+
+type SignEvent = (
+  event: Omit<NostrEvent, 'id' | 'pubkey' | 'sig'>
+) => Promise<NostrEvent | null>;
+
+let pending: Promise<string> | null = null;
+
+function load(): StoredSession | null {
+  try {
+    const stored = localStorage.getItem(STORAGE_KEY);
+    return stored ? (JSON.parse(stored) as StoredSession) : null;
+  } catch {
+    return null;
+  }
+}
+
+function store(
+  baseUrl: string,
+  pubkey: string,
+  response: SessionTokenResponse
+): string {
+  const session: StoredSession = {
+    baseUrl,
+    pubkey,
+    accessToken: response.access_token,
+    accessExpiresAt: Date.now() + response.expires_in * 1000,
+    refreshToken: response.refresh_token,
+  };
+  localStorage.setItem(STORAGE_KEY, JSON.stringify(session));
+  return session.accessToken;
+}
+
+async function refresh(session: StoredSession): Promise<string | null> {
+  try {
+    const response = await axios.post<SessionTokenResponse>(
+      `${session.baseUrl}/api/auth/refresh`,
+      { refresh_token: session.refreshToken }
+    );
+    return store(session.baseUrl, session.pubkey, response.data);
+  } catch {
+    localStorage.removeItem(STORAGE_KEY);
+    return null;
+  }
+}
+
+async function login(
+  baseUrl: string,
+  pubkey: string,
+  signEvent: SignEvent
+): Promise<string> {
+  const url = `${baseUrl}/api/auth/login`;
+  const event = await signEvent({
+    kind: 27235,
+    created_at: Math.floor(Date.now() / 1000),
+    content: '',
+    tags: authTags(url, 'POST'),
+  });
+  if (!event) {
+    throw new Error('Login event was not signed');
+  }
+
+  const response = await axios.post<SessionTokenResponse>(url, undefined, {
+    headers: { Authorization: `Nostr ${btoa(JSON.stringify(event))}` },
+  });
+  return store(baseUrl, pubkey, response.data);
+}
+
+async function obtain(
+  baseUrl: string,
+  pubkey: string,
+  signEvent: SignEvent
+): Promise<string> {
+  const session = load();
+  if (session && session.baseUrl === baseUrl && session.pubkey === pubkey) {
+    if (session.accessExpiresAt - EXPIRY_MARGIN_MS > Date.now()) {
+      return session.accessToken;
+    }
+    const refreshed = await refresh(session);
+    if (refreshed) {
+      return refreshed;
+    }
+  }
+  return login(baseUrl, pubkey, signEvent);
+}
+
+/**
+ * Access token for `pubkey` on the server at `baseUrl`, logging in or
+ * refreshing as needed. Concurrent callers share one login.
+ */
+export function getSessionToken(
+  baseUrl: string,
+  pubkey: string,
+  signEvent: SignEvent
+): Promise<string> {
+  if (!pending) {
+    pending = obtain(baseUrl, pubkey, signEvent).finally(() => {
+      pending = null;
+    });
+  }
+  return pending;
+}
+
+/** Revokes the stored session on the server and forgets it. */
+export async function endSession(): Promise<void> {
+  const session = load();
+  localStorage.removeItem(STORAGE_KEY);
+  if (!session || session.accessExpiresAt <= Date.now()) {
+    return;
+  }
+  try {
+    await axios.post(`${session.baseUrl}/api/auth/logout`, undefined, {
+      headers: { Authorization: `Bearer ${session.accessToken}` },
+    });
+  } catch (error) {
+    console.warn('Failed to revoke session:', error);
+  }
+}
+
+/** Forgets the stored session without contacting the server. */
+export function clearSession(): void {
+  localStorage.removeItem(STORAGE_KEY);
+}
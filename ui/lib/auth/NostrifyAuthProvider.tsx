@@ -13,6 +13,7 @@ import { NostrEvent, NPool, NRelay1, NSecSigner } from '@nostrify/nostrify';
 import { NostrContext } from '@nostrify/react';
 import { nip19 } from 'nostr-tools';
 import { toast } from 'sonner';
+import { endSession } from '../api/session';
 
 interface NostrifyProviderProps {
   children: React.ReactNode;
@@ -233,6 +234,7 @@ const NostrifyProvider: React.FC<NostrifyProviderProps> = ({
 
   // Logout
   const logout = useCallback(() => {
+    void endSession();
     setActiveUser(null);
     setCurrentSigner(null);
     localStorage.removeItem('nostr_auth_method');